@@ -0,0 +1,157 @@
+//! Reading recipes that are not UTF-8
+//!
+//! Recipes exported from old apps or written with old editors are often in
+//! Windows-1252, the Windows superset of latin-1, or UTF-16 with a BOM. They
+//! are converted to UTF-8 instead of failing to read them.
+
+use std::fmt;
+
+/// Encoding a text was read as
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Encoding {
+    Utf8,
+    Utf16Le,
+    Utf16Be,
+    Windows1252,
+}
+
+impl fmt::Display for Encoding {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Encoding::Utf8 => "UTF-8",
+            Encoding::Utf16Le => "UTF-16LE",
+            Encoding::Utf16Be => "UTF-16BE",
+            Encoding::Windows1252 => "Windows-1252",
+        })
+    }
+}
+
+/// Chars of the bytes `0x80..=0x9F` in Windows-1252
+///
+/// The 5 bytes it leaves undefined are the C1 control chars, like latin-1.
+const WINDOWS_1252_HIGH: [char; 32] = [
+    '€', '\u{81}', '‚', 'ƒ', '„', '…', '†', '‡', 'ˆ', '‰', 'Š', '‹', 'Œ', '\u{8D}', 'Ž', '\u{8F}',
+    '\u{90}', '‘', '’', '“', '”', '•', '–', '—', '˜', '™', 'š', '›', 'œ', '\u{9D}', 'ž', 'Ÿ',
+];
+
+/// Converts the bytes of a text file to a string
+///
+/// UTF-8 is used if the bytes are valid UTF-8, without the BOM if any. Then
+/// UTF-16 if there is a BOM for it, and Windows-1252 for anything else, which
+/// can't fail.
+pub fn decode(bytes: Vec<u8>) -> (String, Encoding) {
+    let bytes = match String::from_utf8(bytes) {
+        Ok(mut text) => {
+            if text.starts_with('\u{FEFF}') {
+                text.drain(..'\u{FEFF}'.len_utf8());
+            }
+            return (text, Encoding::Utf8);
+        }
+        Err(e) => e.into_bytes(),
+    };
+    if let Some(text) = decode_utf16(&bytes) {
+        return text;
+    }
+    let text = bytes
+        .iter()
+        .map(|&b| match b {
+            0x80..=0x9F => WINDOWS_1252_HIGH[usize::from(b - 0x80)],
+            _ => char::from(b),
+        })
+        .collect();
+    (text, Encoding::Windows1252)
+}
+
+fn decode_utf16(bytes: &[u8]) -> Option<(String, Encoding)> {
+    let (encoding, rest) = match bytes {
+        [0xFF, 0xFE, rest @ ..] => (Encoding::Utf16Le, rest),
+        [0xFE, 0xFF, rest @ ..] => (Encoding::Utf16Be, rest),
+        _ => return None,
+    };
+    if rest.len() % 2 != 0 {
+        return None;
+    }
+    let units = rest.chunks_exact(2).map(|pair| {
+        let pair = [pair[0], pair[1]];
+        match encoding {
+            Encoding::Utf16Le => u16::from_le_bytes(pair),
+            _ => u16::from_be_bytes(pair),
+        }
+    });
+    let text = char::decode_utf16(units)
+        .collect::<Result<String, _>>()
+        .ok()?;
+    Some((text, encoding))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn utf16(text: &str, encoding: Encoding) -> Vec<u8> {
+        let mut bytes = match encoding {
+            Encoding::Utf16Le => vec![0xFF, 0xFE],
+            _ => vec![0xFE, 0xFF],
+        };
+        for unit in text.encode_utf16() {
+            match encoding {
+                Encoding::Utf16Le => bytes.extend(unit.to_le_bytes()),
+                _ => bytes.extend(unit.to_be_bytes()),
+            }
+        }
+        bytes
+    }
+
+    #[test]
+    fn utf8() {
+        let text = "Add @crème fraîche{100%g} 🥄";
+        assert_eq!(
+            decode(text.as_bytes().to_vec()),
+            (text.to_string(), Encoding::Utf8)
+        );
+        let mut bytes = "\u{FEFF}".as_bytes().to_vec();
+        bytes.extend(text.as_bytes());
+        assert_eq!(decode(bytes), (text.to_string(), Encoding::Utf8));
+        assert_eq!(decode(vec![]), (String::new(), Encoding::Utf8));
+    }
+
+    #[test]
+    fn utf16_with_bom() {
+        let text = "Add @crème fraîche{100%g} 🥄";
+        for encoding in [Encoding::Utf16Le, Encoding::Utf16Be] {
+            let bytes = utf16(text, encoding);
+            assert_eq!(decode(bytes), (text.to_string(), encoding));
+        }
+        // the BOM alone is an empty text
+        assert_eq!(decode(vec![0xFF, 0xFE]), (String::new(), Encoding::Utf16Le));
+    }
+
+    #[test]
+    fn invalid_utf16_is_windows_1252() {
+        // odd length
+        let mut bytes = utf16("ab", Encoding::Utf16Le);
+        bytes.push(b'c');
+        assert_eq!(
+            decode(bytes),
+            ("ÿþa\0b\0c".to_string(), Encoding::Windows1252)
+        );
+        // unpaired surrogate
+        let bytes = vec![0xFE, 0xFF, 0xD8, 0x00];
+        assert_eq!(decode(bytes), ("þÿØ\0".to_string(), Encoding::Windows1252));
+    }
+
+    #[test]
+    fn windows_1252() {
+        let (text, encoding) = decode(b"Cr\xE8me br\xFBl\xE9e".to_vec());
+        assert_eq!(encoding, Encoding::Windows1252);
+        assert_eq!(text, "Crème brûlée");
+
+        let (text, _) = decode(vec![0x80, b' ', 0x85, 0x93, b'x', 0x94, 0x99, 0x9F]);
+        assert_eq!(text, "€ …“x”™Ÿ");
+        // undefined in Windows-1252, kept as the C1 control chars
+        let (text, _) = decode(vec![0x81, 0x8D, 0x8F, 0x90, 0x9D]);
+        assert_eq!(text, "\u{81}\u{8D}\u{8F}\u{90}\u{9D}");
+        let all = (0x80..=0x9F).collect::<Vec<u8>>();
+        assert_eq!(decode(all).0.chars().count(), 32);
+    }
+}
@@ -7,6 +7,8 @@
 //! from a path. The index can be lazy or eager. Both created with
 //! [`new_index`].
 
+mod encoding;
+mod metadata_cache;
 mod walker;
 
 use std::{cell::RefCell, collections::HashMap, sync::OnceLock};
@@ -15,8 +17,10 @@ use camino::{Utf8Component, Utf8Path, Utf8PathBuf};
 use cooklang::quantity::QuantityValue;
 use serde::Serialize;
 
-pub use walker::DirEntry;
+pub use encoding::{decode, Encoding};
+pub use metadata_cache::MetadataCache;
 use walker::Walker;
+pub use walker::{DirEntry, FileInfo, WalkError};
 
 pub fn new_index(
     base_path: impl AsRef<std::path::Path>,
@@ -28,6 +32,7 @@ pub fn new_index(
 pub struct FsIndexBuilder {
     base_path: Utf8PathBuf,
     walker: Walker,
+    ignore_diacritics: bool,
 }
 
 impl FsIndexBuilder {
@@ -41,6 +46,7 @@ impl FsIndexBuilder {
         Ok(Self {
             base_path: base_path.to_path_buf(),
             walker,
+            ignore_diacritics: false,
         })
     }
 
@@ -60,6 +66,16 @@ impl FsIndexBuilder {
         self
     }
 
+    /// Looks up names ignoring accents and other diacritics
+    ///
+    /// Names are always compared ignoring case. With this, `creme brulee`
+    /// also finds `Crème Brûlée.cook`. Recipes with names that only differ in
+    /// their diacritics become [conflicts](FsIndex::conflicts).
+    pub fn ignore_diacritics(mut self, ignore: bool) -> Self {
+        self.ignore_diacritics = ignore;
+        self
+    }
+
     /// Create a new [lazy index](`LazyFsIndex`)
     ///
     /// The structure this creates is not completely thread safe, see
@@ -68,31 +84,52 @@ impl FsIndexBuilder {
         LazyFsIndex {
             base_path: self.base_path,
             walker: RefCell::new(self.walker),
-            cache: RefCell::new(Cache::default()),
+            cache: RefCell::new(Cache::new(self.ignore_diacritics)),
+            issues: RefCell::new(Vec::new()),
         }
     }
 
     /// Create a new [complete index](`FsIndex`)
-    pub fn indexed(mut self) -> Result<FsIndex, Error> {
-        let mut cache = Cache::default();
-        index_all(&mut cache, &mut self.walker)?;
-        Ok(FsIndex {
+    ///
+    /// Files or dirs that can't be read are skipped, see [`FsIndex::issues`].
+    pub fn indexed(mut self) -> FsIndex {
+        let mut cache = Cache::new(self.ignore_diacritics);
+        let issues = index_all(&mut cache, &mut self.walker);
+        FsIndex {
             base_path: self.base_path,
             cache,
-        })
+            issues,
+        }
+    }
+
+    /// Walks the dir without indexing it
+    ///
+    /// Yields the dirs, recipes and images found, and the files or dirs that
+    /// can't be read, the same ones that end in [`FsIndex::issues`].
+    pub fn walk(self) -> impl Iterator<Item = Result<DirEntry, WalkError>> {
+        self.walker
     }
 }
 
-#[tracing::instrument(level = "debug", skip_all, ret)]
-fn index_all(cache: &mut Cache, walker: &mut Walker) -> Result<(), Error> {
+/// Walks everything left, returning the entries that could not be read
+#[tracing::instrument(level = "debug", skip_all)]
+fn index_all(cache: &mut Cache, walker: &mut Walker) -> Vec<WalkError> {
+    let mut issues = Vec::new();
     for entry in walker {
-        let entry = entry?;
+        let entry = match entry {
+            Ok(entry) => entry,
+            Err(e) => {
+                tracing::warn!("Skipping entry: {e}");
+                issues.push(e);
+                continue;
+            }
+        };
         let Some((entry_name, path)) = process_entry(&entry) else {
             continue;
         };
         cache.insert(entry_name, path);
     }
-    Ok(())
+    issues
 }
 
 /// Lazy index of a directory for cooklang recipes
@@ -110,6 +147,7 @@ pub struct LazyFsIndex {
     base_path: Utf8PathBuf,
     cache: RefCell<Cache>,
     walker: RefCell<Walker>,
+    issues: RefCell<Vec<WalkError>>,
 }
 
 /// Index of a directory for cooklang recipes
@@ -119,31 +157,45 @@ pub struct LazyFsIndex {
 pub struct FsIndex {
     base_path: Utf8PathBuf,
     cache: Cache,
+    issues: Vec<WalkError>,
 }
 
-#[derive(Debug, Default)]
+#[derive(Debug)]
 struct Cache {
+    /// By [`Cache::key`] of the name
     recipes: HashMap<String, Vec<Utf8PathBuf>>,
+    ignore_diacritics: bool,
 }
 
 #[derive(Debug, thiserror::Error)]
+#[non_exhaustive]
 pub enum Error {
     #[error("Recipe not found: '{0}'")]
     NotFound(String),
     #[error(transparent)]
     Io(#[from] std::io::Error),
+    #[error(transparent)]
+    Walk(#[from] WalkError),
     #[error("Invalid name: '{0}'")]
     InvalidName(String),
     #[error(transparent)]
     NotRecipe(#[from] NotRecipe),
     #[error("Path points outside the base dir: '{0}'")]
     OutsideBase(String),
+    #[error("Invalid metadata in recipe: '{0}'")]
+    InvalidMetadata(String),
 }
 
 #[derive(Debug, thiserror::Error)]
-#[error("Non UTF8 path")]
+#[error("Non UTF8 path: '{}'", .0.display())]
 pub struct NonUtf8(std::path::PathBuf);
 
+impl NonUtf8 {
+    pub fn path(&self) -> &std::path::Path {
+        &self.0
+    }
+}
+
 impl FsIndex {
     pub fn base_path(&self) -> &Utf8Path {
         &self.base_path
@@ -156,6 +208,13 @@ impl FsIndex {
         self.cache.get(&name, &path).is_some()
     }
 
+    /// Files and dirs that were skipped because they could not be read
+    ///
+    /// These are not fatal, the rest of the collection is indexed anyway.
+    pub fn issues(&self) -> &[WalkError] {
+        &self.issues
+    }
+
     /// Resolves a recipe query first trying directly as a path and if it fails
     /// performs a lookup in the index.
     ///
@@ -184,6 +243,23 @@ impl FsIndex {
             .map(|p| RecipeEntry::new(p.to_path_buf()))
     }
 
+    /// Recipe names shared by more than one recipe
+    ///
+    /// Looking up one of these names only by name gives the first path, the
+    /// outermost and then alphabetically first, shadowing the others. The
+    /// names are lowercase and sorted, the paths are in lookup order.
+    pub fn conflicts(&self) -> Vec<(&str, &[Utf8PathBuf])> {
+        let mut conflicts = self
+            .cache
+            .recipes
+            .iter()
+            .filter(|(_, paths)| paths.len() > 1)
+            .map(|(name, paths)| (name.as_str(), paths.as_slice()))
+            .collect::<Vec<_>>();
+        conflicts.sort_unstable_by_key(|(name, _)| *name);
+        conflicts
+    }
+
     /// Remove a recipe from the index
     ///
     /// The parameter is the path in disk and has to be prefixed with the
@@ -247,14 +323,25 @@ impl LazyFsIndex {
     }
 
     /// Completes the lazy indexing returning a complete [`FsIndex`]
-    pub fn index_all(self) -> Result<FsIndex, Error> {
+    ///
+    /// Files or dirs that can't be read are skipped, and together with the
+    /// ones found by previous lookups, are in [`FsIndex::issues`].
+    pub fn index_all(self) -> FsIndex {
         let mut cache = self.cache.into_inner();
         let mut walker = self.walker.into_inner();
-        index_all(&mut cache, &mut walker)?;
-        Ok(FsIndex {
+        let mut issues = self.issues.into_inner();
+        issues.extend(index_all(&mut cache, &mut walker));
+        FsIndex {
             base_path: self.base_path,
             cache,
-        })
+            issues,
+        }
+    }
+
+    /// Takes the files and dirs skipped by lookups so far because they could
+    /// not be read
+    pub fn take_issues(&self) -> Vec<WalkError> {
+        std::mem::take(&mut *self.issues.borrow_mut())
     }
 
     /// Resolves a recipe query first trying directly as a path and if it fails
@@ -286,7 +373,14 @@ impl LazyFsIndex {
         // be the wanted: outermost alphabetically
         let mut walker = self.walker.borrow_mut();
         for entry in walker.by_ref() {
-            let entry = entry?;
+            let entry = match entry {
+                Ok(entry) => entry,
+                Err(e) => {
+                    tracing::warn!("Skipping entry while looking for '{recipe}': {e}");
+                    self.issues.borrow_mut().push(e);
+                    continue;
+                }
+            };
             let Some((entry_name, entry_path)) = process_entry(&entry) else {
                 continue;
             };
@@ -294,7 +388,7 @@ impl LazyFsIndex {
             // Add to cache
             self.cache.borrow_mut().insert(entry_name, entry_path);
 
-            if compare_path(entry_path, &path) {
+            if self.cache.borrow().matches(entry_path, &path) {
                 return Ok(RecipeEntry::new(entry_path));
             }
         }
@@ -314,14 +408,36 @@ fn process_entry(dir_entry: &DirEntry) -> Option<(&str, &Utf8Path)> {
 }
 
 impl Cache {
+    fn new(ignore_diacritics: bool) -> Self {
+        Self {
+            recipes: HashMap::new(),
+            ignore_diacritics,
+        }
+    }
+
+    fn key(&self, s: &str) -> String {
+        if self.ignore_diacritics {
+            fold_name(s)
+        } else {
+            s.to_lowercase()
+        }
+    }
+
+    fn matches(&self, full: &Utf8Path, suffix: &Utf8Path) -> bool {
+        // only compare the end, so partial paths are a valid form of referencing recipes
+        let key = |p: &Utf8Path| Utf8PathBuf::from(self.key(p.as_str())).with_extension("");
+        key(full).ends_with(key(suffix))
+    }
+
     fn get(&self, name: &str, path: &Utf8Path) -> Option<Utf8PathBuf> {
-        let paths = self.recipes.get(&name.to_lowercase())?;
-        paths.iter().find(|p| compare_path(p, path)).cloned()
+        let paths = self.recipes.get(&self.key(name))?;
+        paths.iter().find(|p| self.matches(p, path)).cloned()
     }
 
     fn insert(&mut self, name: &str, path: &Utf8Path) {
         tracing::trace!("adding {name}:{path} to index cache");
-        let recipes = self.recipes.entry(name.to_lowercase()).or_default();
+        let key = self.key(name);
+        let recipes = self.recipes.entry(key).or_default();
         let pos = recipes.partition_point(|p| {
             // less components first. same, alphabetically
             match p.components().count().cmp(&path.components().count()) {
@@ -335,7 +451,8 @@ impl Cache {
 
     fn remove(&mut self, name: &str, path: &Utf8Path) {
         tracing::trace!("removing {name}:{path} from index cache");
-        if let Some(recipes) = self.recipes.get_mut(&name.to_lowercase()) {
+        let key = self.key(name);
+        if let Some(recipes) = self.recipes.get_mut(&key) {
             // can't do swap so "outer" recipes remain first
             if let Some(index) = recipes.iter().position(|r| r == path) {
                 recipes.remove(index);
@@ -353,13 +470,48 @@ fn into_name_path(recipe: &str) -> Result<(String, Utf8PathBuf), Error> {
     Ok((name, path))
 }
 
-fn compare_path_key(p: &Utf8Path) -> Utf8PathBuf {
-    Utf8PathBuf::from(p.as_str().to_lowercase()).with_extension("")
+/// Lowercase and without diacritics
+///
+/// Combining marks are removed, so names in decomposed form, like the ones
+/// from macOS, are the same as composed ones. Precomposed letters are
+/// replaced for the ones used in latin languages.
+pub fn fold_name(s: &str) -> String {
+    let mut folded = String::with_capacity(s.len());
+    for c in s.chars().flat_map(char::to_lowercase) {
+        match c {
+            '\u{0300}'..='\u{036f}' => {}
+            'ß' => folded.push_str("ss"),
+            'æ' => folded.push_str("ae"),
+            'œ' => folded.push_str("oe"),
+            c => folded.push(fold_char(c)),
+        }
+    }
+    folded
 }
 
-fn compare_path(full: &Utf8Path, suffix: &Utf8Path) -> bool {
-    // only compare the end, so partial paths are a valid form of referencing recipes
-    compare_path_key(full).ends_with(compare_path_key(suffix))
+fn fold_char(c: char) -> char {
+    match c {
+        'à'..='å' | 'ā' | 'ă' | 'ą' => 'a',
+        'ç' | 'ć' | 'ĉ' | 'ċ' | 'č' => 'c',
+        'ð' | 'ď' | 'đ' => 'd',
+        'è'..='ë' | 'ē' | 'ĕ' | 'ė' | 'ę' | 'ě' => 'e',
+        'ĝ' | 'ğ' | 'ġ' | 'ģ' => 'g',
+        'ĥ' | 'ħ' => 'h',
+        'ì'..='ï' | 'ĩ' | 'ī' | 'ĭ' | 'į' | 'ı' => 'i',
+        'ĵ' => 'j',
+        'ķ' => 'k',
+        'ĺ' | 'ļ' | 'ľ' | 'ŀ' | 'ł' => 'l',
+        'ñ' | 'ń' | 'ņ' | 'ň' => 'n',
+        'ò'..='ö' | 'ø' | 'ō' | 'ŏ' | 'ő' => 'o',
+        'ŕ' | 'ŗ' | 'ř' => 'r',
+        'ś' | 'ŝ' | 'ş' | 'š' => 's',
+        'ţ' | 'ť' | 'ŧ' => 't',
+        'ù'..='ü' | 'ũ' | 'ū' | 'ŭ' | 'ů' | 'ű' | 'ų' => 'u',
+        'ŵ' => 'w',
+        'ý' | 'ÿ' | 'ŷ' => 'y',
+        'ź' | 'ż' | 'ž' => 'z',
+        c => c,
+    }
 }
 
 /// Get all recipes from a path with a depth limit
@@ -371,7 +523,7 @@ pub fn all_recipes(
         .as_ref()
         .try_into()
         .map_err(|e: camino::FromPathError| e.into_io_error())?;
-    let walker = Walker::new(base_path, max_depth).flatten();
+    let walker = Walker::new(base_path, max_depth).filter_map(skip_issue);
     let grouped = group_images(walker);
     Ok(grouped.filter_map(|e| match e {
         Entry::Dir(_) => None,
@@ -393,7 +545,13 @@ pub fn walk_dir(
             "dir not found",
         ));
     }
-    Ok(group_images(Walker::new(path, 0).flatten()))
+    Ok(group_images(Walker::new(path, 0).filter_map(skip_issue)))
+}
+
+fn skip_issue(entry: Result<DirEntry, WalkError>) -> Option<DirEntry> {
+    entry
+        .map_err(|e| tracing::warn!("Skipping entry: {e}"))
+        .ok()
 }
 
 fn group_images(walker: impl Iterator<Item = DirEntry>) -> impl Iterator<Item = Entry> {
@@ -422,8 +580,19 @@ fn group_images(walker: impl Iterator<Item = DirEntry>) -> impl Iterator<Item =
                                 images.push(image);
                             }
                         }
+                        if let Some(dir) = r.path().parent() {
+                            images.extend(
+                                image_dirs(dir, recipe_name)
+                                    .flat_map(|d| dir_images(&d, recipe_name)),
+                            );
+                            images.sort_unstable();
+                            images.dedup();
+                        }
+                        let info = r.info();
                         return Some(Entry::Recipe(
-                            RecipeEntry::new(r.into_path()).set_images(images),
+                            RecipeEntry::new(r.into_path())
+                                .set_images(images)
+                                .set_info(info),
                         ));
                     }
                     img if img.is_image() => {
@@ -516,6 +685,7 @@ fn norm_path(path: &Utf8Path) -> Utf8PathBuf {
 pub struct RecipeEntry {
     path: Utf8PathBuf,
     images: OnceLock<Vec<Image>>,
+    info: OnceLock<Option<FileInfo>>,
 }
 
 impl RecipeEntry {
@@ -526,6 +696,7 @@ impl RecipeEntry {
         Self {
             path: path.as_ref().to_path_buf(),
             images: OnceLock::new(),
+            info: OnceLock::new(),
         }
     }
 
@@ -534,6 +705,13 @@ impl RecipeEntry {
         self
     }
 
+    fn set_info(self, info: Option<FileInfo>) -> Self {
+        if let Some(info) = info {
+            _ = self.info.set(Some(info));
+        }
+        self
+    }
+
     pub fn path(&self) -> &Utf8Path {
         &self.path
     }
@@ -550,12 +728,35 @@ impl RecipeEntry {
         self.path.as_str().trim_end_matches(".cook")
     }
 
+    /// If the file is named like a draft, starting with [`DRAFT_PREFIX`]
+    pub fn is_draft_name(&self) -> bool {
+        self.file_name().starts_with(DRAFT_PREFIX)
+    }
+
     /// Reads the content of the entry
+    ///
+    /// Files that are not UTF-8 are converted with [`decode`], with a warning.
     pub fn read(&self) -> std::io::Result<RecipeContent> {
-        let content = std::fs::read_to_string(&self.path)?;
+        let bytes = std::fs::read(&self.path)?;
+        let (content, encoding) = decode(bytes);
+        if encoding != Encoding::Utf8 {
+            tracing::warn!("'{}' is not UTF-8, it was read as {encoding}", self.path);
+        }
         Ok(RecipeContent::new(content))
     }
 
+    /// Parses the metadata of the recipe using a [`MetadataCache`]
+    ///
+    /// The file is only read and parsed again if it changed since it was
+    /// cached.
+    pub fn cached_metadata(
+        &self,
+        cache: &MetadataCache,
+        parser: &cooklang::CooklangParser,
+    ) -> Result<std::sync::Arc<cooklang::Metadata>, Error> {
+        cache.get_or_parse(self, |content| content.metadata(parser))
+    }
+
     /// Finds the images of the recipe
     ///
     /// The result is cached, use the [`recipe_images`] to get a fresh result
@@ -563,6 +764,18 @@ impl RecipeEntry {
     pub fn images(&self) -> &[Image] {
         self.images.get_or_init(|| recipe_images(&self.path))
     }
+
+    /// Size and times of the file
+    ///
+    /// Entries from walking a dir already have it, others read it the first
+    /// time. None if the file can't be read.
+    pub fn info(&self) -> Option<FileInfo> {
+        *self.info.get_or_init(|| {
+            std::fs::metadata(&self.path)
+                .ok()
+                .map(|m| FileInfo::new(&m))
+        })
+    }
 }
 
 #[derive(Debug, thiserror::Error)]
@@ -575,7 +788,8 @@ impl TryFrom<DirEntry> for RecipeEntry {
         if !value.is_cooklang_file() {
             return Err(NotRecipe(value.into_path()));
         }
-        Ok(Self::new(value.into_path()))
+        let info = value.info();
+        Ok(Self::new(value.into_path()).set_info(info))
     }
 }
 
@@ -640,33 +854,7 @@ pub struct ImageIndexes {
 
 impl Image {
     fn new(recipe_name: &str, entry: DirEntry) -> Option<Self> {
-        let parts = entry.file_name().rsplitn(4, '.').collect::<Vec<_>>();
-
-        // no dots, so no extension
-        if parts.len() == 1 {
-            return None;
-        }
-
-        let name = *parts.last().unwrap();
-        let ext = *parts.first().unwrap();
-
-        if name != recipe_name || !IMAGE_EXTENSIONS.contains(&ext) {
-            return None;
-        }
-
-        let indexes = match &parts[1..parts.len() - 1] {
-            [step, section] => {
-                let section = section.parse::<u16>().ok()?;
-                let step = step.parse::<u16>().ok()?;
-                Some(ImageIndexes { section, step })
-            }
-            [step] => {
-                let step = step.parse::<u16>().ok()?;
-                Some(ImageIndexes { section: 0, step })
-            }
-            _ => None,
-        };
-
+        let indexes = image_indexes(recipe_name, entry.file_name())?;
         Some(Image {
             indexes,
             path: entry.into_path(),
@@ -674,13 +862,76 @@ impl Image {
     }
 }
 
+/// Step of an image file name of the recipe, `None` if it's not an image of
+/// the recipe
+///
+/// The file name is the recipe name, optionally the section and step or just
+/// the step, and the extension, like `Pasta.1.2.jpg`. The recipe name can
+/// have dots too.
+fn image_indexes(recipe_name: &str, file_name: &str) -> Option<Option<ImageIndexes>> {
+    let rest = file_name.strip_prefix(recipe_name)?.strip_prefix('.')?;
+    let parts = rest.split('.').collect::<Vec<_>>();
+    let (ext, indexes) = parts.split_last()?;
+    if !IMAGE_EXTENSIONS.contains(ext) {
+        return None;
+    }
+    let indexes = match indexes {
+        [] => None,
+        [step] => Some(ImageIndexes {
+            section: 0,
+            step: step.parse().ok()?,
+        }),
+        [section, step] => Some(ImageIndexes {
+            section: section.parse().ok()?,
+            step: step.parse().ok()?,
+        }),
+        _ => return None,
+    };
+    Some(indexes)
+}
+
+/// Recipe an image belongs to, the opposite of [`recipe_images`]
+///
+/// Names with dots are ambiguous, `Pasta.1.jpg` can be an image of the
+/// recipe `Pasta.1` or of the step 1 of `Pasta`, so the recipe file has to
+/// exist. Longer names are tried first.
+pub fn image_recipe(image: &Utf8Path) -> Option<Utf8PathBuf> {
+    let file_name = image.file_name()?;
+    let dir = image.parent()?;
+    let mut names = file_name
+        .match_indices('.')
+        .map(|(i, _)| &file_name[..i])
+        .collect::<Vec<_>>();
+    names.reverse();
+    names
+        .into_iter()
+        .filter(|name| image_indexes(name, file_name).is_some())
+        .find_map(|name| {
+            let in_image_dir = dir
+                .file_name()
+                .is_some_and(|d| d == IMAGES_DIR || d == name);
+            let outer = dir.parent().filter(|_| in_image_dir);
+            std::iter::once(dir)
+                .chain(outer)
+                .map(|d| d.join(format!("{name}.cook")))
+                .find(|recipe| recipe.is_file())
+        })
+}
+
+/// Start of the file name of draft recipes, like `_Pancakes.cook`
+pub const DRAFT_PREFIX: char = '_';
+
 /// Valid image extensions
 pub const IMAGE_EXTENSIONS: &[&str] = &["jpeg", "jpg", "png", "heic", "gif", "webp"];
 
-/// Get a list of the images of the recipe
+/// Valid video extensions
+pub const VIDEO_EXTENSIONS: &[&str] = &["mp4", "webm", "mov", "m4v", "ogv"];
+
+/// Get a list of the videos of the recipe
 ///
-/// See [IMAGE_EXTENSIONS].
-pub fn recipe_images(path: &Utf8Path) -> Vec<Image> {
+/// They are the files with the same name as the recipe and one of the
+/// [VIDEO_EXTENSIONS], like `Pasta.mp4` for `Pasta.cook`.
+pub fn recipe_videos(path: &Utf8Path) -> Vec<Utf8PathBuf> {
     let Some(dir) = path.parent().and_then(|dir| dir.read_dir_utf8().ok()) else {
         return vec![];
     };
@@ -689,15 +940,69 @@ pub fn recipe_images(path: &Utf8Path) -> Vec<Image> {
         return vec![];
     };
 
-    let mut images = dir
+    let mut videos = dir
         .filter_map(|e| e.ok()) // skip error
         .filter(|e| e.file_type().map(|t| t.is_file()).unwrap_or(false)) // skip non-file
-        .filter_map(|e| Image::new(recipe_name, DirEntry::new(e.path()).ok()?))
+        .map(|e| e.into_path())
+        .filter(|p| {
+            p.file_stem() == Some(recipe_name)
+                && p.extension()
+                    .is_some_and(|ext| VIDEO_EXTENSIONS.contains(&ext))
+        })
+        .collect::<Vec<_>>();
+    videos.sort_unstable();
+    videos
+}
+
+/// Name of the directory next to the recipes that can contain their images
+pub const IMAGES_DIR: &str = "images";
+
+/// Get a list of the images of the recipe
+///
+/// They are next to the recipe, in an [IMAGES_DIR] directory next to it or in
+/// a directory with the same name as the recipe. In every case they are
+/// named like the recipe, like `Pasta.jpg` or `Pasta.1.jpg` for step
+/// images. See [IMAGE_EXTENSIONS].
+pub fn recipe_images(path: &Utf8Path) -> Vec<Image> {
+    let Some(dir) = path.parent() else {
+        return vec![];
+    };
+
+    let Some(recipe_name) = path.file_stem() else {
+        return vec![];
+    };
+
+    let mut images = std::iter::once(dir.to_path_buf())
+        .chain(image_dirs(dir, recipe_name))
+        .flat_map(|d| dir_images(&d, recipe_name))
         .collect::<Vec<_>>();
     images.sort_unstable();
+    images.dedup();
     images
 }
 
+/// Directories other than the recipe's own that can contain its images
+fn image_dirs<'a>(
+    dir: &'a Utf8Path,
+    recipe_name: &'a str,
+) -> impl Iterator<Item = Utf8PathBuf> + 'a {
+    [IMAGES_DIR, recipe_name]
+        .into_iter()
+        .map(|d| dir.join(d))
+        .filter(|d| d.is_dir())
+}
+
+fn dir_images(dir: &Utf8Path, recipe_name: &str) -> Vec<Image> {
+    let Ok(entries) = dir.read_dir_utf8() else {
+        return vec![];
+    };
+    entries
+        .filter_map(|e| e.ok()) // skip error
+        .filter(|e| e.file_type().map(|t| t.is_file()).unwrap_or(false)) // skip non-file
+        .filter_map(|e| Image::new(recipe_name, DirEntry::new(e.path()).ok()?))
+        .collect()
+}
+
 #[derive(Debug, thiserror::Error)]
 pub enum RecipeImageError {
     #[error("No section {section} in recipe, referenced from {image}")]
@@ -0,0 +1,171 @@
+use std::{
+    collections::HashMap,
+    io::Write,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, RwLock,
+    },
+    time::SystemTime,
+};
+
+use camino::{Utf8Path, Utf8PathBuf};
+use cooklang::{Metadata, MetadataResult};
+use serde::{Deserialize, Serialize};
+
+use crate::{Error, RecipeContent, RecipeEntry};
+
+/// Cache of parsed recipe metadata
+///
+/// Entries are keyed by the recipe path and are valid while the modification
+/// time of the file does not change. The cache lives in memory but it can be
+/// saved to and loaded from a file with [`MetadataCache::save_file`] and
+/// [`MetadataCache::load_file`].
+///
+/// The parse options are not part of the key, so use different caches if the
+/// metadata is parsed with different options.
+#[derive(Debug, Default)]
+pub struct MetadataCache {
+    entries: RwLock<HashMap<Utf8PathBuf, CacheEntry>>,
+    changed: AtomicBool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct CacheEntry {
+    modified: SystemTime,
+    metadata: Arc<Metadata>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct CacheFile {
+    version: String,
+    entries: HashMap<Utf8PathBuf, CacheEntry>,
+}
+
+impl MetadataCache {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Gets the metadata of a recipe or parses it if not cached or outdated
+    ///
+    /// Only valid metadata is cached, so warnings from `parse` are lost when
+    /// inserted and errors are returned every time.
+    pub fn get_or_parse(
+        &self,
+        entry: &RecipeEntry,
+        parse: impl FnOnce(&RecipeContent) -> MetadataResult,
+    ) -> Result<Arc<Metadata>, Error> {
+        let modified = match entry.info().and_then(|i| i.modified) {
+            Some(modified) => modified,
+            None => std::fs::metadata(entry.path())?.modified()?,
+        };
+        if let Some(cached) = self.entries.read().unwrap().get(entry.path()) {
+            if cached.modified == modified {
+                return Ok(Arc::clone(&cached.metadata));
+            }
+        }
+
+        tracing::trace!("metadata cache miss for {}", entry.path());
+        let metadata = parse(&entry.read()?)
+            .into_output()
+            .map(Arc::new)
+            .ok_or_else(|| Error::InvalidMetadata(entry.path().to_string()))?;
+        self.entries.write().unwrap().insert(
+            entry.path().to_owned(),
+            CacheEntry {
+                modified,
+                metadata: Arc::clone(&metadata),
+            },
+        );
+        self.changed.store(true, Ordering::Relaxed);
+        Ok(metadata)
+    }
+
+    /// Removes a recipe from the cache
+    pub fn invalidate(&self, path: &Utf8Path) {
+        if self.entries.write().unwrap().remove(path).is_some() {
+            self.changed.store(true, Ordering::Relaxed);
+        }
+    }
+
+    /// Removes all the entries
+    pub fn clear(&self) {
+        self.entries.write().unwrap().clear();
+        self.changed.store(true, Ordering::Relaxed);
+    }
+
+    /// Adds the entries stored in a file
+    ///
+    /// Files written by another version of this crate are ignored. Entries
+    /// already in memory have preference.
+    pub fn load_file(&self, path: &Utf8Path) -> std::io::Result<()> {
+        let file = match std::fs::File::open(path) {
+            Ok(f) => f,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(()),
+            Err(e) => return Err(e),
+        };
+        let Ok(cache_file) = serde_json::from_reader::<_, CacheFile>(std::io::BufReader::new(file))
+        else {
+            tracing::debug!("ignoring invalid metadata cache file {path}");
+            return Ok(());
+        };
+        if cache_file.version != env!("CARGO_PKG_VERSION") {
+            tracing::debug!("ignoring metadata cache file from other version {path}");
+            return Ok(());
+        }
+        let mut entries = self.entries.write().unwrap();
+        for (recipe, entry) in cache_file.entries {
+            entries.entry(recipe).or_insert(entry);
+        }
+        Ok(())
+    }
+
+    /// Writes the cache to a file, if something changed since the last save
+    ///
+    /// If the write fails the cache is still marked as changed, so the next
+    /// save tries again.
+    pub fn save_file(&self, path: &Utf8Path) -> std::io::Result<()> {
+        // cleared before taking the entries, so a change while writing is
+        // saved the next time
+        if !self.changed.swap(false, Ordering::Relaxed) {
+            return Ok(());
+        }
+        let res = self.write_file(path);
+        if res.is_err() {
+            self.changed.store(true, Ordering::Relaxed);
+        }
+        res
+    }
+
+    fn write_file(&self, path: &Utf8Path) -> std::io::Result<()> {
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        let cache_file = CacheFile {
+            version: env!("CARGO_PKG_VERSION").to_string(),
+            entries: self.entries.read().unwrap().clone(),
+        };
+        let mut file = std::io::BufWriter::new(std::fs::File::create(path)?);
+        serde_json::to_writer(&mut file, &cache_file)?;
+        file.flush()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn save_again_after_error() {
+        let dir = tempfile::tempdir().unwrap();
+        let dir = Utf8Path::from_path(dir.path()).unwrap();
+        std::fs::write(dir.join("file"), "").unwrap();
+
+        let cache = MetadataCache::new();
+        cache.clear();
+        assert!(cache.save_file(&dir.join("file/cache.json")).is_err());
+        let path = dir.join("cache.json");
+        cache.save_file(&path).unwrap();
+        assert!(path.is_file());
+    }
+}
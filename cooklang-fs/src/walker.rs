@@ -1,8 +1,8 @@
-use std::{collections::VecDeque, fs::FileType};
+use std::{collections::VecDeque, fs::FileType, time::SystemTime};
 
 use camino::{Utf8Path, Utf8PathBuf};
 
-use crate::IMAGE_EXTENSIONS;
+use crate::{NonUtf8, IMAGE_EXTENSIONS};
 
 /// Breadth-first, sorted by file name, .cook filtered, dir walker.
 ///
@@ -20,6 +20,8 @@ pub struct Walker {
     current: std::vec::IntoIter<DirEntry>,
     config_dir: Option<String>,
     ignore: Vec<String>,
+    /// Errors of single entries, so one bad file does not hide the whole dir
+    entry_errors: Vec<WalkError>,
 }
 
 impl Walker {
@@ -34,6 +36,7 @@ impl Walker {
             current: Vec::new().into_iter(),
             config_dir: None,
             ignore: Vec::new(),
+            entry_errors: Vec::new(),
         }
     }
 
@@ -55,34 +58,64 @@ impl Walker {
     }
 
     #[tracing::instrument(level = "trace", skip(self), ret)]
-    fn process_dir(&mut self, dir: &Utf8Path) -> Result<(), std::io::Error> {
+    fn process_dir(&mut self, dir: &Utf8Path) -> Result<(), WalkError> {
         // the entire dir needs to be processed as one because entry order
         // is not guaranteed, so we need to sort
         let mut new_dirs = Vec::new();
         let mut new_entries = Vec::new();
-        for e in dir.read_dir_utf8()? {
-            let e = e?;
-            let ft = e.file_type()?;
+        let read_dir = dir.read_dir().map_err(|source| WalkError::ReadDir {
+            path: dir.to_path_buf(),
+            source,
+        })?;
+        for e in read_dir {
+            let e = match e {
+                Ok(e) => e,
+                Err(source) => {
+                    self.entry_errors.push(WalkError::Entry {
+                        path: dir.to_path_buf(),
+                        source,
+                    });
+                    continue;
+                }
+            };
+            let path = match Utf8PathBuf::try_from(e.path()) {
+                Ok(path) => path,
+                Err(err) => {
+                    self.entry_errors
+                        .push(WalkError::NonUtf8Name(NonUtf8(err.into_path_buf())));
+                    continue;
+                }
+            };
+            let ft = match e.file_type() {
+                Ok(ft) => ft,
+                Err(source) => {
+                    self.entry_errors.push(WalkError::Entry { path, source });
+                    continue;
+                }
+            };
+            let file_name = path.file_name().unwrap_or_default();
 
             // print warning for unexpected config dir
             if let Some(config_dir) = &self.config_dir {
-                if ft.is_dir()
-                    && e.file_name() == config_dir
-                    && entry_depth(e.path(), &self.base_path) > 1
+                if ft.is_dir() && file_name == config_dir && entry_depth(&path, &self.base_path) > 1
                 {
                     tracing::warn!("Config dir `{config_dir}` found not in base path. It will be ignored. You may be running the application in the wrong directory.");
                 }
             }
 
             // filter dot files/dirs and explicit filters
-            if e.file_name().starts_with('.') || self.ignore.iter().any(|d| d == e.file_name()) {
+            if file_name.starts_with('.') || self.ignore.iter().any(|d| d == file_name) {
                 continue;
             }
 
-            let entry = DirEntry {
-                path: e.into_path(),
+            let mut entry = DirEntry {
+                path,
                 file_type: ft,
+                info: None,
             };
+            if entry.is_cooklang_file() {
+                entry.info = e.metadata().ok().map(|m| FileInfo::new(&m));
+            }
 
             if entry.file_type.is_dir() {
                 let depth = entry_depth(entry.path(), &self.base_path);
@@ -108,9 +141,13 @@ impl Walker {
 }
 
 impl Iterator for Walker {
-    type Item = Result<DirEntry, std::io::Error>;
+    type Item = Result<DirEntry, WalkError>;
 
     fn next(&mut self) -> Option<Self::Item> {
+        if let Some(e) = self.entry_errors.pop() {
+            return Some(Err(e));
+        }
+
         // take from que queue
         if let Some(entry) = self.current.next() {
             return Some(Ok(entry));
@@ -121,6 +158,9 @@ impl Iterator for Walker {
             if let Err(e) = self.process_dir(&dir) {
                 return Some(Err(e));
             }
+            if let Some(e) = self.entry_errors.pop() {
+                return Some(Err(e));
+            }
             if let Some(entry) = self.current.next() {
                 return Some(Ok(entry));
             }
@@ -129,10 +169,59 @@ impl Iterator for Walker {
     }
 }
 
+/// Problem with a single file or dir found while walking
+///
+/// The walk goes on after any of them, only the entry is skipped.
+#[derive(Debug, thiserror::Error)]
+pub enum WalkError {
+    #[error("Could not read dir '{path}': {source}")]
+    ReadDir {
+        path: Utf8PathBuf,
+        source: std::io::Error,
+    },
+    #[error("Could not read '{path}': {source}")]
+    Entry {
+        path: Utf8PathBuf,
+        source: std::io::Error,
+    },
+    #[error(transparent)]
+    NonUtf8Name(NonUtf8),
+}
+
+impl WalkError {
+    /// Path of the entry, or the dir it's in if the entry can't be named
+    pub fn path(&self) -> &std::path::Path {
+        match self {
+            WalkError::ReadDir { path, .. } | WalkError::Entry { path, .. } => path.as_std_path(),
+            WalkError::NonUtf8Name(NonUtf8(path)) => path,
+        }
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct DirEntry {
     path: Utf8PathBuf,
     file_type: FileType,
+    info: Option<FileInfo>,
+}
+
+/// Size and times of a file, read once when it's found
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FileInfo {
+    pub size: u64,
+    pub modified: Option<SystemTime>,
+    /// Not available in every platform or file system
+    pub created: Option<SystemTime>,
+}
+
+impl FileInfo {
+    pub fn new(metadata: &std::fs::Metadata) -> Self {
+        Self {
+            size: metadata.len(),
+            modified: metadata.modified().ok(),
+            created: metadata.created().ok(),
+        }
+    }
 }
 
 impl DirEntry {
@@ -141,6 +230,7 @@ impl DirEntry {
         Ok(Self {
             path: path.to_path_buf(),
             file_type: metadata.file_type(),
+            info: Some(FileInfo::new(&metadata)),
         })
     }
 
@@ -159,6 +249,13 @@ impl DirEntry {
     pub fn file_type(&self) -> FileType {
         self.file_type
     }
+    /// Size and times of the file
+    ///
+    /// Only read while walking for recipes, other entries from the walk
+    /// don't have it.
+    pub fn info(&self) -> Option<FileInfo> {
+        self.info
+    }
 
     pub fn is_cooklang_file(&self) -> bool {
         self.file_type.is_file() && self.path.extension().is_some_and(|e| e == "cook")
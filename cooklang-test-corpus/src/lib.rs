@@ -0,0 +1,123 @@
+//! Recipes and golden files to test cooklang formatters
+//!
+//! The [`RECIPES`] use most of the syntax, with every extension. Formatters
+//! write each one and compare the output with a golden file with
+//! [`assert_golden`], so any change in the output shows up in the tests.
+
+use std::{env, fs, path::Path};
+
+/// A recipe of the corpus
+#[derive(Debug, Clone, Copy)]
+pub struct CorpusRecipe {
+    /// File name without the extension
+    pub name: &'static str,
+    /// Cooklang source
+    pub content: &'static str,
+}
+
+macro_rules! corpus {
+    ($($name:literal),* $(,)?) => {
+        &[$(CorpusRecipe {
+            name: $name,
+            content: include_str!(concat!("../recipes/", $name, ".cook")),
+        }),*]
+    };
+}
+
+/// Every recipe of the corpus
+pub const RECIPES: &[CorpusRecipe] = corpus![
+    "basic",
+    "metadata",
+    "modifiers",
+    "quantities",
+    "references",
+    "sections",
+    "text",
+];
+
+/// Env var to rewrite the golden files with the current output
+pub const UPDATE_ENV: &str = "UPDATE_GOLDEN";
+
+/// Compares an output with the golden file `name` in `dir`
+///
+/// With the [`UPDATE_ENV`] env var set, the golden file is written with the
+/// output instead, also if it's missing.
+///
+/// # Panics
+/// If the output is not the same as the golden file or there is no golden
+/// file.
+pub fn assert_golden(dir: impl AsRef<Path>, name: &str, actual: &str) {
+    let path = dir.as_ref().join(name);
+    let update = env::var_os(UPDATE_ENV).is_some_and(|v| !v.is_empty());
+
+    if update {
+        return write_golden(&path, actual);
+    }
+    let expected = match fs::read_to_string(&path) {
+        Ok(expected) => expected,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => panic!(
+            "Golden file {} not found\n\
+             Run with {UPDATE_ENV}=1 to create it.",
+            path.display()
+        ),
+        Err(e) => panic!("Could not read golden file {}: {e}", path.display()),
+    };
+
+    if let Some((line, exp, act)) = first_difference(&expected, actual) {
+        panic!(
+            "Output does not match {} at line {line}\n\
+             expected: {exp:?}\n  actual: {act:?}\n\
+             Run with {UPDATE_ENV}=1 to update it if the change is expected.",
+            path.display()
+        );
+    }
+}
+
+fn write_golden(path: &Path, content: &str) {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent).expect("Could not create golden files dir");
+    }
+    fs::write(path, content)
+        .unwrap_or_else(|e| panic!("Could not write golden file {}: {e}", path.display()));
+}
+
+/// Line number and contents of the first different line
+fn first_difference<'a>(
+    expected: &'a str,
+    actual: &'a str,
+) -> Option<(usize, Option<&'a str>, Option<&'a str>)> {
+    if expected == actual {
+        return None;
+    }
+    let mut exp = expected.split('\n');
+    let mut act = actual.split('\n');
+    let mut line = 1;
+    loop {
+        match (exp.next(), act.next()) {
+            (Some(e), Some(a)) if e == a => line += 1,
+            (None, None) => unreachable!("the texts are different"),
+            (e, a) => return Some((line, e, a)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn corpus() {
+        assert_eq!(RECIPES.len(), 7);
+        assert!(RECIPES.iter().all(|r| !r.content.is_empty()));
+    }
+
+    #[test]
+    fn difference() {
+        assert_eq!(first_difference("a\nb", "a\nb"), None);
+        assert_eq!(
+            first_difference("a\nb\nc", "a\nx\nc"),
+            Some((2, Some("b"), Some("x")))
+        );
+        assert_eq!(first_difference("a\n", "a"), Some((2, Some(""), None)));
+    }
+}
@@ -7,40 +7,91 @@ use cooklang::{
     model::{Item, Section, Step},
     parser::{IntermediateData, Modifiers},
     quantity::{Quantity, QuantityValue},
-    IngredientReferenceTarget, Recipe,
+    scale::ScaleOutcome,
+    IngredientReferenceTarget, Recipe, ScaledRecipe,
 };
 use regex::Regex;
 
+/// Writes the recipe as it is
+///
+/// For scaled recipes use [`print_cooklang_scaled`], or the output may not
+/// match the `servings` metadata.
 pub fn print_cooklang<D, V: QuantityValue>(
     recipe: &Recipe<D, V>,
+    writer: impl io::Write,
+) -> io::Result<()> {
+    print(recipe, &Scaling::default(), writer)
+}
+
+/// Writes a scaled recipe so it parses back to the same quantities
+///
+/// The `servings` metadata is replaced by the servings the recipe was scaled
+/// to, and the quantities that were not scaled are written as fixed (`=`) so
+/// they don't scale when the output is scaled again.
+pub fn print_cooklang_scaled(recipe: &ScaledRecipe, writer: impl io::Write) -> io::Result<()> {
+    let scaling = match recipe.scaled_data() {
+        Some(data) => Scaling {
+            servings: Some(data.target.target_servings()),
+            fixed: data
+                .ingredients
+                .iter()
+                .map(|o| matches!(o, ScaleOutcome::Fixed | ScaleOutcome::Error(_)))
+                .collect(),
+        },
+        // default scaling takes the values of the first servings
+        None => Scaling {
+            servings: recipe.metadata.servings().and_then(|s| s.first().copied()),
+            fixed: vec![],
+        },
+    };
+    print(recipe, &scaling, writer)
+}
+
+#[derive(Default)]
+struct Scaling {
+    /// Overrides the `servings` metadata
+    servings: Option<u32>,
+    /// Ingredients with a fixed quantity, by index
+    fixed: Vec<bool>,
+}
+
+fn print<D, V: QuantityValue>(
+    recipe: &Recipe<D, V>,
+    scaling: &Scaling,
     mut writer: impl io::Write,
 ) -> io::Result<()> {
     let w = &mut writer;
 
-    metadata(w, &recipe.metadata)?;
+    metadata(w, &recipe.metadata, scaling.servings)?;
     writeln!(w)?;
-    sections(w, recipe)?;
+    sections(w, recipe, scaling)?;
 
     Ok(())
 }
 
-fn metadata(w: &mut impl io::Write, metadata: &Metadata) -> io::Result<()> {
-    // TODO if the recipe has been scaled and multiple servings are defined
-    // it can lead to the recipe not parsing.
-
+fn metadata(w: &mut impl io::Write, metadata: &Metadata, servings: Option<u32>) -> io::Result<()> {
     for (key, value) in &metadata.map {
         if let Some(key) = key.as_str() {
-            if let Some(val) = value.as_str_like() {
-                writeln!(w, ">> {key}: {val}")?;
+            match servings {
+                Some(servings) if key == "servings" => writeln!(w, ">> {key}: {servings}")?,
+                _ => {
+                    if let Some(val) = value.as_str_like() {
+                        writeln!(w, ">> {key}: {val}")?;
+                    }
+                }
             }
         }
     }
     Ok(())
 }
 
-fn sections<D, V: QuantityValue>(w: &mut impl io::Write, recipe: &Recipe<D, V>) -> io::Result<()> {
+fn sections<D, V: QuantityValue>(
+    w: &mut impl io::Write,
+    recipe: &Recipe<D, V>,
+    scaling: &Scaling,
+) -> io::Result<()> {
     for (index, section) in recipe.sections.iter().enumerate() {
-        w_section(w, section, recipe, index)?;
+        w_section(w, section, recipe, scaling, index)?;
     }
     Ok(())
 }
@@ -49,6 +100,7 @@ fn w_section<D, V: QuantityValue>(
     w: &mut impl io::Write,
     section: &Section,
     recipe: &Recipe<D, V>,
+    scaling: &Scaling,
     index: usize,
 ) -> io::Result<()> {
     if let Some(name) = &section.name {
@@ -58,7 +110,7 @@ fn w_section<D, V: QuantityValue>(
     }
     for content in &section.content {
         match content {
-            cooklang::Content::Step(step) => w_step(w, step, recipe)?,
+            cooklang::Content::Step(step) => w_step(w, step, recipe, scaling)?,
             cooklang::Content::Text(text) => w_text_block(w, text)?,
         }
         writeln!(w)?;
@@ -70,6 +122,7 @@ fn w_step<D, V: QuantityValue>(
     w: &mut impl io::Write,
     step: &Step,
     recipe: &Recipe<D, V>,
+    scaling: &Scaling,
 ) -> io::Result<()> {
     let mut step_str = String::new();
     for item in &step.items {
@@ -90,6 +143,7 @@ fn w_step<D, V: QuantityValue>(
                     name: Some(&igr.name),
                     alias: igr.alias.as_deref(),
                     quantity: igr.quantity.as_ref(),
+                    fixed: scaling.fixed.get(index).copied().unwrap_or(false),
                     note: igr.note.as_deref(),
                 }
                 .format(&mut step_str)
@@ -103,6 +157,7 @@ fn w_step<D, V: QuantityValue>(
                     name: Some(&cw.name),
                     alias: cw.alias.as_deref(),
                     quantity: cw.quantity.clone().map(|v| Quantity::new(v, None)).as_ref(),
+                    fixed: false,
                     note: None,
                 }
                 .format(&mut step_str)
@@ -116,6 +171,7 @@ fn w_step<D, V: QuantityValue>(
                     name: t.name.as_deref(),
                     alias: None,
                     quantity: t.quantity.as_ref(),
+                    fixed: false,
                     note: None,
                 }
                 .format(&mut step_str)
@@ -188,6 +244,7 @@ struct ComponentFormatter<'a, V: QuantityValue> {
     name: Option<&'a str>,
     alias: Option<&'a str>,
     quantity: Option<&'a Quantity<V>>,
+    fixed: bool,
     note: Option<&'a str>,
 }
 
@@ -244,6 +301,9 @@ impl<'a, V: QuantityValue> ComponentFormatter<'a, V> {
         }
         if let Some(q) = self.quantity {
             w.push('{');
+            if self.fixed {
+                w.push('=');
+            }
             w.push_str(&q.value().to_string());
             if let Some(unit) = q.unit() {
                 write!(w, "%{}", unit).unwrap();
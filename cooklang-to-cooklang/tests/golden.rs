@@ -0,0 +1,28 @@
+//! Cooklang of every recipe of the corpus, as is and scaled, compared with
+//! `tests/golden`
+
+use cooklang::CooklangParser;
+use cooklang_test_corpus::{assert_golden, RECIPES};
+
+#[test]
+fn golden() {
+    let parser = CooklangParser::extended();
+    let dir = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/golden");
+    for recipe in RECIPES {
+        let parsed = parser
+            .parse(recipe.content)
+            .into_output()
+            .expect("corpus recipe with errors");
+
+        let mut out = Vec::new();
+        cooklang_to_cooklang::print_cooklang(&parsed, &mut out).unwrap();
+        let out = String::from_utf8(out).unwrap();
+        assert_golden(dir, &format!("{}.cook", recipe.name), &out);
+
+        let scaled = parsed.scale(3, parser.converter());
+        let mut out = Vec::new();
+        cooklang_to_cooklang::print_cooklang_scaled(&scaled, &mut out).unwrap();
+        let out = String::from_utf8(out).unwrap();
+        assert_golden(dir, &format!("{}.x3.cook", recipe.name), &out);
+    }
+}
@@ -8,11 +8,11 @@ use std::{collections::HashMap, io, time::Duration};
 use cooklang::{
     convert::Converter,
     ingredient_list::GroupedIngredient,
-    metadata::CooklangValueExt,
+    metadata::{CooklangValueExt, Metadata, NameAndUrl},
     model::{Ingredient, IngredientReferenceTarget, Item},
     quantity::Quantity,
     scale::ScaleOutcome,
-    ScaledRecipe, Section, Step,
+    Modifiers, ScaledRecipe, Section, Step,
 };
 use std::fmt::Write;
 use tabular::{Row, Table};
@@ -24,23 +24,82 @@ pub use style::{set_styles, CookStyles};
 
 pub type Result<T = ()> = std::result::Result<T, io::Error>;
 
+/// Options for [`print_human_with_options`]
+#[derive(Default)]
+pub struct Options<'a> {
+    /// Gets the URL of a referenced recipe from its name
+    ///
+    /// When it returns one, the name of the ingredient is written as a
+    /// terminal hyperlink (OSC 8) to it.
+    pub recipe_link: Option<&'a dyn Fn(&str) -> Option<String>>,
+    /// Print only a section or a step of the recipe
+    ///
+    /// Instead of the metadata and the full ingredient list, only the
+    /// ingredients and cookware used in that part are listed.
+    pub part: Option<Part>,
+    /// Writes the times of the recipe, given in minutes
+    ///
+    /// The default is like `1h 30m`.
+    pub duration: Option<&'a dyn Fn(u32) -> String>,
+}
+
+/// Part of a recipe to print, see [`Options::part`]
+#[derive(Debug, Clone, Copy)]
+pub struct Part {
+    /// Index of the section
+    pub section: usize,
+    /// Number of a step in the section, every step if none
+    pub step: Option<u32>,
+}
+
 pub fn print_human(
+    recipe: &ScaledRecipe,
+    name: &str,
+    converter: &Converter,
+    writer: impl std::io::Write,
+) -> Result {
+    print_human_with_options(recipe, name, converter, writer, &Options::default())
+}
+
+pub fn print_human_with_options(
     recipe: &ScaledRecipe,
     name: &str,
     converter: &Converter,
     mut writer: impl std::io::Write,
+    opts: &Options,
 ) -> Result {
     let w = &mut writer;
 
     header(w, recipe, name)?;
-    metadata(w, recipe, converter)?;
-    ingredients(w, recipe, converter)?;
+    if let Some(part) = opts.part {
+        part_ingredients(w, recipe, part, opts)?;
+        part_cookware(w, recipe, part)?;
+        steps(w, recipe, opts)?;
+        return Ok(());
+    }
+    metadata(w, recipe, converter, opts)?;
+    ingredients(w, recipe, converter, opts)?;
     cookware(w, recipe)?;
-    steps(w, recipe)?;
+    steps(w, recipe, opts)?;
 
     Ok(())
 }
 
+/// Name of the ingredient, as a hyperlink if it's a recipe with a link
+fn ingredient_name(igr: &Ingredient, text: String, opts: &Options) -> String {
+    if !igr.modifiers().contains(Modifiers::RECIPE) {
+        return text;
+    }
+    match opts.recipe_link.and_then(|link| link(&igr.name)) {
+        Some(url) => hyperlink(&text, &url),
+        None => text,
+    }
+}
+
+fn hyperlink(text: &str, url: &str) -> String {
+    format!("\x1b]8;;{url}\x1b\\{text}\x1b]8;;\x1b\\")
+}
+
 fn header(w: &mut impl io::Write, recipe: &ScaledRecipe, name: &str) -> Result {
     let title_text = format!(
         " {}{} ",
@@ -84,7 +143,12 @@ fn tag_color(tag: &str) -> yansi::Color {
     }
 }
 
-fn metadata(w: &mut impl io::Write, recipe: &ScaledRecipe, converter: &Converter) -> Result {
+fn metadata(
+    w: &mut impl io::Write,
+    recipe: &ScaledRecipe,
+    converter: &Converter,
+    opts: &Options,
+) -> Result {
     if let Some(desc) = recipe.metadata.description() {
         print_wrapped_with_options(w, desc, |o| {
             o.initial_indent("\u{2502} ").subsequent_indent("\u{2502}")
@@ -94,20 +158,22 @@ fn metadata(w: &mut impl io::Write, recipe: &ScaledRecipe, converter: &Converter
 
     let mut meta_fmt =
         |name: &str, value: &str| writeln!(w, "{}: {}", name.paint(styles().meta_key), value);
-    if let Some(author) = recipe.metadata.author() {
-        let text = author.name().or(author.url()).unwrap_or("-");
-        meta_fmt("author", text)?;
-    }
-    if let Some(source) = recipe.metadata.source() {
-        let text = source.name().or(source.url()).unwrap_or("-");
-        meta_fmt("source", text)?;
+    for key in ["author", "source"] {
+        let entries = name_and_url_list(&recipe.metadata, key);
+        if entries.is_empty() {
+            continue;
+        }
+        let text = entries
+            .iter()
+            .map(|e| e.name().or(e.url()).unwrap_or("-"))
+            .collect::<Vec<_>>()
+            .join(", ");
+        meta_fmt(key, &text)?;
     }
     if let Some(time) = recipe.metadata.time(converter) {
-        let time_fmt = |t: u32| {
-            format!(
-                "{}",
-                humantime::format_duration(Duration::from_secs(t as u64 * 60))
-            )
+        let time_fmt = |t: u32| match opts.duration {
+            Some(duration) => duration(t),
+            None => humantime::format_duration(Duration::from_secs(t as u64 * 60)).to_string(),
         };
         match time {
             cooklang::metadata::RecipeTime::Total(t) => meta_fmt("time", &time_fmt(t))?,
@@ -174,7 +240,29 @@ fn metadata(w: &mut impl io::Write, recipe: &ScaledRecipe, converter: &Converter
     Ok(())
 }
 
-fn ingredients(w: &mut impl io::Write, recipe: &ScaledRecipe, converter: &Converter) -> Result {
+/// Entries of a name and URL key like `author` or `source`
+///
+/// The value can be a single entry or a list of them.
+fn name_and_url_list(metadata: &Metadata, key: &str) -> Vec<NameAndUrl> {
+    // reuse the parser handling of a single entry
+    let parse = |value: &serde_yaml::Value| {
+        let mut single = Metadata::default();
+        single.map.insert("author".into(), value.clone());
+        single.author()
+    };
+    match metadata.get(key) {
+        Some(serde_yaml::Value::Sequence(entries)) => entries.iter().filter_map(parse).collect(),
+        Some(value) => parse(value).into_iter().collect(),
+        None => vec![],
+    }
+}
+
+fn ingredients(
+    w: &mut impl io::Write,
+    recipe: &ScaledRecipe,
+    converter: &Converter,
+    opts: &Options,
+) -> Result {
     if recipe.ingredients.is_empty() {
         return Ok(());
     }
@@ -211,7 +299,8 @@ fn ingredients(w: &mut impl io::Write, recipe: &ScaledRecipe, converter: &Conver
                 ScaleOutcome::Scaled | ScaleOutcome::NoQuantity => (yansi::Style::new(), ""),
             })
             .unwrap_or_default();
-        let mut row = Row::new().with_cell(igr.display_name());
+        let mut row =
+            Row::new().with_ansi_cell(ingredient_name(igr, igr.display_name().into(), opts));
         if igr.modifiers().is_optional() {
             row.add_ansi_cell("(optional)".paint(styles().opt_marker));
         } else {
@@ -248,6 +337,108 @@ fn ingredients(w: &mut impl io::Write, recipe: &ScaledRecipe, converter: &Conver
     writeln!(w)
 }
 
+/// Ingredients used in a part of the recipe, with the quantity of each use
+///
+/// References are listed too, as the part can't be made without them.
+fn part_ingredients(
+    w: &mut impl io::Write,
+    recipe: &ScaledRecipe,
+    part: Part,
+    opts: &Options,
+) -> Result {
+    let Some(section) = recipe.sections.get(part.section) else {
+        return Ok(());
+    };
+    // ingredient, intermediate reference and quantities
+    let mut groups: Vec<(&Ingredient, Option<String>, Vec<String>)> = Vec::new();
+    for item in part_items(recipe, part) {
+        let &Item::Ingredient { index } = item else {
+            continue;
+        };
+        let igr = &recipe.ingredients[index];
+        if igr.modifiers().contains(Modifiers::HIDDEN) {
+            continue;
+        }
+        let source = inter_ref_text(igr, section);
+        let pos = match groups
+            .iter()
+            .position(|(g, s, _)| g.name == igr.name && *s == source)
+        {
+            Some(pos) => pos,
+            None => {
+                groups.push((igr, source, Vec::new()));
+                groups.len() - 1
+            }
+        };
+        if let Some(q) = &igr.quantity {
+            groups[pos].2.push(quantity_fmt(q));
+        }
+    }
+    if groups.is_empty() {
+        return Ok(());
+    }
+    writeln!(w, "Ingredients:")?;
+    let mut table = Table::new("  {:<} {:<}    {:<} {:<}");
+    for (igr, source, quantities) in groups {
+        let mut row =
+            Row::new().with_ansi_cell(ingredient_name(igr, igr.display_name().into(), opts));
+        match source {
+            Some(source) => {
+                row.add_ansi_cell(format!("from {source}").paint(styles().intermediate_ref))
+            }
+            None if igr.modifiers().is_optional() => {
+                row.add_ansi_cell("(optional)".paint(styles().opt_marker))
+            }
+            None => row.add_cell(""),
+        };
+        row.add_cell(quantities.join(", "));
+        if let Some(note) = &igr.note {
+            row.add_cell(format!("({note})"));
+        } else {
+            row.add_cell("");
+        }
+        table.add_row(row);
+    }
+    write!(w, "{table}")?;
+    writeln!(w)
+}
+
+fn part_cookware(w: &mut impl io::Write, recipe: &ScaledRecipe, part: Part) -> Result {
+    let mut names = Vec::new();
+    for item in part_items(recipe, part) {
+        let &Item::Cookware { index } = item else {
+            continue;
+        };
+        let cw = &recipe.cookware[index];
+        if !cw.modifiers().contains(Modifiers::HIDDEN) && !names.contains(&cw.name.as_str()) {
+            names.push(cw.name.as_str());
+        }
+    }
+    if names.is_empty() {
+        return Ok(());
+    }
+    writeln!(w, "Cookware:")?;
+    for name in names {
+        writeln!(w, "  {name}")?;
+    }
+    writeln!(w)
+}
+
+/// Items of the steps in a part of the recipe
+fn part_items(recipe: &ScaledRecipe, part: Part) -> impl Iterator<Item = &Item> {
+    recipe
+        .sections
+        .get(part.section)
+        .into_iter()
+        .flat_map(|section| &section.content)
+        .filter_map(|content| match content {
+            cooklang::Content::Step(step) => Some(step),
+            cooklang::Content::Text(_) => None,
+        })
+        .filter(move |step| part.step.is_none() || part.step == Some(step.number))
+        .flat_map(|step| &step.items)
+}
+
 fn cookware(w: &mut impl io::Write, recipe: &ScaledRecipe) -> Result {
     if recipe.cookware.is_empty() {
         return Ok(());
@@ -291,9 +482,12 @@ fn cookware(w: &mut impl io::Write, recipe: &ScaledRecipe) -> Result {
     Ok(())
 }
 
-fn steps(w: &mut impl io::Write, recipe: &ScaledRecipe) -> Result {
+fn steps(w: &mut impl io::Write, recipe: &ScaledRecipe, opts: &Options) -> Result {
     writeln!(w, "Steps:")?;
     for (section_index, section) in recipe.sections.iter().enumerate() {
+        if opts.part.is_some_and(|p| p.section != section_index) {
+            continue;
+        }
         if recipe.sections.len() > 1 {
             writeln!(
                 w,
@@ -308,9 +502,14 @@ fn steps(w: &mut impl io::Write, recipe: &ScaledRecipe) -> Result {
         }
 
         for content in &section.content {
+            if let Some(Part { step: Some(n), .. }) = opts.part {
+                if !matches!(content, cooklang::Content::Step(step) if step.number == n) {
+                    continue;
+                }
+            }
             match content {
                 cooklang::Content::Step(step) => {
-                    let (step_text, step_ingredients) = step_text(recipe, section, step);
+                    let (step_text, step_ingredients) = step_text(recipe, section, step, opts);
                     let step_text = format!("{:>2}. {}", step.number, step_text.trim());
                     print_wrapped_with_options(w, &step_text, |o| o.subsequent_indent("    "))?;
                     print_wrapped_with_options(w, &step_ingredients, |o| {
@@ -336,7 +535,12 @@ fn steps(w: &mut impl io::Write, recipe: &ScaledRecipe) -> Result {
     Ok(())
 }
 
-fn step_text(recipe: &ScaledRecipe, section: &Section, step: &Step) -> (String, String) {
+fn step_text(
+    recipe: &ScaledRecipe,
+    section: &Section,
+    step: &Step,
+    opts: &Options,
+) -> (String, String) {
     let mut step_text = String::new();
 
     let step_igrs_dedup = build_step_igrs_dedup(step, recipe);
@@ -350,12 +554,8 @@ fn step_text(recipe: &ScaledRecipe, section: &Section, step: &Step) -> (String,
             Item::Text { value } => step_text += value,
             &Item::Ingredient { index } => {
                 let igr = &recipe.ingredients[index];
-                write!(
-                    &mut step_text,
-                    "{}",
-                    igr.display_name().paint(styles().ingredient)
-                )
-                .unwrap();
+                let name = igr.display_name().paint(styles().ingredient).to_string();
+                step_text += &ingredient_name(igr, name, opts);
                 let pos = write_igr_count(&mut step_text, &step_igrs_dedup, index, &igr.name);
                 if step_igrs_dedup[igr.name.as_str()].contains(&index) {
                     step_igrs_line.push((igr, pos));
@@ -441,16 +641,36 @@ fn step_text(recipe: &ScaledRecipe, section: &Section, step: &Step) -> (String,
     (step_text, igrs_text)
 }
 
-fn inter_ref_text(igr: &Ingredient, section: &Section) -> Option<String> {
-    match igr.relation.references_to() {
-        Some((target_sect, IngredientReferenceTarget::Section)) => {
-            Some(format!("section {}", target_sect + 1))
-        }
-        Some((target_step, IngredientReferenceTarget::Step)) => {
-            let step = &section.content[target_step].unwrap_step();
-            Some(format!("step {}", step.number))
+/// Where the intermediate preparation of an ingredient comes from
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum IntermediateRef {
+    /// Number of a step in the same section
+    Step(u32),
+    /// Number of a section, starting at 1
+    Section(usize),
+}
+
+/// The step or section an ingredient references, if it's an intermediate
+/// reference
+///
+/// `section` is the section where the ingredient is used.
+pub fn intermediate_ref(igr: &Ingredient, section: &Section) -> Option<IntermediateRef> {
+    match igr.relation.references_to()? {
+        (target_sect, IngredientReferenceTarget::Section) => {
+            Some(IntermediateRef::Section(target_sect + 1))
         }
-        _ => None,
+        (target_step, IngredientReferenceTarget::Step) => match &section.content[target_step] {
+            cooklang::Content::Step(step) => Some(IntermediateRef::Step(step.number)),
+            cooklang::Content::Text(_) => None,
+        },
+        (_, IngredientReferenceTarget::Ingredient) => None,
+    }
+}
+
+fn inter_ref_text(igr: &Ingredient, section: &Section) -> Option<String> {
+    match intermediate_ref(igr, section)? {
+        IntermediateRef::Step(number) => Some(format!("step {number}")),
+        IntermediateRef::Section(number) => Some(format!("section {number}")),
     }
 }
 
@@ -0,0 +1,28 @@
+//! Human output, without colors, of every recipe of the corpus, compared with
+//! `tests/golden`
+
+use cooklang::CooklangParser;
+use cooklang_test_corpus::{assert_golden, RECIPES};
+
+#[test]
+fn golden() {
+    yansi::disable();
+    let parser = CooklangParser::extended();
+    let dir = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/golden");
+    for recipe in RECIPES {
+        let parsed = parser
+            .parse(recipe.content)
+            .into_output()
+            .expect("corpus recipe with errors");
+        let mut out = Vec::new();
+        cooklang_to_human::print_human(
+            &parsed.default_scale(),
+            recipe.name,
+            parser.converter(),
+            &mut out,
+        )
+        .unwrap();
+        let out = String::from_utf8(out).unwrap();
+        assert_golden(dir, &format!("{}.txt", recipe.name), &out);
+    }
+}
@@ -4,8 +4,11 @@ use std::{fmt::Write, io};
 
 use cooklang::{
     convert::Converter,
-    metadata::Metadata,
-    model::{Item, Section, Step},
+    metadata::{Metadata, NameAndUrl},
+    model::{Ingredient, Item, Section, Step},
+    parser::Modifiers,
+    quantity::Value,
+    scale::ScaleOutcome,
     ScaledRecipe,
 };
 use serde::{Deserialize, Serialize};
@@ -57,6 +60,14 @@ pub struct Options {
     /// 1\. Step.
     /// ```
     pub escape_step_numbers: bool,
+    /// Write the ingredients, cookware and steps as task lists
+    ///
+    /// Each item is a GitHub-flavored markdown task, so the recipe becomes a
+    /// checklist when pasted in issues or notes apps:
+    /// ```md
+    /// - [ ] *200 g* flour
+    /// ```
+    pub task_lists: bool,
     /// Display amounts in italics
     ///
     /// This will affect the ingredients list, cookware list and inline
@@ -71,6 +82,12 @@ pub struct Options {
     pub heading: Headings,
     /// Text to write when an ingredient or cookware item is optional
     pub optional_marker: String,
+    /// Text to write after a quantity that has a fixed value when scaling
+    pub fixed_marker: String,
+    /// Text to write after a quantity that could not be scaled
+    pub scale_error_marker: String,
+    /// How to write ingredients that reference other recipes
+    pub recipe_links: RecipeLinks,
 }
 
 impl Default for Options {
@@ -79,10 +96,14 @@ impl Default for Options {
             tags: true,
             description: DescriptionStyle::Blockquote,
             escape_step_numbers: false,
+            task_lists: false,
             italic_amounts: true,
             front_matter_name: FrontMatterName::default(),
             heading: Headings::default(),
             optional_marker: "(optional)".to_string(),
+            fixed_marker: "(fixed)".to_string(),
+            scale_error_marker: "(error scaling)".to_string(),
+            recipe_links: RecipeLinks::default(),
         }
     }
 }
@@ -109,6 +130,34 @@ impl From<bool> for DescriptionStyle {
     }
 }
 
+/// Style of the recipe references
+///
+/// The link target is the referenced recipe path, as written in the recipe,
+/// relative to the recipe.
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum RecipeLinks {
+    /// Just the name, no link
+    #[default]
+    Text,
+    /// Wiki links like `[[sauces/Tomato sauce|Tomato sauce]]`, as used by
+    /// Obsidian or Logseq
+    Wiki,
+    /// Regular markdown links like `[Tomato sauce](sauces/Tomato%20sauce.md)`
+    Markdown {
+        /// Added before the path
+        #[serde(default)]
+        prefix: String,
+        /// Extension of the linked files, without the dot
+        #[serde(default = "default_link_extension")]
+        extension: String,
+    },
+}
+
+fn default_link_extension() -> String {
+    "md".into()
+}
+
 #[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
 #[serde(transparent)]
 pub struct FrontMatterName(pub Option<String>);
@@ -258,6 +307,17 @@ fn frontmatter(
 
     let mut map = metadata.map.clone();
 
+    // write author and source in their structured form
+    for key in ["author", "source"] {
+        let entries = name_and_url_list(metadata, key);
+        let value = match entries.as_slice() {
+            [] => continue,
+            [single] => serde_yaml::to_value(single)?,
+            many => serde_yaml::to_value(many)?,
+        };
+        map.insert(key.into(), value);
+    }
+
     if let Some(name_key) = &opts.front_matter_name.0 {
         // add name, will be overrided if other given
         map.insert(name_key.as_str().into(), name.into());
@@ -270,6 +330,23 @@ fn frontmatter(
     Ok(())
 }
 
+/// Entries of a name and URL key like `author` or `source`
+///
+/// The value can be a single entry or a list of them.
+fn name_and_url_list(metadata: &Metadata, key: &str) -> Vec<NameAndUrl> {
+    // reuse the parser handling of a single entry
+    let parse = |value: &serde_yaml::Value| {
+        let mut single = Metadata::default();
+        single.map.insert("author".into(), value.clone());
+        single.author()
+    };
+    match metadata.get(key) {
+        Some(serde_yaml::Value::Sequence(entries)) => entries.iter().filter_map(parse).collect(),
+        Some(value) => parse(value).into_iter().collect(),
+        None => vec![],
+    }
+}
+
 fn ingredients(
     w: &mut impl io::Write,
     recipe: &ScaledRecipe,
@@ -289,16 +366,21 @@ fn ingredients(
             continue;
         }
 
-        write!(w, "- ")?;
+        write!(w, "{}", list_marker(opts))?;
         if !entry.quantity.is_empty() {
             if opts.italic_amounts {
                 write!(w, "*{}* ", entry.quantity)?;
             } else {
                 write!(w, "{} ", entry.quantity)?;
             }
+            match entry.outcome {
+                Some(ScaleOutcome::Fixed) => write!(w, "{} ", opts.fixed_marker)?,
+                Some(ScaleOutcome::Error(_)) => write!(w, "{} ", opts.scale_error_marker)?,
+                _ => {}
+            }
         }
 
-        write!(w, "{}", ingredient.display_name())?;
+        write!(w, "{}", ingredient_name(ingredient, opts))?;
 
         if ingredient.modifiers().is_optional() {
             write!(w, " {}", opts.optional_marker)?;
@@ -322,7 +404,7 @@ fn cookware(w: &mut impl io::Write, recipe: &ScaledRecipe, opts: &Options) -> Re
     writeln!(w, "## {}\n", opts.heading.cookware)?;
     for item in recipe.group_cookware() {
         let cw = item.cookware;
-        write!(w, "- ")?;
+        write!(w, "{}", list_marker(opts))?;
         if !item.amount.is_empty() {
             if opts.italic_amounts {
                 write!(w, "*{} * ", item.amount)?;
@@ -380,8 +462,14 @@ fn w_section(
 }
 
 fn w_step(w: &mut impl io::Write, step: &Step, recipe: &ScaledRecipe, opts: &Options) -> Result {
-    let mut step_str = step.number.to_string();
-    if opts.escape_step_numbers {
+    let mut step_str = if opts.task_lists {
+        list_marker(opts).to_string()
+    } else {
+        String::new()
+    };
+    step_str.push_str(&step.number.to_string());
+    // inside a task the number is just text
+    if opts.escape_step_numbers && !opts.task_lists {
         step_str.push_str("\\. ")
     } else {
         step_str.push_str(". ")
@@ -392,7 +480,7 @@ fn w_step(w: &mut impl io::Write, step: &Step, recipe: &ScaledRecipe, opts: &Opt
             Item::Text { value } => step_str.push_str(value),
             &Item::Ingredient { index } => {
                 let igr = &recipe.ingredients[index];
-                step_str.push_str(igr.display_name().as_ref());
+                step_str.push_str(&ingredient_name(igr, opts));
             }
             &Item::Cookware { index } => {
                 let cw = &recipe.cookware[index];
@@ -417,10 +505,86 @@ fn w_step(w: &mut impl io::Write, step: &Step, recipe: &ScaledRecipe, opts: &Opt
             }
         }
     }
-    print_wrapped(w, &step_str)?;
+    // continuation lines of a task are indented to stay in the item
+    let indent = if opts.task_lists { "  " } else { "" };
+    if opts.recipe_links == RecipeLinks::Wiki {
+        print_wrapped_with_options(w, &step_str, |o| {
+            o.word_separator(textwrap::WordSeparator::Custom(wiki_link_word_separator))
+                .break_words(false)
+                .subsequent_indent(indent)
+        })?;
+    } else {
+        print_wrapped_with_options(w, &step_str, |o| o.subsequent_indent(indent))?;
+    }
     Ok(())
 }
 
+fn list_marker(opts: &Options) -> &'static str {
+    if opts.task_lists {
+        "- [ ] "
+    } else {
+        "- "
+    }
+}
+
+/// Name of the ingredient, as a link if it's a recipe reference
+fn ingredient_name(igr: &Ingredient<Value>, opts: &Options) -> String {
+    let name = igr.display_name();
+    if !igr.modifiers().contains(Modifiers::RECIPE) {
+        return name.into_owned();
+    }
+    let target = igr.name.replace('\\', "/");
+    let target = target.trim_start_matches("./");
+    match &opts.recipe_links {
+        RecipeLinks::Text => name.into_owned(),
+        RecipeLinks::Wiki if target == name => format!("[[{target}]]"),
+        RecipeLinks::Wiki => format!("[[{target}|{name}]]"),
+        RecipeLinks::Markdown { prefix, extension } => {
+            let mut url = format!("{prefix}{target}");
+            if !extension.is_empty() {
+                url.push('.');
+                url.push_str(extension);
+            }
+            let url = url
+                .replace(' ', "%20")
+                .replace('(', "%28")
+                .replace(')', "%29");
+            format!("[{name}]({url})")
+        }
+    }
+}
+
+// Wiki links can't be split in two lines
+fn wiki_link_word_separator<'a>(
+    line: &'a str,
+) -> Box<dyn Iterator<Item = textwrap::core::Word<'a>> + 'a> {
+    use textwrap::core::Word;
+
+    let mut words = vec![];
+    let mut rest = 0;
+    let default_separator = textwrap::WordSeparator::new();
+
+    while let Some(start) = line[rest..].find("[[").map(|i| i + rest) {
+        let Some(end) = line[start..].find("]]").map(|i| i + start + 2) else {
+            break;
+        };
+        // the link may have punctuation just after it, keep it in the same word
+        let end = line[end..]
+            .find(char::is_whitespace)
+            .map_or(line.len(), |i| i + end);
+        if rest < start {
+            words.extend(default_separator.find_words(&line[rest..start]));
+        }
+        let whitespace = line[end..].len() - line[end..].trim_start().len();
+        words.push(Word::from(&line[start..end + whitespace]));
+        rest = end + whitespace;
+    }
+    if rest < line.len() {
+        words.extend(default_separator.find_words(&line[rest..]));
+    }
+    Box::new(words.into_iter())
+}
+
 fn print_wrapped(w: &mut impl io::Write, text: &str) -> Result {
     print_wrapped_with_options(w, text, |o| o)
 }
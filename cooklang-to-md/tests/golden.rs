@@ -0,0 +1,72 @@
+//! Markdown of every recipe of the corpus, compared with `tests/golden`
+
+use cooklang::CooklangParser;
+use cooklang_test_corpus::{assert_golden, RECIPES};
+use cooklang_to_md::{Options, RecipeLinks};
+
+#[test]
+fn golden() {
+    let parser = CooklangParser::extended();
+    let dir = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/golden");
+    for recipe in RECIPES {
+        let parsed = parser
+            .parse(recipe.content)
+            .into_output()
+            .expect("corpus recipe with errors");
+        let mut out = Vec::new();
+        cooklang_to_md::print_md(
+            &parsed.default_scale(),
+            recipe.name,
+            parser.converter(),
+            &mut out,
+        )
+        .unwrap();
+        let out = String::from_utf8(out).unwrap();
+        assert_golden(dir, &format!("{}.md", recipe.name), &out);
+    }
+}
+
+/// Recipe references with every link style
+#[test]
+fn recipe_links() {
+    let parser = CooklangParser::extended();
+    let dir = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/golden");
+    let recipe = RECIPES.iter().find(|r| r.name == "references").unwrap();
+    let parsed = parser
+        .parse(recipe.content)
+        .into_output()
+        .expect("corpus recipe with errors")
+        .default_scale();
+
+    let styles = [
+        ("text", RecipeLinks::Text, "Tomato sauce"),
+        ("wiki", RecipeLinks::Wiki, "[[sauces/Tomato sauce"),
+        (
+            "links",
+            RecipeLinks::Markdown {
+                prefix: "/recipes/".into(),
+                extension: "md".into(),
+            },
+            "(/recipes/sauces/Tomato%20sauce.md)",
+        ),
+    ];
+    for (style, recipe_links, expected) in styles {
+        let mut opts = Options::default();
+        opts.recipe_links = recipe_links;
+        let mut out = Vec::new();
+        cooklang_to_md::print_md_with_options(
+            &parsed,
+            recipe.name,
+            &opts,
+            parser.converter(),
+            &mut out,
+        )
+        .unwrap();
+        let out = String::from_utf8(out).unwrap();
+        assert!(out.contains(expected), "no {style} reference in:\n{out}");
+        if style == "text" {
+            assert!(!out.contains("[[") && !out.contains("]("));
+        }
+        assert_golden(dir, &format!("{}.{style}.md", recipe.name), &out);
+    }
+}
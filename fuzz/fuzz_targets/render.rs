@@ -0,0 +1,31 @@
+//! Parses arbitrary input and renders it with every output format
+//!
+//! Nothing should panic, invalid recipes must be reported as errors.
+
+#![no_main]
+
+use std::sync::OnceLock;
+
+use cooklang::CooklangParser;
+use libfuzzer_sys::fuzz_target;
+
+fn parser() -> &'static CooklangParser {
+    static PARSER: OnceLock<CooklangParser> = OnceLock::new();
+    PARSER.get_or_init(CooklangParser::extended)
+}
+
+fuzz_target!(|input: &str| {
+    let parser = parser();
+    let Some(recipe) = parser.parse(input).into_output() else {
+        return;
+    };
+    let converter = parser.converter();
+
+    let _ = cooklang_to_cooklang::print_cooklang(&recipe, std::io::sink());
+
+    for recipe in [recipe.clone().default_scale(), recipe.scale(3, converter)] {
+        let _ = cooklang_to_human::print_human(&recipe, "fuzz", converter, std::io::sink());
+        let _ = cooklang_to_md::print_md(&recipe, "fuzz", converter, std::io::sink());
+        let _ = cooklang_to_cooklang::print_cooklang_scaled(&recipe, std::io::sink());
+    }
+});
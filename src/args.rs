@@ -1,10 +1,10 @@
 use camino::Utf8PathBuf;
-use clap::{Args, Parser, Subcommand};
+use clap::{Args, Parser, Subcommand, ValueEnum};
 use cooklang::Extensions;
 
 use crate::cmd::{
-    collection, config, convert, edit, generate_completions, list, new, recipe, shopping_list,
-    units,
+    book, check, collection, config, convert, doctor, edit, generate_completions, grep, list, new,
+    recipe, reveal, shopping_list, units,
 };
 
 #[cfg(feature = "serve")]
@@ -33,12 +33,19 @@ pub enum Command {
     /// List all the recipes
     #[command(visible_alias = "ls")]
     List(list::ListArgs),
+    /// Search recipes by ingredients, cookware, text or metadata
+    Grep(grep::GrepArgs),
     #[cfg(feature = "serve")]
     /// Recipes web server
     Serve(serve::ServeArgs),
+    #[cfg(feature = "serve")]
+    /// Export the web UI as a static site
+    ExportSite(serve::export_site::ExportSiteArgs),
     /// Creates a shopping list from a given list of recipes
     #[command(visible_alias = "sl")]
     ShoppingList(shopping_list::ShoppingListArgs),
+    /// Generate a markdown cookbook with several recipes
+    Book(book::BookArgs),
     /// List loaded units
     Units(units::UnitsArgs),
     /// Convert values to other units
@@ -48,12 +55,18 @@ pub enum Command {
     Config(config::ConfigArgs),
     /// Manage the recipe collection
     Collection(collection::CollectionArgs),
+    /// Check recipes for errors and warnings
+    Check(check::CheckArgs),
+    /// Check the environment and the collection for problems
+    Doctor(doctor::DoctorArgs),
     /// Generate shell completions
     GenerateCompletions(generate_completions::GenerateCompletionsArgs),
     /// Create a new recipe
     New(new::NewArgs),
     /// Edit an existing recipe
     Edit(edit::EditArgs),
+    /// Open the directory of a recipe in the file manager
+    Reveal(reveal::RevealArgs),
 }
 
 #[derive(Debug, Args)]
@@ -110,6 +123,20 @@ pub struct GlobalArgs {
     )]
     pub extensions: Vec<Extensions>,
 
+    /// Only accept recipes that follow the cooklang spec
+    ///
+    /// Disables every extension, treats warnings as errors and fails if a
+    /// recipe is read differently with extensions, so it can be shared with
+    /// other cooklang tools.
+    #[arg(
+        long,
+        group = "ext",
+        conflicts_with = "ignore_warnings",
+        hide_short_help = true,
+        global = true
+    )]
+    pub strict_canonical: bool,
+
     /// Treat warnings as errors
     #[arg(long, hide_short_help = true, global = true)]
     pub warnings_as_errors: bool,
@@ -123,6 +150,19 @@ pub struct GlobalArgs {
     )]
     pub ignore_warnings: bool,
 
+    /// How to print errors and warnings of recipes
+    ///
+    /// "json" prints one JSON object per diagnostic and line to stderr, to be
+    /// used by editors and CI.
+    #[arg(
+        long,
+        value_enum,
+        default_value_t = MessageFormat::Human,
+        hide_short_help = true,
+        global = true
+    )]
+    pub message_format: MessageFormat,
+
     #[command(flatten)]
     pub color: colorchoice_clap::Color,
 
@@ -133,6 +173,18 @@ pub struct GlobalArgs {
     #[arg(long, value_name = "PATH", value_hint = clap::ValueHint::DirPath, global = true)]
     pub path: Option<Utf8PathBuf>,
 
+    /// Use a collection by its name in the registry
+    ///
+    /// See `chef collection add`.
+    #[arg(
+        long,
+        value_name = "NAME",
+        conflicts_with = "path",
+        hide_short_help = true,
+        global = true
+    )]
+    pub collection: Option<String>,
+
     /// Skip checking if referenced recipes exist
     #[arg(long, hide_short_help = true, global = true)]
     pub no_recipe_ref_check: bool,
@@ -140,13 +192,45 @@ pub struct GlobalArgs {
     /// Override recipe indexing depth
     ///
     /// This is used to search for referenced recipes.
-    #[arg(long, hide_short_help = true, global = true, default_value_t = 10)]
-    pub max_depth: usize,
+    #[arg(long, hide_short_help = true, global = true)]
+    pub max_depth: Option<usize>,
 
     #[arg(long, hide_short_help = true, global = true)]
     pub debug_trace: bool,
 
+    /// Print the time spent indexing, parsing, scaling, converting and
+    /// rendering when the command ends
+    #[arg(long, hide_short_help = true, global = true)]
+    pub timings: bool,
+
+    /// Only print the output, warnings and errors
+    ///
+    /// Messages about what was done, like the file written, are left out, so
+    /// scripts only get what they asked for.
+    #[arg(short, long, global = true)]
+    pub quiet: bool,
+
+    /// Include draft recipes in lists and searches
+    ///
+    /// Drafts are named like `_Pancakes.cook` or have `draft: true` in the
+    /// metadata.
+    #[arg(long, hide_short_help = true, global = true)]
+    pub include_drafts: bool,
+
+    /// Never write to the collection
+    ///
+    /// Commands that would write to it fail, useful for read-only mounts and
+    /// CI. The web UI keeps favorites and cook log changes in memory.
+    #[arg(long, hide_short_help = true, global = true)]
+    pub no_fs_write: bool,
+
     /// Use a specific configuration fileignoring the expected path
     #[arg(long = "config", global = true, hide_short_help = true)]
     pub config_file: Option<Utf8PathBuf>,
 }
+
+#[derive(Debug, Clone, Copy, PartialEq, ValueEnum)]
+pub enum MessageFormat {
+    Human,
+    Json,
+}
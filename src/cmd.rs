@@ -1,11 +1,16 @@
+pub mod book;
+pub mod check;
 pub mod collection;
 pub mod config;
 pub mod convert;
+pub mod doctor;
 pub mod edit;
 pub mod generate_completions;
+pub mod grep;
 pub mod list;
 pub mod new;
 pub mod recipe;
+pub mod reveal;
 #[cfg(feature = "serve")]
 pub mod serve;
 pub mod shopping_list;
@@ -0,0 +1,391 @@
+//! A cookbook with several recipes in a single markdown or HTML document
+//!
+//! Recipes are grouped in chapters by the directory they are in. The book
+//! starts with a table of contents and ends with an index of ingredients. The
+//! HTML book starts every recipe in a new page when printed.
+
+use std::{
+    collections::{BTreeMap, HashMap},
+    fmt::Write as _,
+};
+
+use anyhow::{bail, Result};
+use camino::{Utf8Path, Utf8PathBuf};
+use clap::{Args, ValueEnum};
+use cooklang_fs::{all_recipes, RecipeEntry};
+
+use crate::{
+    cmd::shopping_list::split_servings,
+    util::{
+        escape_markup, has_tag, markdown_to_html, meta_name, respell_units, write_to_output,
+        CachedRecipeEntry, Input,
+    },
+    Context,
+};
+
+#[derive(Debug, Args)]
+pub struct BookArgs {
+    /// Recipes to include with optional servings number
+    ///
+    /// If none are given, all the recipes of the collection are included.
+    #[arg(value_name = "RECIPE[*<SERVINGS>]")]
+    recipes: Vec<String>,
+
+    /// Only include recipes with all these tags
+    #[arg(short, long)]
+    tag: Vec<String>,
+
+    /// Only include the recipes in a directory of the collection
+    #[arg(short, long, conflicts_with = "recipes")]
+    dir: Option<Utf8PathBuf>,
+
+    /// Title of the book
+    #[arg(long, default_value = "Recipes")]
+    title: String,
+
+    /// Do not add the index of ingredients
+    #[arg(long)]
+    no_index: bool,
+
+    /// Output file, none for stdout.
+    #[arg(short, long)]
+    output: Option<Utf8PathBuf>,
+
+    /// Output format
+    ///
+    /// Tries to infer it from output file extension. Defaults to "markdown".
+    #[arg(short, long, value_enum)]
+    format: Option<BookFormat>,
+}
+
+#[derive(Debug, Clone, Copy, ValueEnum)]
+enum BookFormat {
+    #[value(alias("md"))]
+    Markdown,
+    Html,
+}
+
+struct BookRecipe {
+    name: String,
+    anchor: String,
+    markdown: String,
+    ingredients: Vec<String>,
+}
+
+pub fn run(ctx: &Context, args: BookArgs) -> Result<()> {
+    let explicit = !args.recipes.is_empty();
+    let entries = if explicit {
+        args.recipes
+            .iter()
+            .map(|entry| {
+                let (query, servings) = split_servings(entry);
+                Ok((ctx.recipe_index.resolve(query, None)?, servings))
+            })
+            .collect::<Result<Vec<_>>>()?
+    } else {
+        let dir = match &args.dir {
+            Some(dir) => ctx.base_path.join(dir),
+            None => ctx.base_path.clone(),
+        };
+        if !dir.is_dir() {
+            bail!("Directory not found: '{dir}'");
+        }
+        all_recipes(&dir, ctx.config.max_depth)?
+            .filter(|entry| !CachedRecipeEntry::new(entry.clone()).is_hidden_draft(ctx))
+            .map(|entry| (entry, None))
+            .collect()
+    };
+
+    let converter = ctx.parser()?.converter();
+    let mut anchors = HashMap::new();
+    // chapters in order of appearance
+    let mut chapters: Vec<(String, Vec<BookRecipe>)> = Vec::new();
+
+    for (entry, servings) in entries {
+        let chapter = chapter_name(&entry, &ctx.base_path);
+        let input = Input::File {
+            entry,
+            override_name: None,
+        };
+        let recipe = match input.parse(ctx) {
+            Ok(recipe) => recipe,
+            Err(e) if explicit => return Err(e),
+            Err(e) => {
+                tracing::warn!("Skipping '{}': {e}", input.display_path());
+                continue;
+            }
+        };
+        if !args
+            .tag
+            .iter()
+            .all(|t| has_tag(&recipe.metadata, t, &ctx.config.tag_aliases))
+        {
+            continue;
+        }
+        let mut recipe = tracing::debug_span!("scaling").in_scope(|| match servings {
+            Some(servings) => recipe.scale(servings, converter),
+            None => recipe.default_scale(),
+        });
+        respell_units(&mut recipe, &ctx.config.display_units, converter);
+        let name = match meta_name(&recipe.metadata) {
+            Some(name) => name.to_string(),
+            None => input.name()?.to_string(),
+        };
+
+        let mut buf = Vec::new();
+        tracing::debug_span!("rendering").in_scope(|| {
+            cooklang_to_md::print_md_with_options(
+                &recipe,
+                &name,
+                &ctx.config.export.markdown,
+                converter,
+                &mut buf,
+            )
+        })?;
+        let mut ingredients = recipe
+            .ingredients
+            .iter()
+            .filter(|i| i.modifiers().should_be_listed())
+            .map(|i| i.display_name().to_string())
+            .collect::<Vec<_>>();
+        ingredients.sort();
+        ingredients.dedup();
+
+        let book_recipe = BookRecipe {
+            anchor: unique_anchor(&mut anchors, &name),
+            name,
+            markdown: book_section(&String::from_utf8(buf)?, 2),
+            ingredients,
+        };
+        match chapters.iter_mut().find(|(c, _)| *c == chapter) {
+            Some((_, recipes)) => recipes.push(book_recipe),
+            None => chapters.push((chapter, vec![book_recipe])),
+        }
+    }
+
+    if chapters.is_empty() {
+        bail!("No recipes to add to the book");
+    }
+    // recipes at the root go first and without a chapter heading
+    chapters.sort_by_key(|(c, _)| !c.is_empty());
+
+    let format = args.format.unwrap_or_else(|| match &args.output {
+        Some(p) => match p.extension() {
+            Some("html" | "htm") => BookFormat::Html,
+            _ => BookFormat::Markdown,
+        },
+        None => BookFormat::Markdown,
+    });
+    let contents = contents_md(&chapters);
+    let index = (!args.no_index).then(|| index_md(&chapters));
+
+    write_to_output(args.output.as_deref(), |mut w| {
+        match format {
+            BookFormat::Markdown => {
+                writeln!(w, "# {}\n", args.title)?;
+                writeln!(w, "## Contents\n")?;
+                writeln!(w, "{contents}")?;
+                for (chapter, recipes) in &chapters {
+                    if !chapter.is_empty() {
+                        writeln!(w, "## {chapter}\n")?;
+                    }
+                    for r in recipes {
+                        writeln!(w, "<a id=\"{}\"></a>\n", r.anchor)?;
+                        writeln!(w, "{}", r.markdown.trim_end())?;
+                        writeln!(w)?;
+                    }
+                }
+                if let Some(index) = &index {
+                    writeln!(w, "## Ingredient index\n")?;
+                    write!(w, "{index}")?;
+                }
+            }
+            BookFormat::Html => {
+                let html = book_html(&args.title, &contents, &chapters, index.as_deref());
+                w.write_all(html.as_bytes())?;
+            }
+        }
+        Ok(())
+    })
+}
+
+/// Markdown list of the chapters and their recipes
+fn contents_md(chapters: &[(String, Vec<BookRecipe>)]) -> String {
+    let mut out = String::new();
+    for (chapter, recipes) in chapters {
+        let indent = if chapter.is_empty() {
+            ""
+        } else {
+            writeln!(out, "- {chapter}").unwrap();
+            "  "
+        };
+        for r in recipes {
+            writeln!(out, "{indent}- [{}](#{})", r.name, r.anchor).unwrap();
+        }
+    }
+    out
+}
+
+/// Markdown list of the ingredients with links to the recipes that use them
+fn index_md(chapters: &[(String, Vec<BookRecipe>)]) -> String {
+    // lowercase name -> (name, recipes)
+    let mut index = BTreeMap::<String, (&str, Vec<&BookRecipe>)>::new();
+    for r in chapters.iter().flat_map(|(_, recipes)| recipes) {
+        for igr in &r.ingredients {
+            index
+                .entry(igr.to_lowercase())
+                .or_insert_with(|| (igr.as_str(), Vec::new()))
+                .1
+                .push(r);
+        }
+    }
+    let mut out = String::new();
+    for (name, recipes) in index.values() {
+        let links = recipes
+            .iter()
+            .map(|r| format!("[{}](#{})", r.name, r.anchor))
+            .collect::<Vec<_>>()
+            .join(", ");
+        writeln!(out, "- **{name}**: {links}").unwrap();
+    }
+    out
+}
+
+const STYLE: &str = "
+body { font-family: serif; max-width: 45rem; margin: 2rem auto; padding: 0 1rem; line-height: 1.5; }
+a { color: inherit; }
+@media print { section, body > h2 { break-before: page; } }
+";
+
+/// The book as a single HTML page
+fn book_html(
+    title: &str,
+    contents: &str,
+    chapters: &[(String, Vec<BookRecipe>)],
+    index: Option<&str>,
+) -> String {
+    let title = escape_markup(title);
+    let mut out = String::new();
+    out.push_str("<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n");
+    writeln!(out, "<title>{title}</title>").unwrap();
+    writeln!(out, "<style>{STYLE}</style>\n</head>\n<body>").unwrap();
+    writeln!(out, "<h1>{title}</h1>").unwrap();
+    out.push_str("<nav>\n<h2>Contents</h2>\n");
+    out.push_str(&markdown_to_html(contents));
+    out.push_str("</nav>\n");
+    for (chapter, recipes) in chapters {
+        if !chapter.is_empty() {
+            writeln!(out, "<h2>{}</h2>", escape_markup(chapter)).unwrap();
+        }
+        for r in recipes {
+            writeln!(out, "<section id=\"{}\">", escape_markup(&r.anchor)).unwrap();
+            out.push_str(&markdown_to_html(&r.markdown));
+            out.push_str("</section>\n");
+        }
+    }
+    if let Some(index) = index {
+        out.push_str("<h2>Ingredient index</h2>\n");
+        out.push_str(&markdown_to_html(index));
+    }
+    out.push_str("</body>\n</html>\n");
+    out
+}
+
+/// Relative dir of the recipe in the collection, empty for the root
+fn chapter_name(entry: &RecipeEntry, base_path: &Utf8Path) -> String {
+    entry
+        .path()
+        .parent()
+        .and_then(|p| p.strip_prefix(base_path).ok())
+        .map(|p| p.as_str().replace('\\', "/"))
+        .unwrap_or_default()
+}
+
+/// Removes the front-matter of a recipe in markdown and moves its headings
+/// `shift` levels down
+fn book_section(md: &str, shift: usize) -> String {
+    let mut body = md;
+    if let Some(rest) = md.strip_prefix("---\n") {
+        if let Some(end) = rest.find("\n---\n") {
+            body = rest[end + 5..].trim_start_matches('\n');
+        }
+    }
+    let prefix = "#".repeat(shift);
+    let mut out = String::with_capacity(body.len());
+    for line in body.lines() {
+        // tags are written like `#tag`, without space
+        if line.trim_start_matches('#').starts_with(' ') && line.starts_with('#') {
+            out.push_str(&prefix);
+        }
+        out.push_str(line);
+        out.push('\n');
+    }
+    out
+}
+
+/// Anchor for a heading, like the ones markdown renderers generate
+fn unique_anchor(used: &mut HashMap<String, usize>, text: &str) -> String {
+    let mut anchor = String::new();
+    for c in text.trim().to_lowercase().chars() {
+        if c.is_alphanumeric() {
+            anchor.push(c);
+        } else if c == ' ' || c == '-' || c == '_' {
+            anchor.push('-');
+        }
+    }
+    let count = used.entry(anchor.clone()).or_insert(0);
+    *count += 1;
+    if *count > 1 {
+        anchor = format!("{anchor}-{}", *count - 1);
+    }
+    anchor
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sections() {
+        let md = "---\nservings: 2\n---\n\n# Pasta\n\n#quick #easy\n\n## Ingredients\n- salt\n";
+        assert_eq!(
+            book_section(md, 2),
+            "### Pasta\n\n#quick #easy\n\n#### Ingredients\n- salt\n"
+        );
+    }
+
+    #[test]
+    fn anchors() {
+        let mut used = HashMap::new();
+        assert_eq!(unique_anchor(&mut used, "Mac & Cheese"), "mac--cheese");
+        assert_eq!(unique_anchor(&mut used, "Crème brûlée"), "crème-brûlée");
+        assert_eq!(unique_anchor(&mut used, "Mac & cheese"), "mac--cheese-1");
+    }
+
+    #[test]
+    fn html() {
+        let recipe = |name: &str, chapter: &str| {
+            (
+                chapter.to_string(),
+                vec![BookRecipe {
+                    name: name.to_string(),
+                    anchor: name.to_lowercase(),
+                    markdown: format!("### {name}\n\nMix <b>it</b>.\n"),
+                    ingredients: vec!["salt".to_string()],
+                }],
+            )
+        };
+        let chapters = [recipe("Pasta", ""), recipe("Soup", "winter")];
+        let contents = contents_md(&chapters);
+        assert_eq!(contents, "- [Pasta](#pasta)\n- winter\n  - [Soup](#soup)\n");
+        let index = index_md(&chapters);
+        assert_eq!(index, "- **salt**: [Pasta](#pasta), [Soup](#soup)\n");
+
+        let html = book_html("Fish & chips", &contents, &chapters, Some(&index));
+        assert!(html.contains("<h1>Fish &amp; chips</h1>"));
+        assert!(html.contains("<section id=\"soup\">\n<h3>Soup</h3>"));
+        assert!(html.contains("<h2>winter</h2>"));
+        assert!(html.contains(r##"<a href="#pasta">Pasta</a>"##));
+        assert!(html.contains("Mix &lt;b&gt;it&lt;/b&gt;."));
+    }
+}
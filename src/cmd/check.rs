@@ -0,0 +1,233 @@
+//! Checks recipes for errors and warnings
+//!
+//! Reports are printed like when reading a recipe and can also be written as
+//! a single HTML file, to share with people that don't use a terminal.
+
+use std::fmt::Write as _;
+
+use anstream::eprintln;
+use anyhow::{bail, Result};
+use camino::Utf8PathBuf;
+use clap::{Args, ValueEnum};
+use cooklang::error::{Severity, SourceDiag};
+use cooklang_fs::all_recipes;
+
+use crate::{
+    exit_code::ExitError,
+    util::{check_canonical, escape_markup, line_column, print_report, write_to_output, Input},
+    Context,
+};
+
+#[derive(Debug, Args)]
+pub struct CheckArgs {
+    /// Recipes to check, all the collection if none
+    recipes: Vec<String>,
+
+    /// Also write a report to a file
+    #[arg(long, value_name = "FILE")]
+    report: Option<Utf8PathBuf>,
+
+    /// Format of the report
+    #[arg(long, value_enum, default_value_t = ReportFormat::Html)]
+    report_format: ReportFormat,
+}
+
+#[derive(Debug, Clone, Copy, ValueEnum)]
+enum ReportFormat {
+    Html,
+}
+
+/// Diagnostics of a recipe with its source
+struct RecipeDiags {
+    path: String,
+    text: String,
+    diags: Vec<SourceDiag>,
+    /// Errors not in a part of the source
+    other: Vec<String>,
+}
+
+pub fn run(ctx: &Context, args: CheckArgs) -> Result<()> {
+    let entries = if args.recipes.is_empty() {
+        all_recipes(&ctx.base_path, ctx.config.max_depth)?.collect::<Vec<_>>()
+    } else {
+        args.recipes
+            .iter()
+            .map(|r| ctx.recipe_index.resolve(r, None))
+            .collect::<Result<_, _>>()?
+    };
+
+    let total = entries.len();
+    let mut checked = Vec::new();
+    let (mut errors, mut warnings) = (0, 0);
+    for entry in entries {
+        let input = Input::File {
+            entry,
+            override_name: None,
+        };
+        let text = input.text()?.into_owned();
+        let res = input.parse_result(ctx)?;
+        let diags = res.report().iter().cloned().collect::<Vec<_>>();
+        let mut other = Vec::new();
+        if let Some(recipe) = res.output().filter(|_| ctx.global_args.strict_canonical) {
+            if let Err(e) = check_canonical(recipe, &input, ctx) {
+                eprintln!("Error: {e}");
+                other.push(e.to_string());
+            }
+        }
+        if diags.is_empty() && other.is_empty() {
+            continue;
+        }
+        if !diags.is_empty() {
+            print_report(res.report(), &input, &text, ctx)?;
+        }
+        errors += other.len();
+        for d in &diags {
+            match d.severity {
+                Severity::Error => errors += 1,
+                Severity::Warning => warnings += 1,
+            }
+        }
+        checked.push(RecipeDiags {
+            path: input.display_path().to_string(),
+            text,
+            diags,
+            other,
+        });
+    }
+
+    if let Some(file) = &args.report {
+        let html = match args.report_format {
+            ReportFormat::Html => html_report(&checked, total),
+        };
+        write_to_output(Some(file), |mut w| {
+            w.write_all(html.as_bytes())?;
+            Ok(())
+        })?;
+        ctx.status(format!("Report written to '{file}'"));
+    }
+
+    ctx.status(format!(
+        "{total} recipes checked: {errors} errors, {warnings} warnings"
+    ));
+    if errors > 0 || ctx.global_args.warnings_as_errors && warnings > 0 {
+        bail!(ExitError::recipe("Some recipes have errors"));
+    }
+    Ok(())
+}
+
+const STYLE: &str = "
+body { font-family: sans-serif; max-width: 60rem; margin: 2rem auto; padding: 0 1rem; }
+h2 { font-family: monospace; border-bottom: 1px solid #ccc; }
+.error { color: #c62828; }
+.warning { color: #b26a00; }
+pre { background: #f5f5f5; padding: 0.5rem; overflow-x: auto; }
+.ln { color: #888; user-select: none; }
+mark { background: #ffe082; }
+.hint { color: #555; }
+";
+
+fn html_report(recipes: &[RecipeDiags], total: usize) -> String {
+    let mut out = String::new();
+    out.push_str("<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n");
+    out.push_str("<title>Recipe check report</title>\n");
+    writeln!(out, "<style>{STYLE}</style>\n</head>\n<body>").unwrap();
+    out.push_str("<h1>Recipe check report</h1>\n");
+    writeln!(
+        out,
+        "<p>{total} recipes checked, {} with problems.</p>",
+        recipes.len()
+    )
+    .unwrap();
+    for recipe in recipes {
+        writeln!(out, "<h2>{}</h2>", escape_markup(&recipe.path)).unwrap();
+        for message in &recipe.other {
+            writeln!(
+                out,
+                "<h3 class=\"error\">error: {}</h3>",
+                escape_markup(message)
+            )
+            .unwrap();
+        }
+        for diag in &recipe.diags {
+            let class = match diag.severity {
+                Severity::Error => "error",
+                Severity::Warning => "warning",
+            };
+            writeln!(
+                out,
+                "<h3 class=\"{class}\">{class}: {}</h3>",
+                escape_markup(&diag.message)
+            )
+            .unwrap();
+            for (span, message) in &diag.labels {
+                out.push_str(&excerpt(
+                    &recipe.text,
+                    span.start(),
+                    span.end(),
+                    message.as_deref(),
+                ));
+            }
+            for hint in &diag.hints {
+                writeln!(out, "<p class=\"hint\">help: {}</p>", escape_markup(hint)).unwrap();
+            }
+        }
+    }
+    out.push_str("</body>\n</html>\n");
+    out
+}
+
+/// Lines of `text` with the span highlighted and line numbers
+fn excerpt(text: &str, start: usize, end: usize, message: Option<&str>) -> String {
+    let end = end.clamp(start, text.len());
+    let start = start.min(end);
+    let line_start = text[..start].rfind('\n').map_or(0, |i| i + 1);
+    let line_end = text[end..].find('\n').map_or(text.len(), |i| end + i);
+    let (first_line, _) = line_column(text, start);
+
+    let title = message
+        .map(|m| format!(" title=\"{}\"", escape_markup(m)))
+        .unwrap_or_default();
+    let marked = format!(
+        "{}<mark{title}>{}</mark>{}",
+        escape_markup(&text[line_start..start]),
+        escape_markup(&text[start..end]),
+        escape_markup(&text[end..line_end])
+    );
+
+    let mut out = String::from("<pre>");
+    for (i, line) in marked.split('\n').enumerate() {
+        if i > 0 {
+            out.push('\n');
+        }
+        write!(
+            out,
+            "<span class=\"ln\">{:>4} </span>{line}",
+            first_line + i
+        )
+        .unwrap();
+    }
+    out.push_str("</pre>\n");
+    if let Some(message) = message {
+        writeln!(out, "<p>{}</p>", escape_markup(message)).unwrap();
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn excerpts() {
+        let text = "first\nadd @salt{<1} and\nthird\n";
+        let start = text.find("{<1}").unwrap();
+        assert_eq!(
+            excerpt(text, start, start + 4, Some("bad")),
+            "<pre><span class=\"ln\">   2 </span>add @salt<mark title=\"bad\">{&lt;1}</mark> and</pre>\n<p>bad</p>\n"
+        );
+        assert_eq!(
+            excerpt(text, 0, 7, None),
+            "<pre><span class=\"ln\">   1 </span><mark>first\n<span class=\"ln\">   2 </span>a</mark>dd @salt{&lt;1} and</pre>\n"
+        );
+    }
+}
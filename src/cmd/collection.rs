@@ -42,6 +42,23 @@ enum Command {
     Get,
     /// Removes the default collection
     Unset,
+    /// List the collections in the registry
+    #[command(alias = "ls")]
+    List,
+    /// Add a collection to the registry with a name
+    Add {
+        name: String,
+        /// Defaults to the current dir
+        #[arg(value_name = "PATH", conflicts_with = "path")]
+        collection_path: Option<Utf8PathBuf>,
+    },
+    /// Remove a collection from the registry
+    ///
+    /// The files of the collection are not touched.
+    #[command(alias = "rm")]
+    Remove { name: String },
+    /// Set a collection of the registry as the default one
+    Switch { name: String },
 }
 
 pub fn run(ctx: &Context, args: CollectionArgs) -> Result<()> {
@@ -52,6 +69,7 @@ pub fn run(ctx: &Context, args: CollectionArgs) -> Result<()> {
             set_default,
             force,
         } => {
+            ctx.check_fs_write()?;
             create_collection(&path, force)?;
             if copy_config {
                 let config = config_file_path(&path);
@@ -67,32 +85,92 @@ pub fn run(ctx: &Context, args: CollectionArgs) -> Result<()> {
             }
         }
         Command::Set { default_path: path } => {
-            let path = path
-                .or_else(|| Utf8PathBuf::from_path_buf(std::env::current_dir().ok()?).ok())
-                .ok_or(anyhow!("Invalid collection path"))?;
-            if !path.is_dir() {
-                bail!("The path is not a dir: {path}");
-            }
-            if !path.join(COOK_DIR).is_dir() {
-                bail!("The '{COOK_DIR}' dir was not found in the path: {path}");
-            }
+            let path = collection_path(path)?;
             set_default_collection(&ctx.chef_config, Some(path))?;
         }
         Command::Unset => {
             set_default_collection(&ctx.chef_config, None)?;
-            eprintln!("Default collection removed");
+            ctx.status("Default collection removed");
         }
         Command::Get => {
             if let Some(default) = &ctx.chef_config.default_collection {
                 println!("{default}");
             } else {
-                eprintln!("No default collection is set");
+                ctx.status("No default collection is set");
             }
         }
+        Command::List => list_collections(ctx),
+        Command::Add {
+            name,
+            collection_path: path,
+        } => {
+            let path = collection_path(path)?.canonicalize_utf8()?;
+            let mut global = ctx.chef_config.clone();
+            if let Some(old) = global.collections.insert(name.clone(), path) {
+                tracing::warn!("Replaced collection '{name}', it was at: {old}");
+            }
+            global_store(CHEF_CONFIG_FILE, &global)?;
+        }
+        Command::Remove { name } => {
+            let mut global = ctx.chef_config.clone();
+            if global.collections.remove(&name).is_none() {
+                bail!("Unknown collection '{name}'");
+            }
+            global_store(CHEF_CONFIG_FILE, &global)?;
+            ctx.status(format!("Collection '{name}' removed"));
+        }
+        Command::Switch { name } => {
+            let path = ctx.chef_config.collection(&name)?.to_owned();
+            set_default_collection(&ctx.chef_config, Some(path))?;
+            ctx.status(format!("Default collection is now '{name}'"));
+        }
     }
     Ok(())
 }
 
+/// Checks the path is a collection, the current dir if none
+fn collection_path(path: Option<Utf8PathBuf>) -> Result<Utf8PathBuf> {
+    let path = path
+        .or_else(|| Utf8PathBuf::from_path_buf(std::env::current_dir().ok()?).ok())
+        .ok_or(anyhow!("Invalid collection path"))?;
+    if !path.is_dir() {
+        bail!("The path is not a dir: {path}");
+    }
+    if !path.join(COOK_DIR).is_dir() {
+        bail!("The '{COOK_DIR}' dir was not found in the path: {path}");
+    }
+    Ok(path)
+}
+
+fn list_collections(ctx: &Context) {
+    use yansi::Paint;
+
+    let global = &ctx.chef_config;
+    if global.collections.is_empty() {
+        ctx.status("No collections registered. Add one with `chef collection add <NAME> [PATH]`");
+        return;
+    }
+    let mut table = tabular::Table::new("{:<} {:<} {:<}");
+    for (name, path) in &global.collections {
+        let is_default = global.default_collection.as_ref() == Some(path);
+        let mut row = tabular::Row::new();
+        if is_default {
+            row.add_ansi_cell("*".green());
+            row.add_ansi_cell(name.green().bold());
+        } else {
+            row.add_cell("");
+            row.add_cell(name);
+        }
+        if path.is_dir() {
+            row.add_cell(path);
+        } else {
+            row.add_ansi_cell(format!("{path} (not found)").red());
+        }
+        table.add_row(row);
+    }
+    print!("{table}");
+}
+
 fn create_collection(path: &Utf8Path, force: bool) -> Result<()> {
     if path.exists() {
         if !path.is_dir() {
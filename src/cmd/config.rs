@@ -2,20 +2,24 @@ use std::{fs, path::Path};
 
 use anstream::{print, println};
 use anyhow::{bail, Context as _, Result};
-use camino::Utf8PathBuf;
-use clap::Args;
+use camino::{Utf8Path, Utf8PathBuf};
+use clap::{Args, Subcommand};
 use cooklang::Extensions;
 
 use crate::{
     config::{
-        config_file_path, global_file_path, global_store, store_at_path, ChefConfig, Config,
-        CHEF_CONFIG_FILE, DEFAULT_CONFIG_FILE,
+        config_file_path, env_overrides, from_toml, global_file_path, global_store, store_at_path,
+        ChefConfig, Config, CHEF_CONFIG_FILE, DEFAULT_CONFIG_FILE,
     },
+    util::open_editor,
     Context, COOK_DIR, UTF8_PATH_PANIC,
 };
 
 #[derive(Debug, Args)]
+#[command(args_conflicts_with_subcommands = true)]
 pub struct ConfigArgs {
+    #[command(subcommand)]
+    command: Option<ConfigCommand>,
     /// Run the basic interactive config setup
     #[arg(long, exclusive = true)]
     setup: bool,
@@ -24,6 +28,29 @@ pub struct ConfigArgs {
     chef: bool,
 }
 
+#[derive(Debug, Subcommand)]
+enum ConfigCommand {
+    /// Set a config key, keeping the rest of the file as is
+    ///
+    /// The config file of the collection is changed, or the default config
+    /// outside a collection.
+    Set {
+        /// Key, with dots for nested keys, like `ui.public_url`
+        key: String,
+        /// TOML value, taken as a string if it's not valid TOML
+        value: String,
+        /// Change the chef config, common to all collections
+        #[arg(long)]
+        chef: bool,
+    },
+    /// Open the config file in the editor
+    Edit {
+        /// Edit the chef config, common to all collections
+        #[arg(long)]
+        chef: bool,
+    },
+}
+
 pub fn run_setup(config: &Config, chef_config: &ChefConfig) -> Result<()> {
     use inquire::{Confirm, Text};
     use yansi::Paint;
@@ -166,7 +193,14 @@ fn extensions_prompt(enabled: Extensions) -> Result<Extensions> {
 }
 
 pub fn run(ctx: &Context, args: ConfigArgs) -> Result<()> {
+    match args.command {
+        Some(ConfigCommand::Set { key, value, chef }) => return set(ctx, &key, &value, chef),
+        Some(ConfigCommand::Edit { chef }) => return edit(ctx, chef),
+        None => {}
+    }
+
     if args.setup {
+        ctx.check_fs_write()?;
         run_setup(&ctx.config, &ctx.chef_config)?;
         return Ok(());
     }
@@ -197,6 +231,11 @@ fn display_regular(ctx: &Context) -> Result<()> {
     println!("{}", c.trim());
     println!("{fence}");
 
+    let overrides = env_overrides();
+    if !overrides.is_empty() {
+        println!("Overridden by: {}", overrides.join(", ").yellow());
+    }
+
     for file in ctx
         .config
         .units(&ctx.base_path)
@@ -227,3 +266,143 @@ fn display_chef_config(ctx: &Context) -> Result<()> {
     println!("{fence}");
     Ok(())
 }
+
+/// File changed by `config set` and `config edit`
+fn target_file(ctx: &Context, chef: bool) -> Result<Utf8PathBuf> {
+    if chef {
+        global_file_path(CHEF_CONFIG_FILE)
+    } else if let Some(file) = &ctx.global_args.config_file {
+        Ok(file.clone())
+    } else if ctx.is_collection {
+        Ok(config_file_path(&ctx.base_path))
+    } else {
+        global_file_path(DEFAULT_CONFIG_FILE)
+    }
+}
+
+/// Fails for a file in the collection when writing to it is disabled
+fn check_write(ctx: &Context, path: &Utf8Path) -> Result<()> {
+    if path.starts_with(&ctx.base_path) {
+        ctx.check_fs_write()?;
+    }
+    Ok(())
+}
+
+/// Checks that the content is a valid config of its kind
+///
+/// Keys that do not exist are only a warning.
+fn check_config(content: &str, chef: bool) -> Result<()> {
+    if chef {
+        from_toml::<ChefConfig>(content)?;
+    } else {
+        from_toml::<Config>(content)?;
+    }
+    Ok(())
+}
+
+fn set(ctx: &Context, key: &str, value: &str, chef: bool) -> Result<()> {
+    let path = target_file(ctx, chef)?;
+    check_write(ctx, &path)?;
+    let content = match fs::read_to_string(&path) {
+        Ok(content) => content,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => String::new(),
+        Err(e) => return Err(e).context("Failed to read config file"),
+    };
+    let content = set_key(&content, key, value)?;
+    check_config(&content, chef).context("The new config is not valid")?;
+
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent).context("Failed to create config directory")?;
+    }
+    fs::write(&path, content).context("Failed to write config file")?;
+    tracing::info!("Config written to {path}");
+    Ok(())
+}
+
+/// Sets a key in a TOML document, keeping comments and formatting
+fn set_key(content: &str, key: &str, value: &str) -> Result<String> {
+    let mut doc = content
+        .parse::<toml_edit::DocumentMut>()
+        .context("Bad TOML data")?;
+    let mut value = value
+        .parse::<toml_edit::Value>()
+        .unwrap_or_else(|_| value.into());
+
+    let parts = key.split('.').collect::<Vec<_>>();
+    if parts.iter().any(|p| p.is_empty()) {
+        bail!("Invalid key: '{key}'");
+    }
+    let (last, tables) = parts.split_last().unwrap();
+    let mut item = doc.as_item_mut();
+    for (i, part) in tables.iter().enumerate() {
+        if !(item.is_table_like() || item.is_none()) {
+            bail!("'{}' is not a table", parts[..i].join("."));
+        }
+        if item.is_none() {
+            let mut table = toml_edit::Table::new();
+            table.set_implicit(true);
+            *item = toml_edit::Item::Table(table);
+        }
+        item = &mut item[*part];
+    }
+    if !(item.is_table_like() || item.is_none()) {
+        bail!("'{}' is not a table", tables.join("."));
+    }
+    // keep the comments around the old value
+    if let Some(old) = item.get(last).and_then(|i| i.as_value()) {
+        *value.decor_mut() = old.decor().clone();
+    }
+    item[*last] = toml_edit::Item::Value(value);
+    Ok(doc.to_string())
+}
+
+fn edit(ctx: &Context, chef: bool) -> Result<()> {
+    let path = target_file(ctx, chef)?;
+    check_write(ctx, &path)?;
+    if !path.is_file() {
+        if chef {
+            store_at_path(&path, &ctx.chef_config)?;
+        } else {
+            store_at_path(&path, &ctx.config)?;
+        }
+    }
+
+    open_editor(ctx, &path)?;
+
+    let content = fs::read_to_string(&path).context("Failed to read config file")?;
+    check_config(&content, chef).with_context(|| format!("The config in '{path}' is not valid"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn set_keeps_comments() {
+        let content =
+            "# my config\nmax_depth = 10 # deep\n\n[ui]\n# share links\npublic_url = \"a\"\n";
+        let new = set_key(content, "ui.public_url", "https://example.com").unwrap();
+        assert_eq!(
+            new,
+            "# my config\nmax_depth = 10 # deep\n\n[ui]\n# share links\npublic_url = \"https://example.com\"\n"
+        );
+        let new = set_key(&new, "max_depth", "3").unwrap();
+        assert!(new.contains("max_depth = 3 # deep"));
+        let new = set_key("", "export.markdown.tags", "false").unwrap();
+        assert_eq!(
+            toml::from_str::<toml::Table>(&new).unwrap()["export"]["markdown"]["tags"],
+            toml::Value::Boolean(false)
+        );
+        assert!(set_key("a = 1", "a.b", "2").is_err());
+        assert!(set_key("", "a..b", "2").is_err());
+    }
+
+    #[test]
+    fn check_unknown_keys() {
+        assert!(check_config("max_depth = 3", false).is_ok());
+        assert!(check_config("max_dephts = 3", false).is_ok());
+        assert!(check_config("[ui]\npublik_url = 'x'", false).is_ok());
+        assert!(check_config("editor_comand = ['vi']", true).is_ok());
+        assert!(check_config("max_depth = 'deep'", false).is_err());
+    }
+}
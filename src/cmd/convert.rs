@@ -1,45 +1,244 @@
+use std::io::BufRead;
+
 use anstream::println;
+use anyhow::{bail, Context as _};
 use clap::Args;
 use cooklang::{
     convert::{ConvertTo, Converter, System},
     quantity::Number,
     Quantity, Value,
 };
+use serde::Serialize;
 
 #[derive(Debug, Args)]
 pub struct ConvertArgs {
-    /// Value to convert, can have decimals
-    value: f64,
-    /// Unit to convert from
-    unit: String,
-    /// Unit to convert to. Can also be "metric", "imperial", or "fit"
+    /// Conversion expression, none for stdin
+    ///
+    /// The expression is `<VALUE> <UNIT> [to] <TARGET>`. The value can be a
+    /// number with decimals, a fraction (`1/2`), a mixed number (`2 1/4`) or
+    /// a range (`2-3`).
     ///
-    /// "metric" and "imperial" will convert to the best possible unit
-    /// in one of those systems.
+    /// The target can be a unit or "metric", "imperial", or "fit".
+    /// "metric" and "imperial" will convert to the best possible unit in one
+    /// of those systems. "fit" will try to convert to the best unit in the
+    /// same system.
     ///
-    /// "fit" will try to convert to the best unit in the same system.
-    to: String,
+    /// When reading from stdin, every non empty line is an expression.
+    #[arg(value_name = "EXPRESSION", allow_negative_numbers = true)]
+    expression: Vec<String>,
+
+    /// Output in JSON format, one object per line
+    #[arg(long)]
+    json: bool,
 }
 
 pub fn run(converter: &Converter, args: ConvertArgs) -> anyhow::Result<()> {
+    if !args.expression.is_empty() {
+        let expr = args.expression.join(" ");
+        return convert_expr(converter, &expr, args.json);
+    }
+
+    for (line_no, line) in std::io::stdin().lock().lines().enumerate() {
+        let line = line.context("Failed to read stdin")?;
+        let line = line.trim();
+        if line.is_empty() {
+            continue;
+        }
+        convert_expr(converter, line, args.json)
+            .with_context(|| format!("Error in line {}", line_no + 1))?;
+    }
+    Ok(())
+}
+
+fn convert_expr(converter: &Converter, expr: &str, json: bool) -> anyhow::Result<()> {
     use yansi::Paint;
 
-    let to = match args.to.as_str() {
+    let Expr { amount, unit, to } =
+        parse_expr(expr).with_context(|| format!("Invalid expression: '{expr}'"))?;
+
+    let to = match to {
         "fit" | "best" => ConvertTo::SameSystem,
         "metric" => ConvertTo::Best(System::Metric),
         "imperial" => ConvertTo::Best(System::Imperial),
-        _ => ConvertTo::Unit(cooklang::convert::ConvertUnit::Key(&args.to)),
+        _ => ConvertTo::Unit(cooklang::convert::ConvertUnit::Key(to)),
     };
 
-    let mut quantity = Quantity::new(Value::Number(Number::Regular(args.value)), Some(args.unit));
+    let value = match amount {
+        Amount::Single(n) => Value::Number(Number::Regular(n)),
+        Amount::Range(start, end) => Value::Range {
+            start: Number::Regular(start),
+            end: Number::Regular(end),
+        },
+    };
+    let mut quantity = Quantity::new(value, Some(unit.to_string()));
 
     quantity.convert(to, converter)?;
 
-    println!(
-        "{:#} {}",
-        quantity.value(),
-        quantity.unit().unwrap().italic()
-    );
+    let unit = quantity.unit().unwrap();
+    if json {
+        #[derive(Serialize)]
+        #[serde(untagged)]
+        enum JsonValue {
+            Number(f64),
+            Range { start: f64, end: f64 },
+        }
+
+        #[derive(Serialize)]
+        struct JsonConversion<'a> {
+            value: JsonValue,
+            unit: &'a str,
+            system: Option<System>,
+        }
+
+        let value = match quantity.value() {
+            Value::Number(n) => JsonValue::Number(n.value()),
+            Value::Range { start, end } => JsonValue::Range {
+                start: start.value(),
+                end: end.value(),
+            },
+            Value::Text(_) => bail!("Text values cannot be converted"),
+        };
+        let conversion = JsonConversion {
+            value,
+            unit,
+            system: quantity.unit_info(converter).and_then(|u| u.system),
+        };
+        println!("{}", serde_json::to_string(&conversion)?);
+    } else {
+        println!("{:#} {}", quantity.value(), unit.italic());
+    }
 
     Ok(())
 }
+
+#[derive(Debug, PartialEq)]
+struct Expr<'a> {
+    amount: Amount,
+    unit: &'a str,
+    to: &'a str,
+}
+
+#[derive(Debug, PartialEq)]
+enum Amount {
+    Single(f64),
+    Range(f64, f64),
+}
+
+fn parse_expr(expr: &str) -> anyhow::Result<Expr<'_>> {
+    let expr = expr.trim();
+    let (from, to) = match expr.rsplit_once(" to ") {
+        Some(parts) => parts,
+        None => expr
+            .rsplit_once(char::is_whitespace)
+            .context("Missing conversion target")?,
+    };
+    let to = to.trim();
+    if to.is_empty() {
+        bail!("Missing conversion target");
+    }
+
+    let (amount, unit) = parse_amount(from)?;
+    let unit = unit.trim();
+    if unit.is_empty() {
+        bail!("Missing unit");
+    }
+
+    Ok(Expr { amount, unit, to })
+}
+
+fn parse_amount(s: &str) -> anyhow::Result<(Amount, &str)> {
+    let s = s.trim_start();
+    let (negative, s) = match s.strip_prefix('-') {
+        Some(rest) => (true, rest),
+        None => (false, s),
+    };
+    let (mut start, rest) = parse_number(s).context("Missing value")?;
+    if negative {
+        start = -start;
+    }
+
+    if let Some(end_str) = rest.trim_start().strip_prefix('-') {
+        let (end, rest) = parse_number(end_str).context("Missing range end")?;
+        return Ok((Amount::Range(start, end), rest));
+    }
+
+    Ok((Amount::Single(start), rest))
+}
+
+fn parse_number(s: &str) -> Option<(f64, &str)> {
+    let (first, rest) = split_number_token(s)?;
+    let mut value = parse_simple_number(first)?;
+
+    // mixed numbers like `2 1/4`
+    if !first.contains(['.', '/']) {
+        if let Some((second, mixed_rest)) = split_number_token(rest) {
+            if second.contains('/') {
+                value += parse_simple_number(second)?;
+                return Some((value, mixed_rest));
+            }
+        }
+    }
+
+    Some((value, rest))
+}
+
+fn split_number_token(s: &str) -> Option<(&str, &str)> {
+    let s = s.trim_start();
+    let end = s
+        .find(|c: char| !(c.is_ascii_digit() || c == '.' || c == '/'))
+        .unwrap_or(s.len());
+    if end == 0 {
+        return None;
+    }
+    Some(s.split_at(end))
+}
+
+fn parse_simple_number(s: &str) -> Option<f64> {
+    match s.split_once('/') {
+        Some((num, den)) => {
+            let num = num.parse::<f64>().ok()?;
+            let den = den.parse::<f64>().ok()?;
+            (den != 0.0).then(|| num / den)
+        }
+        None => s.parse().ok(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_expr() {
+        let expr = |amount, unit, to| Expr { amount, unit, to };
+
+        assert_eq!(
+            parse_expr("3 cups metric").unwrap(),
+            expr(Amount::Single(3.0), "cups", "metric")
+        );
+        assert_eq!(
+            parse_expr("2 1/4 cups to ml").unwrap(),
+            expr(Amount::Single(2.25), "cups", "ml")
+        );
+        assert_eq!(
+            parse_expr("1/2 fl oz to ml").unwrap(),
+            expr(Amount::Single(0.5), "fl oz", "ml")
+        );
+        assert_eq!(
+            parse_expr("1.5-2 kg imperial").unwrap(),
+            expr(Amount::Range(1.5, 2.0), "kg", "imperial")
+        );
+        assert_eq!(
+            parse_expr("-5 C to F").unwrap(),
+            expr(Amount::Single(-5.0), "C", "F")
+        );
+        assert_eq!(
+            parse_expr("200g fit").unwrap(),
+            expr(Amount::Single(200.0), "g", "fit")
+        );
+        assert!(parse_expr("cups to ml").is_err());
+        assert!(parse_expr("3 to ml").is_err());
+        assert!(parse_expr("3").is_err());
+        assert!(parse_expr("1/0 cups ml").is_err());
+    }
+}
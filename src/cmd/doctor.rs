@@ -0,0 +1,421 @@
+//! Diagnostics of the environment and the collection
+//!
+//! Every check prints a line with its result and, when something is wrong, a
+//! hint on how to fix it.
+
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+};
+
+use anstream::println;
+use anyhow::{bail, Result};
+use camino::{Utf8Path, Utf8PathBuf};
+use clap::Args;
+use cooklang::error::Severity;
+use cooklang_fs::{all_recipes, check_recipe_images, WalkError, IMAGES_DIR};
+use yansi::Paint;
+
+use crate::{
+    cmd::shopping_list::prices::PriceConf,
+    config::{config_file_path, global_file_path, CHEF_CONFIG_FILE},
+    exit_code::ExitError,
+    util::Input,
+    Context, COOK_DIR, RECIPE_REF_ERROR,
+};
+
+#[derive(Debug, Args)]
+pub struct DoctorArgs {
+    /// Do not parse every recipe of the collection
+    ///
+    /// Recipe errors and broken references will not be checked.
+    #[arg(long)]
+    quick: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Status {
+    Ok,
+    Warning,
+    Error,
+}
+
+#[derive(Default)]
+struct Report {
+    warnings: usize,
+    errors: usize,
+}
+
+impl Report {
+    fn section(&self, title: &str) {
+        println!("\n{}", title.bold());
+    }
+
+    fn check(&mut self, status: Status, message: impl std::fmt::Display, hint: Option<&str>) {
+        let mark = match status {
+            Status::Ok => "✓".green().to_string(),
+            Status::Warning => {
+                self.warnings += 1;
+                "!".yellow().bold().to_string()
+            }
+            Status::Error => {
+                self.errors += 1;
+                "✗".red().bold().to_string()
+            }
+        };
+        println!("  {mark} {message}");
+        if let Some(hint) = hint {
+            println!("      {}", hint.dim());
+        }
+    }
+
+    fn ok(&mut self, message: impl std::fmt::Display) {
+        self.check(Status::Ok, message, None)
+    }
+
+    fn warn(&mut self, message: impl std::fmt::Display, hint: &str) {
+        self.check(Status::Warning, message, Some(hint))
+    }
+
+    fn error(&mut self, message: impl std::fmt::Display, hint: &str) {
+        self.check(Status::Error, message, Some(hint))
+    }
+}
+
+pub fn run(ctx: &Context, args: DoctorArgs) -> Result<()> {
+    let mut report = Report::default();
+
+    report.section("Environment");
+    check_config(ctx, &mut report);
+    check_editor(ctx, &mut report);
+    check_units(ctx, &mut report);
+    check_aisle(ctx, &mut report);
+    check_prices(ctx, &mut report);
+
+    report.section("Collection");
+    if !ctx.is_collection {
+        report.warn(
+            format!("'{}' is not a collection", ctx.base_path),
+            "Create one with `chef collection new` or pass the path of one with `--path`",
+        );
+    }
+    check_files(ctx, &mut report)?;
+    if !args.quick {
+        check_recipes(ctx, &mut report)?;
+    }
+
+    println!();
+    if report.errors > 0 {
+        bail!(ExitError::recipe(format!(
+            "{} errors and {} warnings found",
+            report.errors, report.warnings
+        )));
+    }
+    if report.warnings > 0 {
+        println!("{} warnings found", report.warnings);
+    } else {
+        println!("No problems found");
+    }
+    Ok(())
+}
+
+fn check_config(ctx: &Context, report: &mut Report) {
+    // invalid config files stop chef before getting here, so only report
+    // which ones are used
+    let local = ctx
+        .global_args
+        .config_file
+        .clone()
+        .unwrap_or_else(|| config_file_path(&ctx.base_path));
+    if local.is_file() {
+        report.ok(format!("Config file: {local}"));
+    } else {
+        report.ok("Config file: using the global default config");
+    }
+    match global_file_path(CHEF_CONFIG_FILE) {
+        Ok(path) => report.ok(format!(
+            "Global config dir: {}",
+            path.parent().unwrap_or(&path)
+        )),
+        Err(e) => report.error(
+            format!("Global config dir: {e}"),
+            "Make sure the HOME environment variable is set",
+        ),
+    }
+}
+
+fn check_editor(ctx: &Context, report: &mut Report) {
+    let cmd = match ctx.chef_config.editor() {
+        Ok(cmd) => cmd,
+        Err(e) => {
+            report.error(
+                format!("Editor: {e}"),
+                "Set `editor_command` in the global config with `chef config --setup`",
+            );
+            return;
+        }
+    };
+    let Some(program) = cmd.first() else {
+        return;
+    };
+    match find_executable(program) {
+        Some(path) => report.ok(format!("Editor: {program} ({})", path.display())),
+        None => report.error(
+            format!("Editor: '{program}' not found"),
+            "Install it, set the EDITOR environment variable or `editor_command` in the global config",
+        ),
+    }
+}
+
+/// Finds a program like a shell would, using the `PATH` variable
+fn find_executable(program: &str) -> Option<PathBuf> {
+    let program = Path::new(program);
+    if program.components().count() > 1 {
+        return program.is_file().then(|| program.to_path_buf());
+    }
+    let extensions: &[&str] = if cfg!(windows) {
+        &["", "exe", "cmd", "bat"]
+    } else {
+        &[""]
+    };
+    let path = std::env::var_os("PATH")?;
+    std::env::split_paths(&path).find_map(|dir| {
+        extensions.iter().find_map(|ext| {
+            let candidate = dir.join(program).with_extension(ext);
+            candidate.is_file().then_some(candidate)
+        })
+    })
+}
+
+fn check_units(ctx: &Context, report: &mut Report) {
+    let mut valid = true;
+    for file in ctx.config.units(&ctx.base_path) {
+        let res = std::fs::read_to_string(&file)
+            .map_err(anyhow::Error::from)
+            .and_then(|text| Ok(toml::from_str::<cooklang::convert::UnitsFile>(&text)?));
+        match res {
+            Ok(_) => report.ok(format!("Units file: {file}")),
+            Err(e) => {
+                valid = false;
+                report.error(
+                    format!("Units file {file}: {e}"),
+                    "Fix the file or remove it from `load.units` in the config",
+                );
+            }
+        }
+    }
+    // files can be valid on their own but conflict with each other
+    if valid {
+        if let Err(e) = ctx.parser() {
+            report.error(
+                format!("Units: {e:#}"),
+                "Check the units files for conflicting definitions",
+            );
+        }
+    }
+}
+
+fn check_aisle(ctx: &Context, report: &mut Report) {
+    let Some(path) = ctx.config.aisle(&ctx.base_path) else {
+        return;
+    };
+    let res = std::fs::read_to_string(&path)
+        .map_err(anyhow::Error::from)
+        .and_then(|content| {
+            cooklang::aisle::parse(&content)
+                .map(|_| ())
+                .map_err(|e| anyhow::anyhow!("{e}"))
+        });
+    match res {
+        Ok(()) => report.ok(format!("Aisle file: {path}")),
+        Err(e) => report.error(
+            format!("Aisle file {path}: {e}"),
+            "Run `chef shopping-list` with a recipe to see the errors in detail",
+        ),
+    }
+}
+
+fn check_prices(ctx: &Context, report: &mut Report) {
+    let Some(path) = ctx.config.prices(&ctx.base_path) else {
+        return;
+    };
+    match PriceConf::load(&path) {
+        Ok(_) => report.ok(format!("Prices file: {path}")),
+        Err(e) => report.error(
+            format!("{e:#}"),
+            "Each item needs a `price` and an optional `unit`",
+        ),
+    }
+}
+
+/// Walks the collection looking for files chef can't handle
+fn check_files(ctx: &Context, report: &mut Report) -> Result<()> {
+    let mut non_utf8 = Vec::new();
+    let mut io_errors = Vec::new();
+    let mut recipes = HashMap::<Utf8PathBuf, Vec<String>>::new();
+    let mut images = Vec::new();
+
+    let walk = cooklang_fs::new_index(&ctx.base_path, ctx.config.max_depth)?
+        .config_dir(COOK_DIR.to_string())
+        .walk();
+    for entry in walk {
+        match entry {
+            Ok(entry) if entry.is_cooklang_file() => {
+                let dir = entry.path().parent().unwrap_or(Utf8Path::new(""));
+                recipes
+                    .entry(dir.to_path_buf())
+                    .or_default()
+                    .push(entry.file_stem().to_string());
+            }
+            Ok(entry) if entry.file_type().is_file() && entry.is_image() => {
+                images.push(entry.into_path())
+            }
+            Ok(_) => {}
+            Err(WalkError::NonUtf8Name(e)) => non_utf8.push(e.path().to_path_buf()),
+            Err(e) => io_errors.push(e),
+        }
+    }
+    let orphans = images
+        .into_iter()
+        .filter(|image| !has_recipe(image, &recipes))
+        .collect::<Vec<_>>();
+
+    if io_errors.is_empty() {
+        report.ok("All files and directories are readable");
+    }
+    for e in io_errors {
+        report.error(e, "Check the permissions of the file or directory");
+    }
+    if non_utf8.is_empty() {
+        report.ok("All paths are UTF-8");
+    }
+    for path in non_utf8 {
+        report.error(
+            format!("Not UTF-8: {}", path.display()),
+            "chef ignores this file, rename it",
+        );
+    }
+    if orphans.is_empty() {
+        report.ok("No orphan images");
+    }
+    for image in orphans {
+        report.warn(
+            format!("Orphan image: {image}"),
+            "Images are named like the recipe, `Pasta.jpg` or `Pasta.1.jpg` for `Pasta.cook`",
+        );
+    }
+    Ok(())
+}
+
+/// If an image is named like a recipe that can have it, see
+/// [`cooklang_fs::recipe_images`]
+///
+/// `recipes` are the names of the recipes in each dir.
+fn has_recipe(image: &Utf8Path, recipes: &HashMap<Utf8PathBuf, Vec<String>>) -> bool {
+    let (Some(dir), Some(name)) = (image.parent(), image.file_name()) else {
+        return false;
+    };
+    let mut candidates = recipes
+        .get(dir)
+        .into_iter()
+        .flatten()
+        .map(String::as_str)
+        .collect::<Vec<_>>();
+    // or in a dir next to the recipes
+    if let (Some(parent), Some(dir_name)) = (dir.parent(), dir.file_name()) {
+        let mut outer = recipes.get(parent).into_iter().flatten();
+        if dir_name == IMAGES_DIR {
+            candidates.extend(outer.map(String::as_str));
+        } else if outer.any(|r| r == dir_name) {
+            candidates.push(dir_name);
+        }
+    }
+    candidates.iter().any(|r| {
+        name.strip_prefix(r)
+            .is_some_and(|rest| rest.starts_with('.'))
+    })
+}
+
+fn check_recipes(ctx: &Context, report: &mut Report) -> Result<()> {
+    let mut total = 0;
+    let mut problems = false;
+    for entry in all_recipes(&ctx.base_path, ctx.config.max_depth)? {
+        total += 1;
+        let path = entry.path().to_owned();
+        let images = entry.images().to_vec();
+        let input = Input::File {
+            entry,
+            override_name: None,
+        };
+        let res = match input.parse_result(ctx) {
+            Ok(res) => res,
+            Err(e) => {
+                problems = true;
+                report.error(format!("{path}: {e:#}"), "Check the file can be read");
+                continue;
+            }
+        };
+        let diags = res.report();
+        let errors = diags
+            .iter()
+            .filter(|d| matches!(d.severity, Severity::Error))
+            .count();
+        if errors > 0 {
+            problems = true;
+            report.error(
+                format!("{path}: {errors} errors"),
+                &format!("See them with `chef recipe \"{path}\"`"),
+            );
+        }
+        let broken_refs = diags
+            .iter()
+            .filter(|d| d.hints.iter().any(|h| h == RECIPE_REF_ERROR))
+            .count();
+        if broken_refs > 0 {
+            problems = true;
+            report.warn(
+                format!("{path}: {broken_refs} references to recipes not found"),
+                &format!("See them with `chef recipe \"{path}\"`"),
+            );
+        }
+        if let Some(recipe) = res.output() {
+            if let Err(image_errors) = check_recipe_images(&images, recipe) {
+                problems = true;
+                for e in image_errors {
+                    report.warn(e, "Rename the image to point to an existing step");
+                }
+            }
+        }
+    }
+    if !ctx.config.recipe_ref_check {
+        report.warn(
+            "Recipe references are not checked",
+            "Enable `recipe_ref_check` in the config",
+        );
+    }
+    if !problems {
+        report.ok(format!("{total} recipes parsed without errors"));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn image_recipe() {
+        let recipes = HashMap::from([
+            ("c".into(), vec!["Pasta".to_string()]),
+            ("c/Soup".into(), vec![]),
+        ]);
+        let has = |image: &str| has_recipe(Utf8Path::new(image), &recipes);
+        assert!(has("c/Pasta.jpg"));
+        assert!(has("c/Pasta.1.jpg"));
+        assert!(has("c/images/Pasta.png"));
+        assert!(has("c/Pasta/Pasta.png"));
+        assert!(!has("c/Pastas.jpg"));
+        assert!(!has("c/Pizza.jpg"));
+        assert!(!has("c/Soup/Soup.jpg"));
+    }
+}
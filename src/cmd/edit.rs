@@ -1,7 +1,7 @@
-use anyhow::{Context as _, Result};
+use anyhow::Result;
 use clap::Args;
 
-use crate::Context;
+use crate::{util::open_editor, Context};
 
 #[derive(Debug, Args)]
 pub struct EditArgs {
@@ -10,24 +10,7 @@ pub struct EditArgs {
 }
 
 pub fn run(args: EditArgs, ctx: &Context) -> Result<()> {
+    ctx.check_fs_write()?;
     let entry = ctx.recipe_index.resolve(&args.name, None)?;
-    let path = entry.path();
-
-    let editor = ctx
-        .chef_config
-        .editor()
-        .context("Could not determine editor")?;
-    let (cmd, args) = editor.split_first().expect("empty editor cmd");
-
-    let ok = std::process::Command::new(cmd)
-        .args(args)
-        .arg(path)
-        .status()?
-        .success();
-
-    if !ok {
-        tracing::warn!("Editor didn't exit successfully")
-    }
-
-    Ok(())
+    open_editor(ctx, entry.path())
 }
@@ -0,0 +1,288 @@
+use std::ops::Range;
+
+use anstream::println;
+use anyhow::{bail, Result};
+use clap::Args;
+use cooklang::{metadata::CooklangValueExt, model::Item, Content, ScalableRecipe};
+use cooklang_fs::all_recipes;
+use yansi::Paint;
+
+use crate::{
+    exit_code::ExitError,
+    util::{has_tag, CachedRecipeEntry},
+    Context,
+};
+
+#[derive(Debug, Args)]
+pub struct GrepArgs {
+    /// Recipes using an ingredient
+    ///
+    /// Matches if the ingredient name contains the given text, ignoring
+    /// case. Can be specified multiple times.
+    #[arg(short, long, value_name = "NAME")]
+    ingredient: Vec<String>,
+
+    /// Recipes using a cookware item
+    ///
+    /// Same as `--ingredient` but for cookware.
+    #[arg(short, long, value_name = "NAME")]
+    cookware: Vec<String>,
+
+    /// Recipes with a tag
+    #[arg(short, long)]
+    tag: Vec<String>,
+
+    /// Recipes with some text in the steps, ignoring case
+    #[arg(short = 'x', long)]
+    text: Vec<String>,
+
+    /// Recipes with a metadata key, optionally containing a value
+    ///
+    /// The format is `KEY` or `KEY=VALUE`.
+    #[arg(short, long, value_name = "KEY[=VALUE]")]
+    meta: Vec<String>,
+
+    /// Only print the matching recipe paths
+    #[arg(short = 'l', long)]
+    files_with_matches: bool,
+
+    /// Force to search recipes even outside a collection
+    #[arg(short, long)]
+    force: bool,
+}
+
+impl GrepArgs {
+    fn needs_full_parse(&self) -> bool {
+        !(self.ingredient.is_empty() && self.cookware.is_empty() && self.text.is_empty())
+    }
+
+    fn is_empty(&self) -> bool {
+        !self.needs_full_parse() && self.tag.is_empty() && self.meta.is_empty()
+    }
+
+    /// Texts to highlight in the recipe source
+    fn needles(&self) -> impl Iterator<Item = &str> + Clone {
+        self.ingredient
+            .iter()
+            .chain(&self.cookware)
+            .chain(&self.text)
+            .map(String::as_str)
+    }
+}
+
+pub fn run(ctx: &Context, args: GrepArgs) -> Result<()> {
+    if !args.force && !ctx.is_collection {
+        bail!(ExitError::usage(
+            "`grep` needs to run inside a collection or pass `--force`"
+        ));
+    }
+    if args.is_empty() {
+        bail!(ExitError::usage("At least one search predicate is needed"));
+    }
+
+    let mut matches = all_recipes(&ctx.base_path, ctx.config.max_depth)?
+        .map(CachedRecipeEntry::new)
+        .filter(|entry| !entry.is_hidden_draft(ctx))
+        .filter(|entry| match is_match(ctx, &args, entry) {
+            Ok(m) => m,
+            Err(err) => {
+                tracing::warn!("Skipping '{}': {err}", entry.path());
+                false
+            }
+        })
+        .collect::<Vec<_>>();
+    matches.sort_unstable_by(|a, b| a.path().cmp(b.path()));
+    ctx.save_metadata_cache();
+
+    for entry in &matches {
+        if args.files_with_matches {
+            println!("{}", entry.path());
+            continue;
+        }
+        println!("{}", entry.path().bold().magenta());
+        let content = entry.read()?;
+        print_highlighted(content.text(), args.needles());
+    }
+
+    if matches.is_empty() {
+        bail!("No recipes found");
+    }
+    Ok(())
+}
+
+fn is_match(ctx: &Context, args: &GrepArgs, entry: &CachedRecipeEntry) -> Result<bool> {
+    let metadata = entry.metadata(ctx, args.needs_full_parse())?;
+
+    let tags_match = args
+        .tag
+        .iter()
+        .all(|t| has_tag(metadata, t, &ctx.config.tag_aliases));
+    let meta_match = args.meta.iter().all(|m| {
+        let (key, value) = match m.split_once('=') {
+            Some((key, value)) => (key, Some(value)),
+            None => (m.as_str(), None),
+        };
+        let Some(entry_value) = metadata.get(key.trim()) else {
+            return false;
+        };
+        match value {
+            Some(value) => entry_value
+                .as_str_like()
+                .is_some_and(|v| !find_ignore_case(&v, value.trim()).is_empty()),
+            None => true,
+        }
+    });
+    if !(tags_match && meta_match) {
+        return Ok(false);
+    }
+
+    if !args.needs_full_parse() {
+        return Ok(true);
+    }
+    let Some(recipe) = entry.parsed(ctx)?.output() else {
+        bail!(ExitError::recipe("could not parse recipe"));
+    };
+    Ok(recipe_match(args, recipe))
+}
+
+fn recipe_match(args: &GrepArgs, recipe: &ScalableRecipe) -> bool {
+    let contains = |haystack: &str, needle: &str| !find_ignore_case(haystack, needle).is_empty();
+
+    let ingredients_match = args.ingredient.iter().all(|needle| {
+        recipe
+            .ingredients
+            .iter()
+            .any(|igr| contains(&igr.name, needle))
+    });
+    let cookware_match = args
+        .cookware
+        .iter()
+        .all(|needle| recipe.cookware.iter().any(|cw| contains(&cw.name, needle)));
+    let text_match = args.text.iter().all(|needle| {
+        recipe
+            .sections
+            .iter()
+            .flat_map(|s| &s.content)
+            .any(|content| match content {
+                Content::Step(step) => step.items.iter().any(|item| match item {
+                    Item::Text { value } => contains(value, needle),
+                    _ => false,
+                }),
+                Content::Text(text) => contains(text, needle),
+            })
+    });
+
+    ingredients_match && cookware_match && text_match
+}
+
+fn print_highlighted<'a>(text: &str, needles: impl Iterator<Item = &'a str> + Clone) {
+    for (line_no, line) in text.lines().enumerate() {
+        let spans = match_spans(line, needles.clone());
+        if spans.is_empty() {
+            continue;
+        }
+
+        let mut highlighted = String::with_capacity(line.len());
+        let mut last = 0;
+        for span in spans {
+            highlighted.push_str(&line[last..span.start]);
+            highlighted.push_str(&line[span.clone()].red().bold().to_string());
+            last = span.end;
+        }
+        highlighted.push_str(&line[last..]);
+        println!(
+            "{:>4}{} {}",
+            (line_no + 1).green(),
+            ":".dim(),
+            highlighted.trim()
+        );
+    }
+}
+
+/// Sorted and non overlapping byte ranges of the matches of any needle
+fn match_spans<'a>(line: &str, needles: impl Iterator<Item = &'a str>) -> Vec<Range<usize>> {
+    let mut spans = needles
+        .flat_map(|needle| find_ignore_case(line, needle))
+        .collect::<Vec<_>>();
+    spans.sort_unstable_by_key(|s| s.start);
+    let mut last = 0;
+    spans.retain(|span| {
+        let keep = span.start >= last;
+        if keep {
+            last = span.end;
+        }
+        keep
+    });
+    spans
+}
+
+/// Byte ranges of the non overlapping matches of `needle` in `haystack`,
+/// ignoring case
+///
+/// The text is compared char by char, so the ranges are always char
+/// boundaries of `haystack`, even if lowercasing changes the length of a char.
+fn find_ignore_case(haystack: &str, needle: &str) -> Vec<Range<usize>> {
+    let mut matches = Vec::new();
+    if needle.is_empty() {
+        return matches;
+    }
+
+    let mut start = 0;
+    'outer: while start < haystack.len() {
+        let mut needle_chars = needle.chars().flat_map(char::to_lowercase);
+        for (i, c) in haystack[start..].char_indices() {
+            let mut lower = c.to_lowercase();
+            let matched = lower.all(|lc| needle_chars.next() == Some(lc));
+            if !matched {
+                break;
+            }
+            let end = start + i + c.len_utf8();
+            if needle_chars.clone().next().is_none() {
+                matches.push(start..end);
+                start = end;
+                continue 'outer;
+            }
+        }
+        // advance one char
+        start += haystack[start..].chars().next().map_or(1, char::len_utf8);
+    }
+    matches
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_find_ignore_case() {
+        assert_eq!(
+            find_ignore_case("Chicken and chicken", "chicken"),
+            [0..7, 12..19]
+        );
+        assert_eq!(find_ignore_case("Add the CHICKEN", "Chicken"), [8..15]);
+        assert_eq!(find_ignore_case("jalapeño", "PEÑO"), [4..9]);
+        assert_eq!(find_ignore_case("aaa", "aa"), [0..2]);
+        assert!(find_ignore_case("chick", "chicken").is_empty());
+        assert!(find_ignore_case("anything", "").is_empty());
+    }
+
+    #[test]
+    fn find_ignore_case_non_ascii() {
+        assert_eq!(find_ignore_case("crème brûlée", "BRÛLÉE"), [7..15]);
+        // the kelvin sign is 3 bytes and lowercases to `k`
+        assert_eq!(find_ignore_case("\u{212A}ale", "kale"), [0..6]);
+        assert_eq!(find_ignore_case("kale", "\u{212A}ALE"), [0..4]);
+        // `ẞ` is 3 bytes and `ß` 2
+        assert_eq!(find_ignore_case("STRAẞE", "straße"), [0..8]);
+        // `İ` lowercases to 2 chars
+        assert!(find_ignore_case("İ", "i").is_empty());
+    }
+
+    #[test]
+    fn spans_are_char_boundaries() {
+        let line = "Añade el AJÍ y el ají, y luego más ají";
+        let spans = match_spans(line, ["ají", "JÍ Y", "más"].into_iter());
+        let found = spans.iter().map(|s| &line[s.clone()]).collect::<Vec<_>>();
+        assert_eq!(found, ["AJÍ", "ají", "más", "ají"]);
+    }
+}
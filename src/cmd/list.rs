@@ -1,9 +1,15 @@
-use anstream::print;
+use std::{cmp::Reverse, collections::BTreeMap};
+
+use anstream::{print, println};
 use anyhow::{bail, Result};
-use clap::{builder::ArgPredicate, Args};
+use clap::{builder::ArgPredicate, Args, ValueEnum};
 use cooklang_fs::all_recipes;
 
-use crate::{util::CachedRecipeEntry, Context};
+use crate::{
+    exit_code::ExitError,
+    util::{has_tag, recipe_tags, CachedRecipeEntry, MetaField},
+    Context, COOK_DIR,
+};
 
 #[derive(Debug, Args)]
 pub struct ListArgs {
@@ -19,6 +25,18 @@ pub struct ListArgs {
     #[arg(short, long)]
     tag: Vec<String>,
 
+    /// Filter entries by difficulty, like easy, medium or hard
+    #[arg(long)]
+    difficulty: Option<String>,
+
+    /// Filter entries by cuisine
+    #[arg(long)]
+    cuisine: Option<String>,
+
+    /// Filter entries by course, like breakfast, main or dessert
+    #[arg(long)]
+    course: Option<String>,
+
     /// Show tags in the list
     #[arg(short = 'T',
         long,
@@ -45,19 +63,67 @@ pub struct ListArgs {
     #[arg(short = 'n', long, conflicts_with_all = ["paths", "absolute_paths"])]
     count: bool,
 
+    /// Show the recipes as a tree of directories
+    ///
+    /// Each directory has the number of recipes in it. Recipes whose metadata
+    /// can't be parsed are in red.
+    #[arg(long, conflicts_with_all = ["count", "paths", "absolute_paths", "sort"])]
+    tree: bool,
+
+    /// Order of the list
+    #[arg(long, value_enum, default_value_t = SortBy::Name, conflicts_with = "count")]
+    sort: SortBy,
+
     /// Force to list recipes even outside a collection
     #[arg(short, long)]
     force: bool,
+
+    /// List recipe names used by more than one recipe
+    ///
+    /// Only the first path listed is found when referencing the recipe just
+    /// by its name.
+    #[arg(long, conflicts_with_all = ["check", "images", "tag", "difficulty", "cuisine", "course", "long", "count", "tree"])]
+    duplicates: bool,
+}
+
+#[derive(Debug, Clone, Copy, ValueEnum)]
+enum SortBy {
+    /// By path
+    Name,
+    /// Last modified first
+    Modified,
+    /// Last created first
+    Created,
+    /// Biggest first
+    Size,
 }
 
 pub fn run(ctx: &Context, args: ListArgs) -> Result<()> {
     if !args.force && !ctx.is_collection {
-        bail!("`list` needs to run inside a collection or pass `--force`");
+        bail!(ExitError::usage(
+            "`list` needs to run inside a collection or pass `--force`"
+        ));
+    }
+
+    if args.duplicates {
+        return list_duplicates(ctx, &args);
     }
 
+    let fields = [
+        (MetaField::Difficulty, &args.difficulty),
+        (MetaField::Cuisine, &args.cuisine),
+        (MetaField::Course, &args.course),
+    ]
+    .into_iter()
+    .filter_map(|(field, value)| Some((field, field.normalize(value.as_deref()?))))
+    .collect::<Vec<_>>();
+
     let iter = all_recipes(&ctx.base_path, ctx.config.max_depth)?.filter_map(|entry| {
         let entry = CachedRecipeEntry::new(entry);
-        if args.tag.is_empty() {
+        if entry.is_hidden_draft(ctx) {
+            return None;
+        }
+        if args.tag.is_empty() && fields.is_empty() {
             return Some(entry);
         }
         let m = entry.metadata(ctx, args.check); // try full parse if check to avoid parsing the recipe twice
@@ -65,11 +131,17 @@ pub fn run(ctx: &Context, args: ListArgs) -> Result<()> {
             tracing::warn!("Skipping '{}': could not parse metadata", entry.path());
             return None;
         };
-        if !args.tag.iter().all(|t| {
-            metadata
-                .tags()
-                .is_some_and(|tags| tags.iter().any(|tt| tt == t))
-        }) {
+        if !args
+            .tag
+            .iter()
+            .all(|t| has_tag(metadata, t, &ctx.config.tag_aliases))
+        {
+            return None;
+        }
+        if !fields
+            .iter()
+            .all(|(field, value)| field.get(metadata).as_ref() == Some(value))
+        {
             return None;
         }
         Some(entry)
@@ -113,10 +185,21 @@ pub fn run(ctx: &Context, args: ListArgs) -> Result<()> {
             table.add_row(row!("Total images", total_images));
         }
         print!("{table}");
+    } else if args.tree {
+        let mut all = iter.collect::<Vec<_>>();
+        all.sort_unstable_by(|a, b| a.path().cmp(b.path()));
+        print_tree(ctx, &all);
     } else {
         let mut table = tabular::Table::new("{:<}{:<}{:<}{:<}");
         let mut all = iter.collect::<Vec<_>>();
         all.sort_unstable_by(|a, b| a.path().cmp(b.path()));
+        // the info is read while walking the collection, not for each recipe
+        match args.sort {
+            SortBy::Name => {}
+            SortBy::Modified => all.sort_by_key(|e| Reverse(e.info().and_then(|i| i.modified))),
+            SortBy::Created => all.sort_by_key(|e| Reverse(e.info().and_then(|i| i.created))),
+            SortBy::Size => all.sort_by_key(|e| Reverse(e.info().map(|i| i.size))),
+        }
         for entry in &all {
             let row = list_row(ctx, &args, entry)?;
             table.add_row(row);
@@ -124,9 +207,102 @@ pub fn run(ctx: &Context, args: ListArgs) -> Result<()> {
         print!("{table}");
     }
 
+    ctx.save_metadata_cache();
+    Ok(())
+}
+
+fn list_duplicates(ctx: &Context, args: &ListArgs) -> Result<()> {
+    use yansi::Paint;
+
+    let index = cooklang_fs::new_index(&ctx.base_path, ctx.config.max_depth)?
+        .config_dir(COOK_DIR.to_string())
+        .ignore_diacritics(ctx.config.ignore_diacritics)
+        .indexed();
+    let conflicts = index.conflicts();
+    if conflicts.is_empty() {
+        ctx.status("No duplicate recipe names");
+        return Ok(());
+    }
+    for (name, paths) in conflicts {
+        println!("{}", name.bold());
+        for (i, path) in paths.iter().enumerate() {
+            let path = if args.absolute_paths {
+                path.canonicalize()?.to_string_lossy().to_string()
+            } else if args.paths {
+                path.to_string()
+            } else {
+                path.strip_prefix(&ctx.base_path)
+                    .unwrap_or(path)
+                    .to_string()
+            };
+            if i == 0 {
+                println!("  {path} {}", "(found by name)".green());
+            } else {
+                println!("  {}", path.dim());
+            }
+        }
+    }
     Ok(())
 }
 
+#[derive(Default)]
+struct TreeDir<'a> {
+    dirs: BTreeMap<&'a str, TreeDir<'a>>,
+    recipes: Vec<&'a CachedRecipeEntry>,
+    /// Recipes in this dir and the ones inside
+    count: usize,
+}
+
+fn print_tree(ctx: &Context, entries: &[CachedRecipeEntry]) {
+    use yansi::Paint;
+
+    let mut root = TreeDir::default();
+    for entry in entries {
+        let relative = entry
+            .path()
+            .strip_prefix(&ctx.base_path)
+            .unwrap_or(entry.path());
+        let mut dir = &mut root;
+        dir.count += 1;
+        for component in relative.parent().into_iter().flat_map(|p| p.iter()) {
+            dir = dir.dirs.entry(component).or_default();
+            dir.count += 1;
+        }
+        dir.recipes.push(entry);
+    }
+
+    let name = ctx.base_path.file_name().unwrap_or(".");
+    println!("{} ({})", name.cyan().bold(), root.count);
+    print_tree_dir(ctx, &root, "");
+}
+
+fn print_tree_dir(ctx: &Context, dir: &TreeDir, prefix: &str) {
+    use yansi::Paint;
+
+    let total = dir.dirs.len() + dir.recipes.len();
+    let lines = |i: usize| {
+        if i + 1 == total {
+            ("└── ", "    ")
+        } else {
+            ("├── ", "│   ")
+        }
+    };
+    for (i, (name, sub)) in dir.dirs.iter().enumerate() {
+        let (branch, indent) = lines(i);
+        println!("{prefix}{branch}{} ({})", name.cyan().bold(), sub.count);
+        print_tree_dir(ctx, sub, &format!("{prefix}{indent}"));
+    }
+    for (i, entry) in dir.recipes.iter().enumerate() {
+        let (branch, _) = lines(dir.dirs.len() + i);
+        let name = entry.name();
+        if entry.metadata(ctx, false).is_ok() {
+            println!("{prefix}{branch}{name}");
+        } else {
+            println!("{prefix}{branch}{}", name.red());
+        }
+    }
+}
+
 fn list_row(ctx: &Context, args: &ListArgs, entry: &CachedRecipeEntry) -> Result<tabular::Row> {
     use yansi::Paint;
 
@@ -156,7 +332,8 @@ fn list_row(ctx: &Context, args: &ListArgs, entry: &CachedRecipeEntry) -> Result
 
     if args.tags {
         if let Ok(metadata) = entry.metadata(ctx, args.check) {
-            if let Some(tags) = metadata.tags() {
+            let tags = recipe_tags(metadata, &ctx.config.tag_aliases);
+            if !tags.is_empty() {
                 row.add_cell(format!(" [{}]", tags.join(", ")));
             } else {
                 row.add_ansi_cell(format!(" [{}]", "-".dim()));
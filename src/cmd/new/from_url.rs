@@ -0,0 +1,463 @@
+//! Scaffold a recipe from the schema.org data of a web page
+
+use std::{fmt::Write, io::Read};
+
+use anyhow::{bail, Context as _, Result};
+use cooklang::Converter;
+use serde_json::Value;
+
+use crate::util::{is_valid_tag, regex};
+
+/// Recipe data extracted from a web page
+#[derive(Debug, Default)]
+pub struct Imported {
+    pub name: Option<String>,
+    /// Cooklang text of the recipe
+    pub text: String,
+}
+
+/// Fetches a web page and converts its recipe to cooklang
+///
+/// If `url` is `-` the page is read from stdin.
+pub fn import(url: &str, converter: &Converter) -> Result<Imported> {
+    let html = if url == "-" {
+        let mut buf = String::new();
+        std::io::stdin()
+            .read_to_string(&mut buf)
+            .context("Failed to read page from stdin")?;
+        buf
+    } else {
+        ureq::get(url)
+            .set("User-Agent", concat!("chef/", env!("CARGO_PKG_VERSION")))
+            .call()
+            .with_context(|| format!("Failed to fetch {url}"))?
+            .into_string()
+            .context("Failed to read the page")?
+    };
+
+    let Some(recipe) = find_recipe(&html) else {
+        bail!("No schema.org recipe found in the page");
+    };
+    let source = (url != "-").then_some(url);
+    Ok(scaffold(&recipe, source, converter))
+}
+
+/// Finds the first schema.org `Recipe` in the JSON-LD blocks of the page
+fn find_recipe(html: &str) -> Option<Value> {
+    let re = regex!(r#"(?is)<script[^>]*application/ld\+json[^>]*>(.*?)</script>"#);
+    re.captures_iter(html)
+        .filter_map(|cap| serde_json::from_str::<Value>(cap[1].trim()).ok())
+        .find_map(|json| find_recipe_node(&json).cloned())
+}
+
+fn find_recipe_node(value: &Value) -> Option<&Value> {
+    match value {
+        Value::Array(items) => items.iter().find_map(find_recipe_node),
+        Value::Object(obj) => {
+            let is_recipe = match obj.get("@type") {
+                Some(Value::String(t)) => t == "Recipe",
+                Some(Value::Array(types)) => types.iter().any(|t| t == "Recipe"),
+                _ => false,
+            };
+            if is_recipe {
+                Some(value)
+            } else {
+                obj.get("@graph").and_then(find_recipe_node)
+            }
+        }
+        _ => None,
+    }
+}
+
+fn scaffold(recipe: &Value, source: Option<&str>, converter: &Converter) -> Imported {
+    let name = recipe.get("name").and_then(text);
+    let mut out = String::new();
+
+    // metadata
+    let mut meta = |key: &str, value: Option<String>| {
+        if let Some(value) = value.filter(|v| !v.is_empty()) {
+            let _ = writeln!(out, ">> {key}: {value}");
+        }
+    };
+    meta("description", recipe.get("description").and_then(text));
+    meta("servings", recipe.get("recipeYield").and_then(servings));
+    meta("time", recipe.get("totalTime").and_then(duration));
+    meta("prep_time", recipe.get("prepTime").and_then(duration));
+    meta("cook_time", recipe.get("cookTime").and_then(duration));
+    meta("author", recipe.get("author").and_then(text));
+    meta("source", source.map(String::from));
+    let tags = tags(recipe);
+    meta("tags", (!tags.is_empty()).then(|| tags.join(", ")));
+
+    // ingredients
+    let ingredients = recipe
+        .get("recipeIngredient")
+        .or_else(|| recipe.get("ingredients"))
+        .map(list)
+        .unwrap_or_default();
+    if !ingredients.is_empty() {
+        out.push_str("\n== Ingredients ==\n");
+        for line in &ingredients {
+            out.push_str(&ingredient_line(line, converter));
+            out.push('\n');
+        }
+    }
+
+    // steps
+    let sections = recipe
+        .get("recipeInstructions")
+        .map(instructions)
+        .unwrap_or_default();
+    for (index, (section, steps)) in sections.iter().enumerate() {
+        match section {
+            Some(name) => {
+                let _ = write!(out, "\n== {name} ==\n");
+            }
+            None if index > 0 || !ingredients.is_empty() => out.push_str("\n====\n"),
+            None => {}
+        }
+        for step in steps {
+            let _ = write!(out, "\n{step}\n");
+        }
+    }
+
+    Imported { name, text: out }
+}
+
+/// Converts a free text ingredient to a cooklang ingredient
+///
+/// When the quantity cannot be extracted, the original line is kept in a
+/// comment so it can be checked by hand.
+fn ingredient_line(line: &str, converter: &Converter) -> String {
+    let re = regex!(
+        r"^\s*(\d+\s+\d+/\d+|\d+/\d+|\d+(?:[.,]\d+)?)(?:\s*(?:-|–|to)\s*(\d+/\d+|\d+(?:[.,]\d+)?))?\s*(.*)$"
+    );
+    let line = replace_fractions(line);
+
+    let Some(cap) = re.captures(&line) else {
+        let (name, note) = split_name(&line);
+        return format!("@{name}{{}}{note} -- check: {}", line.trim());
+    };
+
+    let mut value = number(&cap[1]);
+    if let Some(to) = cap.get(2) {
+        value = format!("{value}-{}", number(to.as_str()));
+    }
+    let rest = cap[3].trim();
+
+    let (unit, rest) = match rest.split_once(char::is_whitespace) {
+        Some((word, rest)) if is_unit(word, converter) => (Some(word), rest),
+        _ => (None, rest),
+    };
+    let (name, note) = split_name(rest);
+    if name.is_empty() {
+        return format!("@ingredient{{{value}}} -- check: {}", line.trim());
+    }
+    match unit {
+        Some(unit) => format!("@{name}{{{value}%{}}}{note}", unit.trim_end_matches('.')),
+        None => format!("@{name}{{{value}}}{note}"),
+    }
+}
+
+fn is_unit(word: &str, converter: &Converter) -> bool {
+    let word = word.trim_end_matches('.');
+    converter.all_units().any(|u| {
+        u.names
+            .iter()
+            .chain(&u.symbols)
+            .chain(&u.aliases)
+            .any(|n| n.eq_ignore_ascii_case(word))
+    })
+}
+
+/// Splits the ingredient name from a note after a comma or in parentheses
+fn split_name(text: &str) -> (String, String) {
+    let text = text.trim();
+    let (name, note) = match text.find([',', '(']) {
+        Some(pos) => text.split_at(pos),
+        None => (text, ""),
+    };
+    let name = clean_name(name);
+    let note = note
+        .trim_start_matches(',')
+        .trim()
+        .trim_start_matches('(')
+        .trim_end_matches(')')
+        .replace(['(', ')'], "");
+    let note = if note.is_empty() {
+        String::new()
+    } else {
+        format!("({note})")
+    };
+    (name.trim().to_string(), note)
+}
+
+/// Removes the chars that would end or change the cooklang component
+///
+/// Dots are only kept inside numbers, like in `2.5%`, so abbreviations like
+/// `St.` lose them.
+fn clean_name(name: &str) -> String {
+    let chars = name.chars().collect::<Vec<_>>();
+    chars
+        .iter()
+        .enumerate()
+        .filter(|&(i, c)| match c {
+            '@' | '#' | '~' | '{' | '}' => false,
+            '.' => {
+                let digit_at = |i: Option<usize>| {
+                    i.and_then(|i| chars.get(i))
+                        .is_some_and(|c| c.is_ascii_digit())
+                };
+                digit_at(i.checked_sub(1)) && digit_at(Some(i + 1))
+            }
+            _ => true,
+        })
+        .map(|(_, c)| c)
+        .collect()
+}
+
+fn replace_fractions(s: &str) -> String {
+    let mut out = String::with_capacity(s.len());
+    for c in s.chars() {
+        let frac = match c {
+            '½' => "1/2",
+            '⅓' => "1/3",
+            '⅔' => "2/3",
+            '¼' => "1/4",
+            '¾' => "3/4",
+            '⅛' => "1/8",
+            _ => {
+                out.push(c);
+                continue;
+            }
+        };
+        if out.ends_with(|c: char| c.is_ascii_digit()) {
+            out.push(' ');
+        }
+        out.push_str(frac);
+    }
+    out
+}
+
+/// Cooklang does not support mixed numbers, so they are written as decimals
+fn number(s: &str) -> String {
+    let s = s.trim().replace(',', ".");
+    if let Some((whole, frac)) = s.split_once(char::is_whitespace) {
+        if let (Ok(whole), Some((n, d))) = (whole.parse::<f64>(), frac.trim().split_once('/')) {
+            if let (Ok(n), Ok(d)) = (n.parse::<f64>(), d.parse::<f64>()) {
+                if d != 0.0 {
+                    let v = ((whole + n / d) * 100.0).round() / 100.0;
+                    return v.to_string();
+                }
+            }
+        }
+    }
+    s
+}
+
+/// Gets text from a string or an object with a name or text
+fn text(value: &Value) -> Option<String> {
+    let s = match value {
+        Value::String(s) => clean_html(s),
+        Value::Object(obj) => return obj.get("name").or_else(|| obj.get("text")).and_then(text),
+        Value::Array(items) => {
+            let all = items.iter().filter_map(text).collect::<Vec<_>>();
+            all.join(", ")
+        }
+        Value::Number(n) => n.to_string(),
+        _ => return None,
+    };
+    let s = s.split_whitespace().collect::<Vec<_>>().join(" ");
+    (!s.is_empty()).then_some(s)
+}
+
+fn list(value: &Value) -> Vec<String> {
+    match value {
+        Value::Array(items) => items.iter().filter_map(text).collect(),
+        other => text(other).into_iter().collect(),
+    }
+}
+
+/// Sections of steps, from `HowToSection`s, `HowToStep`s or plain text
+fn instructions(value: &Value) -> Vec<(Option<String>, Vec<String>)> {
+    let mut sections = vec![(None, Vec::new())];
+    match value {
+        Value::Array(items) => items
+            .iter()
+            .for_each(|item| add_instruction(item, &mut sections)),
+        Value::String(s) => {
+            let s = clean_html_keep_lines(s);
+            for line in s.lines().map(str::trim).filter(|l| !l.is_empty()) {
+                sections[0].1.push(line.to_string());
+            }
+        }
+        other => add_instruction(other, &mut sections),
+    }
+    sections.retain(|(name, steps)| name.is_some() || !steps.is_empty());
+    sections
+}
+
+fn add_instruction(value: &Value, sections: &mut Vec<(Option<String>, Vec<String>)>) {
+    match value.get("@type").and_then(Value::as_str) {
+        Some("HowToSection") => {
+            let steps = value
+                .get("itemListElement")
+                .map(instructions)
+                .unwrap_or_default()
+                .into_iter()
+                .flat_map(|(_, s)| s)
+                .collect();
+            sections.push((value.get("name").and_then(text), steps));
+        }
+        _ => {
+            let step = match value {
+                Value::Object(obj) => obj.get("text").or_else(|| obj.get("name")).and_then(text),
+                _ => text(value),
+            };
+            if let Some(step) = step {
+                sections.last_mut().unwrap().1.push(step);
+            }
+        }
+    }
+}
+
+fn servings(value: &Value) -> Option<String> {
+    match value {
+        Value::Number(n) => Some(n.to_string()),
+        Value::String(s) => {
+            let n: String = s
+                .trim()
+                .chars()
+                .take_while(|c| c.is_ascii_digit())
+                .collect();
+            (!n.is_empty()).then_some(n)
+        }
+        Value::Array(items) => items.iter().find_map(servings),
+        _ => None,
+    }
+}
+
+fn tags(recipe: &Value) -> Vec<String> {
+    let mut tags = Vec::new();
+    for key in ["keywords", "recipeCategory", "recipeCuisine"] {
+        let Some(value) = recipe.get(key) else {
+            continue;
+        };
+        let words = match value {
+            Value::String(s) => s.split(',').map(str::to_string).collect(),
+            other => list(other),
+        };
+        for word in words {
+            let tag = slug(&word);
+            if is_valid_tag(&tag) && !tags.contains(&tag) {
+                tags.push(tag);
+            }
+        }
+    }
+    tags
+}
+
+fn slug(s: &str) -> String {
+    let mut out = String::new();
+    for c in s.trim().chars().flat_map(char::to_lowercase) {
+        if c.is_alphanumeric() {
+            out.push(c);
+        } else if !out.is_empty() && !out.ends_with('-') {
+            out.push('-');
+        }
+    }
+    out.trim_end_matches('-').to_string()
+}
+
+/// Converts an ISO 8601 duration like `PT1H30M` to `1 h 30 min`
+fn duration(value: &Value) -> Option<String> {
+    let s = value.as_str()?.trim();
+    let s = s.strip_prefix('P')?;
+    let (days, time) = match s.split_once('T') {
+        Some((d, t)) => (d, t),
+        None => (s, ""),
+    };
+    let mut minutes = 0u64;
+    let mut num = String::new();
+    for (part, is_time) in [(days, false), (time, true)] {
+        for c in part.chars() {
+            if c.is_ascii_digit() {
+                num.push(c);
+                continue;
+            }
+            let n: u64 = num.parse().ok()?;
+            num.clear();
+            minutes += match (c, is_time) {
+                ('D', false) => n * 24 * 60,
+                ('H', true) => n * 60,
+                ('M', true) => n,
+                ('S', true) => 0,
+                _ => return None,
+            };
+        }
+    }
+    if minutes == 0 {
+        return None;
+    }
+    let (h, m) = (minutes / 60, minutes % 60);
+    Some(match (h, m) {
+        (0, m) => format!("{m} min"),
+        (h, 0) => format!("{h} h"),
+        (h, m) => format!("{h} h {m} min"),
+    })
+}
+
+fn clean_html(s: &str) -> String {
+    clean_html_keep_lines(s).replace('\n', " ")
+}
+
+fn clean_html_keep_lines(s: &str) -> String {
+    let s = regex!(r"(?i)<br\s*/?>|</p>").replace_all(s, "\n");
+    let s = regex!(r"<[^>]*>").replace_all(&s, "");
+    s.replace("&amp;", "&")
+        .replace("&quot;", "\"")
+        .replace("&#39;", "'")
+        .replace("&apos;", "'")
+        .replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&nbsp;", " ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ingredient_lines() {
+        let converter = Converter::bundled();
+        let line = |s: &str| ingredient_line(s, &converter);
+        assert_eq!(line("salt"), "@salt{} -- check: salt");
+        assert_eq!(line("3 eggs"), "@eggs{3}");
+        assert_eq!(line("2 cups flour, sifted"), "@flour{2%cups}(sifted)");
+        assert_eq!(line("1 ½ tbsp. olive oil"), "@olive oil{1.5%tbsp}");
+    }
+
+    #[test]
+    fn dotted_ingredient_names() {
+        let converter = Converter::bundled();
+        let line = |s: &str| ingredient_line(s, &converter);
+        assert_eq!(line("100 g St. Agur cheese"), "@St Agur cheese{100%g}");
+        assert_eq!(line("2 lg. eggs"), "@lg eggs{2}");
+        assert_eq!(line("1 bottle 7.5% beer."), "@bottle 7.5% beer{1}");
+        assert_eq!(
+            line("Mr. Brown's sauce"),
+            "@Mr Brown's sauce{} -- check: Mr. Brown's sauce"
+        );
+    }
+
+    #[test]
+    fn iso_duration() {
+        let d = |s: &str| duration(&Value::String(s.into()));
+        assert_eq!(d("PT45M").as_deref(), Some("45 min"));
+        assert_eq!(d("PT1H30M").as_deref(), Some("1 h 30 min"));
+        assert_eq!(d("P0DT2H0M").as_deref(), Some("2 h"));
+        assert_eq!(d("P1D").as_deref(), Some("24 h"));
+        assert_eq!(d("PT0S"), None);
+        assert_eq!(d("1 hour"), None);
+    }
+}
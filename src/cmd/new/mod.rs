@@ -0,0 +1,82 @@
+use std::fs;
+
+use anyhow::{bail, Result};
+use camino::Utf8Path;
+use clap::Args;
+
+use crate::{exit_code::ExitError, util::open_editor, Context};
+
+#[cfg(feature = "import")]
+mod from_url;
+
+#[derive(Debug, Args)]
+pub struct NewArgs {
+    /// Recipe name
+    ///
+    /// Split directories with "/". When importing, the default is the name
+    /// of the imported recipe.
+    #[cfg_attr(feature = "import", arg(required_unless_present = "from_url"))]
+    #[cfg_attr(not(feature = "import"), arg(required = true))]
+    name: Option<String>,
+
+    /// Import the recipe from a web page with schema.org recipe data
+    ///
+    /// The recipe is converted to cooklang and the URL is added as the
+    /// `source`. Ingredients that could not be converted are marked with a
+    /// comment, so review the recipe. Use `-` to read the page from stdin.
+    #[cfg(feature = "import")]
+    #[arg(long, value_name = "URL")]
+    from_url: Option<String>,
+
+    /// Skip opening the editor
+    #[arg(long, short = 'E')]
+    no_edit: bool,
+}
+
+pub fn run(args: NewArgs, ctx: &Context) -> Result<()> {
+    ctx.check_fs_write()?;
+
+    #[allow(unused_mut)]
+    let mut name = args.name;
+    #[allow(unused_mut)]
+    let mut content = String::new();
+
+    #[cfg(feature = "import")]
+    if let Some(url) = &args.from_url {
+        let imported = from_url::import(url, ctx.parser()?.converter())?;
+        if name.is_none() {
+            name = imported.name.map(|n| n.replace(['/', '\\'], "-"));
+        }
+        content = imported.text;
+    }
+
+    let Some(name) = name else {
+        bail!(ExitError::usage("Missing recipe name"));
+    };
+
+    let file = Utf8Path::new(&name).with_extension("cook");
+    let valid = !file.is_absolute()
+        && file
+            .components()
+            .all(|c| matches!(c, camino::Utf8Component::Normal(_)));
+    if !valid {
+        bail!("Invalid name: {}", name);
+    }
+
+    let path = ctx.base_path.join(file);
+
+    if path.is_file() {
+        bail!("File already exists: {}", path);
+    }
+
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    fs::write(&path, content)?;
+
+    if !args.no_edit {
+        open_editor(ctx, &path)?;
+    }
+
+    Ok(())
+}
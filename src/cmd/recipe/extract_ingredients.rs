@@ -0,0 +1,189 @@
+use anyhow::Result;
+use camino::Utf8PathBuf;
+use clap::{Args, ValueEnum};
+use cooklang::{ingredient_list::GroupedIngredient, quantity::Quantity, scale::ScaleOutcome};
+use serde::Serialize;
+
+use crate::{
+    cmd::shopping_list::split_servings,
+    util::{meta_name, write_to_output, Input},
+    Context,
+};
+
+#[derive(Debug, Args)]
+pub struct ExtractIngredientsArgs {
+    /// Recipes to extract the ingredients from with optional servings number
+    #[arg(required = true, value_name = "RECIPE[*<SERVINGS>]")]
+    recipes: Vec<String>,
+
+    /// Output file, none for stdout.
+    #[arg(short, long)]
+    output: Option<Utf8PathBuf>,
+
+    /// Output format
+    ///
+    /// Tries to infer it from output file extension. Defaults to "plain".
+    #[arg(short, long, value_enum)]
+    format: Option<ExtractFormat>,
+
+    /// Pretty output format, if available
+    #[arg(long)]
+    pretty: bool,
+}
+
+#[derive(Debug, Clone, Copy, ValueEnum, PartialEq)]
+enum ExtractFormat {
+    Plain,
+    Csv,
+    Json,
+}
+
+/// One row of the output. Ingredients with quantities in different units
+/// have a row for each one.
+#[derive(Debug, Serialize)]
+struct IngredientRow {
+    recipe: String,
+    name: String,
+    quantity: Option<String>,
+    unit: Option<String>,
+    optional: bool,
+    note: Option<String>,
+    /// The quantity is fixed or could not be scaled
+    not_scaled: bool,
+}
+
+pub fn run(ctx: &Context, args: ExtractIngredientsArgs) -> Result<()> {
+    let converter = ctx.parser()?.converter();
+
+    let mut rows = Vec::new();
+    for entry in &args.recipes {
+        let (query, servings) = split_servings(entry);
+        let input = Input::File {
+            entry: ctx.recipe_index.resolve(query, None)?,
+            override_name: None,
+        };
+        let recipe = input.parse(ctx)?;
+        let name = match meta_name(&recipe.metadata) {
+            Some(name) => name.to_string(),
+            None => input.name()?.to_string(),
+        };
+        let recipe = match servings {
+            Some(servings) => recipe.scale(servings, converter),
+            None => recipe.default_scale(),
+        };
+
+        for entry in recipe.group_ingredients(converter) {
+            let GroupedIngredient {
+                ingredient: igr,
+                quantity,
+                outcome,
+                ..
+            } = entry;
+            if !igr.modifiers().should_be_listed() {
+                continue;
+            }
+            let row = |quantity: Option<&Quantity>| IngredientRow {
+                recipe: name.clone(),
+                name: igr.display_name().to_string(),
+                quantity: quantity.map(|q| q.value().to_string()),
+                unit: quantity.and_then(|q| q.unit()).map(str::to_string),
+                optional: igr.modifiers().is_optional(),
+                note: igr.note.clone(),
+                not_scaled: matches!(outcome, Some(ScaleOutcome::Fixed | ScaleOutcome::Error(_))),
+            };
+            if quantity.is_empty() {
+                rows.push(row(None));
+            } else {
+                rows.extend(quantity.iter().map(|q| row(Some(q))));
+            }
+        }
+    }
+
+    let format = args.format.unwrap_or_else(|| match &args.output {
+        Some(p) => match p.extension() {
+            Some("json") => ExtractFormat::Json,
+            Some("csv") => ExtractFormat::Csv,
+            _ => ExtractFormat::Plain,
+        },
+        None => ExtractFormat::Plain,
+    });
+
+    write_to_output(args.output.as_deref(), |mut w| {
+        match format {
+            ExtractFormat::Plain => {
+                let several = args.recipes.len() > 1;
+                let mut last_recipe = None;
+                for row in &rows {
+                    if several && last_recipe != Some(&row.recipe) {
+                        if last_recipe.is_some() {
+                            writeln!(w)?;
+                        }
+                        writeln!(w, "# {}", row.recipe)?;
+                        last_recipe = Some(&row.recipe);
+                    }
+                    writeln!(w, "{}", plain_line(row))?;
+                }
+            }
+            ExtractFormat::Csv => {
+                writeln!(w, "recipe,name,quantity,unit,optional,note")?;
+                for row in &rows {
+                    let fields = [
+                        row.recipe.as_str(),
+                        row.name.as_str(),
+                        row.quantity.as_deref().unwrap_or_default(),
+                        row.unit.as_deref().unwrap_or_default(),
+                        if row.optional { "true" } else { "false" },
+                        row.note.as_deref().unwrap_or_default(),
+                    ];
+                    let line = fields.map(csv_field).join(",");
+                    writeln!(w, "{line}")?;
+                }
+            }
+            ExtractFormat::Json => {
+                if args.pretty {
+                    serde_json::to_writer_pretty(w, &rows)?;
+                } else {
+                    serde_json::to_writer(w, &rows)?;
+                }
+            }
+        }
+        Ok(())
+    })
+}
+
+fn plain_line(row: &IngredientRow) -> String {
+    let mut line = row.name.clone();
+    match (&row.quantity, &row.unit) {
+        (Some(q), Some(u)) => line = format!("{line}: {q} {u}"),
+        (Some(q), None) => line = format!("{line}: {q}"),
+        _ => {}
+    }
+    if row.optional {
+        line.push_str(" (optional)");
+    }
+    if let Some(note) = &row.note {
+        line = format!("{line} ({note})");
+    }
+    line
+}
+
+/// Quotes the field if needed, as in RFC 4180
+fn csv_field(field: &str) -> String {
+    if field.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn csv_quoting() {
+        assert_eq!(csv_field("flour"), "flour");
+        assert_eq!(csv_field("salt, to taste"), "\"salt, to taste\"");
+        assert_eq!(csv_field("the \"good\" oil"), "\"the \"\"good\"\" oil\"");
+    }
+}
@@ -0,0 +1,274 @@
+//! Structure of a recipe as a graph
+//!
+//! Sections contain steps and steps use components. References to other
+//! components, steps or sections are dashed edges.
+
+use std::fmt::Write;
+
+use cooklang::{
+    model::{IngredientReferenceTarget, Item},
+    Content, ScaledRecipe,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum GraphFormat {
+    Dot,
+    Mermaid,
+}
+
+struct Node {
+    id: String,
+    label: String,
+    shape: Shape,
+}
+
+#[derive(Clone, Copy)]
+enum Shape {
+    Recipe,
+    Section,
+    Step,
+    Ingredient,
+    Cookware,
+    Timer,
+}
+
+struct Edge {
+    from: String,
+    to: String,
+    reference: bool,
+}
+
+pub fn graph(recipe: &ScaledRecipe, name: &str, format: GraphFormat) -> String {
+    let (nodes, edges) = build(recipe, name);
+    match format {
+        GraphFormat::Dot => dot(&nodes, &edges),
+        GraphFormat::Mermaid => mermaid(&nodes, &edges),
+    }
+}
+
+fn build(recipe: &ScaledRecipe, name: &str) -> (Vec<Node>, Vec<Edge>) {
+    let mut nodes = vec![Node {
+        id: "recipe".into(),
+        label: name.into(),
+        shape: Shape::Recipe,
+    }];
+    let mut edges = Vec::new();
+    let edge = |from: &str, to: &str, reference| Edge {
+        from: from.into(),
+        to: to.into(),
+        reference,
+    };
+
+    for (s_index, section) in recipe.sections.iter().enumerate() {
+        let section_id = format!("s{s_index}");
+        nodes.push(Node {
+            id: section_id.clone(),
+            label: section
+                .name
+                .clone()
+                .unwrap_or_else(|| format!("Section {}", s_index + 1)),
+            shape: Shape::Section,
+        });
+        edges.push(edge("recipe", &section_id, false));
+
+        for (c_index, content) in section.content.iter().enumerate() {
+            let Content::Step(step) = content else {
+                continue;
+            };
+            let step_id = format!("s{s_index}_{c_index}");
+            nodes.push(Node {
+                id: step_id.clone(),
+                label: format!("Step {}", step.number),
+                shape: Shape::Step,
+            });
+            edges.push(edge(&section_id, &step_id, false));
+
+            for item in &step.items {
+                match *item {
+                    Item::Ingredient { index } => {
+                        let igr = &recipe.ingredients[index];
+                        let id = format!("i{index}");
+                        let mut label = igr.display_name().to_string();
+                        if let Some(q) = &igr.quantity {
+                            write!(label, ": {q}").unwrap();
+                        }
+                        nodes.push(Node {
+                            id: id.clone(),
+                            label,
+                            shape: Shape::Ingredient,
+                        });
+                        edges.push(edge(&step_id, &id, false));
+                        match igr.relation.references_to() {
+                            Some((target, IngredientReferenceTarget::Ingredient)) => {
+                                edges.push(edge(&id, &format!("i{target}"), true))
+                            }
+                            Some((target, IngredientReferenceTarget::Step)) => {
+                                edges.push(edge(&format!("s{s_index}_{target}"), &id, true))
+                            }
+                            Some((target, IngredientReferenceTarget::Section)) => {
+                                edges.push(edge(&format!("s{target}"), &id, true))
+                            }
+                            None => {}
+                        }
+                    }
+                    Item::Cookware { index } => {
+                        let cw = &recipe.cookware[index];
+                        let id = format!("c{index}");
+                        nodes.push(Node {
+                            id: id.clone(),
+                            label: cw.display_name().to_string(),
+                            shape: Shape::Cookware,
+                        });
+                        edges.push(edge(&step_id, &id, false));
+                        if let Some(target) = cw.relation.references_to() {
+                            edges.push(edge(&id, &format!("c{target}"), true));
+                        }
+                    }
+                    Item::Timer { index } => {
+                        let tm = &recipe.timers[index];
+                        let id = format!("t{index}");
+                        let label = match (&tm.name, &tm.quantity) {
+                            (Some(name), Some(q)) => format!("{name}: {q}"),
+                            (Some(name), None) => name.clone(),
+                            (None, Some(q)) => q.to_string(),
+                            (None, None) => "timer".into(),
+                        };
+                        nodes.push(Node {
+                            id: id.clone(),
+                            label,
+                            shape: Shape::Timer,
+                        });
+                        edges.push(edge(&step_id, &id, false));
+                    }
+                    Item::Text { .. } | Item::InlineQuantity { .. } => {}
+                }
+            }
+        }
+    }
+    (nodes, edges)
+}
+
+fn dot(nodes: &[Node], edges: &[Edge]) -> String {
+    let mut out = String::from("digraph recipe {\n    rankdir=LR;\n");
+    for node in nodes {
+        let attrs = match node.shape {
+            Shape::Recipe => "shape=doubleoctagon",
+            Shape::Section => "shape=folder",
+            Shape::Step => "shape=box",
+            Shape::Ingredient => "shape=ellipse, color=green",
+            Shape::Cookware => "shape=ellipse, color=orange",
+            Shape::Timer => "shape=ellipse, color=blue",
+        };
+        let label = node.label.replace('\\', "\\\\").replace('"', "\\\"");
+        writeln!(out, "    {} [label=\"{label}\", {attrs}];", node.id).unwrap();
+    }
+    for e in edges {
+        let style = if e.reference { " [style=dashed]" } else { "" };
+        writeln!(out, "    {} -> {}{style};", e.from, e.to).unwrap();
+    }
+    out.push_str("}\n");
+    out
+}
+
+fn mermaid(nodes: &[Node], edges: &[Edge]) -> String {
+    let mut out = String::from("flowchart LR\n");
+    for node in nodes {
+        let label = node.label.replace('"', "#quot;");
+        let (open, close) = match node.shape {
+            Shape::Recipe => ("{{", "}}"),
+            Shape::Section => ("[[", "]]"),
+            Shape::Step => ("[", "]"),
+            Shape::Ingredient | Shape::Cookware | Shape::Timer => ("([", "])"),
+        };
+        writeln!(out, "    {}{open}\"{label}\"{close}", node.id).unwrap();
+    }
+    for e in edges {
+        let arrow = if e.reference { "-.->" } else { "-->" };
+        writeln!(out, "    {} {arrow} {}", e.from, e.to).unwrap();
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample() -> (Vec<Node>, Vec<Edge>) {
+        let nodes = vec![
+            Node {
+                id: "recipe".into(),
+                label: "Say \"cheese\"".into(),
+                shape: Shape::Recipe,
+            },
+            Node {
+                id: "s0".into(),
+                label: "Section 1".into(),
+                shape: Shape::Section,
+            },
+        ];
+        let edges = vec![Edge {
+            from: "recipe".into(),
+            to: "s0".into(),
+            reference: true,
+        }];
+        (nodes, edges)
+    }
+
+    #[test]
+    fn output_formats() {
+        let (nodes, edges) = sample();
+        let dot = dot(&nodes, &edges);
+        assert!(dot.contains(r#"recipe [label="Say \"cheese\"", shape=doubleoctagon];"#));
+        assert!(dot.contains("recipe -> s0 [style=dashed];"));
+        let mermaid = mermaid(&nodes, &edges);
+        assert!(mermaid.contains(r#"recipe{{"Say #quot;cheese#quot;"}}"#));
+        assert!(mermaid.contains("recipe -.-> s0"));
+    }
+
+    #[test]
+    fn build_edges() {
+        let parser = cooklang::CooklangParser::extended();
+        let recipe = parser
+            .parse(
+                "Mix @flour{200%g} and @water{100%ml} in a #bowl{}.\n\n\
+                 Knead the @&(~1)dough{} for ~{10%min}.\n\n\
+                 Add @&flour{50%g} to the #&bowl{}.\n",
+            )
+            .into_output()
+            .unwrap();
+        let (nodes, edges) = build(&recipe.default_scale(), "Bread");
+
+        let ids = nodes.iter().map(|n| n.id.as_str()).collect::<Vec<_>>();
+        assert_eq!(
+            ids,
+            ["recipe", "s0", "s0_0", "i0", "i1", "c0", "s0_1", "i2", "t0", "s0_2", "i3", "c1"]
+        );
+        assert_eq!(nodes[0].label, "Bread");
+        assert_eq!(nodes[3].label, "flour: 200 g");
+
+        let edges = edges
+            .iter()
+            .map(|e| (e.from.as_str(), e.to.as_str(), e.reference))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            edges,
+            [
+                ("recipe", "s0", false),
+                ("s0", "s0_0", false),
+                ("s0_0", "i0", false),
+                ("s0_0", "i1", false),
+                ("s0_0", "c0", false),
+                ("s0", "s0_1", false),
+                ("s0_1", "i2", false),
+                // the dough is made in the first step
+                ("s0_0", "i2", true),
+                ("s0_1", "t0", false),
+                ("s0", "s0_2", false),
+                ("s0_2", "i3", false),
+                ("i3", "i0", true),
+                ("s0_2", "c1", false),
+                ("c1", "c0", true),
+            ]
+        );
+    }
+}
@@ -0,0 +1,115 @@
+//! Stable ids of the parts of a recipe, for the JSON output
+//!
+//! Ids are hashes of the content of each part, without quantities, so they
+//! don't change when other parts of the recipe are edited or when it's
+//! scaled. Repeated parts hash their number of repetition too.
+
+use std::collections::HashMap;
+
+use cooklang::{
+    model::{Item, Step},
+    Content, ScaledRecipe,
+};
+use serde::Serialize;
+
+#[derive(Serialize)]
+pub struct RecipeIds {
+    ingredients: Vec<String>,
+    cookware: Vec<String>,
+    /// An id for every step and text in each section
+    sections: Vec<Vec<String>>,
+}
+
+#[derive(Default)]
+struct IdGen {
+    seen: HashMap<String, u32>,
+}
+
+impl IdGen {
+    fn id(&mut self, kind: &str, content: &str) -> String {
+        let mut key = format!("{kind}:{content}");
+        let n = self.seen.entry(key.clone()).or_default();
+        *n += 1;
+        if *n > 1 {
+            key = format!("{key}#{n}");
+        }
+        format!("{kind}-{:012x}", fnv1a(key.as_bytes()) >> 16)
+    }
+}
+
+pub fn recipe_ids(recipe: &ScaledRecipe) -> RecipeIds {
+    let mut ids = IdGen::default();
+    let ingredients = recipe
+        .ingredients
+        .iter()
+        .map(|i| ids.id("i", &i.name.to_lowercase()))
+        .collect();
+    let cookware = recipe
+        .cookware
+        .iter()
+        .map(|c| ids.id("c", &c.name.to_lowercase()))
+        .collect();
+    let mut sections = Vec::with_capacity(recipe.sections.len());
+    for section in &recipe.sections {
+        let mut content_ids = Vec::with_capacity(section.content.len());
+        for content in &section.content {
+            content_ids.push(match content {
+                Content::Step(step) => ids.id("s", &step_text(recipe, step)),
+                Content::Text(text) => ids.id("t", text.trim()),
+            });
+        }
+        sections.push(content_ids);
+    }
+    RecipeIds {
+        ingredients,
+        cookware,
+        sections,
+    }
+}
+
+/// Text of a step with the names of the components, but no quantities
+fn step_text(recipe: &ScaledRecipe, step: &Step) -> String {
+    let mut text = String::new();
+    for item in &step.items {
+        match item {
+            Item::Text { value } => text.push_str(value),
+            &Item::Ingredient { index } => text.push_str(&recipe.ingredients[index].name),
+            &Item::Cookware { index } => text.push_str(&recipe.cookware[index].name),
+            &Item::Timer { index } => {
+                if let Some(name) = &recipe.timers[index].name {
+                    text.push_str(name);
+                }
+            }
+            Item::InlineQuantity { .. } => {}
+        }
+    }
+    text.trim().to_lowercase()
+}
+
+/// 64 bit FNV-1a, which unlike the std hasher is the same in every version
+fn fnv1a(bytes: &[u8]) -> u64 {
+    let mut hash = 0xcbf29ce484222325_u64;
+    for b in bytes {
+        hash ^= *b as u64;
+        hash = hash.wrapping_mul(0x100000001b3);
+    }
+    hash
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ids() {
+        assert_eq!(fnv1a(b""), 0xcbf29ce484222325);
+        assert_eq!(fnv1a(b"a"), 0xaf63dc4c8601ec8c);
+
+        let mut ids = IdGen::default();
+        let first = ids.id("i", "flour");
+        assert_eq!(first.len(), 14);
+        assert_ne!(ids.id("i", "flour"), first);
+        assert_ne!(ids.id("c", "flour"), first);
+        assert_eq!(IdGen::default().id("i", "flour"), first);
+    }
+}
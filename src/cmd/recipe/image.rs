@@ -0,0 +1,67 @@
+use anyhow::{bail, Result};
+use camino::Utf8PathBuf;
+use clap::{Args, Subcommand};
+
+use crate::{remote_image, Context};
+
+use super::read_input;
+
+#[derive(Debug, Args)]
+pub struct ImageArgs {
+    #[command(subcommand)]
+    command: ImageCommand,
+}
+
+#[derive(Debug, Subcommand)]
+enum ImageCommand {
+    /// Download the image url of the `image` metadata to the collection
+    ///
+    /// The copy is saved in `.cooklang/images` and the web UI shows it
+    /// instead of loading the one of the other site.
+    Fetch(FetchArgs),
+}
+
+#[derive(Debug, Args)]
+struct FetchArgs {
+    /// Recipe with the image
+    ///
+    /// This can be a full path, a partial path, or just the name.
+    #[arg(value_hint = clap::ValueHint::FilePath)]
+    recipe: Utf8PathBuf,
+
+    /// Download the image again if it was already
+    #[arg(long)]
+    force: bool,
+}
+
+pub fn run(ctx: &Context, args: ImageArgs) -> Result<()> {
+    match args.command {
+        ImageCommand::Fetch(args) => fetch(ctx, args),
+    }
+}
+
+fn fetch(ctx: &Context, args: FetchArgs) -> Result<()> {
+    ctx.check_fs_write()?;
+    if !ctx.is_collection {
+        bail!("Images can only be downloaded inside a collection");
+    }
+    let input = read_input(&ctx.recipe_index, Some(&args.recipe), None)?;
+    let recipe = input.parse(ctx)?;
+    let Some(url) = recipe.metadata.map.get("image").and_then(|v| v.as_str()) else {
+        bail!("The recipe has no `image` metadata");
+    };
+    if !remote_image::is_remote(url) {
+        bail!("The image is not a http or https url: {url}");
+    }
+
+    let key = remote_image::cache_key(url);
+    if let Some(path) = remote_image::cached(&ctx.base_path, &key).filter(|_| !args.force) {
+        ctx.status(format!(
+            "Already downloaded to '{path}', use `--force` to download it again"
+        ));
+        return Ok(());
+    }
+    let path = remote_image::download(&ctx.base_path, url)?;
+    ctx.status(format!("Image saved to '{path}'"));
+    Ok(())
+}
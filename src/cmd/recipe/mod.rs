@@ -0,0 +1,546 @@
+use std::io::Read;
+
+use anyhow::{bail, Context as _, Result};
+use camino::{Utf8Path, Utf8PathBuf};
+use clap::{Args, Subcommand, ValueEnum};
+use cooklang_fs::{check_recipe_images, recipe_images, LazyFsIndex, RecipeEntry};
+use yansi::Paint;
+
+use crate::{
+    exit_code::ExitError,
+    util::{
+        convert_recipe, meta_name, preferred_system, print_report, respell_units, scale_report,
+        unwrap_recipe, url_encode_path, write_to_output, Input,
+    },
+    Context,
+};
+
+mod extract_ingredients;
+mod graph;
+mod ids;
+#[cfg(feature = "import")]
+mod image;
+mod scale_file;
+mod timers;
+
+use graph::GraphFormat;
+
+#[derive(Debug, Args)]
+#[command(args_conflicts_with_subcommands = true, subcommand_negates_reqs = true)]
+pub struct ReadArgs {
+    #[command(subcommand)]
+    command: Option<RecipeCommand>,
+
+    /// Input recipe, none for stdin
+    ///
+    /// This can be a full path, a partial path, or just the name.
+    #[arg(value_hint = clap::ValueHint::FilePath)]
+    recipe: Option<Utf8PathBuf>,
+
+    /// Give or override a name for the recipe
+    ///
+    /// If not given will be obtained from input path.
+    #[arg(long, required_unless_present = "recipe")]
+    name: Option<String>,
+
+    /// Output file, none for stdout.
+    #[arg(short, long)]
+    output: Option<Utf8PathBuf>,
+
+    /// Output format
+    ///
+    /// Tries to infer it from output file extension. Defaults to "human".
+    #[arg(short, long, value_enum)]
+    format: Option<OutputFormat>,
+
+    /// Pretty output format, if available
+    #[arg(long)]
+    pretty: bool,
+
+    #[group(flatten)]
+    values: ScalingArgs,
+
+    /// Print only a section of the recipe, starting at 1
+    ///
+    /// Only the ingredients and cookware used in it are listed.
+    #[arg(long, value_parser = clap::value_parser!(u32).range(1..))]
+    section: Option<u32>,
+
+    /// Print only a step of the section, or of the first one
+    #[arg(long, value_parser = clap::value_parser!(u32).range(1..))]
+    step: Option<u32>,
+
+    #[group(flatten)]
+    debug: DebugArgs,
+
+    /// Check the recipe for errors, warnings and images
+    #[arg(long, conflicts_with_all = ["ScalingArgs", "DebugArgs", "section", "step"])]
+    check: bool,
+}
+
+#[derive(Debug, Subcommand)]
+enum RecipeCommand {
+    /// List the timers of a recipe
+    ///
+    /// They can also be exported as an iCalendar file with an alarm for
+    /// each timer, one after the other.
+    Timers(timers::TimersArgs),
+    /// Print only the ingredient list of one or more recipes
+    ///
+    /// Each ingredient is listed with its quantity, unit, whether it's
+    /// optional and its note, for use in other tools.
+    ExtractIngredients(extract_ingredients::ExtractIngredientsArgs),
+    /// Write a copy of a recipe scaled to some servings
+    ///
+    /// The copy has the quantities of the servings and can be shared with
+    /// people that can't scale recipes.
+    ScaleFile(scale_file::ScaleFileArgs),
+    /// Manage the image url of a recipe
+    ///
+    /// Images in other sites can be downloaded to the collection, so they
+    /// are still there if the site goes down.
+    #[cfg(feature = "import")]
+    Image(image::ImageArgs),
+}
+
+#[derive(Debug, Args)]
+#[group(multiple = true)]
+struct ScalingArgs {
+    /// Scale to a number of servings
+    #[arg(short, long, alias = "servings", value_name = "SERVINGS")]
+    scale: Option<u32>,
+
+    /// Convert to a unit system
+    #[arg(short, long, alias = "system", value_name = "SYSTEM")]
+    convert: Option<System>,
+}
+
+#[derive(Debug, Args)]
+#[group(conflicts_with_all = ["ScalingArgs", "section", "step"], multiple = false)]
+struct DebugArgs {
+    /// Debug output as events
+    #[arg(long, hide = true)]
+    events: bool,
+    /// Debug output as AST
+    #[arg(long, hide = true)]
+    ast: bool,
+}
+
+#[derive(Debug, Clone, Copy, ValueEnum, PartialEq)]
+enum OutputFormat {
+    Human,
+    Json,
+    #[value(alias("cook"))]
+    Cooklang,
+    #[value(alias("md"))]
+    Markdown,
+    /// Graphviz graph of the recipe structure
+    #[value(alias("graph"))]
+    Dot,
+    /// Mermaid flowchart of the recipe structure
+    Mermaid,
+    #[value(hide = true)]
+    Debug,
+}
+
+#[derive(Debug, Clone, Copy, ValueEnum)]
+enum System {
+    Metric,
+    #[value(alias("freedom"))]
+    Imperial,
+}
+
+pub fn run(ctx: &Context, args: ReadArgs) -> Result<()> {
+    if let Some(command) = args.command {
+        return match command {
+            RecipeCommand::Timers(args) => timers::run(ctx, args),
+            RecipeCommand::ExtractIngredients(args) => extract_ingredients::run(ctx, args),
+            RecipeCommand::ScaleFile(args) => scale_file::run(ctx, args),
+            #[cfg(feature = "import")]
+            RecipeCommand::Image(args) => image::run(ctx, args),
+        };
+    }
+    if args.debug.events || args.debug.ast {
+        return just_events(ctx, args);
+    }
+    if args.check {
+        return just_check(ctx, args);
+    }
+
+    let input = args.read(&ctx.recipe_index)?;
+
+    let recipe = input.parse(ctx)?;
+
+    let mut scaled_recipe = tracing::debug_span!("scaling").in_scope(|| -> Result<_> {
+        Ok(if let Some(scale) = args.values.scale {
+            recipe.scale(scale, ctx.parser()?.converter())
+        } else {
+            recipe.default_scale()
+        })
+    })?;
+
+    let target = match args.values.convert {
+        Some(System::Metric) => Some(cooklang::convert::System::Metric),
+        Some(System::Imperial) => Some(cooklang::convert::System::Imperial),
+        None => preferred_system(&scaled_recipe.metadata),
+    };
+    if let Some(to) = target {
+        let _span = tracing::debug_span!("conversion").entered();
+        convert_recipe(
+            &mut scaled_recipe,
+            to,
+            &ctx.config.unit_thresholds,
+            ctx.parser()?.converter(),
+        );
+    }
+
+    let format = args.format.unwrap_or_else(|| match &args.output {
+        Some(p) => match p.extension() {
+            Some("json") => OutputFormat::Json,
+            Some("cook") => OutputFormat::Cooklang,
+            Some("md") => OutputFormat::Markdown,
+            Some("dot" | "gv") => OutputFormat::Dot,
+            Some("mmd") => OutputFormat::Mermaid,
+            _ => OutputFormat::Human,
+        },
+        None => OutputFormat::Human,
+    });
+
+    let part = recipe_part(&scaled_recipe, args.section, args.step)?;
+    if part.is_some() && format != OutputFormat::Human {
+        bail!(ExitError::usage(
+            "`--section` and `--step` only work with the human output"
+        ));
+    }
+
+    // human and markdown outputs mark these themselves
+    if matches!(format, OutputFormat::Json | OutputFormat::Cooklang) {
+        let report = scale_report(&scaled_recipe);
+        if !report.fixed.is_empty() {
+            tracing::warn!("Fixed ingredients not scaled: {}", report.fixed.join(", "));
+        }
+        for (name, err) in &report.errors {
+            tracing::warn!("Error scaling '{name}': {err}");
+        }
+    }
+
+    // json is for other programs, so it keeps the units as written
+    if !matches!(format, OutputFormat::Json) {
+        respell_units(
+            &mut scaled_recipe,
+            &ctx.config.display_units,
+            ctx.parser()?.converter(),
+        );
+    }
+
+    let name = match meta_name(&scaled_recipe.metadata) {
+        Some(n) => n,
+        None => input.name()?,
+    };
+
+    write_to_output(args.output.as_deref(), |mut writer| {
+        match format {
+            OutputFormat::Human => {
+                let link = |name: &str| recipe_link(ctx, name, input.path());
+                let duration = |minutes: u32| ctx.config.duration.format(minutes);
+                let opts = cooklang_to_human::Options {
+                    recipe_link: ctx
+                        .config
+                        .hyperlinks
+                        .is_some()
+                        .then_some(&link as &dyn Fn(&str) -> Option<String>),
+                    part,
+                    duration: Some(&duration),
+                };
+                cooklang_to_human::print_human_with_options(
+                    &scaled_recipe,
+                    name,
+                    ctx.parser()?.converter(),
+                    writer,
+                    &opts,
+                )?
+            }
+            OutputFormat::Json => {
+                #[derive(serde::Serialize)]
+                struct JsonRecipe<'a> {
+                    name: &'a str,
+                    #[serde(flatten)]
+                    recipe: &'a cooklang::ScaledRecipe,
+                    ids: ids::RecipeIds,
+                }
+
+                let recipe = JsonRecipe {
+                    recipe: &scaled_recipe,
+                    name,
+                    ids: ids::recipe_ids(&scaled_recipe),
+                };
+
+                if args.pretty {
+                    serde_json::to_writer_pretty(writer, &recipe)?;
+                } else {
+                    serde_json::to_writer(writer, &recipe)?;
+                }
+            }
+            OutputFormat::Cooklang => {
+                cooklang_to_cooklang::print_cooklang_scaled(&scaled_recipe, writer)?
+            }
+            OutputFormat::Markdown => cooklang_to_md::print_md_with_options(
+                &scaled_recipe,
+                name,
+                &ctx.config.export.markdown,
+                ctx.parser()?.converter(),
+                writer,
+            )?,
+            OutputFormat::Dot => write!(
+                writer,
+                "{}",
+                graph::graph(&scaled_recipe, name, GraphFormat::Dot)
+            )?,
+            OutputFormat::Mermaid => write!(
+                writer,
+                "{}",
+                graph::graph(&scaled_recipe, name, GraphFormat::Mermaid)
+            )?,
+            OutputFormat::Debug => write!(writer, "{scaled_recipe:?}")?,
+        }
+
+        Ok(())
+    })?;
+
+    Ok(())
+}
+
+impl ReadArgs {
+    fn read(&self, index: &LazyFsIndex) -> Result<Input> {
+        read_input(index, self.recipe.as_deref(), self.name.clone())
+    }
+}
+
+/// Checks the `--section` and `--step` args against the recipe
+fn recipe_part(
+    recipe: &cooklang::ScaledRecipe,
+    section: Option<u32>,
+    step: Option<u32>,
+) -> Result<Option<cooklang_to_human::Part>> {
+    if section.is_none() && step.is_none() {
+        return Ok(None);
+    }
+    let number = section.unwrap_or(1);
+    let index = number as usize - 1;
+    let Some(s) = recipe.sections.get(index) else {
+        bail!(ExitError::usage(format!(
+            "There is no section {number}, the recipe has {}",
+            recipe.sections.len()
+        )));
+    };
+    if let Some(step) = step {
+        let found = s
+            .content
+            .iter()
+            .any(|c| matches!(c, cooklang::Content::Step(st) if st.number == step));
+        if !found {
+            bail!(ExitError::usage(format!(
+                "There is no step {step} in section {number}"
+            )));
+        }
+    }
+    Ok(Some(cooklang_to_human::Part {
+        section: index,
+        step,
+    }))
+}
+
+/// URL of a referenced recipe for the terminal hyperlinks
+fn recipe_link(ctx: &Context, name: &str, relative_to: Option<&Utf8Path>) -> Option<String> {
+    let target = ctx.config.hyperlinks.as_deref()?;
+    let entry = ctx
+        .recipe_index
+        .resolve(name, relative_to.and_then(|p| p.parent()))
+        .ok()?;
+    if target == "file" {
+        let path = entry.path().canonicalize_utf8().ok()?;
+        Some(format!("file://{}", url_encode_path(path.as_str())))
+    } else {
+        let path = entry.path().strip_prefix(&ctx.base_path).ok()?;
+        Some(format!(
+            "{}/r/{}",
+            target.trim_end_matches('/'),
+            url_encode_path(path.with_extension("").as_str())
+        ))
+    }
+}
+
+fn read_input(
+    index: &LazyFsIndex,
+    query: Option<&Utf8Path>,
+    name: Option<String>,
+) -> Result<Input> {
+    let input = if let Some(query) = query {
+        let entry = if query.extension().is_some_and(|e| e == "cook") && query.is_file() {
+            RecipeEntry::new(query)
+        } else {
+            // RecipeInputArgs::recipe is a pathbuf even if inmediatly converted
+            // to a string to enforce validation.
+            index.resolve(query.as_str(), None)?
+        };
+
+        Input::File {
+            entry,
+            override_name: name,
+        }
+    } else {
+        let mut buf = Vec::new();
+        std::io::stdin()
+            .read_to_end(&mut buf)
+            .context("Failed to read stdin")?;
+        let (text, encoding) = cooklang_fs::decode(buf);
+        if encoding != cooklang_fs::Encoding::Utf8 {
+            tracing::warn!("The input is not UTF-8, it was read as {encoding}");
+        }
+        Input::Stdin { text, name }
+    };
+    Ok(input)
+}
+
+fn just_events(ctx: &Context, args: ReadArgs) -> Result<()> {
+    let input = args.read(&ctx.recipe_index)?;
+    let text = input.text()?;
+
+    let events = cooklang::parser::PullParser::new(text.as_ref(), ctx.parser()?.extensions());
+
+    if args.debug.ast {
+        let r = cooklang::ast::build_ast(events);
+        if !r.is_valid() || ctx.global_args.warnings_as_errors && r.report().has_warnings() {
+            let mut report = r.into_report();
+            if ctx.global_args.ignore_warnings {
+                report.remove_warnings();
+            }
+            print_report(&report, &input, text.as_ref(), ctx)?;
+            bail!(ExitError::recipe("Error parsing recipe"));
+        };
+        let (ast, warnings) = r.into_result().unwrap();
+        if !ctx.global_args.ignore_warnings && !warnings.is_empty() {
+            print_report(&warnings, &input, text.as_ref(), ctx)?;
+        }
+
+        let format = args.format.unwrap_or_else(|| {
+            args.output
+                .as_ref()
+                .map(|p| match p.extension() {
+                    Some("json") => OutputFormat::Json,
+                    Some("dot" | "gv") => OutputFormat::Dot,
+                    Some("mmd") => OutputFormat::Mermaid,
+                    _ => OutputFormat::Debug,
+                })
+                .unwrap_or(OutputFormat::Debug)
+        });
+
+        write_to_output(args.output.as_deref(), |mut w| {
+            match format {
+                OutputFormat::Json => {
+                    if args.pretty {
+                        serde_json::to_writer_pretty(w, &ast)?;
+                    } else {
+                        serde_json::to_writer(w, &ast)?;
+                    }
+                }
+                OutputFormat::Debug => write!(w, "{ast:#?}")?,
+                // the references are only resolved in the full recipe
+                OutputFormat::Dot | OutputFormat::Mermaid => {
+                    let graph_format = if format == OutputFormat::Dot {
+                        GraphFormat::Dot
+                    } else {
+                        GraphFormat::Mermaid
+                    };
+                    let recipe = input.parse(ctx)?.default_scale();
+                    let name = match meta_name(&recipe.metadata) {
+                        Some(n) => n,
+                        None => input.name()?,
+                    };
+                    write!(w, "{}", graph::graph(&recipe, name, graph_format))?
+                }
+                _ => bail!("Format not supported"),
+            };
+            Ok(())
+        })?;
+    } else {
+        write_to_output(args.output.as_deref(), |mut w| {
+            if args.format.is_some_and(|f| f != OutputFormat::Debug) {
+                bail!("Format not supported");
+            }
+            for ev in events {
+                use cooklang::parser::Event;
+                let color = match ev {
+                    Event::Warning(_) => yansi::Color::Yellow,
+                    Event::Error(_) => yansi::Color::Red,
+                    Event::Start(_) | Event::End(_) => yansi::Color::Cyan,
+                    Event::Ingredient(_) => yansi::Color::BrightGreen,
+                    Event::Cookware(_) => yansi::Color::BrightYellow,
+                    Event::Timer(_) => yansi::Color::BrightBlue,
+                    Event::Metadata { .. } => yansi::Color::Magenta,
+                    Event::Section { .. } => yansi::Color::BrightCyan,
+                    _ => yansi::Color::default(),
+                };
+                if args.pretty {
+                    writeln!(w, "{:#?}", ev.paint(color))?;
+                } else {
+                    writeln!(w, "{:?}", ev.paint(color))?;
+                }
+            }
+            Ok(())
+        })?;
+    }
+
+    Ok(())
+}
+
+fn just_check(ctx: &Context, args: ReadArgs) -> Result<()> {
+    let input = args.read(&ctx.recipe_index)?;
+    let res = input.parse_result(ctx)?;
+    let mut n_warns = 0;
+    let mut n_errs = 0;
+    let mut n_image_errs = 0;
+    for err in res.report().iter() {
+        match err.severity {
+            cooklang::error::Severity::Error => n_errs += 1,
+            cooklang::error::Severity::Warning => n_warns += 1,
+        }
+    }
+    let recipe = unwrap_recipe(res, &input, input.text()?.as_ref(), ctx).ok();
+
+    if let Some(recipe) = &recipe {
+        if let Some(path) = &input.path() {
+            let images = recipe_images(path);
+
+            if let Err(errors) = check_recipe_images(&images, recipe) {
+                n_image_errs = errors.len();
+                for e in errors {
+                    eprintln!("{e}");
+                }
+            } else {
+                ctx.status(format!("Found {} image(s)", images.len()));
+            }
+        } else {
+            tracing::warn!("Could not check images, no path given");
+        }
+    }
+
+    if n_errs > 0 {
+        println!("{}: {}", "Errors".red().bold(), n_errs);
+    }
+    if n_image_errs > 0 {
+        println!("{}: {}", "Image errors".magenta().bold(), n_image_errs);
+    }
+    if n_warns > 0 {
+        println!("{}: {}", "Warnings".yellow().bold(), n_warns);
+    }
+
+    if n_errs > 0 || n_image_errs > 0 || n_warns > 0 && ctx.global_args.warnings_as_errors {
+        bail!(ExitError::recipe("The recipe has errors"));
+    }
+    if n_warns == 0 {
+        ctx.status("Ok".green().bold());
+    }
+
+    Ok(())
+}
@@ -0,0 +1,84 @@
+use anyhow::{bail, Result};
+use camino::{Utf8Path, Utf8PathBuf};
+use clap::Args;
+
+use crate::{
+    util::{scale_report, write_to_output},
+    Context,
+};
+
+use super::read_input;
+
+#[derive(Debug, Args)]
+pub struct ScaleFileArgs {
+    /// Recipe to scale
+    ///
+    /// This can be a full path, a partial path, or just the name.
+    #[arg(value_hint = clap::ValueHint::FilePath)]
+    recipe: Utf8PathBuf,
+
+    /// Servings of the copy, or a scale factor if the recipe has no servings
+    servings: u32,
+
+    /// Output file
+    ///
+    /// Defaults to the recipe name with the servings, like
+    /// `Pancakes (x12).cook`, next to the recipe.
+    #[arg(short, long)]
+    output: Option<Utf8PathBuf>,
+
+    /// Overwrite the output file if it exists
+    #[arg(long)]
+    force: bool,
+}
+
+pub fn run(ctx: &Context, args: ScaleFileArgs) -> Result<()> {
+    ctx.check_fs_write()?;
+    let input = read_input(&ctx.recipe_index, Some(&args.recipe), None)?;
+    let recipe = input.parse(ctx)?;
+    let converter = ctx.parser()?.converter();
+    let scaled =
+        tracing::debug_span!("scaling").in_scope(|| recipe.scale(args.servings, converter));
+
+    let report = scale_report(&scaled);
+    if !report.fixed.is_empty() {
+        tracing::warn!("Fixed ingredients not scaled: {}", report.fixed.join(", "));
+    }
+    for (name, err) in &report.errors {
+        tracing::warn!("Error scaling '{name}': {err}");
+    }
+
+    let path = input.path().expect("recipe read from a file");
+    let output = args
+        .output
+        .unwrap_or_else(|| copy_path(path, args.servings));
+    if output.exists() && !args.force {
+        bail!("'{output}' already exists, use `--force` to overwrite it");
+    }
+
+    write_to_output(Some(output.as_path()), |w| {
+        cooklang_to_cooklang::print_cooklang_scaled(&scaled, w)?;
+        Ok(())
+    })?;
+    ctx.status(format!("Scaled copy written to '{output}'"));
+    Ok(())
+}
+
+/// `Pancakes.cook` to `Pancakes (x12).cook` in the same directory
+fn copy_path(path: &Utf8Path, servings: u32) -> Utf8PathBuf {
+    let stem = path.file_stem().unwrap_or("recipe");
+    path.with_file_name(format!("{stem} (x{servings}).cook"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn copy_name() {
+        assert_eq!(
+            copy_path(Utf8Path::new("Breakfast/Pancakes.cook"), 12),
+            Utf8Path::new("Breakfast/Pancakes (x12).cook")
+        );
+    }
+}
@@ -0,0 +1,319 @@
+use std::{
+    fmt::Write as _,
+    time::{Duration, SystemTime},
+};
+
+use anyhow::Result;
+use camino::Utf8PathBuf;
+use clap::{Args, ValueEnum};
+use cooklang::{model::Item, Content, Converter, ScaledRecipe};
+use serde::Serialize;
+use yansi::Paint;
+
+use crate::{
+    util::{civil_from_days, meta_name, write_to_output},
+    Context,
+};
+
+use super::read_input;
+
+#[derive(Debug, Args)]
+pub struct TimersArgs {
+    /// Input recipe, none for stdin
+    ///
+    /// This can be a full path, a partial path, or just the name.
+    #[arg(value_hint = clap::ValueHint::FilePath)]
+    recipe: Option<Utf8PathBuf>,
+
+    /// Give or override a name for the recipe
+    ///
+    /// If not given will be obtained from input path.
+    #[arg(long, required_unless_present = "recipe")]
+    name: Option<String>,
+
+    /// Scale to a number of servings
+    #[arg(short, long, alias = "servings", value_name = "SERVINGS")]
+    scale: Option<u32>,
+
+    /// Output file, none for stdout.
+    #[arg(short, long)]
+    output: Option<Utf8PathBuf>,
+
+    /// Output format
+    ///
+    /// Tries to infer it from output file extension. Defaults to "human".
+    #[arg(short, long, value_enum)]
+    format: Option<TimersFormat>,
+
+    /// Pretty output format, if available
+    #[arg(long)]
+    pretty: bool,
+
+    /// Minutes from now until the first timer starts in the iCalendar output
+    #[arg(long, default_value_t = 0, value_name = "MINUTES")]
+    delay: u64,
+}
+
+#[derive(Debug, Clone, Copy, ValueEnum, PartialEq)]
+enum TimersFormat {
+    Human,
+    Json,
+    #[value(alias("ical"))]
+    Ics,
+}
+
+#[derive(Debug, Serialize)]
+struct TimerEntry {
+    name: Option<String>,
+    quantity: Option<String>,
+    /// Duration in seconds, the start if it's a range
+    seconds: Option<f64>,
+    /// End of the duration in seconds if it's a range
+    seconds_max: Option<f64>,
+    section: usize,
+    section_name: Option<String>,
+    step: u32,
+    step_text: String,
+}
+
+pub fn run(ctx: &Context, args: TimersArgs) -> Result<()> {
+    let input = read_input(&ctx.recipe_index, args.recipe.as_deref(), args.name.clone())?;
+    let recipe = input.parse(ctx)?;
+    let converter = ctx.parser()?.converter();
+    let recipe = if let Some(scale) = args.scale {
+        recipe.scale(scale, converter)
+    } else {
+        recipe.default_scale()
+    };
+
+    let name = match meta_name(&recipe.metadata) {
+        Some(n) => n.to_string(),
+        None => input.name()?.to_string(),
+    };
+
+    let timers = timer_entries(&recipe, converter);
+
+    let format = args.format.unwrap_or_else(|| match &args.output {
+        Some(p) => match p.extension() {
+            Some("json") => TimersFormat::Json,
+            Some("ics") => TimersFormat::Ics,
+            _ => TimersFormat::Human,
+        },
+        None => TimersFormat::Human,
+    });
+
+    write_to_output(args.output.as_deref(), |mut w| {
+        match format {
+            TimersFormat::Human => {
+                if timers.is_empty() {
+                    writeln!(w, "No timers in '{name}'")?;
+                }
+                for t in &timers {
+                    let mut title = match &t.section_name {
+                        Some(section) => format!("{section}, step {}", t.step),
+                        None if recipe.sections.len() > 1 => {
+                            format!("Section {}, step {}", t.section, t.step)
+                        }
+                        None => format!("Step {}", t.step),
+                    };
+                    if let Some(name) = &t.name {
+                        write!(title, " ({name})")?;
+                    }
+                    let quantity = t.quantity.as_deref().unwrap_or("-");
+                    writeln!(w, "{}: {}", title.bold(), quantity.bright_blue())?;
+                    let text = textwrap::indent(&textwrap::fill(&t.step_text, 76), "  ");
+                    writeln!(w, "{}", text.dim())?;
+                }
+            }
+            TimersFormat::Json => {
+                if args.pretty {
+                    serde_json::to_writer_pretty(w, &timers)?;
+                } else {
+                    serde_json::to_writer(w, &timers)?;
+                }
+            }
+            TimersFormat::Ics => {
+                let start = SystemTime::now() + Duration::from_secs(args.delay * 60);
+                w.write_all(ics(&name, &timers, start).as_bytes())?;
+            }
+        }
+        Ok(())
+    })
+}
+
+fn timer_entries(recipe: &ScaledRecipe, converter: &Converter) -> Vec<TimerEntry> {
+    let mut entries = Vec::new();
+    for (section_index, section) in recipe.sections.iter().enumerate() {
+        for content in &section.content {
+            let Content::Step(step) = content else {
+                continue;
+            };
+            for item in &step.items {
+                let &Item::Timer { index } = item else {
+                    continue;
+                };
+                let timer = &recipe.timers[index];
+                let (seconds, seconds_max) = match &timer.quantity {
+                    Some(q) => {
+                        let mut q = q.clone();
+                        match q.convert("s", converter) {
+                            Ok(_) => match q.value() {
+                                cooklang::Value::Number(n) => (Some(n.value()), None),
+                                cooklang::Value::Range { start, end } => {
+                                    (Some(start.value()), Some(end.value()))
+                                }
+                                cooklang::Value::Text(_) => (None, None),
+                            },
+                            Err(_) => (None, None),
+                        }
+                    }
+                    None => (None, None),
+                };
+                entries.push(TimerEntry {
+                    name: timer.name.clone(),
+                    quantity: timer.quantity.as_ref().map(|q| q.to_string()),
+                    seconds,
+                    seconds_max,
+                    section: section_index + 1,
+                    section_name: section.name.clone(),
+                    step: step.number,
+                    step_text: step_text(recipe, &step.items),
+                });
+            }
+        }
+    }
+    entries
+}
+
+fn step_text(recipe: &ScaledRecipe, items: &[Item]) -> String {
+    let mut text = String::new();
+    for item in items {
+        match item {
+            Item::Text { value } => text.push_str(value),
+            &Item::Ingredient { index } => {
+                text.push_str(recipe.ingredients[index].display_name().as_ref())
+            }
+            &Item::Cookware { index } => text.push_str(&recipe.cookware[index].name),
+            &Item::Timer { index } => {
+                let t = &recipe.timers[index];
+                match (&t.quantity, &t.name) {
+                    (Some(q), _) => write!(text, "{q}").unwrap(),
+                    (None, Some(name)) => text.push_str(name),
+                    (None, None) => {}
+                }
+            }
+            &Item::InlineQuantity { index } => {
+                write!(text, "{}", recipe.inline_quantities[index]).unwrap()
+            }
+        }
+    }
+    text.trim().to_string()
+}
+
+/// iCalendar with an event for each timer, one after the other, starting at
+/// `start`. Each event has an alarm when it ends.
+///
+/// Timers without a duration are skipped.
+fn ics(recipe_name: &str, timers: &[TimerEntry], start: SystemTime) -> String {
+    let stamp = ics_datetime(SystemTime::now());
+    let mut lines = vec![
+        "BEGIN:VCALENDAR".to_string(),
+        "VERSION:2.0".to_string(),
+        "PRODID:-//cooklang-chef//timers//EN".to_string(),
+        "CALSCALE:GREGORIAN".to_string(),
+    ];
+    let mut cursor = start;
+    for (i, t) in timers.iter().enumerate() {
+        let Some(seconds) = t.seconds_max.or(t.seconds) else {
+            tracing::warn!("Skipping timer without a duration in step {}", t.step);
+            continue;
+        };
+        let end = cursor + Duration::from_secs_f64(seconds.max(0.0));
+        let summary = match &t.name {
+            Some(name) => format!("{recipe_name}: {name}"),
+            None => format!("{recipe_name}: step {}", t.step),
+        };
+        lines.extend([
+            "BEGIN:VEVENT".to_string(),
+            format!("UID:{stamp}-{i}@cooklang-chef"),
+            format!("DTSTAMP:{stamp}"),
+            format!("DTSTART:{}", ics_datetime(cursor)),
+            format!("DTEND:{}", ics_datetime(end)),
+            format!("SUMMARY:{}", ics_escape(&summary)),
+            format!("DESCRIPTION:{}", ics_escape(&t.step_text)),
+            "BEGIN:VALARM".to_string(),
+            "ACTION:DISPLAY".to_string(),
+            format!("DESCRIPTION:{}", ics_escape(&summary)),
+            "TRIGGER;RELATED=END:PT0S".to_string(),
+            "END:VALARM".to_string(),
+            "END:VEVENT".to_string(),
+        ]);
+        cursor = end;
+    }
+    lines.push("END:VCALENDAR".to_string());
+
+    let mut out = String::new();
+    for line in lines {
+        fold_line(&mut out, &line);
+    }
+    out
+}
+
+fn ics_escape(s: &str) -> String {
+    s.replace('\\', "\\\\")
+        .replace(';', "\\;")
+        .replace(',', "\\,")
+        .replace('\n', "\\n")
+}
+
+/// Lines longer than 75 bytes are folded with a space at the start of the
+/// next one
+fn fold_line(out: &mut String, line: &str) {
+    let mut len = 0;
+    for c in line.chars() {
+        if len + c.len_utf8() > 75 {
+            out.push_str("\r\n ");
+            len = 1;
+        }
+        out.push(c);
+        len += c.len_utf8();
+    }
+    out.push_str("\r\n");
+}
+
+/// UTC date time in the iCalendar format
+fn ics_datetime(time: SystemTime) -> String {
+    let secs = time
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs();
+    let (days, rem) = (secs / 86400, secs % 86400);
+    let (year, month, day) = civil_from_days(days as i64);
+    format!(
+        "{year:04}{month:02}{day:02}T{:02}{:02}{:02}Z",
+        rem / 3600,
+        rem % 3600 / 60,
+        rem % 60
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn datetime() {
+        assert_eq!(ics_datetime(SystemTime::UNIX_EPOCH), "19700101T000000Z");
+        let t = SystemTime::UNIX_EPOCH + Duration::from_secs(1_709_210_096);
+        assert_eq!(ics_datetime(t), "20240229T123456Z");
+    }
+
+    #[test]
+    fn folding() {
+        let mut out = String::new();
+        fold_line(&mut out, &"a".repeat(100));
+        let lines: Vec<_> = out.split("\r\n").collect();
+        assert_eq!(lines[0].len(), 75);
+        assert_eq!(lines[1], format!(" {}", "a".repeat(25)));
+    }
+}
@@ -0,0 +1,21 @@
+use anyhow::{Context as _, Result};
+use clap::Args;
+
+use crate::{util::run_with_path, Context};
+
+#[derive(Debug, Args)]
+pub struct RevealArgs {
+    /// Recipe name
+    name: String,
+}
+
+pub fn run(args: RevealArgs, ctx: &Context) -> Result<()> {
+    let entry = ctx.recipe_index.resolve(&args.name, None)?;
+    let dir = entry.path().parent().expect("no parent for recipe entry");
+
+    let file_manager = ctx
+        .chef_config
+        .file_manager()
+        .context("Could not determine file manager")?;
+    run_with_path(&file_manager, dir, "file manager")
+}
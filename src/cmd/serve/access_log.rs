@@ -0,0 +1,126 @@
+//! Request ids and access logs
+//!
+//! Every request gets an id, the `X-Request-Id` of a proxy or a counter, that
+//! is in the tracing span of the handlers and in the response headers. With
+//! `--access-log`, a JSON line with the path, status, duration and client of
+//! each request is written too.
+
+use std::{
+    io::Write,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc, Mutex,
+    },
+    time::Instant,
+};
+
+use anyhow::{Context as _, Result};
+use axum::{
+    extract::{Request, State},
+    http::HeaderValue,
+    middleware::Next,
+    response::Response,
+};
+use camino::Utf8Path;
+use serde_json::json;
+use tracing::Instrument;
+
+use super::client_ip::ClientIp;
+use crate::util::now_rfc3339;
+
+const REQUEST_ID_HEADER: &str = "x-request-id";
+
+pub struct AccessLog {
+    out: Option<Mutex<Box<dyn Write + Send>>>,
+    next_id: AtomicU64,
+}
+
+impl AccessLog {
+    /// Writes the log to a file, appending to it, or to stdout with `-`.
+    /// Without one only the ids are added.
+    pub fn new(path: Option<&Utf8Path>) -> Result<Self> {
+        let out = match path {
+            None => None,
+            Some(p) if p == "-" => Some(Box::new(std::io::stdout()) as Box<dyn Write + Send>),
+            Some(p) => {
+                let file = std::fs::OpenOptions::new()
+                    .create(true)
+                    .append(true)
+                    .open(p)
+                    .with_context(|| format!("Failed to open the access log '{p}'"))?;
+                Some(Box::new(std::io::LineWriter::new(file)) as Box<dyn Write + Send>)
+            }
+        };
+        Ok(Self {
+            out: out.map(Mutex::new),
+            ..Default::default()
+        })
+    }
+
+    fn request_id(&self, req: &Request) -> String {
+        req.headers()
+            .get(REQUEST_ID_HEADER)
+            .and_then(|v| v.to_str().ok())
+            .filter(|id| is_valid_id(id))
+            .map(String::from)
+            .unwrap_or_else(|| self.next_id.fetch_add(1, Ordering::Relaxed).to_string())
+    }
+}
+
+impl Default for AccessLog {
+    fn default() -> Self {
+        Self {
+            out: None,
+            next_id: AtomicU64::new(1),
+        }
+    }
+}
+
+pub async fn access_log_layer(
+    State(log): State<Arc<AccessLog>>,
+    req: Request,
+    next: Next,
+) -> Response {
+    let id = log.request_id(&req);
+    let client = req
+        .extensions()
+        .get::<ClientIp>()
+        .map(|ClientIp(ip)| ip.to_string());
+    let method = req.method().to_string();
+    let uri = req.uri().to_string();
+
+    let span = tracing::info_span!(
+        "request",
+        id = %id,
+        client = client.as_deref().unwrap_or("-")
+    );
+    let start = Instant::now();
+    let mut res = next.run(req).instrument(span).await;
+    let duration = start.elapsed();
+
+    if let Ok(value) = HeaderValue::from_str(&id) {
+        res.headers_mut().insert(REQUEST_ID_HEADER, value);
+    }
+
+    if let Some(out) = &log.out {
+        let line = json!({
+            "time": now_rfc3339(),
+            "id": id,
+            "client": client,
+            "method": method,
+            "uri": uri,
+            "status": res.status().as_u16(),
+            "duration_ms": duration.as_secs_f64() * 1000.0,
+        });
+        let mut out = out.lock().unwrap();
+        if let Err(e) = writeln!(out, "{line}") {
+            tracing::error!("Could not write the access log: {e}");
+        }
+    }
+    res
+}
+
+/// Ids from proxies are only used if they are short and printable
+fn is_valid_id(id: &str) -> bool {
+    !id.is_empty() && id.len() <= 64 && id.bytes().all(|b| b.is_ascii_graphic())
+}
@@ -1,70 +1,134 @@
 use std::{
-    collections::BTreeMap,
+    collections::{BTreeMap, HashMap},
     path::{Path, PathBuf},
-    sync::Arc,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc, RwLock as SyncRwLock,
+    },
     time::Duration,
 };
 
 use anyhow::Result;
 use camino::{Utf8Path, Utf8PathBuf};
 use cooklang::{CooklangParser, Metadata};
-use cooklang_fs::{FsIndex, RecipeEntry};
+use cooklang_fs::{FileInfo, FsIndex, RecipeEntry};
 use notify::{RecommendedWatcher, Watcher};
 use serde::Serialize;
 use tokio::sync::{broadcast, mpsc, RwLock};
 
+use super::slugs::{SlugLookup, Slugs};
+use crate::util::{is_draft_metadata, normalize_tags};
+
 pub struct AsyncFsIndex {
     indexes: Arc<RwLock<Indexes>>,
+    /// Drafts are left out of listings and searches when `false`
+    include_drafts: bool,
+    /// Only with `ui.slugs`. Not behind the async lock so urls can be made
+    /// while the index is read.
+    slugs: Option<Arc<SyncRwLock<Slugs>>>,
+    /// Incremented after every update of the index
+    generation: Arc<AtomicU64>,
 }
 
 pub struct RecipeData {
     pub metadata: Option<Metadata>,
     pub ingredients: Vec<String>,
     pub cookware: Vec<String>,
+    /// Size and times of the file when it was loaded
+    pub info: Option<FileInfo>,
+}
+
+impl RecipeData {
+    /// Reads and parses a recipe, keeping only what the pages need
+    fn load(entry: &RecipeEntry, loader: &Loader) -> std::io::Result<Self> {
+        let recipe = entry.read()?.parse(&loader.parser);
+        let mut ingredients = Vec::new();
+        let mut cookware = Vec::new();
+        let mut metadata = None;
+        if let Some(r) = recipe.valid_output() {
+            let mut m = r.metadata.to_owned();
+            normalize_tags(&mut m, &loader.tag_aliases);
+            metadata = Some(m);
+            for ingredient in &r.ingredients {
+                ingredients.push(ingredient.name.to_owned());
+            }
+            for tool in &r.cookware {
+                cookware.push(tool.name.to_string());
+            }
+        }
+        Ok(Self {
+            metadata,
+            ingredients,
+            cookware,
+            info: entry.info(),
+        })
+    }
+}
+
+/// What is needed to load the data of a recipe
+pub struct Loader {
+    pub parser: Arc<CooklangParser>,
+    pub tag_aliases: HashMap<String, String>,
+    pub include_drafts: bool,
+    pub slugs: bool,
+}
+
+/// Max number of threads used to parse the collection on startup
+const MAX_PARSE_THREADS: usize = 8;
+
+/// Parses every recipe, spreading them in a bounded number of threads
+fn load_all(entries: &[RecipeEntry], loader: &Loader) -> BTreeMap<Utf8PathBuf, RecipeData> {
+    let threads = std::thread::available_parallelism()
+        .map_or(1, |n| n.get())
+        .min(MAX_PARSE_THREADS);
+    let chunk_size = entries.len().div_ceil(threads).max(1);
+    std::thread::scope(|s| {
+        let handles = entries
+            .chunks(chunk_size)
+            .map(|chunk| {
+                s.spawn(move || {
+                    chunk
+                        .iter()
+                        .filter_map(|entry| match RecipeData::load(entry, loader) {
+                            Ok(data) => Some((entry.path().to_owned(), data)),
+                            Err(e) => {
+                                tracing::warn!("Could not read '{}': {e}", entry.path());
+                                None
+                            }
+                        })
+                        .collect::<Vec<_>>()
+                })
+            })
+            .collect::<Vec<_>>();
+        handles
+            .into_iter()
+            .flat_map(|h| h.join().expect("recipe parsing thread panicked"))
+            .collect()
+    })
 }
 
 struct Indexes {
-    parser: Arc<CooklangParser>,
     fs: FsIndex,
     srch: BTreeMap<Utf8PathBuf, RecipeData>,
 }
 
 impl Indexes {
-    fn new(fs: FsIndex, parser: Arc<CooklangParser>) -> Self {
-        let mut srch = BTreeMap::new();
-        let insert_search_entry = |index: &mut BTreeMap<_, _>, entry: RecipeEntry| {
-            let recipe = entry.read().expect("can't read recipe").parse(&parser);
-            let mut ingredients = Vec::new();
-            let mut cookware = Vec::new();
-            let mut metadata = None;
-            if let Some(r) = recipe.valid_output() {
-                metadata = Some(r.metadata.to_owned());
-                for ingredient in &r.ingredients {
-                    ingredients.push(ingredient.name.to_owned());
-                }
-                for tool in &r.cookware {
-                    cookware.push(tool.name.to_string());
-                }
-            }
-            index.insert(
-                entry.path().to_owned(),
-                RecipeData {
-                    metadata,
-                    ingredients,
-                    cookware,
-                },
-            );
-        };
-        for entry in fs.get_all() {
-            insert_search_entry(&mut srch, entry);
-        }
-
-        Self { fs, srch, parser }
+    fn new(fs: FsIndex, loader: &Loader) -> Self {
+        let entries = fs.get_all().collect::<Vec<_>>();
+        let srch = load_all(&entries, loader);
+        Self { fs, srch }
     }
 
-    fn revalidate(&mut self, path: &Utf8Path) -> Result<(), cooklang_fs::Error> {
-        self.srch.remove(path);
-        self.insert_srch(path)
+    /// Replaces the data of a recipe, or removes it if it could not be loaded
+    fn revalidate(&mut self, path: &Utf8Path, data: Option<RecipeData>) {
+        match data {
+            Some(data) => {
+                self.srch.insert(path.to_owned(), data);
+            }
+            None => {
+                self.srch.remove(path);
+            }
+        }
     }
 
     fn remove(&mut self, path: &Utf8Path) {
@@ -72,34 +136,28 @@ impl Indexes {
         let _ = self.fs.remove(path);
     }
 
-    fn insert_srch(&mut self, path: &Utf8Path) -> Result<(), cooklang_fs::Error> {
-        let recipe = RecipeEntry::new(path).read()?.parse(&self.parser);
-        let mut ingredients = Vec::new();
-        let mut cookware = Vec::new();
-        let mut metadata = None;
-        if let Some(r) = recipe.valid_output() {
-            metadata = Some(r.metadata.to_owned());
-            for ingredient in &r.ingredients {
-                ingredients.push(ingredient.name.to_owned());
-            }
-            for tool in &r.cookware {
-                cookware.push(tool.name.to_string());
-            }
-        }
-        self.srch.insert(
-            path.to_owned(),
-            RecipeData {
-                metadata,
-                ingredients,
-                cookware,
-            },
-        );
-        Ok(())
+    fn insert(&mut self, path: &Utf8Path, data: Option<RecipeData>) {
+        let _ = self.fs.insert(path);
+        self.revalidate(path, data);
     }
+}
 
-    fn insert(&mut self, path: &Utf8Path) -> Result<(), cooklang_fs::Error> {
-        let _ = self.fs.insert(path);
-        self.insert_srch(path)
+/// Parses a changed recipe in the blocking thread pool, so the index is only
+/// locked to store the result
+async fn load_changed(path: &Utf8Path, loader: &Arc<Loader>) -> Option<RecipeData> {
+    let entry = RecipeEntry::new(path);
+    let loader = Arc::clone(loader);
+    let res = tokio::task::spawn_blocking(move || RecipeData::load(&entry, &loader)).await;
+    match res {
+        Ok(Ok(data)) => Some(data),
+        Ok(Err(e)) => {
+            tracing::warn!("Could not read '{path}': {e}");
+            None
+        }
+        Err(e) => {
+            tracing::error!("Error parsing '{path}': {e}");
+            None
+        }
     }
 }
 
@@ -108,38 +166,66 @@ impl Indexes {
 #[derive(Debug, Clone, Serialize)]
 #[serde(tag = "type", rename_all = "camelCase")]
 pub enum Update {
-    Modified { path: Utf8PathBuf },
-    Added { path: Utf8PathBuf },
-    Deleted { path: Utf8PathBuf },
-    Renamed { from: Utf8PathBuf, to: Utf8PathBuf },
+    Modified {
+        path: Utf8PathBuf,
+    },
+    Added {
+        path: Utf8PathBuf,
+    },
+    Deleted {
+        path: Utf8PathBuf,
+    },
+    Renamed {
+        from: Utf8PathBuf,
+        to: Utf8PathBuf,
+    },
+    /// An image of a recipe was added, modified or deleted
+    Image {
+        path: Utf8PathBuf,
+        recipe: Utf8PathBuf,
+    },
 }
 
 impl AsyncFsIndex {
-    pub fn new(index: FsIndex, parser: Arc<CooklangParser>) -> (Self, broadcast::Receiver<Update>) {
+    pub fn new(index: FsIndex, loader: Loader) -> (Self, broadcast::Receiver<Update>) {
+        let include_drafts = loader.include_drafts;
+        let slugs = loader.slugs.then(|| {
+            let slugs = Slugs::new(index.get_all().map(|e| e.path().to_owned()));
+            Arc::new(SyncRwLock::new(slugs))
+        });
+        let loader = Arc::new(loader);
         let (in_updt_tx, mut in_updt_rx) = mpsc::channel::<Update>(1);
         let (out_updates_tx, out_updates_rx) = broadcast::channel::<Update>(1);
         watch_changes_task(in_updt_tx, index.base_path());
 
-        let indexes = Arc::new(RwLock::new(Indexes::new(index, parser)));
+        let indexes = Arc::new(RwLock::new(Indexes::new(index, &loader)));
+
+        let generation = Arc::new(AtomicU64::new(0));
 
         let indexes2 = Arc::clone(&indexes);
+        let generation2 = Arc::clone(&generation);
+        let slugs2 = slugs.clone();
         tokio::spawn(async move {
             let indexes = indexes2;
-            while let Some(update) = in_updt_rx.recv().await {
+            while let Some(mut update) = in_updt_rx.recv().await {
+                let mut new_recipe = None;
                 match &update {
                     Update::Modified { path } => {
+                        let data = load_changed(path, &loader).await;
                         let mut indexes = indexes.write().await;
                         if indexes.fs.contains(path.as_str()) {
                             tracing::info!("Updated '{path}'");
-                            let _ = indexes.revalidate(path);
+                            indexes.revalidate(path, data);
                         } else {
                             tracing::info!("Added '{path}'");
-                            let _ = indexes.insert(path);
+                            indexes.insert(path, data);
+                            new_recipe = Some(path.clone());
                         }
                     }
                     Update::Added { path } => {
                         tracing::info!("Added '{path}'");
-                        let _ = indexes.write().await.insert(path);
+                        let data = load_changed(path, &loader).await;
+                        indexes.write().await.insert(path, data);
                     }
                     Update::Deleted { path } => {
                         tracing::info!("Deleted '{path}'");
@@ -147,17 +233,65 @@ impl AsyncFsIndex {
                     }
                     Update::Renamed { from, to } => {
                         tracing::info!("Renamed '{from}' to '{to}'");
+                        let data = load_changed(to, &loader).await;
                         let mut indexes = indexes.write().await;
                         indexes.remove(from);
-                        let _ = indexes.insert(to);
+                        indexes.insert(to, data);
                     }
+                    Update::Image { path, .. } => {
+                        tracing::info!("Image changed '{path}'");
+                    }
+                }
+                // clients only refresh what changed, so tell them it's new
+                if let Some(path) = new_recipe {
+                    update = Update::Added { path };
                 }
+                if let Some(slugs) = &slugs2 {
+                    let mut slugs = slugs.write().unwrap();
+                    match &update {
+                        Update::Added { path } => slugs.insert(path),
+                        Update::Deleted { path } => _ = slugs.remove(path),
+                        Update::Renamed { from, to } => slugs.rename(from, to),
+                        Update::Modified { .. } | Update::Image { .. } => {}
+                    }
+                }
+                generation2.fetch_add(1, Ordering::Relaxed);
                 // resend update after index is updated
                 let _ = out_updates_tx.send(update);
             }
         });
 
-        (Self { indexes }, out_updates_rx)
+        (
+            Self {
+                indexes,
+                include_drafts,
+                slugs,
+                generation,
+            },
+            out_updates_rx,
+        )
+    }
+
+    /// Changes every time the index is updated
+    ///
+    /// Used to invalidate cached data that may depend on other recipes.
+    pub fn generation(&self) -> u64 {
+        self.generation.load(Ordering::Relaxed)
+    }
+
+    /// Slug of a recipe, None without `ui.slugs`
+    pub fn slug(&self, path: &Utf8Path) -> Option<String> {
+        let slugs = self.slugs.as_ref()?.read().unwrap();
+        slugs.slug(path).map(str::to_string)
+    }
+
+    /// Finds a recipe by its slug, None without `ui.slugs`
+    pub fn lookup_slug(&self, slug: &str) -> Option<SlugLookup> {
+        self.slugs.as_ref()?.read().unwrap().lookup(slug)
+    }
+
+    pub fn has_slugs(&self) -> bool {
+        self.slugs.is_some()
     }
 
     pub fn resolve_blocking(
@@ -175,6 +309,35 @@ impl AsyncFsIndex {
         indexes.fs.get(recipe)
     }
 
+    /// Maps entries with their parsed data, if they are in the index
+    ///
+    /// Drafts are left out.
+    pub async fn with_data<T>(
+        &self,
+        entries: impl IntoIterator<Item = RecipeEntry>,
+        map: impl Fn(RecipeEntry, Option<&RecipeData>) -> T,
+    ) -> Vec<T> {
+        let indexes = self.indexes.read().await;
+        entries
+            .into_iter()
+            .filter_map(|entry| {
+                let data = indexes.srch.get(entry.path());
+                if self.is_hidden_draft(&entry, data) {
+                    return None;
+                }
+                Some(map(entry, data))
+            })
+            .collect()
+    }
+
+    fn is_hidden_draft(&self, entry: &RecipeEntry, data: Option<&RecipeData>) -> bool {
+        !self.include_drafts
+            && (entry.is_draft_name()
+                || data
+                    .and_then(|d| d.metadata.as_ref())
+                    .is_some_and(is_draft_metadata))
+    }
+
     pub async fn search<T>(
         &self,
         pred: impl Fn(&RecipeEntry, Option<&RecipeData>) -> bool,
@@ -188,6 +351,9 @@ impl AsyncFsIndex {
             .get_all()
             .filter_map(|entry| {
                 let tokens = indexes.srch.get(entry.path());
+                if self.is_hidden_draft(&entry, tokens) {
+                    return None;
+                }
                 match pred(&entry, tokens) {
                     true => Some((entry, tokens)),
                     false => None,
@@ -238,6 +404,27 @@ fn watch_changes_task(tx: mpsc::Sender<Update>, base_path: &Utf8Path) {
                     continue;
                 }
             };
+
+            // images only matter to refresh the recipe they belong to
+            let images = iter_image_paths(&watched_path, &ev.paths).collect::<Vec<_>>();
+            if !images.is_empty() {
+                if matches!(
+                    ev.kind,
+                    notify::EventKind::Create(_)
+                        | notify::EventKind::Modify(_)
+                        | notify::EventKind::Remove(_)
+                ) {
+                    for path in images {
+                        if let Some(recipe) = image_recipe(&watched_path, &path) {
+                            send(Update::Image {
+                                path: restore_path(path),
+                                recipe: restore_path(recipe),
+                            });
+                        }
+                    }
+                }
+            }
+
             let paths = iter_paths(&watched_path, &ev.paths);
             match ev.kind {
                 notify::EventKind::Create(_) => {
@@ -346,6 +533,34 @@ fn iter_paths<'a>(
         .filter(|p| p.extension() == Some("cook"))
 }
 
+fn iter_image_paths<'a>(
+    base_path: &'a Path,
+    paths: &'a [PathBuf],
+) -> impl Iterator<Item = Utf8PathBuf> + 'a {
+    paths
+        .iter()
+        .filter_map(move |path| {
+            path.strip_prefix(base_path)
+                .ok()
+                .and_then(|p| Utf8Path::from_path(p).map(Utf8Path::to_path_buf))
+        })
+        .filter(|p| {
+            p.extension()
+                .is_some_and(|ext| cooklang_fs::IMAGE_EXTENSIONS.contains(&ext))
+        })
+}
+
+/// Recipe an image belongs to, with [`cooklang_fs::image_recipe`]. Both paths
+/// are relative to the base path.
+fn image_recipe(base_path: &Path, image: &Utf8Path) -> Option<Utf8PathBuf> {
+    let base_path = Utf8Path::from_path(base_path)?;
+    let recipe = cooklang_fs::image_recipe(&base_path.join(image))?;
+    recipe
+        .strip_prefix(base_path)
+        .ok()
+        .map(Utf8Path::to_path_buf)
+}
+
 fn async_watcher() -> notify::Result<(
     RecommendedWatcher,
     mpsc::Receiver<notify::Result<notify::Event>>,
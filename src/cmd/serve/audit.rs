@@ -0,0 +1,122 @@
+//! Log of the actions that run programs or write files
+//!
+//! Opening the editor or the file manager and changing favorites or the cook
+//! log are appended to a file in the collection, one line per action. When the
+//! file grows too big it's moved to `<file>.1`, replacing the previous one.
+
+use std::{io::Write, net::IpAddr, sync::Mutex};
+
+use camino::{Utf8Path, Utf8PathBuf};
+use serde::Serialize;
+
+use crate::util::now_rfc3339;
+
+/// Size from which the log is rotated
+const MAX_SIZE: u64 = 1024 * 1024;
+
+#[derive(Debug, Serialize)]
+pub struct AuditEntry {
+    /// UTC, like `2025-01-14T18:30:00Z`
+    pub time: String,
+    pub client: String,
+    pub action: String,
+    pub path: String,
+}
+
+pub struct AuditLog {
+    path: Utf8PathBuf,
+    /// Nothing is recorded when `false`
+    writable: bool,
+    lock: Mutex<()>,
+}
+
+impl AuditLog {
+    pub fn new(path: Utf8PathBuf, writable: bool) -> Self {
+        Self {
+            path,
+            writable,
+            lock: Mutex::new(()),
+        }
+    }
+
+    /// Appends an action on a recipe made by a client
+    pub fn record(&self, client: IpAddr, action: &str, path: &str) {
+        if !self.writable {
+            return;
+        }
+        let line = format!("{}\t{client}\t{action}\t{}\n", now_rfc3339(), clean(path));
+        let _guard = self.lock.lock().unwrap();
+        if let Err(e) = append(&self.path, &line) {
+            tracing::error!("Could not write the audit log: {e}");
+        }
+    }
+
+    /// Last entries, newest first
+    pub fn last(&self, max: usize) -> Vec<AuditEntry> {
+        let _guard = self.lock.lock().unwrap();
+        let mut entries = Vec::new();
+        for path in [self.path.clone(), rotated(&self.path)] {
+            let Ok(content) = std::fs::read_to_string(&path) else {
+                continue;
+            };
+            entries.extend(content.lines().rev().filter_map(parse_line));
+            if entries.len() >= max {
+                break;
+            }
+        }
+        entries.truncate(max);
+        entries
+    }
+}
+
+fn append(path: &Utf8Path, line: &str) -> std::io::Result<()> {
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    if std::fs::metadata(path).is_ok_and(|m| m.len() >= MAX_SIZE) {
+        std::fs::rename(path, rotated(path))?;
+    }
+    let mut file = std::fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)?;
+    file.write_all(line.as_bytes())
+}
+
+fn rotated(path: &Utf8Path) -> Utf8PathBuf {
+    let mut name = path.file_name().unwrap_or_default().to_string();
+    name.push_str(".1");
+    path.with_file_name(name)
+}
+
+fn parse_line(line: &str) -> Option<AuditEntry> {
+    let mut fields = line.splitn(4, '\t');
+    Some(AuditEntry {
+        time: fields.next()?.to_string(),
+        client: fields.next()?.to_string(),
+        action: fields.next()?.to_string(),
+        path: fields.next()?.to_string(),
+    })
+}
+
+/// Keeps every entry in one line
+fn clean(s: &str) -> String {
+    s.replace(char::is_control, " ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn entries() {
+        let line = format!(
+            "2025-01-14T18:30:00Z\t127.0.0.1\topen_editor\t{}",
+            clean("Breakfast/Pan\tcakes")
+        );
+        let entry = parse_line(&line).unwrap();
+        assert_eq!(entry.action, "open_editor");
+        assert_eq!(entry.path, "Breakfast/Pan cakes");
+        assert!(parse_line("garbage").is_none());
+    }
+}
@@ -0,0 +1,103 @@
+//! HTTP conditional requests support
+
+use std::hash::{DefaultHasher, Hash, Hasher};
+
+use axum::{
+    extract::Request,
+    http::{header, HeaderMap, HeaderValue, StatusCode},
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+
+/// Checks if the `If-None-Match` header of a request matches an etag
+///
+/// Uses the weak comparison, as the spec says for `If-None-Match`.
+pub fn etag_matches(headers: &HeaderMap, etag: &str) -> bool {
+    let Some(if_none_match) = headers
+        .get(header::IF_NONE_MATCH)
+        .and_then(|v| v.to_str().ok())
+    else {
+        return false;
+    };
+    let opaque = |tag: &str| tag.trim().trim_start_matches("W/").to_string();
+    let etag = opaque(etag);
+    if_none_match
+        .split(',')
+        .any(|tag| tag.trim() == "*" || opaque(tag) == etag)
+}
+
+/// Builds a quoted etag from anything hashable
+pub fn make_etag(value: impl Hash) -> String {
+    let mut hasher = DefaultHasher::new();
+    // templates and assets are embedded in the binary
+    env!("CARGO_PKG_VERSION").hash(&mut hasher);
+    value.hash(&mut hasher);
+    format!("\"{:016x}\"", hasher.finish())
+}
+
+/// Response to send when the client cache is still valid
+pub fn not_modified(etag: &str) -> Response {
+    (
+        StatusCode::NOT_MODIFIED,
+        [
+            (header::ETAG, etag.to_string()),
+            (header::CACHE_CONTROL, "no-cache".to_string()),
+        ],
+    )
+        .into_response()
+}
+
+/// Adds an etag to the files served from the collection
+///
+/// The etag is computed from the last modification time and size of the
+/// file, the same way other static file servers do. The last modified time
+/// is already handled by the file service.
+pub async fn src_etag(req: Request, next: Next) -> Response {
+    let req_headers = req.headers().clone();
+    let mut res = next.run(req).await;
+    if res.status() != StatusCode::OK {
+        return res;
+    }
+
+    let headers = res.headers();
+    let (Some(modified), Some(len)) = (
+        headers.get(header::LAST_MODIFIED),
+        headers.get(header::CONTENT_LENGTH),
+    ) else {
+        return res;
+    };
+    let etag = format!("W/{}", make_etag((modified.as_bytes(), len.as_bytes())));
+
+    if etag_matches(&req_headers, &etag) {
+        return not_modified(&etag);
+    }
+
+    let headers = res.headers_mut();
+    if let Ok(value) = HeaderValue::from_str(&etag) {
+        headers.insert(header::ETAG, value);
+    }
+    headers.insert(header::CACHE_CONTROL, HeaderValue::from_static("no-cache"));
+    res
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_etag_matches() {
+        let headers = |v: &'static str| {
+            let mut h = HeaderMap::new();
+            h.insert(header::IF_NONE_MATCH, HeaderValue::from_static(v));
+            h
+        };
+
+        assert!(etag_matches(&headers("\"abc\""), "\"abc\""));
+        assert!(etag_matches(&headers("W/\"abc\""), "\"abc\""));
+        assert!(etag_matches(&headers("\"abc\""), "W/\"abc\""));
+        assert!(etag_matches(&headers("\"xyz\", \"abc\""), "\"abc\""));
+        assert!(etag_matches(&headers("*"), "\"abc\""));
+        assert!(!etag_matches(&headers("\"xyz\""), "\"abc\""));
+        assert!(!etag_matches(&HeaderMap::new(), "\"abc\""));
+    }
+}
@@ -0,0 +1,143 @@
+//! Address of the client behind reverse proxies
+//!
+//! Requests from a proxy in `ui.trusted_proxies` take the client address
+//! from the `X-Forwarded-For` header, so the local only features and the
+//! logs see the real client and not the proxy. Only these proxies can tell
+//! the scheme of the client with `X-Forwarded-Proto`.
+
+use std::{
+    net::{IpAddr, SocketAddr},
+    sync::Arc,
+};
+
+use axum::{
+    extract::{ConnectInfo, FromRequestParts, Request, State},
+    http::{request::Parts, HeaderMap, StatusCode},
+    middleware::Next,
+    response::Response,
+};
+
+/// IP of the client that made the request
+#[derive(Clone, Copy)]
+pub struct ClientIp(pub IpAddr);
+
+impl<S> FromRequestParts<S> for ClientIp
+where
+    S: Send + Sync,
+{
+    type Rejection = StatusCode;
+
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+        if let Some(ip) = parts.extensions.get::<ClientIp>() {
+            return Ok(*ip);
+        }
+        parts
+            .extensions
+            .get::<ConnectInfo<SocketAddr>>()
+            .map(|ConnectInfo(addr)| Self(addr.ip()))
+            .ok_or(StatusCode::INTERNAL_SERVER_ERROR)
+    }
+}
+
+/// Finds the client of every request
+pub async fn client_ip_layer(
+    State(trusted): State<Arc<[IpAddr]>>,
+    mut req: Request,
+    next: Next,
+) -> Response {
+    let Some(ConnectInfo(peer)) = req.extensions().get::<ConnectInfo<SocketAddr>>().copied() else {
+        return next.run(req).await;
+    };
+    let forwarded_for = req
+        .headers()
+        .get_all("x-forwarded-for")
+        .iter()
+        .filter_map(|v| v.to_str().ok())
+        .collect::<Vec<_>>()
+        .join(",");
+    let ip = client_ip(peer.ip(), &forwarded_for, &trusted);
+    req.extensions_mut().insert(ClientIp(ip));
+    if !trusted.contains(&peer.ip()) {
+        req.headers_mut().remove(FORWARDED_PROTO);
+    }
+    next.run(req).await
+}
+
+const FORWARDED_PROTO: &str = "x-forwarded-proto";
+
+/// Scheme the client used to make the request, `http` or `https`
+///
+/// From `X-Forwarded-Proto`, only kept in requests from trusted proxies by
+/// [`client_ip_layer`], or `https` if the server has TLS.
+pub fn client_scheme(headers: &HeaderMap, tls: bool) -> &'static str {
+    let forwarded = headers
+        .get(FORWARDED_PROTO)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.split(',').next())
+        .map(str::trim);
+    match forwarded {
+        Some(proto) if proto.eq_ignore_ascii_case("https") => "https",
+        Some(proto) if proto.eq_ignore_ascii_case("http") => "http",
+        _ if tls => "https",
+        _ => "http",
+    }
+}
+
+/// Goes through the `X-Forwarded-For` list from the end while the hops are
+/// trusted proxies
+///
+/// Addresses before the last untrusted one could be made up by the client,
+/// so they are never used.
+fn client_ip(peer: IpAddr, forwarded_for: &str, trusted: &[IpAddr]) -> IpAddr {
+    let mut ip = peer;
+    for hop in forwarded_for.rsplit(',') {
+        if !trusted.contains(&ip) {
+            break;
+        }
+        match hop.trim().parse() {
+            Ok(hop) => ip = hop,
+            Err(_) => break,
+        }
+    }
+    ip
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn forwarded_for() {
+        let ip = |s: &str| s.parse::<IpAddr>().unwrap();
+        let proxy = ip("127.0.0.1");
+        let trusted = [proxy, ip("10.0.0.2")];
+
+        assert_eq!(client_ip(proxy, "203.0.113.7", &[]), proxy);
+        assert_eq!(client_ip(proxy, "203.0.113.7", &trusted), ip("203.0.113.7"));
+        assert_eq!(
+            client_ip(proxy, "1.1.1.1, 203.0.113.7, 10.0.0.2", &trusted),
+            ip("203.0.113.7")
+        );
+        assert_eq!(
+            client_ip(ip("203.0.113.7"), "127.0.0.1", &trusted),
+            ip("203.0.113.7")
+        );
+        assert_eq!(client_ip(proxy, "", &trusted), proxy);
+        assert_eq!(client_ip(proxy, "unknown", &trusted), proxy);
+    }
+
+    #[test]
+    fn scheme() {
+        let headers = |proto: &str| {
+            let mut headers = HeaderMap::new();
+            headers.insert(FORWARDED_PROTO, proto.parse().unwrap());
+            headers
+        };
+        assert_eq!(client_scheme(&HeaderMap::new(), false), "http");
+        assert_eq!(client_scheme(&HeaderMap::new(), true), "https");
+        assert_eq!(client_scheme(&headers("https"), false), "https");
+        assert_eq!(client_scheme(&headers("https, http"), false), "https");
+        assert_eq!(client_scheme(&headers("http"), true), "http");
+        assert_eq!(client_scheme(&headers("ftp"), true), "https");
+    }
+}
@@ -0,0 +1,122 @@
+//! Dated notes of every time a recipe was cooked
+//!
+//! They are shared by every client and stored in the database by recipe.
+//! Entries of logged in users, with `ui.users`, say who cooked.
+
+use std::sync::Arc;
+
+use anyhow::Result;
+use camino::Utf8Path;
+use redb::TableDefinition;
+use serde::{Deserialize, Serialize};
+
+use super::db::{Db, Table};
+
+/// Max length of a note, in chars
+pub const MAX_NOTE_LEN: usize = 1000;
+
+const TABLE: Table = TableDefinition::new("cook_log");
+
+#[derive(Debug, Clone, Hash, Serialize, Deserialize)]
+pub struct LogEntry {
+    /// `YYYY-MM-DD`
+    pub date: String,
+    pub note: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub user: Option<String>,
+}
+
+pub struct CookLogStore {
+    db: Arc<Db>,
+}
+
+impl CookLogStore {
+    /// Imports the log file used before the database, if it exists
+    pub fn new(db: Arc<Db>, legacy_file: &Utf8Path) -> Self {
+        if let Err(e) = db.import(TABLE, legacy_file) {
+            tracing::warn!("Ignoring the old cook log: {e:#}");
+        }
+        Self { db }
+    }
+
+    /// Entries of a recipe, newest first. The recipe is a path relative to
+    /// the collection without extension.
+    pub fn get(&self, recipe: &str) -> Vec<LogEntry> {
+        let mut entries: Vec<LogEntry> = self
+            .db
+            .get(TABLE, recipe)
+            .unwrap_or_else(|e| {
+                tracing::error!("Could not read the cook log: {e:#}");
+                None
+            })
+            .unwrap_or_default();
+        entries.reverse();
+        entries
+    }
+
+    /// Adds an entry and returns all the entries of the recipe, newest first
+    pub fn add(&self, recipe: &str, entry: LogEntry) -> Result<Vec<LogEntry>> {
+        self.db
+            .update(TABLE, recipe, |entries: &mut Option<Vec<LogEntry>>| {
+                let entries = entries.get_or_insert_default();
+                // keep them sorted by date, and by insertion for the same day
+                let pos = entries.partition_point(|e| e.date <= entry.date);
+                entries.insert(pos, entry);
+                entries.iter().rev().cloned().collect()
+            })
+    }
+}
+
+/// Checks the date is like `YYYY-MM-DD`, the format of date inputs
+pub fn is_valid_date(date: &str) -> bool {
+    let parts = date.split('-').collect::<Vec<_>>();
+    let [year, month, day] = parts[..] else {
+        return false;
+    };
+    let number = |s: &str, len: usize| -> Option<u32> {
+        if s.len() != len || !s.bytes().all(|b| b.is_ascii_digit()) {
+            return None;
+        }
+        s.parse().ok()
+    };
+    matches!(
+        (number(year, 4), number(month, 2), number(day, 2)),
+        (Some(_), Some(1..=12), Some(1..=31))
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(date: &str, note: &str) -> LogEntry {
+        LogEntry {
+            date: date.into(),
+            note: note.into(),
+            user: None,
+        }
+    }
+
+    #[test]
+    fn newest_first() {
+        let store = CookLogStore {
+            db: Arc::new(Db::in_memory()),
+        };
+        store.add("Pancakes", entry("2024-05-02", "b")).unwrap();
+        store.add("Pancakes", entry("2024-05-01", "a")).unwrap();
+        let entries = store.add("Pancakes", entry("2024-05-02", "c")).unwrap();
+        let notes = entries.iter().map(|e| e.note.as_str()).collect::<Vec<_>>();
+        assert_eq!(notes, ["c", "b", "a"]);
+        assert_eq!(store.get("Pancakes").len(), 3);
+        assert!(store.get("Soups/Ramen").is_empty());
+    }
+
+    #[test]
+    fn dates() {
+        assert!(is_valid_date("2024-02-29"));
+        assert!(!is_valid_date("2024-2-29"));
+        assert!(!is_valid_date("2024-13-01"));
+        assert!(!is_valid_date("2024-01-01T00:00"));
+        assert!(!is_valid_date(""));
+    }
+}
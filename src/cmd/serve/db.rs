@@ -0,0 +1,204 @@
+//! Embedded database of the web UI
+//!
+//! Favorites, cook logs and shopping lists are tables of a single redb file in
+//! the collection `.cooklang` dir. Keys are strings and values are JSON, so a
+//! table is a map like the JSON files used before it.
+//!
+//! When writing to the collection is disabled, the database is a copy of the
+//! file in memory.
+
+use anyhow::{Context as _, Result};
+use camino::Utf8Path;
+use redb::{
+    backends::InMemoryBackend, Database, ReadableTable, ReadableTableMetadata, StorageBackend,
+    TableDefinition, TableError, TableHandle,
+};
+use serde::{de::DeserializeOwned, Serialize};
+
+/// Table of JSON values by string keys
+pub type Table = TableDefinition<'static, &'static str, &'static str>;
+
+pub struct Db {
+    db: Database,
+}
+
+impl Db {
+    /// Opens the database file, creating it if needed
+    ///
+    /// It fails if another process has it open, like another `chef serve` of
+    /// the same collection.
+    pub fn open(path: &Utf8Path, writable: bool) -> Result<Self> {
+        let db = if writable {
+            if let Some(parent) = path.parent() {
+                std::fs::create_dir_all(parent)?;
+            }
+            Database::create(path)
+        } else {
+            let backend = InMemoryBackend::new();
+            match std::fs::read(path) {
+                Ok(bytes) => {
+                    backend.set_len(bytes.len() as u64)?;
+                    backend.write(0, &bytes)?;
+                }
+                Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+                Err(e) => return Err(e).with_context(|| format!("Failed to read '{path}'")),
+            }
+            Database::builder().create_with_backend(backend)
+        };
+        let db = db.with_context(|| format!("Failed to open the database '{path}'"))?;
+        Ok(Self { db })
+    }
+
+    /// Empty database in memory
+    #[cfg(test)]
+    pub fn in_memory() -> Self {
+        let db = Database::builder()
+            .create_with_backend(InMemoryBackend::new())
+            .unwrap();
+        Self { db }
+    }
+
+    /// Value of a key, if any
+    pub fn get<T: DeserializeOwned>(&self, table: Table, key: &str) -> Result<Option<T>> {
+        let txn = self.db.begin_read()?;
+        let table = match txn.open_table(table) {
+            Ok(table) => table,
+            Err(TableError::TableDoesNotExist(_)) => return Ok(None),
+            Err(e) => return Err(e.into()),
+        };
+        let Some(value) = table.get(key)? else {
+            return Ok(None);
+        };
+        Ok(Some(serde_json::from_str(value.value())?))
+    }
+
+    /// Changes the value of a key in a single transaction
+    ///
+    /// The value is `None` if it does not exist, and it's removed if it's
+    /// `None` after the change.
+    pub fn update<T, R>(
+        &self,
+        table: Table,
+        key: &str,
+        f: impl FnOnce(&mut Option<T>) -> R,
+    ) -> Result<R>
+    where
+        T: Serialize + DeserializeOwned,
+    {
+        let txn = self.db.begin_write()?;
+        let res = {
+            let mut table = txn.open_table(table)?;
+            let mut value = match table.get(key)? {
+                Some(value) => Some(serde_json::from_str(value.value())?),
+                None => None,
+            };
+            let res = f(&mut value);
+            match value {
+                Some(value) => {
+                    let json = serde_json::to_string(&value)?;
+                    table.insert(key, json.as_str())?;
+                }
+                None => {
+                    table.remove(key)?;
+                }
+            }
+            res
+        };
+        txn.commit()?;
+        Ok(res)
+    }
+
+    /// Copies a JSON file with a map of keys and values to a table
+    ///
+    /// Only the first time, when the table does not exist yet. The file is
+    /// not removed.
+    pub fn import(&self, table: Table, path: &Utf8Path) -> Result<()> {
+        let txn = self.db.begin_write()?;
+        if txn.list_tables()?.any(|t| t.name() == table.name()) {
+            return Ok(());
+        }
+        {
+            let mut table = txn.open_table(table)?;
+            match std::fs::read_to_string(path) {
+                Ok(content) => {
+                    let map: serde_json::Map<String, serde_json::Value> =
+                        serde_json::from_str(&content)
+                            .with_context(|| format!("Invalid file '{path}'"))?;
+                    for (key, value) in &map {
+                        table.insert(key.as_str(), value.to_string().as_str())?;
+                    }
+                    tracing::info!("Imported {} entries from '{path}'", table.len()?);
+                }
+                Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+                Err(e) => return Err(e).with_context(|| format!("Failed to read '{path}'")),
+            }
+        }
+        txn.commit()?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TABLE: Table = TableDefinition::new("test");
+
+    #[test]
+    fn update() {
+        let db = Db::in_memory();
+        assert_eq!(db.get::<Vec<u32>>(TABLE, "a").unwrap(), None);
+        db.update(TABLE, "a", |v: &mut Option<Vec<u32>>| {
+            v.get_or_insert_default().push(1)
+        })
+        .unwrap();
+        db.update(TABLE, "a", |v: &mut Option<Vec<u32>>| {
+            v.get_or_insert_default().push(2)
+        })
+        .unwrap();
+        assert_eq!(db.get(TABLE, "a").unwrap(), Some(vec![1, 2]));
+        let removed = db.update(TABLE, "a", |v: &mut Option<Vec<u32>>| v.take());
+        assert_eq!(removed.unwrap(), Some(vec![1, 2]));
+        assert_eq!(db.get::<Vec<u32>>(TABLE, "a").unwrap(), None);
+    }
+
+    #[test]
+    fn import() {
+        let dir = tempfile::tempdir().unwrap();
+        let dir = Utf8Path::from_path(dir.path()).unwrap();
+        let file = dir.join("old.json");
+        std::fs::write(&file, r#"{"a": [1, 2], "b": []}"#).unwrap();
+
+        let db = Db::open(&dir.join("chef.redb"), true).unwrap();
+        db.import(TABLE, &file).unwrap();
+        assert_eq!(db.get(TABLE, "a").unwrap(), Some(vec![1, 2]));
+        // only once
+        db.update(TABLE, "a", |v: &mut Option<Vec<u32>>| *v = None)
+            .unwrap();
+        db.import(TABLE, &file).unwrap();
+        assert_eq!(db.get::<Vec<u32>>(TABLE, "a").unwrap(), None);
+
+        std::fs::write(&file, "not json").unwrap();
+        assert!(db.import(TableDefinition::new("other"), &file).is_err());
+    }
+
+    #[test]
+    fn read_only() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = Utf8Path::from_path(dir.path()).unwrap().join("chef.redb");
+        let db = Db::open(&path, true).unwrap();
+        db.update(TABLE, "a", |v: &mut Option<u32>| *v = Some(1))
+            .unwrap();
+        drop(db);
+
+        let db = Db::open(&path, false).unwrap();
+        assert_eq!(db.get(TABLE, "a").unwrap(), Some(1));
+        db.update(TABLE, "a", |v: &mut Option<u32>| *v = Some(2))
+            .unwrap();
+        assert_eq!(db.get(TABLE, "a").unwrap(), Some(2));
+        drop(db);
+
+        let db = Db::open(&path, true).unwrap();
+        assert_eq!(db.get(TABLE, "a").unwrap(), Some(1));
+    }
+}
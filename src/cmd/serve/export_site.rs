@@ -0,0 +1,219 @@
+//! Static copy of the web UI
+//!
+//! Every page is rendered by sending a request to the same router used by
+//! `serve`, with the templates in static mode so they don't use anything that
+//! needs the server.
+
+use std::{collections::BTreeSet, fs, net::SocketAddr, sync::Arc};
+
+use anyhow::{bail, Context as _, Result};
+use axum::{body::Body, extract::ConnectInfo, http::Request, Router};
+use camino::{Utf8Path, Utf8PathBuf};
+use clap::Args;
+use rust_embed::RustEmbed;
+use tower::ServiceExt;
+
+use super::{
+    access_log::AccessLog,
+    build_state,
+    handlers::{clean_path, pages::page_names, recipe_url_name},
+    make_router, normalize_base_url, Assets,
+};
+use crate::{
+    util::{tag_levels, url_encode_path},
+    Context,
+};
+
+#[derive(Debug, Args)]
+pub struct ExportSiteArgs {
+    /// Output directory
+    #[arg(value_hint = clap::ValueHint::DirPath)]
+    output: Utf8PathBuf,
+
+    /// Path the site will be published under, like `/recipes`
+    ///
+    /// For example, the repository name for GitHub Pages project sites.
+    /// Overrides the `ui.base_url` config.
+    #[arg(long, value_name = "PATH")]
+    base_url: Option<String>,
+
+    /// Write into the output directory even if it's not empty
+    #[arg(long)]
+    force: bool,
+}
+
+#[tokio::main]
+pub async fn run(ctx: Context, args: ExportSiteArgs) -> Result<()> {
+    if !ctx.is_collection {
+        bail!("`export-site` needs to run inside a collection");
+    }
+    let out = &args.output;
+    if !args.force && out.is_dir() && out.read_dir()?.next().is_some() {
+        bail!("The output dir is not empty: {out}. Use `--force` to write into it");
+    }
+
+    let base_url = normalize_base_url(
+        args.base_url
+            .as_deref()
+            .or(ctx.config.ui.base_url.as_deref())
+            .unwrap_or_default(),
+    );
+    let state = build_state(ctx, base_url, true, false).context("failed to build the site")?;
+    let app = make_router(Arc::clone(&state), AccessLog::default());
+
+    let entries = state
+        .recipe_index
+        .search(
+            |_, _| true,
+            |entry, tokens| {
+                let tags = tokens
+                    .and_then(|t| t.metadata.as_ref())
+                    .and_then(|m| m.tags())
+                    .unwrap_or_default()
+                    .iter()
+                    .map(|t| t.to_string())
+                    .collect::<Vec<_>>();
+                (entry, tags)
+            },
+            0,
+            usize::MAX,
+        )
+        .await;
+
+    let mut pages = vec!["/".to_string(), "/about".to_string()];
+    for name in page_names(&state.base_path)? {
+        pages.push(format!("/pages/{name}"));
+    }
+    for dir in collection_dirs(&state.base_path)? {
+        pages.push(format!("/d/{dir}"));
+    }
+    let mut tags = BTreeSet::new();
+    for (entry, recipe_tags) in &entries {
+        pages.push(format!("/r/{}", recipe_url_name(entry.path(), &state)));
+        // parents of hierarchical tags have their page too
+        tags.extend(
+            recipe_tags
+                .iter()
+                .flat_map(|t| tag_levels(t))
+                .map(str::to_string),
+        );
+
+        // sources and images, the pages link to them in `/src`
+        let mut files = vec![entry.path().to_path_buf()];
+        files.extend(entry.images().iter().map(|i| i.path.clone()));
+        files.extend(cooklang_fs::recipe_videos(entry.path()));
+        for file in files {
+            let rel = clean_path(&file, &state.base_path);
+            copy_file(&file, &out.join("src").join(rel))?;
+        }
+    }
+
+    for page in &pages {
+        let content = fetch(&app, &state.base_url, page).await?;
+        write_file(&page_file(out, page), &content)?;
+    }
+    // the links of the codes need to know where the site will be
+    if state.config.ui.public_url.is_some() {
+        for (entry, _) in &entries {
+            let qr = format!("/r/{}/qr.svg", recipe_url_name(entry.path(), &state));
+            let content = fetch(&app, &state.base_url, &qr).await?;
+            write_file(&out.join(qr.trim_start_matches('/')), &content)?;
+        }
+        for file in ["sitemap.xml", "feed.xml"] {
+            let content = fetch(&app, &state.base_url, &format!("/{file}")).await?;
+            write_file(&out.join(file), &content)?;
+        }
+    } else {
+        tracing::warn!(
+            "Set `ui.public_url` in the config to include share QR codes, the sitemap and the feed"
+        );
+    }
+    for tag in &tags {
+        let url = format!("/search?q={}", url_encode_path(&format!("tag:{tag}")));
+        let content = fetch(&app, &state.base_url, &url).await?;
+        write_file(&page_file(out, &format!("/tags/{tag}")), &content)?;
+    }
+
+    for name in Assets::iter() {
+        let asset = Assets::get(&name).expect("listed asset");
+        write_file(&out.join(name.as_ref()), &asset.data)?;
+    }
+
+    tracing::info!(
+        "Exported {} recipes, {} tags and {} other pages to {out}",
+        entries.len(),
+        tags.len(),
+        pages.len() - entries.len()
+    );
+    Ok(())
+}
+
+/// Renders a page with the app
+///
+/// The path of `url` is encoded here, the query must be already encoded.
+async fn fetch(app: &Router, base_url: &str, url: &str) -> Result<Vec<u8>> {
+    let uri = match url.split_once('?') {
+        Some((path, query)) => format!("{base_url}{}?{query}", url_encode_path(path)),
+        None => format!("{base_url}{}", url_encode_path(url)),
+    };
+    let mut req = Request::builder().uri(uri).body(Body::empty())?;
+    // not a loopback address so local only features are not rendered
+    req.extensions_mut()
+        .insert(ConnectInfo(SocketAddr::from(([0, 0, 0, 0], 0))));
+    let res = app.clone().oneshot(req).await?;
+    if !res.status().is_success() {
+        bail!("Could not render '{url}': {}", res.status());
+    }
+    let body = axum::body::to_bytes(res.into_body(), usize::MAX).await?;
+    Ok(body.to_vec())
+}
+
+/// Every dir in the collection with recipes in it, relative to it
+fn collection_dirs(base_path: &Utf8Path) -> Result<Vec<String>> {
+    let mut dirs = Vec::new();
+    let mut pending = vec![base_path.to_path_buf()];
+    while let Some(dir) = pending.pop() {
+        for entry in cooklang_fs::walk_dir(&dir)? {
+            if let cooklang_fs::Entry::Dir(dir) = entry {
+                let rel = clean_path(dir.path(), base_path);
+                dirs.push(rel.to_string());
+                pending.push(dir.path().to_path_buf());
+            }
+        }
+    }
+    dirs.sort();
+    Ok(dirs)
+}
+
+/// Static hosts serve `index.html` for a dir, so `/r/Pasta` works
+fn page_file(out: &Utf8Path, page: &str) -> Utf8PathBuf {
+    let path = page.split('?').next().unwrap_or(page).trim_matches('/');
+    out.join(path).join("index.html")
+}
+
+fn write_file(path: &Utf8Path, content: &[u8]) -> Result<()> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    fs::write(path, content).with_context(|| format!("Failed to write '{path}'"))
+}
+
+fn copy_file(from: &Utf8Path, to: &Utf8Path) -> Result<()> {
+    if let Some(parent) = to.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    fs::copy(from, to).with_context(|| format!("Failed to copy '{from}'"))?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn page_files() {
+        let out = Utf8Path::new("site");
+        assert_eq!(page_file(out, "/"), "site/index.html");
+        assert_eq!(page_file(out, "/r/a/Pasta"), "site/r/a/Pasta/index.html");
+    }
+}
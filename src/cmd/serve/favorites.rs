@@ -0,0 +1,110 @@
+//! Favorite recipes of every client
+//!
+//! They are stored in the database by [`ClientId`](super::users::ClientId), so
+//! logged in users have their own favorites in any browser.
+
+use std::{collections::BTreeSet, sync::Arc};
+
+use anyhow::Result;
+use camino::Utf8Path;
+use redb::TableDefinition;
+
+use super::db::{Db, Table};
+
+const TABLE: Table = TableDefinition::new("favorites");
+
+pub struct FavoritesStore {
+    db: Arc<Db>,
+}
+
+impl FavoritesStore {
+    /// Imports the favorites file used before the database, if it exists
+    pub fn new(db: Arc<Db>, legacy_file: &Utf8Path) -> Self {
+        if let Err(e) = db.import(TABLE, legacy_file) {
+            tracing::warn!("Ignoring the old favorites: {e:#}");
+        }
+        Self { db }
+    }
+
+    /// Favorite recipes of a client, as paths relative to the collection
+    /// without extension
+    pub fn get(&self, id: Option<&str>) -> BTreeSet<String> {
+        let Some(id) = id else {
+            return BTreeSet::new();
+        };
+        self.db
+            .get(TABLE, id)
+            .unwrap_or_else(|e| {
+                tracing::error!("Could not read favorites: {e:#}");
+                None
+            })
+            .unwrap_or_default()
+    }
+
+    /// Adds or removes a favorite and returns if it's now a favorite
+    pub fn toggle(&self, id: &str, recipe: &str) -> Result<bool> {
+        self.db
+            .update(TABLE, id, |set: &mut Option<BTreeSet<String>>| {
+                let favorites = set.get_or_insert_default();
+                let added = if favorites.remove(recipe) {
+                    false
+                } else {
+                    favorites.insert(recipe.to_string());
+                    true
+                };
+                if favorites.is_empty() {
+                    *set = None;
+                }
+                added
+            })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn add_and_remove() {
+        let store = FavoritesStore {
+            db: Arc::new(Db::in_memory()),
+        };
+        assert!(store.toggle("abc", "Pancakes").unwrap());
+        assert!(store.toggle("abc", "Soups/Ramen").unwrap());
+        assert_eq!(
+            store.get(Some("abc")),
+            BTreeSet::from(["Pancakes".to_string(), "Soups/Ramen".to_string()])
+        );
+        assert!(!store.toggle("abc", "Pancakes").unwrap());
+        assert_eq!(store.get(Some("abc")).len(), 1);
+        assert!(store.get(Some("other")).is_empty());
+        assert!(store.get(None).is_empty());
+    }
+
+    #[test]
+    fn legacy_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let dir = Utf8Path::from_path(dir.path()).unwrap();
+        let file = dir.join("favorites.json");
+        std::fs::write(&file, r#"{"abc": ["Pancakes"]}"#).unwrap();
+
+        let db = Arc::new(Db::open(&dir.join("chef.redb"), true).unwrap());
+        let store = FavoritesStore::new(Arc::clone(&db), &file);
+        assert!(store.get(Some("abc")).contains("Pancakes"));
+        // the last favorite also removes the client
+        store.toggle("abc", "Pancakes").unwrap();
+        let store = FavoritesStore::new(db, &file);
+        assert!(store.get(Some("abc")).is_empty());
+    }
+
+    #[test]
+    fn invalid_legacy_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let dir = Utf8Path::from_path(dir.path()).unwrap();
+        let file = dir.join("favorites.json");
+        std::fs::write(&file, "not json").unwrap();
+        let store = FavoritesStore::new(Arc::new(Db::in_memory()), &file);
+        assert!(store.get(Some("abc")).is_empty());
+        assert!(store.toggle("abc", "Pancakes").unwrap());
+    }
+}
@@ -0,0 +1,66 @@
+//! Partial pages for htmx
+//!
+//! Every page route is also available under `/fragments`, which renders only
+//! the `fragment` block of the page template, the part inside `#content`.
+//! The response tells htmx to push the URL of the full page, so the history
+//! and reloads keep working.
+
+use std::sync::Arc;
+
+use axum::{
+    extract::{FromRequestParts, Request, State},
+    http::{request::Parts, HeaderValue},
+    middleware::Next,
+    response::Response,
+};
+use minijinja::{Template, Value};
+
+/// Block of the page templates rendered for fragments
+const BLOCK: &str = "fragment";
+
+#[derive(Clone, Copy)]
+struct FragmentRequest;
+
+/// Whether the request is for a fragment instead of the full page
+pub struct Fragment(pub bool);
+
+impl<S> FromRequestParts<S> for Fragment
+where
+    S: Send + Sync,
+{
+    type Rejection = std::convert::Infallible;
+
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+        Ok(Self(parts.extensions.get::<FragmentRequest>().is_some()))
+    }
+}
+
+/// Marks the requests of the `/fragments` router, which are received
+/// without the prefix
+pub async fn fragment_layer(
+    State(base_url): State<Arc<str>>,
+    mut req: Request,
+    next: Next,
+) -> Response {
+    let page_url = match req.uri().path_and_query() {
+        Some(pq) => format!("{base_url}{pq}"),
+        None => format!("{base_url}/"),
+    };
+    req.extensions_mut().insert(FragmentRequest);
+    let mut res = next.run(req).await;
+    if res.status().is_success() {
+        if let Ok(value) = HeaderValue::from_str(&page_url) {
+            res.headers_mut().insert("HX-Push-Url", value);
+        }
+    }
+    res
+}
+
+/// Renders the full page or only its fragment
+pub fn render(tmpl: &Template, ctx: Value, fragment: bool) -> Result<String, minijinja::Error> {
+    if fragment {
+        tmpl.eval_to_state(ctx)?.render_block(BLOCK)
+    } else {
+        tmpl.render(ctx)
+    }
+}
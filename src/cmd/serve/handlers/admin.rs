@@ -0,0 +1,30 @@
+use axum::{
+    extract::State,
+    http::StatusCode,
+    response::{Html, IntoResponse, Response},
+};
+use minijinja::context;
+
+use crate::cmd::serve::{client_ip::ClientIp, locale::UserLocale, S};
+
+use super::mj_ok;
+
+/// Entries shown in the audit log page
+const MAX_ENTRIES: usize = 500;
+
+/// Last entries of the audit log, only for the same machine
+pub async fn audit_log(
+    UserLocale(t): UserLocale,
+    State(state): State<S>,
+    ClientIp(who): ClientIp,
+) -> Response {
+    if !who.is_loopback() {
+        tracing::warn!("Denied audit log request from '{who}': Not loopback ip");
+        return StatusCode::UNAUTHORIZED.into_response();
+    }
+    let entries = state.audit.last(MAX_ENTRIES);
+    let tmpl = mj_ok!(state.templates.get_template("audit_log.html"));
+    let res = tmpl.render(context! { t, entries, max_entries => MAX_ENTRIES });
+    let content = mj_ok!(res);
+    Html(content).into_response()
+}
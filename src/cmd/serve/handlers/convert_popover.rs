@@ -4,10 +4,13 @@ use axum::{
     response::{Html, IntoResponse, Response},
     Json,
 };
-use cooklang::ScaledQuantity;
+use cooklang::{convert::System, ScaledQuantity};
 use minijinja::context;
 
-use crate::cmd::serve::{locale::UserLocale, S};
+use crate::{
+    cmd::serve::{locale::UserLocale, S},
+    util::compatible_units,
+};
 
 use super::mj_ok;
 
@@ -24,9 +27,9 @@ pub async fn convert_popover(
         None => return StatusCode::BAD_REQUEST.into_response(),
     };
 
-    if quantity.unit().is_none() {
+    let Some(unit_text) = quantity.unit() else {
         return StatusCode::BAD_REQUEST.into_response();
-    }
+    };
     let unit = match quantity.unit_info(converter) {
         Some(unit) => unit,
         None => return StatusCode::BAD_REQUEST.into_response(),
@@ -41,9 +44,33 @@ pub async fn convert_popover(
         })
         .collect();
 
+    // every other unit, when the best ones are not the wanted one
+    let more: Vec<_> = compatible_units(converter, unit_text)
+        .unwrap_or_default()
+        .into_iter()
+        .filter_map(|(system, units)| {
+            let system = match system {
+                Some(System::Metric) => "metric",
+                Some(System::Imperial) => "imperial",
+                None => "other",
+            };
+            let targets = units
+                .into_iter()
+                .filter_map(|u| {
+                    let target = u.symbols.first().or(u.names.first())?;
+                    let mut q = quantity.clone();
+                    q.convert(target.as_ref(), converter).ok()?;
+                    let shown = conversions.iter().any(|c| c.unit() == q.unit());
+                    (!shown).then_some(q)
+                })
+                .collect::<Vec<_>>();
+            (!targets.is_empty()).then(|| context! { system, conversions => targets })
+        })
+        .collect();
+
     let tmpl = mj_ok!(state
         .templates
         .get_template("components/convert_popover.html"));
-    let html = mj_ok!(tmpl.render(context! { t, conversions, triggered_by }));
+    let html = mj_ok!(tmpl.render(context! { t, conversions, more, triggered_by }));
     Html(html).into_response()
 }
@@ -0,0 +1,80 @@
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    response::{Html, IntoResponse, Response},
+    Form, Json,
+};
+use minijinja::context;
+use serde::Deserialize;
+
+use crate::{
+    cmd::serve::{
+        client_ip::ClientIp,
+        cook_log::{is_valid_date, LogEntry, MAX_NOTE_LEN},
+        locale::UserLocale,
+        users::CurrentUser,
+        S,
+    },
+    util::today,
+};
+
+use super::{check_path, mj_ok, ok_status};
+
+#[derive(Deserialize)]
+pub struct NewEntry {
+    date: String,
+    note: String,
+}
+
+/// Entries of the log of a recipe as JSON, newest first
+pub async fn cook_log(State(state): State<S>, Path(path): Path<String>) -> Response {
+    if let Err(e) = check_path(&path) {
+        return e.into_response();
+    }
+    if state.recipe_index.get(&path).await.is_err() {
+        return StatusCode::NOT_FOUND.into_response();
+    }
+    Json(state.cook_log.get(&path)).into_response()
+}
+
+/// Adds an entry to the log of a recipe and renders the new log
+pub async fn add_cook_log(
+    UserLocale(t): UserLocale,
+    State(state): State<S>,
+    ClientIp(who): ClientIp,
+    CurrentUser(user): CurrentUser,
+    Path(path): Path<String>,
+    Form(entry): Form<NewEntry>,
+) -> Response {
+    if let Err(e) = check_path(&path) {
+        return e.into_response();
+    }
+    if state.recipe_index.get(&path).await.is_err() {
+        return StatusCode::NOT_FOUND.into_response();
+    }
+    let note = entry.note.trim();
+    if !is_valid_date(&entry.date) || note.is_empty() || note.chars().count() > MAX_NOTE_LEN {
+        return StatusCode::UNPROCESSABLE_ENTITY.into_response();
+    }
+
+    state.audit.record(who, "cook_log", &path);
+    let cook_log = ok_status!(state.cook_log.add(
+        &path,
+        LogEntry {
+            date: entry.date,
+            note: note.to_string(),
+            user,
+        },
+    ));
+
+    let tmpl = mj_ok!(state.templates.get_template("components/cook_log.html"));
+    let res = tmpl.render(context! {
+        t,
+        log_path => path,
+        cook_log,
+        today => today(),
+        max_note_len => MAX_NOTE_LEN,
+    });
+    let content = mj_ok!(res);
+    Html(content).into_response()
+}
@@ -0,0 +1,17 @@
+use axum::{
+    extract::State,
+    response::{Html, IntoResponse, Response},
+};
+use minijinja::context;
+
+use crate::cmd::serve::{locale::UserLocale, S};
+
+use super::mj_ok;
+
+/// Files and dirs of the collection that could not be indexed
+pub async fn diagnostics(UserLocale(t): UserLocale, State(state): State<S>) -> Response {
+    let tmpl = mj_ok!(state.templates.get_template("diagnostics.html"));
+    let res = tmpl.render(context! { t, errors => state.index_errors });
+    let content = mj_ok!(res);
+    Html(content).into_response()
+}
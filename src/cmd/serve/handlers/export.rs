@@ -0,0 +1,113 @@
+use axum::{
+    extract::Query,
+    http::{header, StatusCode, Uri},
+    response::{IntoResponse, Response},
+};
+use cooklang::convert::System;
+use serde::Deserialize;
+use tokio::task::block_in_place;
+
+use crate::{
+    cmd::serve::S,
+    util::{map_recipe, meta_name, url_encode_path},
+};
+
+use super::{check_path, ok_status, recipe::scale_and_convert};
+
+#[derive(Deserialize, Clone, Copy)]
+#[serde(rename_all = "lowercase")]
+enum ExportFormat {
+    Md,
+    Cooklang,
+}
+
+impl ExportFormat {
+    fn extension(self) -> &'static str {
+        match self {
+            Self::Md => "md",
+            Self::Cooklang => "cook",
+        }
+    }
+
+    fn content_type(self) -> &'static str {
+        match self {
+            Self::Md => "text/markdown; charset=utf-8",
+            Self::Cooklang => "text/plain; charset=utf-8",
+        }
+    }
+}
+
+#[derive(Deserialize)]
+pub struct ExportQuery {
+    format: ExportFormat,
+    scale: Option<u32>,
+}
+
+/// Downloads the recipe scaled and converted like in the recipe page
+///
+/// The units are the ones already chosen for the page, from the query, the
+/// recipe or the locale.
+pub async fn export_recipe(
+    state: S,
+    path: &str,
+    uri: &Uri,
+    units: Option<System>,
+    locale_units: Option<System>,
+) -> Response {
+    if let Err(e) = check_path(path) {
+        return e.into_response();
+    }
+    let Ok(Query(query)) = Query::<ExportQuery>::try_from_uri(uri) else {
+        return StatusCode::BAD_REQUEST.into_response();
+    };
+
+    let entry = ok_status!(state.recipe_index.get(path).await, NOT_FOUND);
+    let bytes = ok_status!(tokio::fs::read(&entry.path()).await, NOT_FOUND);
+    let (content, _) = cooklang_fs::decode(bytes);
+    let res = block_in_place(|| {
+        state
+            .parser
+            .parse_with_options(&content, state.parse_options(Some(entry.path())))
+            .map(|r| map_recipe(r, &state.config))
+            .into_result()
+    });
+    let (recipe, _) = ok_status!(res, UNPROCESSABLE_ENTITY);
+
+    let scaled = scale_and_convert(recipe, query.scale, units, locale_units, &state);
+    let name = meta_name(&scaled.metadata)
+        .unwrap_or(entry.name())
+        .to_string();
+
+    let mut out = Vec::new();
+    let res: anyhow::Result<()> = match query.format {
+        ExportFormat::Md => cooklang_to_md::print_md_with_options(
+            &scaled,
+            &name,
+            &state.config.export.markdown,
+            state.parser.converter(),
+            &mut out,
+        )
+        .map_err(Into::into),
+        ExportFormat::Cooklang => {
+            cooklang_to_cooklang::print_cooklang_scaled(&scaled, &mut out).map_err(Into::into)
+        }
+    };
+    ok_status!(res);
+
+    let file_name = format!("{}.{}", entry.name(), query.format.extension());
+    let disposition = format!(
+        "attachment; filename*=UTF-8''{}",
+        url_encode_path(&file_name)
+    );
+    (
+        [
+            (
+                header::CONTENT_TYPE,
+                query.format.content_type().to_string(),
+            ),
+            (header::CONTENT_DISPOSITION, disposition),
+        ],
+        out,
+    )
+        .into_response()
+}
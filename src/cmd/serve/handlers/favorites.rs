@@ -0,0 +1,113 @@
+use std::collections::BTreeSet;
+
+use axum::{
+    extract::{Path, State},
+    http::{header, StatusCode},
+    response::{AppendHeaders, Html, IntoResponse, Response},
+};
+use camino::Utf8Path;
+use minijinja::context;
+
+use crate::cmd::serve::{client_ip::ClientIp, locale::UserLocale, users::ClientId, AppState, S};
+
+use super::{check_path, clean_path, mj_ok, ok_status, recipe_entry_context, users::new_client_id};
+
+pub async fn favorites(
+    UserLocale(t): UserLocale,
+    ClientId(id): ClientId,
+    State(state): State<S>,
+) -> Response {
+    let favorites = state.favorites.get(id.as_deref());
+    let recipes = state
+        .recipe_index
+        .search(
+            |entry, _| is_favorite(&state, &favorites, entry.path()),
+            |entry, tokens| recipe_entry_context(entry, &state, tokens),
+            0,
+            usize::MAX,
+        )
+        .await;
+
+    let tmpl = mj_ok!(state.templates.get_template("favorites.html"));
+    let res = tmpl.render(context! {
+        t,
+        recipes,
+        favorites,
+    });
+    let content = mj_ok!(res);
+    Html(content).into_response()
+}
+
+/// Adds or removes a recipe from the favorites and renders the new button
+pub async fn toggle_favorite(
+    UserLocale(t): UserLocale,
+    ClientId(id): ClientId,
+    ClientIp(who): ClientIp,
+    State(state): State<S>,
+    Path(path): Path<String>,
+) -> Response {
+    if let Err(e) = check_path(&path) {
+        return e.into_response();
+    }
+    if state.recipe_index.get(&path).await.is_err() {
+        return StatusCode::NOT_FOUND.into_response();
+    }
+
+    let (id, cookie) = match id {
+        Some(id) => (id, None),
+        None => match new_client_id(&state) {
+            Ok((id, cookie)) => (id, Some(cookie)),
+            Err(res) => return res,
+        },
+    };
+    let is_favorite = ok_status!(state.favorites.toggle(&id, &path));
+    let action = if is_favorite {
+        "favorite"
+    } else {
+        "unfavorite"
+    };
+    state.audit.record(who, action, &path);
+
+    let tmpl = mj_ok!(state
+        .templates
+        .get_template("components/favorite_button.html"));
+    let res = tmpl.render(context! { t, path, is_favorite });
+    let content = mj_ok!(res);
+    let cookie = cookie.map(|cookie| (header::SET_COOKIE, cookie));
+    (AppendHeaders(cookie), Html(content)).into_response()
+}
+
+/// Favorites as a list of recipes, one per line
+///
+/// It can be given to `chef shopping-list`.
+pub async fn export_favorites(ClientId(id): ClientId, State(state): State<S>) -> Response {
+    let favorites = state.favorites.get(id.as_deref());
+    let recipes = state
+        .recipe_index
+        .search(
+            |entry, _| is_favorite(&state, &favorites, entry.path()),
+            |entry, _| clean_path(entry.path(), &state.base_path).to_string(),
+            0,
+            usize::MAX,
+        )
+        .await;
+
+    let mut list = recipes.join("\n");
+    list.push('\n');
+    (
+        [
+            (header::CONTENT_TYPE, "text/plain; charset=utf-8"),
+            (
+                header::CONTENT_DISPOSITION,
+                "attachment; filename=\"favorites.txt\"",
+            ),
+        ],
+        list,
+    )
+        .into_response()
+}
+
+fn is_favorite(state: &AppState, favorites: &BTreeSet<String>, path: &Utf8Path) -> bool {
+    let path = clean_path(path, &state.base_path).with_extension("");
+    favorites.contains(path.as_str())
+}
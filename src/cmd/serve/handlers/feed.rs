@@ -0,0 +1,141 @@
+//! Sitemap and Atom feed of the collection
+//!
+//! Both need absolute urls, so they use `ui.public_url` or the `Host` header
+//! like the QR codes.
+
+use std::{fmt::Write as _, time::SystemTime};
+
+use axum::{
+    extract::State,
+    http::{header, HeaderMap, StatusCode},
+    response::{IntoResponse, Response},
+};
+
+use crate::{
+    cmd::serve::S,
+    util::{escape_markup, meta_name, rfc3339, url_encode_path},
+};
+
+use super::{public_base, recipe_url_name};
+
+/// Number of recipes in the feed
+const FEED_LEN: usize = 20;
+
+struct Item {
+    /// Absolute url of the recipe page
+    url: String,
+    name: String,
+    desc: Option<String>,
+    modified: u64,
+    /// Creation time, or modified if not available
+    created: u64,
+}
+
+/// Every recipe of the collection, newest first
+async fn items(state: &S, base: &str) -> Vec<Item> {
+    let mut items = state
+        .recipe_index
+        .search(
+            |_, _| true,
+            |entry, data| {
+                let meta = data.and_then(|d| d.metadata.as_ref());
+                let name = meta.and_then(meta_name).unwrap_or(entry.name()).to_string();
+                let desc = meta.and_then(|m| m.description()).map(str::to_string);
+                // the times are read when the recipe is indexed
+                let info = data.and_then(|d| d.info);
+                let modified = info.and_then(|i| i.modified).map_or(0, secs);
+                let created = info.and_then(|i| i.created).map_or(modified, secs);
+                let href = recipe_url_name(entry.path(), state);
+                Item {
+                    url: format!("{base}{}/r/{}", state.base_url, url_encode_path(&href)),
+                    name,
+                    desc,
+                    modified,
+                    created,
+                }
+            },
+            0,
+            usize::MAX,
+        )
+        .await;
+    items.sort_by(|a, b| b.created.cmp(&a.created).then(a.url.cmp(&b.url)));
+    items
+}
+
+fn secs(time: SystemTime) -> u64 {
+    time.duration_since(SystemTime::UNIX_EPOCH)
+        .map_or(0, |d| d.as_secs())
+}
+
+pub async fn sitemap(State(state): State<S>, headers: HeaderMap) -> Response {
+    let Some(base) = public_base(&state, &headers) else {
+        return StatusCode::BAD_REQUEST.into_response();
+    };
+    let items = items(&state, &base).await;
+    (
+        [(header::CONTENT_TYPE, "application/xml")],
+        sitemap_xml(&format!("{base}{}/", state.base_url), &items),
+    )
+        .into_response()
+}
+
+pub async fn feed(State(state): State<S>, headers: HeaderMap) -> Response {
+    let Some(base) = public_base(&state, &headers) else {
+        return StatusCode::BAD_REQUEST.into_response();
+    };
+    let mut items = items(&state, &base).await;
+    items.truncate(FEED_LEN);
+    (
+        [(header::CONTENT_TYPE, "application/atom+xml")],
+        feed_xml(&format!("{base}{}", state.base_url), &items),
+    )
+        .into_response()
+}
+
+fn sitemap_xml(home: &str, items: &[Item]) -> String {
+    let mut out = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+    out.push_str("<urlset xmlns=\"http://www.sitemaps.org/schemas/sitemap/0.9\">\n");
+    writeln!(out, "<url><loc>{}</loc></url>", escape_markup(home)).unwrap();
+    for item in items {
+        writeln!(
+            out,
+            "<url><loc>{}</loc><lastmod>{}</lastmod></url>",
+            escape_markup(&item.url),
+            rfc3339(item.modified)
+        )
+        .unwrap();
+    }
+    out.push_str("</urlset>\n");
+    out
+}
+
+fn feed_xml(home: &str, items: &[Item]) -> String {
+    let updated = items.iter().map(|i| i.modified).max().unwrap_or(0);
+    let mut out = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+    out.push_str("<feed xmlns=\"http://www.w3.org/2005/Atom\">\n");
+    out.push_str("<title>New recipes</title>\n");
+    writeln!(out, "<id>{}/</id>", escape_markup(home)).unwrap();
+    writeln!(out, "<link href=\"{}/\"/>", escape_markup(home)).unwrap();
+    writeln!(
+        out,
+        "<link rel=\"self\" href=\"{}/feed.xml\"/>",
+        escape_markup(home)
+    )
+    .unwrap();
+    writeln!(out, "<updated>{}</updated>", rfc3339(updated)).unwrap();
+    out.push_str("<author><name>chef</name></author>\n");
+    for item in items {
+        let url = escape_markup(&item.url);
+        out.push_str("<entry>\n");
+        writeln!(out, "<title>{}</title>", escape_markup(&item.name)).unwrap();
+        writeln!(out, "<id>{url}</id>\n<link href=\"{url}\"/>").unwrap();
+        writeln!(out, "<published>{}</published>", rfc3339(item.created)).unwrap();
+        writeln!(out, "<updated>{}</updated>", rfc3339(item.modified)).unwrap();
+        if let Some(desc) = &item.desc {
+            writeln!(out, "<summary>{}</summary>", escape_markup(desc)).unwrap();
+        }
+        out.push_str("</entry>\n");
+    }
+    out.push_str("</feed>\n");
+    out
+}
@@ -9,9 +9,13 @@ use camino::Utf8PathBuf;
 use minijinja::{context, Value};
 use serde::Deserialize;
 
-use crate::cmd::serve::{locale::UserLocale, S};
+use crate::cmd::serve::{
+    fragment::{self, Fragment},
+    locale::UserLocale,
+    users::ClientId,
+    S,
+};
 
-use super::super::async_index::RecipeData;
 use super::{check_path, clean_path, mj_ok, recipe_entry_context};
 
 #[derive(Deserialize)]
@@ -21,6 +25,8 @@ pub struct IndexQuery {
 
 pub async fn index(
     UserLocale(t): UserLocale,
+    ClientId(id): ClientId,
+    Fragment(fragment): Fragment,
     State(state): State<S>,
     requested_path: Option<Path<String>>,
     Query(q): Query<IndexQuery>,
@@ -48,38 +54,22 @@ pub async fn index(
     };
 
     let mut folders = Vec::new();
-    let mut recipes = Vec::new();
+    let mut entries_in_dir = Vec::new();
     for e in entries {
         match e {
             cooklang_fs::Entry::Dir(dir) => folders.push(context! {
                 name => dir.file_name(),
                 path => clean_path(dir.path(), &state.base_path)
             }),
-            cooklang_fs::Entry::Recipe(r) => {
-                let tokens = r.read().ok().map(|c| {
-                    let recipe = c.parse(&state.parser);
-                    let mut ingredients = Vec::new();
-                    let mut cookware = Vec::new();
-                    let mut metadata = None;
-                    if let Some(r) = recipe.valid_output() {
-                        metadata = Some(r.metadata.to_owned());
-                        for ingredient in &r.ingredients {
-                            ingredients.push(ingredient.name.to_owned());
-                        }
-                        for tool in &r.cookware {
-                            cookware.push(tool.name.to_string());
-                        }
-                    }
-                    RecipeData {
-                        metadata,
-                        ingredients,
-                        cookware,
-                    }
-                });
-                recipes.push(recipe_entry_context(r, &state, tokens.as_ref()).unwrap());
-            }
+            cooklang_fs::Entry::Recipe(r) => entries_in_dir.push(r),
         }
     }
+    let recipes = state
+        .recipe_index
+        .with_data(entries_in_dir, |r, tokens| {
+            recipe_entry_context(r, &state, tokens).unwrap()
+        })
+        .await;
 
     let tmpl = mj_ok!(state.templates.get_template("index.html"));
     let path_parts = path
@@ -88,13 +78,15 @@ pub async fn index(
         .components()
         .map(|c| c.as_str());
 
-    let res = tmpl.render(context! {
+    let ctx = context! {
         t,
         recipes,
         folders,
         path => Value::from_iter(path_parts),
         deleted => q.deleted,
-    });
-    let content = mj_ok!(res);
+        favorites => state.favorites.get(id.as_deref()),
+        index_errors => state.index_errors.len(),
+    };
+    let content = mj_ok!(fragment::render(&tmpl, ctx, fragment));
     Html(content).into_response()
 }
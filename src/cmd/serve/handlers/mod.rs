@@ -6,25 +6,55 @@ use crate::util::get_emoji;
 use crate::{config::UiConfig, util::meta_name};
 
 use super::async_index::RecipeData;
+use super::client_ip;
 use super::AppState;
 
 pub mod about;
+pub mod admin;
 pub mod convert_popover;
+pub mod cook_log;
+pub mod diagnostics;
+pub mod export;
+pub mod favorites;
+pub mod feed;
 pub mod index;
+pub mod offline;
 pub mod open_editor;
+pub mod openapi;
+pub mod pages;
+pub mod qr;
 pub mod recipe;
+pub mod remote_image;
+pub mod reveal;
+pub mod scale_by;
 pub mod search;
+pub mod shopping_list;
 pub mod sse_updates;
 pub mod static_file;
+pub mod users;
 
 pub use about::about;
+pub use admin::audit_log;
 pub use convert_popover::convert_popover;
+pub use cook_log::{add_cook_log, cook_log};
+pub use diagnostics::diagnostics;
+pub use favorites::{export_favorites, favorites, toggle_favorite};
+pub use feed::{feed, sitemap};
 pub use index::index;
+pub use offline::{manifest, service_worker};
 pub use open_editor::open_editor;
+pub use openapi::openapi;
+pub use pages::page;
 pub use recipe::recipe;
+pub use remote_image::remote_image;
+pub use reveal::reveal;
 pub use search::search;
+pub use shopping_list::{
+    clear_shopping_list, export_shopping_list, shopping_list, toggle_shopping_list,
+};
 pub use sse_updates::sse_updates;
 pub use static_file::static_file;
+pub use users::{login, login_page, logout};
 
 macro_rules! ok_status {
     ($res:expr) => {
@@ -81,7 +111,7 @@ pub fn check_path(p: &str) -> Result<(), axum::http::StatusCode> {
     Ok(())
 }
 
-fn clean_path(p: &Utf8Path, base_path: &Utf8Path) -> Utf8PathBuf {
+pub(super) fn clean_path(p: &Utf8Path, base_path: &Utf8Path) -> Utf8PathBuf {
     let p = p
         .strip_prefix(base_path)
         .expect("dir entry path not relative to base path");
@@ -92,6 +122,19 @@ fn clean_path(p: &Utf8Path, base_path: &Utf8Path) -> Utf8PathBuf {
     p
 }
 
+/// Scheme and host the UI is reached at, from `ui.public_url` or the `Host`
+/// header, without the base url
+fn public_base(state: &AppState, headers: &axum::http::HeaderMap) -> Option<String> {
+    match &state.config.ui.public_url {
+        Some(url) => Some(url.trim_end_matches('/').to_string()),
+        None => {
+            let host = headers.get(axum::http::header::HOST)?.to_str().ok()?;
+            let scheme = client_ip::client_scheme(headers, state.tls);
+            Some(format!("{scheme}://{host}"))
+        }
+    }
+}
+
 fn recipe_entry_context(
     r: RecipeEntry,
     state: &AppState,
@@ -109,7 +152,7 @@ fn recipe_entry_context(
                 .map(|t| tag_context(t.as_ref(), &state.config.ui)),
         );
         if let Some(external_image) = m.map.get("image").and_then(|v| v.as_str()) {
-            image = Some(external_image.to_string());
+            image = Some(state.remote_images.url(external_image, &state.base_url));
         }
 
         let name = meta_name(m).unwrap_or(r.name()).to_string();
@@ -128,22 +171,41 @@ fn recipe_entry_context(
             .images()
             .iter()
             .find(|i| i.indexes.is_none())
-            .map(|i| image_url(&i.path, &state.base_path));
+            .map(|i| image_url(&i.path, state));
     }
 
     let path = clean_path(r.path(), &state.base_path).with_extension("");
 
     Some(context! {
         fallback_name => r.name(),
-        href => format!("/r/{path}"),
+        href => recipe_url(r.path(), state),
+        path,
         error,
         image,
         ..metadata,
     })
 }
 
-fn image_url(path: &Utf8Path, base_path: &Utf8Path) -> String {
-    format!("/src/{}", clean_path(path, base_path))
+/// Part of the url of a recipe after `/r/`, the slug with `ui.slugs` or the
+/// path without the extension
+pub(super) fn recipe_url_name(path: &Utf8Path, state: &AppState) -> String {
+    state.recipe_index.slug(path).unwrap_or_else(|| {
+        clean_path(path, &state.base_path)
+            .with_extension("")
+            .into_string()
+    })
+}
+
+fn recipe_url(path: &Utf8Path, state: &AppState) -> String {
+    format!("{}/r/{}", state.base_url, recipe_url_name(path, state))
+}
+
+fn image_url(path: &Utf8Path, state: &AppState) -> String {
+    format!(
+        "{}/src/{}",
+        state.base_url,
+        clean_path(path, &state.base_path)
+    )
 }
 
 fn tag_context(name: &str, ui_config: &UiConfig) -> Value {
@@ -161,41 +223,3 @@ fn tag_context(name: &str, ui_config: &UiConfig) -> Value {
         .map(|e| e.as_str());
     context! { emoji, name }
 }
-
-#[derive(Debug)]
-enum Searcher {
-    All(Vec<Self>),
-    Any(Vec<Self>),
-    Not(Box<Self>),
-    NamePart(String),
-    Tag(String),
-    Ingredient(String),
-    Cookware(String),
-}
-
-impl Searcher {
-    fn matches_recipe(&self, name: &str, tokens: &RecipeData) -> bool {
-        match self {
-            Self::All(v) => v.is_empty() | v.iter().all(|s| s.matches_recipe(name, tokens)),
-            Self::Any(v) => v.is_empty() | v.iter().any(|s| s.matches_recipe(name, tokens)),
-            Self::Not(searcher) => !searcher.matches_recipe(name, tokens),
-            Self::NamePart(part) => name.to_lowercase().contains(part),
-            Self::Tag(tag) => match tokens.metadata.as_ref() {
-                Some(meta) => meta
-                    .tags()
-                    .unwrap_or(vec![])
-                    .iter()
-                    .any(|t| t.contains(tag)),
-                None => false,
-            },
-            Self::Ingredient(ingredient) => tokens
-                .ingredients
-                .iter()
-                .any(|str| str.to_lowercase().contains(ingredient)),
-            Self::Cookware(cookware) => tokens
-                .cookware
-                .iter()
-                .any(|str| str.to_lowercase().contains(cookware)),
-        }
-    }
-}
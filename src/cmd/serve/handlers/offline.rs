@@ -0,0 +1,55 @@
+//! Service worker and web app manifest, to use the UI offline
+//!
+//! The service worker keeps a copy of the visited pages and images and uses
+//! it when the network fails. The script is an asset with the base url and
+//! the version of chef added, so a new version replaces the old copies.
+
+use axum::{
+    extract::State,
+    http::{header, StatusCode},
+    response::{IntoResponse, Response},
+};
+use serde_json::json;
+
+use crate::cmd::serve::{Assets, S};
+
+pub async fn service_worker(State(state): State<S>) -> Response {
+    let Some(script) = Assets::get("js/sw.js") else {
+        return StatusCode::NOT_FOUND.into_response();
+    };
+    let script = format!(
+        "const BASE_URL = {};\nconst VERSION = {};\n\n{}",
+        serde_json::to_string(&state.base_url).unwrap(),
+        serde_json::to_string(env!("CARGO_PKG_VERSION")).unwrap(),
+        String::from_utf8_lossy(&script.data)
+    );
+    (
+        [
+            (header::CONTENT_TYPE, "text/javascript"),
+            // so a new version is found when it's installed
+            (header::CACHE_CONTROL, "no-cache"),
+        ],
+        script,
+    )
+        .into_response()
+}
+
+pub async fn manifest(State(state): State<S>) -> Response {
+    let base = &state.base_url;
+    let manifest = json!({
+        "name": "chef",
+        "short_name": "chef",
+        "start_url": format!("{base}/"),
+        "scope": format!("{base}/"),
+        "display": "standalone",
+        "icons": [
+            { "src": format!("{base}/favicon.svg"), "sizes": "any", "type": "image/svg+xml" },
+            { "src": format!("{base}/favicon.png"), "sizes": "48x48", "type": "image/png" }
+        ]
+    });
+    (
+        [(header::CONTENT_TYPE, "application/manifest+json")],
+        manifest.to_string(),
+    )
+        .into_response()
+}
@@ -1,13 +1,13 @@
-use std::{net::SocketAddr, sync::Arc};
+use std::sync::Arc;
 
 use axum::{
-    extract::{ConnectInfo, Path, State},
+    extract::{Path, State},
     http::StatusCode,
     response::{Html, IntoResponse, Response},
 };
 use minijinja::context;
 
-use crate::cmd::serve::{handlers::mj_ok, locale::UserLocale, S};
+use crate::cmd::serve::{client_ip::ClientIp, handlers::mj_ok, locale::UserLocale, S};
 
 use super::check_path;
 
@@ -15,9 +15,9 @@ pub async fn open_editor(
     UserLocale(t): UserLocale,
     Path(path): Path<String>,
     State(state): State<S>,
-    ConnectInfo(who): ConnectInfo<SocketAddr>,
+    ClientIp(who): ClientIp,
 ) -> Response {
-    if !who.ip().is_loopback() {
+    if !who.is_loopback() {
         tracing::warn!("Denied open editor request from '{who}': Not loopback ip");
         return StatusCode::UNAUTHORIZED.into_response();
     }
@@ -45,12 +45,13 @@ pub async fn open_editor(
 
     tracing::info!("Opening editor for '{}'", entry.path());
 
-    let args = if let Some(args) = &state.editor_command {
-        args
-    } else {
+    let Some((editor, args)) = state
+        .editor_command
+        .as_deref()
+        .and_then(|c| c.split_first())
+    else {
         return (StatusCode::SERVICE_UNAVAILABLE, mj_ok!(err_html())).into_response();
     };
-    let (editor, args) = (&args[0], &args[1..]);
 
     // to be safe
     let editor_count = state
@@ -67,6 +68,7 @@ pub async fn open_editor(
         .spawn()
     {
         Ok(mut child) => {
+            state.audit.record(who, "open_editor", &path);
             let s = Arc::clone(&state);
             s.editor_count
                 .fetch_add(1, std::sync::atomic::Ordering::Relaxed);
@@ -0,0 +1,643 @@
+use axum::{
+    extract::State,
+    response::{IntoResponse, Response},
+    Json,
+};
+use serde_json::{json, Value};
+
+use crate::cmd::serve::S;
+
+/// OpenAPI 3 document of the server routes
+pub async fn openapi(State(state): State<S>) -> Response {
+    Json(spec(&state.base_url)).into_response()
+}
+
+fn spec(base_url: &str) -> Value {
+    let html = |description: &str| {
+        json!({
+            "description": description,
+            "content": { "text/html": { "schema": { "type": "string" } } }
+        })
+    };
+    let not_found = json!({ "description": "The recipe does not exist" });
+    let bad_path = json!({ "description": "Invalid path" });
+    let recipe_path = json!({
+        "name": "path",
+        "in": "path",
+        "required": true,
+        "description": "Path of the recipe relative to the collection, without extension. It can contain `/`. With `ui.slugs`, the slug of the recipe, and paths redirect to it.",
+        "schema": { "type": "string" },
+        "example": "Breakfast/Pancakes"
+    });
+    let log_entries = json!({
+        "type": "array",
+        "items": { "$ref": "#/components/schemas/LogEntry" }
+    });
+
+    json!({
+        "openapi": "3.0.3",
+        "info": {
+            "title": "chef",
+            "description": "Web UI of a cooklang recipe collection. Most routes render HTML pages or fragments for the UI.",
+            "version": env!("CARGO_PKG_VERSION"),
+        },
+        "servers": [{ "url": if base_url.is_empty() { "/" } else { base_url } }],
+        "paths": {
+            "/": {
+                "get": {
+                    "summary": "Recipes and directories in the root of the collection",
+                    "responses": { "200": html("Index page") }
+                }
+            },
+            "/d/{path}": {
+                "get": {
+                    "summary": "Recipes and directories in a directory",
+                    "parameters": [{
+                        "name": "path",
+                        "in": "path",
+                        "required": true,
+                        "description": "Path of the directory relative to the collection",
+                        "schema": { "type": "string" }
+                    }],
+                    "responses": { "200": html("Directory page"), "400": bad_path, "404": { "description": "The directory does not exist" } }
+                }
+            },
+            "/r/{path}": {
+                "get": {
+                    "summary": "A recipe",
+                    "parameters": [
+                        recipe_path,
+                        {
+                            "name": "scale",
+                            "in": "query",
+                            "description": "Servings to scale the recipe to, if it has servings, or a scale factor otherwise",
+                            "schema": { "type": "integer", "minimum": 1 }
+                        },
+                        {
+                            "name": "units",
+                            "in": "query",
+                            "description": "Convert the quantities to a unit system",
+                            "schema": { "type": "string", "enum": ["default", "metric", "imperial"] }
+                        }
+                    ],
+                    "responses": {
+                        "200": html("Recipe page"),
+                        "301": { "description": "Moved to the current slug of the recipe, with `ui.slugs`" },
+                        "304": { "description": "Not modified since the given `If-None-Match`" },
+                        "400": { "description": "Invalid path or query" },
+                        "404": not_found
+                    }
+                }
+            },
+            "/r/{path}/qr.svg": {
+                "get": {
+                    "summary": "QR code with the public URL of a recipe",
+                    "parameters": [recipe_path],
+                    "responses": {
+                        "200": {
+                            "description": "QR code",
+                            "content": { "image/svg+xml": { "schema": { "type": "string" } } }
+                        },
+                        "404": not_found
+                    }
+                }
+            },
+            "/r/{path}/export": {
+                "get": {
+                    "summary": "Download a recipe scaled and converted like in its page",
+                    "description": "Without `units`, the recipe `units` metadata or the ones of the UI language are used, like in the page.",
+                    "parameters": [
+                        recipe_path,
+                        {
+                            "name": "format",
+                            "in": "query",
+                            "required": true,
+                            "schema": { "type": "string", "enum": ["md", "cooklang"] }
+                        },
+                        {
+                            "name": "scale",
+                            "in": "query",
+                            "schema": { "type": "integer", "minimum": 1 }
+                        },
+                        {
+                            "name": "units",
+                            "in": "query",
+                            "schema": { "type": "string", "enum": ["default", "metric", "imperial"] }
+                        }
+                    ],
+                    "responses": {
+                        "200": {
+                            "description": "The recipe file, as an attachment",
+                            "content": {
+                                "text/markdown": { "schema": { "type": "string" } },
+                                "text/plain": { "schema": { "type": "string" } }
+                            }
+                        },
+                        "400": { "description": "Invalid path or query" },
+                        "404": not_found,
+                        "422": { "description": "The recipe has errors" }
+                    }
+                }
+            },
+            "/r/{path}/scale-by": {
+                "get": {
+                    "summary": "Redirect to a recipe scaled to use the available amount of an ingredient",
+                    "description": "The recipe is scaled to the closest whole number of servings.",
+                    "parameters": [
+                        recipe_path,
+                        {
+                            "name": "ingredient",
+                            "in": "query",
+                            "required": true,
+                            "description": "Index of the ingredient in the recipe",
+                            "schema": { "type": "integer", "minimum": 0 }
+                        },
+                        {
+                            "name": "amount",
+                            "in": "query",
+                            "required": true,
+                            "schema": { "type": "number", "minimum": 0, "exclusiveMinimum": true }
+                        },
+                        {
+                            "name": "unit",
+                            "in": "query",
+                            "description": "Unit of the amount, the one of the ingredient if empty",
+                            "schema": { "type": "string" }
+                        },
+                        {
+                            "name": "units",
+                            "in": "query",
+                            "schema": { "type": "string", "enum": ["default", "metric", "imperial"] }
+                        }
+                    ],
+                    "responses": {
+                        "303": { "description": "Redirect to the scaled recipe" },
+                        "400": { "description": "Invalid path or query" },
+                        "404": not_found,
+                        "422": { "description": "The quantity of the ingredient can't be converted to the unit" }
+                    }
+                }
+            },
+            "/search": {
+                "get": {
+                    "summary": "Search recipes",
+                    "parameters": [{
+                        "name": "q",
+                        "in": "query",
+                        "description": "Search query, with terms like `tag:dinner`, `ingredient:egg`, `time:30m`, `course:main` or `missing:servings`",
+                        "schema": { "type": "string" }
+                    }],
+                    "responses": { "200": html("Results page") }
+                }
+            },
+            "/fragments": {
+                "get": {
+                    "summary": "Content of the index page, for htmx",
+                    "description": "Every page route is also under `/fragments` and renders only the content of the page. `HX-Push-Url` has the URL of the full page.",
+                    "responses": { "200": html("Page content") }
+                }
+            },
+            "/fragments/d/{path}": {
+                "get": {
+                    "summary": "Content of a directory page, for htmx",
+                    "parameters": [{
+                        "name": "path",
+                        "in": "path",
+                        "required": true,
+                        "schema": { "type": "string" }
+                    }],
+                    "responses": { "200": html("Page content"), "404": { "description": "The directory does not exist" } }
+                }
+            },
+            "/fragments/r/{path}": {
+                "get": {
+                    "summary": "Content of a recipe page, for htmx",
+                    "description": "Takes the same query as `/r/{path}`.",
+                    "parameters": [recipe_path],
+                    "responses": { "200": html("Page content"), "404": not_found }
+                }
+            },
+            "/fragments/search": {
+                "get": {
+                    "summary": "Content of the search page, for htmx",
+                    "description": "Takes the same query as `/search`.",
+                    "responses": { "200": html("Page content") }
+                }
+            },
+            "/log/{path}": {
+                "get": {
+                    "summary": "Cook log of a recipe, newest first",
+                    "parameters": [recipe_path],
+                    "responses": {
+                        "200": {
+                            "description": "Log entries",
+                            "content": { "application/json": { "schema": log_entries } }
+                        },
+                        "404": not_found
+                    }
+                },
+                "post": {
+                    "summary": "Add an entry to the cook log of a recipe",
+                    "parameters": [recipe_path],
+                    "requestBody": {
+                        "required": true,
+                        "content": {
+                            "application/x-www-form-urlencoded": {
+                                "schema": { "$ref": "#/components/schemas/LogEntry" }
+                            }
+                        }
+                    },
+                    "responses": {
+                        "200": html("The updated log"),
+                        "404": not_found,
+                        "422": { "description": "Invalid date or empty or too long note" }
+                    }
+                }
+            },
+            "/open_editor/{path}": {
+                "get": {
+                    "summary": "Open a recipe in the editor of the server",
+                    "description": "Only allowed from the same machine. Uses `editor_command` from the global config. The response is a toast for htmx.",
+                    "parameters": [recipe_path],
+                    "responses": {
+                        "200": html("Success toast"),
+                        "400": html("Error toast, invalid path"),
+                        "401": { "description": "The client is not in the same machine" },
+                        "404": html("Error toast, the recipe does not exist"),
+                        "500": html("Error toast, the editor could not be started"),
+                        "503": html("Error toast, no editor available or too many open")
+                    }
+                }
+            },
+            "/reveal/{path}": {
+                "get": {
+                    "summary": "Open the directory of a recipe in the file manager of the server",
+                    "description": "Only allowed from the same machine. The response is a toast for htmx.",
+                    "parameters": [recipe_path],
+                    "responses": {
+                        "200": html("Success toast"),
+                        "401": { "description": "The client is not in the same machine" },
+                        "404": not_found,
+                        "503": { "description": "No file manager command available" }
+                    }
+                }
+            },
+            "/admin/log": {
+                "get": {
+                    "summary": "Newest entries of the audit log of the actions that run programs or write files",
+                    "description": "Only allowed from the same machine.",
+                    "responses": {
+                        "200": html("Audit log page"),
+                        "401": { "description": "The client is not in the same machine" }
+                    }
+                }
+            },
+            "/about": {
+                "get": {
+                    "summary": "Version and licenses",
+                    "responses": { "200": html("About page") }
+                }
+            },
+            "/diagnostics": {
+                "get": {
+                    "summary": "Files of the collection that could not be read",
+                    "responses": { "200": html("Diagnostics page") }
+                }
+            },
+            "/pages/{name}": {
+                "get": {
+                    "summary": "Custom page of the collection",
+                    "description": "Rendered from `.cooklang/pages/<name>.md`.",
+                    "parameters": [{
+                        "name": "name",
+                        "in": "path",
+                        "required": true,
+                        "description": "Name of the markdown file, without extension",
+                        "schema": { "type": "string" }
+                    }],
+                    "responses": { "200": html("Page"), "400": bad_path, "404": { "description": "The page does not exist" } }
+                }
+            },
+            "/favorites": {
+                "get": {
+                    "summary": "Favorite recipes of the client",
+                    "responses": { "200": html("Favorites page") }
+                }
+            },
+            "/favorites.txt": {
+                "get": {
+                    "summary": "Favorite recipes of the client, one path per line",
+                    "responses": {
+                        "200": {
+                            "description": "List of recipes",
+                            "content": { "text/plain": { "schema": { "type": "string" } } }
+                        }
+                    }
+                }
+            },
+            "/favorites/{path}": {
+                "post": {
+                    "summary": "Add or remove a recipe from the favorites of the client",
+                    "description": "The client is the logged in user or is identified with a cookie, which is set if missing.",
+                    "parameters": [recipe_path],
+                    "responses": { "200": html("The new favorite button"), "404": not_found }
+                }
+            },
+            "/shopping-list": {
+                "get": {
+                    "summary": "Shopping list of the client, with the ingredients added up",
+                    "responses": { "200": html("Shopping list page") }
+                },
+                "post": {
+                    "summary": "Remove every recipe from the shopping list of the client",
+                    "responses": { "303": { "description": "Back to the shopping list" } }
+                }
+            },
+            "/shopping-list.txt": {
+                "get": {
+                    "summary": "Shopping list of the client as a meal plan for `chef shopping-list --plan`",
+                    "responses": {
+                        "200": {
+                            "description": "One recipe per line, with the servings like `Pasta*4`",
+                            "content": { "text/plain": { "schema": { "type": "string" } } }
+                        }
+                    }
+                }
+            },
+            "/shopping-list/{path}": {
+                "post": {
+                    "summary": "Add or remove a recipe from the shopping list of the client",
+                    "description": "The client is identified like for the favorites. Requests that are not from htmx, or are boosted, are redirected to the list.",
+                    "parameters": [recipe_path],
+                    "requestBody": {
+                        "content": {
+                            "application/x-www-form-urlencoded": {
+                                "schema": {
+                                    "type": "object",
+                                    "properties": {
+                                        "servings": { "type": "integer", "minimum": 0 }
+                                    }
+                                }
+                            }
+                        }
+                    },
+                    "responses": {
+                        "200": html("The new shopping list button"),
+                        "303": { "description": "Back to the shopping list" },
+                        "404": not_found
+                    }
+                }
+            },
+            "/login": {
+                "get": {
+                    "summary": "Login form, or the logged in user, with `ui.users`",
+                    "responses": { "200": html("Login page"), "404": { "description": "There are no users" } }
+                },
+                "post": {
+                    "summary": "Log in and set the session cookie",
+                    "requestBody": {
+                        "required": true,
+                        "content": {
+                            "application/x-www-form-urlencoded": {
+                                "schema": {
+                                    "type": "object",
+                                    "required": ["name"],
+                                    "properties": {
+                                        "name": { "type": "string" },
+                                        "password": { "type": "string" }
+                                    }
+                                }
+                            }
+                        }
+                    },
+                    "responses": {
+                        "303": { "description": "Logged in, redirects to the index" },
+                        "401": html("Login page with the error"),
+                        "404": { "description": "There are no users" },
+                        "429": html("Login page, too many wrong logins from the address")
+                    }
+                }
+            },
+            "/logout": {
+                "post": {
+                    "summary": "End the session of the client",
+                    "responses": { "303": { "description": "Redirects to the login page" } }
+                }
+            },
+            "/updates": {
+                "get": {
+                    "summary": "Server sent events when recipe files change",
+                    "responses": {
+                        "200": {
+                            "description": "Event stream",
+                            "content": { "text/event-stream": { "schema": { "type": "string" } } }
+                        }
+                    }
+                }
+            },
+            "/convert_modal": {
+                "post": {
+                    "summary": "Popover with a quantity converted to other units",
+                    "description": "Sent by htmx when clicking a quantity. The popover is placed next to the element in `HX-Trigger`.",
+                    "parameters": [{
+                        "name": "HX-Trigger",
+                        "in": "header",
+                        "required": true,
+                        "description": "Id of the clicked element",
+                        "schema": { "type": "string" }
+                    }],
+                    "requestBody": {
+                        "required": true,
+                        "content": {
+                            "application/json": {
+                                "schema": {
+                                    "type": "object",
+                                    "description": "A quantity as serialized by the cooklang crate",
+                                    "required": ["value", "unit"],
+                                    "properties": {
+                                        "value": { "type": "object" },
+                                        "unit": { "type": "string" }
+                                    }
+                                }
+                            }
+                        }
+                    },
+                    "responses": {
+                        "200": html("Popover"),
+                        "400": { "description": "No `HX-Trigger` or the quantity has no known unit" }
+                    }
+                }
+            },
+            "/api/openapi.json": {
+                "get": {
+                    "summary": "This document",
+                    "responses": {
+                        "200": {
+                            "description": "OpenAPI 3 document",
+                            "content": { "application/json": { "schema": { "type": "object" } } }
+                        }
+                    }
+                }
+            },
+            "/sitemap.xml": {
+                "get": {
+                    "summary": "Sitemap with the index page and every recipe",
+                    "description": "Urls use `ui.public_url`, or the `Host` header if not set.",
+                    "responses": {
+                        "200": {
+                            "description": "Sitemap",
+                            "content": { "application/xml": { "schema": { "type": "string" } } }
+                        }
+                    }
+                }
+            },
+            "/feed.xml": {
+                "get": {
+                    "summary": "Atom feed of the newest recipes",
+                    "description": "Sorted by the creation time of the files, or the modification time if not available.",
+                    "responses": {
+                        "200": {
+                            "description": "Atom feed",
+                            "content": { "application/atom+xml": { "schema": { "type": "string" } } }
+                        }
+                    }
+                }
+            },
+            "/sw.js": {
+                "get": {
+                    "summary": "Service worker that keeps the visited pages for offline use",
+                    "responses": {
+                        "200": {
+                            "description": "Script",
+                            "content": { "text/javascript": { "schema": { "type": "string" } } }
+                        }
+                    }
+                }
+            },
+            "/manifest.webmanifest": {
+                "get": {
+                    "summary": "Web app manifest, to install the UI as an app",
+                    "responses": {
+                        "200": {
+                            "description": "Manifest",
+                            "content": { "application/manifest+json": { "schema": { "type": "object" } } }
+                        }
+                    }
+                }
+            },
+            "/src/{file}": {
+                "get": {
+                    "summary": "Recipe sources, images and videos of the collection",
+                    "parameters": [{
+                        "name": "file",
+                        "in": "path",
+                        "required": true,
+                        "schema": { "type": "string" },
+                        "example": "Breakfast/Pancakes.cook"
+                    }],
+                    "responses": { "200": { "description": "The file" }, "404": { "description": "Not found or not allowed" } }
+                }
+            },
+            "/remote_image/{key}": {
+                "get": {
+                    "summary": "Image of another site, saved in the collection or loaded through the server",
+                    "parameters": [{
+                        "name": "key",
+                        "in": "path",
+                        "required": true,
+                        "schema": { "type": "string" },
+                        "example": "a3f1c09e77d2b4e5"
+                    }],
+                    "responses": {
+                        "200": { "description": "The image" },
+                        "404": { "description": "Not an image of a recipe" },
+                        "502": { "description": "The other site failed" }
+                    }
+                }
+            }
+        },
+        "components": {
+            "schemas": {
+                "LogEntry": {
+                    "type": "object",
+                    "required": ["date", "note"],
+                    "properties": {
+                        "date": { "type": "string", "format": "date" },
+                        "note": { "type": "string", "maxLength": crate::cmd::serve::cook_log::MAX_NOTE_LEN },
+                        "user": { "type": "string", "description": "Who cooked, with `ui.users`" }
+                    }
+                }
+            }
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeSet;
+
+    use super::*;
+
+    #[test]
+    fn refs_exist() {
+        let spec = spec("/recipes");
+        assert_eq!(spec["servers"][0]["url"], "/recipes");
+        let text = spec.to_string();
+        for r in text.split("\"$ref\":\"").skip(1) {
+            let r = &r[..r.find('"').unwrap()];
+            let name = r.strip_prefix("#/components/schemas/").unwrap();
+            assert!(spec["components"]["schemas"].get(name).is_some(), "{r}");
+        }
+    }
+
+    /// Paths of the routes in `make_router`, read from its source, and the
+    /// ones that match any path under them
+    fn router_paths() -> (BTreeSet<String>, Vec<String>) {
+        let source = include_str!("../mod.rs");
+        let body = &source[source.find("fn make_router").unwrap()..];
+        let body = &body[..body.find("\n}\n").unwrap()];
+        // the routes before them are also nested under `/fragments`
+        let fragments_at = body.find("let fragments").unwrap();
+        let mut paths = BTreeSet::new();
+        let mut wildcards = Vec::new();
+        for (pos, _) in body.match_indices(".route(") {
+            let route = body[pos..].split('"').nth(1).unwrap();
+            let path = route.replace("{*", "{");
+            if route.contains("{*") {
+                wildcards.push(path.clone());
+            }
+            if pos < fragments_at {
+                paths.insert(format!("/fragments{}", path.trim_end_matches('/')));
+            }
+            paths.insert(path);
+        }
+        for (pos, _) in body.match_indices(".nest_service(") {
+            let path = body[pos..].split('"').nth(1).unwrap();
+            paths.insert(format!("{path}/{{file}}"));
+        }
+        (paths, wildcards)
+    }
+
+    #[test]
+    fn every_route_documented() {
+        let (routes, wildcards) = router_paths();
+        assert!(routes.contains("/r/{path}"));
+        assert!(routes.contains("/fragments/search"));
+
+        let spec = spec("");
+        let documented = spec["paths"]
+            .as_object()
+            .unwrap()
+            .keys()
+            .map(|path| {
+                // like `/r/{path}/qr.svg`, handled by `/r/{path}`
+                let wildcard = wildcards.iter().find(|w| {
+                    path.strip_prefix(w.as_str())
+                        .is_some_and(|r| r.starts_with('/'))
+                });
+                wildcard.unwrap_or(path).clone()
+            })
+            .collect::<BTreeSet<_>>();
+        assert_eq!(routes, documented);
+    }
+}
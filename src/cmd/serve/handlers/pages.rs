@@ -0,0 +1,90 @@
+//! Custom pages of the collection
+//!
+//! Markdown files in `.cooklang/pages` are shown at `/pages/<name>`, with the
+//! first heading as the title. Link them in the navigation bar with
+//! `ui.nav`.
+
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    response::{Html, IntoResponse, Response},
+};
+use camino::{Utf8Path, Utf8PathBuf};
+use minijinja::context;
+
+use crate::{
+    cmd::serve::{locale::UserLocale, S},
+    COOK_DIR,
+};
+
+use super::mj_ok;
+
+const PAGES_DIR: &str = "pages";
+
+pub async fn page(
+    UserLocale(t): UserLocale,
+    State(state): State<S>,
+    Path(name): Path<String>,
+) -> Response {
+    if name.is_empty() || name.starts_with('.') || name.contains(['/', '\\']) {
+        return StatusCode::BAD_REQUEST.into_response();
+    }
+    let path = pages_dir(&state.base_path).join(format!("{name}.md"));
+    let Ok(content) = tokio::fs::read_to_string(&path).await else {
+        return StatusCode::NOT_FOUND.into_response();
+    };
+    let title = content
+        .lines()
+        .find_map(|l| l.strip_prefix("# "))
+        .map(|l| l.trim().to_string())
+        .unwrap_or(name);
+
+    let tmpl = mj_ok!(state.templates.get_template("page.html"));
+    let res = tmpl.render(context! { t, title, content });
+    let content = mj_ok!(res);
+    Html(content).into_response()
+}
+
+fn pages_dir(base_path: &Utf8Path) -> Utf8PathBuf {
+    base_path.join(COOK_DIR).join(PAGES_DIR)
+}
+
+/// Names of the custom pages, sorted
+pub fn page_names(base_path: &Utf8Path) -> std::io::Result<Vec<String>> {
+    let dir = pages_dir(base_path);
+    if !dir.is_dir() {
+        return Ok(vec![]);
+    }
+    let mut names = Vec::new();
+    for entry in dir.read_dir_utf8()? {
+        let entry = entry?;
+        let path = entry.path();
+        if path.extension() == Some("md") && !entry.file_name().starts_with('.') && path.is_file() {
+            if let Some(stem) = path.file_stem() {
+                names.push(stem.to_string());
+            }
+        }
+    }
+    names.sort_unstable();
+    Ok(names)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn names() {
+        let dir = tempfile::tempdir().unwrap();
+        let base = Utf8Path::from_path(dir.path()).unwrap();
+        assert!(page_names(base).unwrap().is_empty());
+
+        let pages = pages_dir(base);
+        std::fs::create_dir_all(&pages).unwrap();
+        for file in ["faq.md", "about.md", ".draft.md", "notes.txt", "README"] {
+            std::fs::write(pages.join(file), "# Title").unwrap();
+        }
+        std::fs::create_dir(pages.join("images.md")).unwrap();
+        assert_eq!(page_names(base).unwrap(), ["about", "faq"]);
+    }
+}
@@ -0,0 +1,37 @@
+use axum::{
+    http::{header, HeaderMap, StatusCode, Uri},
+    response::{IntoResponse, Response},
+};
+use qrcode::{render::svg, QrCode};
+
+use crate::cmd::serve::S;
+
+use super::{check_path, ok_status, public_base, recipe_url_name};
+
+/// QR code pointing to the recipe page, with the same query (scale, units...)
+pub async fn recipe_qr(state: S, path: &str, headers: &HeaderMap, uri: &Uri) -> Response {
+    if let Err(e) = check_path(path) {
+        return e.into_response();
+    }
+
+    let entry = ok_status!(state.recipe_index.get(path).await, NOT_FOUND);
+    let href = recipe_url_name(entry.path(), &state);
+
+    let Some(base) = public_base(&state, headers) else {
+        return StatusCode::BAD_REQUEST.into_response();
+    };
+    let mut url = format!("{base}{}/r/{href}", state.base_url);
+    if let Some(query) = uri.query() {
+        url.push('?');
+        url.push_str(query);
+    }
+
+    let code = ok_status!(QrCode::new(url.as_bytes()));
+    let image = code
+        .render::<svg::Color>()
+        .min_dimensions(200, 200)
+        .quiet_zone(false)
+        .build();
+
+    ([(header::CONTENT_TYPE, "image/svg+xml")], image).into_response()
+}
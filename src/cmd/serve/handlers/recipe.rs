@@ -1,32 +1,49 @@
-use std::{collections::HashMap, net::SocketAddr, time::SystemTime};
+use std::{collections::HashMap, time::SystemTime};
 
 use axum::{
-    extract::{ConnectInfo, Path, Query, State},
-    http::{HeaderMap, StatusCode, Uri},
+    extract::{Path, Query, State},
+    http::{header, HeaderMap, StatusCode, Uri},
     response::{Html, IntoResponse, Response},
 };
 use camino::Utf8Path;
 use cooklang::{
-    convert::PhysicalQuantity, error::SourceReport, metadata::CooklangValueExt, Converter,
-    Modifiers, ParseOptions, ScaledRecipe,
+    convert::{PhysicalQuantity, System},
+    error::SourceReport,
+    metadata::CooklangValueExt,
+    model::{IngredientReferenceTarget, Item},
+    scale::ScaleOutcome,
+    Content, Converter, Modifiers, ParseOptions, ScalableRecipe, ScaledRecipe,
 };
+use cooklang_to_human::{intermediate_ref, IntermediateRef};
 use minijinja::{context, Value};
 use serde::{Deserialize, Serialize};
 use tokio::task::block_in_place;
 
 use crate::{
     cmd::serve::{
+        cache::{etag_matches, make_etag, not_modified},
+        client_ip::ClientIp,
+        cook_log::MAX_NOTE_LEN,
+        fragment::{self, Fragment},
         get_cookie,
         handlers::{clean_path, ok_status, tag_context},
-        locale::UserLocale,
+        locale::{Locale, UserLocale},
+        slugs::SlugLookup,
+        users::ClientId,
         AppState, S,
     },
     config::Config,
-    util::{map_recipe, meta_name, metadata_validator},
+    util::{
+        convert_recipe, map_recipe, meta_name, metadata_validator, name_and_url_list,
+        preferred_system, respell_units, scale_report, today,
+    },
     RECIPE_REF_ERROR,
 };
 
-use super::{check_path, image_url, mj_ok};
+use super::{check_path, image_url, mj_ok, recipe_url, recipe_url_name};
+
+/// Pages under a recipe url, the router cannot match after the wildcard
+const SUB_PAGES: &[&str] = &["/qr.svg", "/scale-by", "/export"];
 
 #[derive(Deserialize, Serialize)]
 pub struct RecipeQuery {
@@ -40,10 +57,25 @@ pub async fn recipe(
     Path(path): Path<String>,
     Query(query): Query<RecipeQuery>,
     uri: Uri,
-    ConnectInfo(addr): ConnectInfo<SocketAddr>,
+    ClientIp(client): ClientIp,
+    ClientId(client_id): ClientId,
     UserLocale(t): UserLocale,
+    Fragment(fragment): Fragment,
 ) -> Response {
-    let units: Option<cooklang::convert::System> = match query.units.as_deref() {
+    let path = match from_slug(&state, path, &uri, fragment).await {
+        Ok(path) => path,
+        Err(redirect) => return redirect,
+    };
+
+    // the router cannot match anything after the wildcard
+    if let Some(recipe_path) = path.strip_suffix("/qr.svg") {
+        return super::qr::recipe_qr(state, recipe_path, &headers, &uri).await;
+    }
+    if let Some(recipe_path) = path.strip_suffix("/scale-by") {
+        return super::scale_by::scale_by_ingredient(state, recipe_path, &uri).await;
+    }
+
+    let units: Option<System> = match query.units.as_deref() {
         None => None,
         Some("default") => None,
         Some(sys) => match sys.parse() {
@@ -51,45 +83,80 @@ pub async fn recipe(
             Err(_) => return StatusCode::BAD_REQUEST.into_response(),
         },
     };
+    // only used when nothing is chosen, "default" keeps the recipe units
+    let locale_units = match query.units {
+        None => t
+            .downcast_object_ref::<Locale>()
+            .and_then(|l| l.preferred_system()),
+        Some(_) => None,
+    };
+
+    if let Some(recipe_path) = path.strip_suffix("/export") {
+        return super::export::export_recipe(state, recipe_path, &uri, units, locale_units).await;
+    }
 
     if let Err(e) = check_path(&path) {
         return e.into_response();
     }
 
     let entry = ok_status!(state.recipe_index.get(&path).await, NOT_FOUND);
-    let content = ok_status!(tokio::fs::read_to_string(&entry.path()).await, NOT_FOUND);
+    let bytes = ok_status!(tokio::fs::read(&entry.path()).await, NOT_FOUND);
+    let (content, _) = cooklang_fs::decode(bytes);
+
+    let videos = cooklang_fs::recipe_videos(entry.path());
+    let cook_log = state.cook_log.get(&path);
+    let today = today();
+    let in_list = state
+        .shopping_lists
+        .get(client_id.as_deref())
+        .iter()
+        .any(|e| e.recipe == path);
+
+    // the page depends on the recipe, the query, the user preferences (in the
+    // headers) and maybe other recipes, which are checked with the index
+    // generation
+    let etag = make_etag((
+        &content,
+        entry.images().iter().map(|i| &i.path).collect::<Vec<_>>(),
+        &videos,
+        uri.query(),
+        headers.get(header::COOKIE).map(|h| h.as_bytes()),
+        headers.get(header::ACCEPT_LANGUAGE).map(|h| h.as_bytes()),
+        client.is_loopback(),
+        state.recipe_index.generation(),
+        (&cook_log, &today, in_list),
+    ));
+    if etag_matches(&headers, &etag) {
+        return not_modified(&etag);
+    }
+    let cache_headers = [
+        (header::ETAG, etag),
+        (header::CACHE_CONTROL, "no-cache".to_string()),
+    ];
 
     let res = block_in_place(|| {
         state
             .parser
             .parse_with_options(&content, state.parse_options(Some(entry.path())))
-            .map(map_recipe)
+            .map(|r| map_recipe(r, &state.config))
             .into_result()
     });
 
     let tmpl = mj_ok!(state.templates.get_template("recipe.html"));
 
     let src_path = clean_path(entry.path(), &state.base_path);
+    let url_name = recipe_url_name(entry.path(), &state);
     let ctx = context! {
         t,
         is_valid => res.is_ok(),
-        href => format!("/r/{}", src_path.with_extension("")),
+        href => format!("{}/r/{url_name}", state.base_url),
+        fragment_href => format!("{}/fragments/r/{url_name}", state.base_url),
         src_path,
     };
 
     match res {
         Ok((scalable, warnings)) => {
-            let scaled = {
-                let mut r = if let Some(servings) = query.scale {
-                    scalable.scale(servings, state.parser.converter())
-                } else {
-                    scalable.default_scale()
-                };
-                if let Some(target) = units {
-                    let _ = r.convert(target, state.parser.converter());
-                }
-                r
-            };
+            let scaled = scale_and_convert(scalable, query.scale, units, locale_units, &state);
 
             let report_html = if warnings.is_empty() {
                 None
@@ -120,9 +187,7 @@ pub async fn recipe(
 
                         match res {
                             Ok(entry) => {
-                                let path =
-                                    clean_path(entry.path(), &state.base_path).with_extension("");
-                                let value = Value::from(format!("/r/{path}"));
+                                let value = Value::from(recipe_url(entry.path(), &state));
                                 Some((igr.name.clone(), value))
                             }
                             Err(_) => None,
@@ -134,28 +199,32 @@ pub async fn recipe(
             let images = Value::from_iter(entry.images().iter().map(|img| {
                 context! {
                     indexes => img.indexes,
-                    href => image_url(&img.path, &state.base_path)
+                    href => image_url(&img.path, &state)
                 }
             }));
+            // served from `/src`, which supports range requests for seeking
+            let videos = Value::from_iter(videos.iter().map(|path| image_url(path, &state)));
             let main_image = scaled
                 .metadata
                 .map
                 .get("image")
                 .and_then(|v| v.as_str())
-                .map(|v| v.to_string())
+                .map(|v| state.remote_images.url(v, &state.base_url))
                 .or_else(|| {
                     entry
                         .images()
                         .iter()
                         .find(|img| img.indexes.is_none())
-                        .map(|img| image_url(&img.path, &state.base_path))
+                        .map(|img| image_url(&img.path, &state))
                 });
 
+            let scale_report = Value::from_serialize(scale_report(&scaled));
             let r = make_recipe_context(scaled, state.parser.converter(), &state.config);
 
             let ctx = context! {
                 name,
                 r,
+                scale_report,
                 query,
                 path => uri.path(),
                 recipe_refs,
@@ -163,43 +232,131 @@ pub async fn recipe(
                 times,
                 images,
                 main_image,
-
-                is_loopback => addr.ip().is_loopback(),
+                videos,
+
+                is_loopback => client.is_loopback(),
+                log_path => &path,
+                in_list,
+                cook_log,
+                today,
+                max_note_len => MAX_NOTE_LEN,
                 igr_layout => get_cookie(&headers, "igr_layout").unwrap_or("line"),
 
                 report_html,
                 severity => "warning",
                 ..ctx
             };
-            let content = mj_ok!(tmpl.render(ctx));
-            Html(content).into_response()
+            let content = mj_ok!(fragment::render(&tmpl, ctx, fragment));
+            (cache_headers, Html(content)).into_response()
         }
         Err(report) => {
             let report_html = ok_status!(report_to_html(&report, entry.file_name(), &content));
 
-            let content = mj_ok!(tmpl.render(context! {
+            let ctx = context! {
                 name => entry.name(),
                 report_html,
                 severity => "error",
                 ..ctx
-            }));
-            Html(content).into_response()
+            };
+            let content = mj_ok!(fragment::render(&tmpl, ctx, fragment));
+            (cache_headers, Html(content)).into_response()
         }
     }
 }
 
+/// Path of the recipe of a slug with `ui.slugs`
+///
+/// Urls by path, of a recipe moved to another dir or by a slug that changed
+/// are redirected to the current slug.
+async fn from_slug(state: &S, path: String, uri: &Uri, fragment: bool) -> Result<String, Response> {
+    if !state.recipe_index.has_slugs() {
+        return Ok(path);
+    }
+    let (name, sub_page) = SUB_PAGES
+        .iter()
+        .find_map(|s| Some((path.strip_suffix(s)?, *s)))
+        .unwrap_or((&path, ""));
+
+    let slug = match state.recipe_index.lookup_slug(name) {
+        Some(SlugLookup::Found(p)) => {
+            let p = clean_path(&p, &state.base_path).with_extension("");
+            return Ok(format!("{p}{sub_page}"));
+        }
+        Some(SlugLookup::Moved(slug)) => slug,
+        None => {
+            let index = &state.recipe_index;
+            let file_name = Utf8Path::new(name).file_name().unwrap_or(name);
+            let entry = match index.get(name).await {
+                Ok(entry) => entry,
+                // maybe moved to another dir
+                Err(_) => match index.get(file_name).await {
+                    Ok(entry) => entry,
+                    Err(_) => return Ok(path),
+                },
+            };
+            recipe_url_name(entry.path(), state)
+        }
+    };
+
+    let prefix = if fragment { "/fragments" } else { "" };
+    let mut url = format!("{}{prefix}/r/{slug}{sub_page}", state.base_url);
+    if let Some(query) = uri.query() {
+        url.push('?');
+        url.push_str(query);
+    }
+    Err((StatusCode::MOVED_PERMANENTLY, [(header::LOCATION, url)]).into_response())
+}
+
+/// Scales the recipe and converts it to the units of the query, the ones in
+/// the recipe metadata or the ones of the user locale, in that order
+pub(super) fn scale_and_convert(
+    recipe: ScalableRecipe,
+    servings: Option<u32>,
+    units: Option<System>,
+    locale_units: Option<System>,
+    state: &AppState,
+) -> ScaledRecipe {
+    let converter = state.parser.converter();
+    let mut r = match servings {
+        Some(servings) => recipe.scale(servings, converter),
+        None => recipe.default_scale(),
+    };
+    // the `units` metadata of the recipe is only a default
+    if let Some(target) = units
+        .or_else(|| preferred_system(&r.metadata))
+        .or(locale_units)
+    {
+        convert_recipe(&mut r, target, &state.config.unit_thresholds, converter);
+    }
+    respell_units(&mut r, &state.config.display_units, converter);
+    r
+}
+
+/// Scale outcome of an ingredient, with `fixed` and the `error` if any
+fn outcome_context(outcome: &ScaleOutcome) -> Value {
+    context! {
+        fixed => matches!(outcome, ScaleOutcome::Fixed),
+        error => match outcome {
+            ScaleOutcome::Error(err) => Some(err.to_string()),
+            _ => None,
+        },
+    }
+}
+
 fn make_recipe_context(r: ScaledRecipe, converter: &Converter, config: &Config) -> Value {
+    let mut breakdown = ingredient_breakdown(&r);
     let grouped_ingredients = r
         .group_ingredients(converter)
         .into_iter()
         .map(|entry| {
             context! {
                 index => entry.index,
-                outcome => entry.outcome,
+                outcome => entry.outcome.map(|o| outcome_context(&o)),
                 quantities => entry.quantity.iter().map(|q| context! {
                     value => q.value(),
                     unit => q.unit()
                 }).collect::<Value>(),
+                breakdown => breakdown.remove(&entry.index),
             }
         })
         .collect::<Value>();
@@ -233,6 +390,15 @@ fn make_recipe_context(r: ScaledRecipe, converter: &Converter, config: &Config)
         })
         .collect::<Value>();
 
+    let ingredient_cards = ingredient_cards(&r, converter);
+
+    let ingredient_outcomes = r.scaled_data().map(|data| {
+        data.ingredients
+            .iter()
+            .map(outcome_context)
+            .collect::<Value>()
+    });
+
     context! {
         meta => context! {
             description => r.metadata.description(),
@@ -241,8 +407,8 @@ fn make_recipe_context(r: ScaledRecipe, converter: &Converter, config: &Config)
                     .map(|t| tag_context(t.as_ref(), &config.ui))
             })),
             emoji => r.metadata.get("emoji").and_then(|v| v.as_str()),
-            author => r.metadata.author(),
-            source => r.metadata.source(),
+            author => name_and_url_list(&r.metadata, "author"),
+            source => name_and_url_list(&r.metadata, "source"),
             time => r.metadata.time(converter),
             servings => r.metadata.servings(),
             other => Value::from_iter(r.metadata.map.iter().filter_map(|(key, value)| {
@@ -260,6 +426,8 @@ fn make_recipe_context(r: ScaledRecipe, converter: &Converter, config: &Config)
 
         sections => r.sections,
 
+        ingredient_outcomes,
+        ingredient_cards,
         ingredients => r.ingredients.into_iter().map(TemplateIngredient).map(Value::from_object).collect::<Value>(),
         cookware => r.cookware.into_iter().map(TemplateCookware).map(Value::from_object).collect::<Value>(),
         timers => r.timers,
@@ -269,6 +437,102 @@ fn make_recipe_context(r: ScaledRecipe, converter: &Converter, config: &Config)
     }
 }
 
+/// Quantities of each use of the ingredients used more than once, with the
+/// step where they are, by index of the ingredient in the list
+///
+/// The ingredient list only has the total, this is what adds up to it.
+fn ingredient_breakdown(r: &ScaledRecipe) -> HashMap<usize, Vec<Value>> {
+    let many_sections = r.sections.len() > 1;
+    let mut parts = HashMap::<usize, Vec<Value>>::new();
+    for (section_index, section) in r.sections.iter().enumerate() {
+        for content in &section.content {
+            let Content::Step(step) = content else {
+                continue;
+            };
+            for item in &step.items {
+                let &Item::Ingredient { index } = item else {
+                    continue;
+                };
+                let igr = &r.ingredients[index];
+                let Some(quantity) = &igr.quantity else {
+                    continue;
+                };
+                let group = match igr.relation.references_to() {
+                    Some((target, IngredientReferenceTarget::Ingredient)) => target,
+                    _ => index,
+                };
+                parts.entry(group).or_default().push(context! {
+                    quantity => quantity.to_string(),
+                    step => step.number,
+                    section => many_sections.then_some(section_index + 1),
+                });
+            }
+        }
+    }
+    parts.retain(|_, p| p.len() > 1);
+    parts
+}
+
+/// What to show when hovering each ingredient in the steps, by index
+///
+/// This is the amount in the step, the total in the recipe if it's used more
+/// than once, the note and where it comes from if it's an intermediate
+/// preparation.
+fn ingredient_cards(r: &ScaledRecipe, converter: &Converter) -> Value {
+    // the ingredient list groups every use in the first one
+    let totals: HashMap<usize, String> = r
+        .group_ingredients(converter)
+        .into_iter()
+        .map(|entry| (entry.index, entry.quantity.to_string()))
+        .collect();
+    let mut uses = HashMap::<usize, usize>::new();
+    for (index, igr) in r.ingredients.iter().enumerate() {
+        let group = match igr.relation.references_to() {
+            Some((target, IngredientReferenceTarget::Ingredient)) => target,
+            _ => index,
+        };
+        *uses.entry(group).or_default() += 1;
+    }
+
+    let mut cards = vec![Value::from(()); r.ingredients.len()];
+    for section in &r.sections {
+        for content in &section.content {
+            let Content::Step(step) = content else {
+                continue;
+            };
+            for item in &step.items {
+                let &Item::Ingredient { index } = item else {
+                    continue;
+                };
+                let igr = &r.ingredients[index];
+                let group = match igr.relation.references_to() {
+                    Some((target, IngredientReferenceTarget::Ingredient)) => target,
+                    _ => index,
+                };
+                let (from_step, from_section) = match intermediate_ref(igr, section) {
+                    Some(IntermediateRef::Step(number)) => (Some(number), None),
+                    Some(IntermediateRef::Section(number)) => (None, Some(number)),
+                    None => (None, None),
+                };
+                let total = if uses.get(&group).copied().unwrap_or(0) > 1 {
+                    totals.get(&group).filter(|t| !t.is_empty())
+                } else {
+                    None
+                };
+                cards[index] = context! {
+                    quantity => igr.quantity.as_ref().map(|q| q.to_string()),
+                    total,
+                    note => igr.note,
+                    optional => igr.modifiers().is_optional(),
+                    from_step,
+                    from_section,
+                };
+            }
+        }
+    }
+    Value::from(cards)
+}
+
 macro_rules! mj_opt {
     ($opt:expr) => {
         match $opt {
@@ -320,6 +584,13 @@ impl minijinja::value::Object for TemplateCookware {
 }
 
 async fn get_times(path: &Utf8Path) -> anyhow::Result<Value> {
+    let (modified, created) = file_times(path).await?;
+    Ok(context! { modified, created })
+}
+
+/// Modified and created times of a file in seconds since the epoch, 0 if
+/// the platform does not have them
+pub(super) async fn file_times(path: &Utf8Path) -> std::io::Result<(u64, u64)> {
     fn f(st: std::io::Result<SystemTime>) -> Option<u64> {
         st.ok()
             .and_then(|t| t.duration_since(SystemTime::UNIX_EPOCH).ok())
@@ -328,7 +599,7 @@ async fn get_times(path: &Utf8Path) -> anyhow::Result<Value> {
     let metadata = tokio::fs::metadata(path).await?;
     let modified = f(metadata.modified()).unwrap_or(0);
     let created = f(metadata.created()).unwrap_or(0);
-    Ok(context! { modified, created })
+    Ok((modified, created))
 }
 
 impl AppState {
@@ -355,7 +626,7 @@ impl AppState {
         }
     }
 
-    fn parse_options(&self, relative_to: Option<&Utf8Path>) -> ParseOptions {
+    pub(super) fn parse_options(&self, relative_to: Option<&Utf8Path>) -> ParseOptions {
         ParseOptions {
             recipe_ref_check: self.checker(relative_to),
             metadata_validator: Some(Box::new(metadata_validator)),
@@ -449,3 +720,41 @@ pub fn step_ingredients(
     }
     Ok(Value::from(step_ingredients))
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn cards_of_intermediate_references() {
+        let parser = cooklang::CooklangParser::extended();
+        let recipe = parser
+            .parse(
+                "= Dough\n\
+                 Mix @flour{200%g} and @water{100%ml}.\n\n\
+                 Knead the @&(~1)dough{}.\n\n\
+                 = Filling\n\
+                 Roll the @&(=~1)dough{} and add @&flour{50%g}.\n",
+            )
+            .into_output()
+            .unwrap();
+        let recipe = recipe.default_scale();
+        let cards = ingredient_cards(&recipe, parser.converter());
+        let cards = serde_json::to_value(&cards).unwrap();
+        let names = recipe
+            .ingredients
+            .iter()
+            .map(|i| i.name.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(names, ["flour", "water", "dough", "dough", "flour"]);
+
+        assert_eq!(cards[2]["from_step"], 1);
+        assert!(cards[2]["from_section"].is_null());
+        assert!(cards[3]["from_step"].is_null());
+        assert_eq!(cards[3]["from_section"], 1);
+        assert!(cards[0]["from_step"].is_null());
+        // flour is used twice, so its card has the total
+        assert_eq!(cards[4]["quantity"], "50 g");
+        assert_eq!(cards[4]["total"], "250 g");
+    }
+}
@@ -0,0 +1,32 @@
+use axum::{
+    extract::{Path, State},
+    http::{header, StatusCode},
+    response::{IntoResponse, Response},
+};
+
+use crate::cmd::serve::S;
+
+use super::ok_status;
+
+/// An image of another site, saved in the collection or proxied
+pub async fn remote_image(State(state): State<S>, Path(key): Path<String>) -> Response {
+    let loaded = tokio::task::spawn_blocking(move || state.remote_images.load(&key)).await;
+    match ok_status!(loaded) {
+        None => StatusCode::NOT_FOUND.into_response(),
+        Some(Err(e)) => {
+            tracing::warn!("Could not load the image: {e:#}");
+            StatusCode::BAD_GATEWAY.into_response()
+        }
+        Some(Ok((bytes, ext))) => {
+            let mime = mime_guess::from_ext(&ext).first_or_octet_stream();
+            (
+                [
+                    (header::CONTENT_TYPE, mime.to_string()),
+                    (header::CACHE_CONTROL, "public, max-age=604800".to_string()),
+                ],
+                bytes,
+            )
+                .into_response()
+        }
+    }
+}
@@ -0,0 +1,76 @@
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    response::{Html, IntoResponse, Response},
+};
+use minijinja::context;
+
+use crate::cmd::serve::{client_ip::ClientIp, handlers::mj_ok, locale::UserLocale, S};
+
+use super::check_path;
+
+/// Opens the directory of a recipe in the file manager of the server
+pub async fn reveal(
+    UserLocale(t): UserLocale,
+    Path(path): Path<String>,
+    State(state): State<S>,
+    ClientIp(who): ClientIp,
+) -> Response {
+    if !who.is_loopback() {
+        tracing::warn!("Denied reveal request from '{who}': Not loopback ip");
+        return StatusCode::UNAUTHORIZED.into_response();
+    }
+
+    let toast_html = |text_key: &str, color: &str| -> Result<Html<String>, minijinja::Error> {
+        let tmpl = state.templates.get_template("components/oob_toast.html")?;
+        let html = tmpl.render(context! {
+            t,
+            text_key,
+            color
+        })?;
+        Ok(Html(html))
+    };
+
+    let err_html = || toast_html("reveal.error", "red");
+
+    if let Err(err) = check_path(&path) {
+        return (err, mj_ok!(err_html())).into_response();
+    }
+
+    let entry = match state.recipe_index.get(&path).await {
+        Ok(entry) => entry,
+        Err(_) => return (StatusCode::NOT_FOUND, mj_ok!(err_html())).into_response(),
+    };
+    let Some(dir) = entry.path().parent() else {
+        return (StatusCode::NOT_FOUND, mj_ok!(err_html())).into_response();
+    };
+
+    let Some((cmd, args)) = state
+        .file_manager_command
+        .as_ref()
+        .and_then(|c| c.split_first())
+    else {
+        return (StatusCode::SERVICE_UNAVAILABLE, mj_ok!(err_html())).into_response();
+    };
+
+    tracing::info!("Opening '{dir}' in the file manager");
+
+    match tokio::process::Command::new(cmd)
+        .args(args)
+        .arg(dir)
+        .spawn()
+    {
+        Ok(mut child) => {
+            state.audit.record(who, "reveal", &path);
+            tokio::task::spawn(async move {
+                let _ = child.wait().await;
+            });
+        }
+        Err(e) => {
+            tracing::error!("Could not open the file manager: {e}");
+            return (StatusCode::INTERNAL_SERVER_ERROR, mj_ok!(err_html())).into_response();
+        }
+    }
+
+    mj_ok!(toast_html("reveal.success", "green")).into_response()
+}
@@ -0,0 +1,132 @@
+use axum::{
+    extract::Query,
+    http::{StatusCode, Uri},
+    response::{IntoResponse, Redirect, Response},
+};
+use cooklang::{
+    quantity::{GroupedQuantity, Value},
+    Converter,
+};
+use serde::Deserialize;
+use tokio::task::block_in_place;
+
+use crate::{
+    cmd::serve::S,
+    util::{map_recipe, url_encode_path},
+};
+
+use super::{check_path, ok_status, recipe_url_name};
+
+#[derive(Deserialize)]
+pub struct ScaleByQuery {
+    /// Index of the ingredient in the recipe
+    ingredient: usize,
+    /// How much of the ingredient is available
+    amount: f64,
+    /// Unit of the amount, the one in the recipe if empty
+    #[serde(default)]
+    unit: Option<String>,
+    #[serde(default)]
+    units: Option<String>,
+}
+
+/// Redirects to the recipe scaled to use the amount of an ingredient that
+/// is available
+///
+/// Recipes can only be scaled to a whole number of servings, so the result
+/// is rounded.
+pub async fn scale_by_ingredient(state: S, path: &str, uri: &Uri) -> Response {
+    if let Err(e) = check_path(path) {
+        return e.into_response();
+    }
+    let Ok(Query(query)) = Query::<ScaleByQuery>::try_from_uri(uri) else {
+        return StatusCode::BAD_REQUEST.into_response();
+    };
+    if !(query.amount.is_finite() && query.amount > 0.0) {
+        return StatusCode::BAD_REQUEST.into_response();
+    }
+
+    let entry = ok_status!(state.recipe_index.get(path).await, NOT_FOUND);
+    let bytes = ok_status!(tokio::fs::read(&entry.path()).await, NOT_FOUND);
+    let (content, _) = cooklang_fs::decode(bytes);
+    let res = block_in_place(|| {
+        state
+            .parser
+            .parse_with_options(&content, state.parse_options(Some(entry.path())))
+            .map(|r| map_recipe(r, &state.config))
+            .into_result()
+    });
+    let (recipe, _) = ok_status!(res, UNPROCESSABLE_ENTITY);
+
+    let converter = state.parser.converter();
+    let base = recipe
+        .metadata
+        .servings()
+        .and_then(|s| s.first().copied())
+        .unwrap_or(1);
+    let grouped = recipe.default_scale().group_ingredients(converter);
+    let Some(igr) = grouped.iter().find(|e| e.index == query.ingredient) else {
+        return StatusCode::BAD_REQUEST.into_response();
+    };
+    let unit = query
+        .unit
+        .as_deref()
+        .map(str::trim)
+        .filter(|u| !u.is_empty())
+        .or_else(|| igr.quantity.iter().find_map(|q| q.unit()));
+    let Some(total) = total_in(&igr.quantity, unit, converter) else {
+        return StatusCode::UNPROCESSABLE_ENTITY.into_response();
+    };
+    let target = target_servings(total, query.amount, base);
+
+    let href = recipe_url_name(entry.path(), &state);
+    let mut url = format!(
+        "{}/r/{}?scale={target}",
+        state.base_url,
+        url_encode_path(&href)
+    );
+    if let Some(units) = &query.units {
+        url.push_str("&units=");
+        url.push_str(&url_encode_path(units));
+    }
+    Redirect::to(&url).into_response()
+}
+
+/// Sum of the quantity in `unit`, None if some part is not a number or can't
+/// be converted
+fn total_in(qty: &GroupedQuantity, unit: Option<&str>, converter: &Converter) -> Option<f64> {
+    let mut total = 0.0;
+    for q in qty.iter() {
+        let mut q = q.clone();
+        match (unit, q.unit()) {
+            (Some(unit), Some(_)) => q.convert(unit, converter).ok()?,
+            (None, None) => {}
+            _ => return None,
+        }
+        let Value::Number(n) = q.value() else {
+            return None;
+        };
+        total += n.value();
+    }
+    (total > 0.0).then_some(total)
+}
+
+/// Servings that need `amount` of an ingredient which the recipe uses `total`
+/// of for `base` servings
+fn target_servings(total: f64, amount: f64, base: u32) -> u32 {
+    let target = (amount / total * base as f64).round();
+    target.clamp(1.0, u32::MAX as f64) as u32
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn servings() {
+        assert_eq!(target_servings(500.0, 350.0, 4), 3);
+        assert_eq!(target_servings(500.0, 1000.0, 4), 8);
+        assert_eq!(target_servings(200.0, 1.0, 2), 1);
+        assert_eq!(target_servings(3.0, 6.0, 1), 2);
+    }
+}
@@ -1,19 +1,27 @@
-use std::borrow::Borrow;
+use std::collections::HashMap;
 
 use axum::{
     extract::{Query, State},
     http::HeaderMap,
     response::{Html, IntoResponse, Response},
 };
-use minijinja::context;
+use cooklang_fs::RecipeEntry;
+use minijinja::{context, Value};
 use serde::Deserialize;
 
 use crate::{
-    cmd::serve::{locale::UserLocale, S},
-    util::{is_valid_tag, meta_name},
+    cmd::serve::{
+        async_index::RecipeData,
+        fragment::{self, Fragment},
+        locale::UserLocale,
+        query::Searcher,
+        users::ClientId,
+        S,
+    },
+    util::{meta_name, tag_levels, MetaField},
 };
 
-use super::{mj_ok, recipe_entry_context, Searcher};
+use super::{mj_ok, recipe_entry_context};
 
 #[derive(Deserialize)]
 pub struct SearchQuery {
@@ -41,239 +49,200 @@ pub async fn search(
     State(state): State<S>,
     Query(query): Query<SearchQuery>,
     UserLocale(t): UserLocale,
+    ClientId(id): ClientId,
+    Fragment(fragment): Fragment,
 ) -> Response {
-    let srch = Searcher::from(query);
+    let mut srch = match &query.q {
+        Some(q) => Searcher::parse(q),
+        None => Searcher::All(Vec::new()),
+    };
+    srch.normalize_tags(&state.config.tag_aliases);
+    let converter = state.parser.converter();
+
+    let matches = |entry: &RecipeEntry, tokens: Option<&RecipeData>| match tokens {
+        Some(t) => {
+            let name = if let Some(meta) = t.metadata.as_ref() {
+                meta_name(meta).unwrap_or(entry.name())
+            } else {
+                entry.name()
+            };
+            srch.matches_recipe(name, t, converter)
+        }
+        None => false,
+    };
 
     let recipes = state
         .recipe_index
         .search(
-            |entry, tokens| match tokens {
-                Some(t) => {
-                    let name = if let Some(meta) = t.metadata.as_ref() {
-                        meta_name(meta).unwrap_or(entry.name())
-                    } else {
-                        entry.name()
-                    };
-                    srch.matches_recipe(name, t)
-                }
-                None => false,
-            },
+            matches,
             |entry, tokens| recipe_entry_context(entry, &state, tokens),
             0,
-            12,
+            // static tag pages can't load more
+            if state.static_site { usize::MAX } else { 12 },
+        )
+        .await;
+
+    let facets = state
+        .recipe_index
+        .search(
+            matches,
+            |_, tokens| {
+                let meta = tokens.and_then(|t| t.metadata.as_ref());
+                let tags = meta
+                    .and_then(|m| m.tags())
+                    .unwrap_or_default()
+                    .iter()
+                    .map(|t| t.to_string())
+                    .collect();
+                let time = meta.and_then(|m| m.time(converter)).map(|t| t.total());
+                let fields = meta
+                    .map(|m| {
+                        MetaField::ALL
+                            .into_iter()
+                            .filter_map(|f| Some((f, f.get(m)?)))
+                            .collect()
+                    })
+                    .unwrap_or_default();
+                Facets { tags, time, fields }
+            },
+            0,
+            usize::MAX,
         )
         .await;
 
     let is_htmx_search = headers.get("HX-Trigger").is_some_and(|v| v == "search");
 
     let template = if is_htmx_search {
-        "components/recipe_grid.html"
+        "components/search_results.html"
     } else {
         "search.html"
     };
 
     let tmpl = mj_ok!(state.templates.get_template(template));
-    let res = tmpl.render(context! {
+    let ctx = context! {
         t,
         recipes,
         search_query => srch.to_query(),
+        total => facets.len(),
+        active_filters => active_filters(&srch),
+        suggested_filters => suggested_filters(&srch, &facets),
         is_htmx_search,
-    });
-    let content = mj_ok!(res);
+        favorites => state.favorites.get(id.as_deref()),
+    };
+    let content = mj_ok!(fragment::render(&tmpl, ctx, fragment && !is_htmx_search));
 
     Html(content).into_response()
 }
 
-/// Balances parenthesis in the query.
-fn error_correct_query(query: &str) -> String {
-    let mut depth = 0;
-    let mut pad_left = 0;
-    for ch in query.chars() {
-        if ch == '(' {
-            depth += 1;
-        }
-        if ch == ')' {
-            if depth == 0 {
-                pad_left += 1;
-            } else {
-                depth -= 1;
+/// Data of every matched recipe used to suggest filters
+struct Facets {
+    tags: Vec<String>,
+    time: Option<u32>,
+    fields: Vec<(MetaField, String)>,
+}
+
+/// Top level terms of the query, with the query to remove each one
+fn active_filters(srch: &Searcher) -> Value {
+    srch.terms()
+        .iter()
+        .enumerate()
+        .map(|(i, term)| {
+            context! {
+                label => term.to_query(),
+                query => srch.without_term(i).to_query(),
             }
-        }
-    }
-    let mut working_string = "(".repeat(pad_left);
-    working_string.push_str(query);
-    working_string.push_str(&")".repeat(depth));
-    working_string
+        })
+        .collect()
 }
 
-#[cfg(test)]
-mod tests {
-    use super::*;
+/// Filters that narrow down the current results, with the number of recipes
+/// left after applying each one
+fn suggested_filters(srch: &Searcher, facets: &[Facets]) -> Value {
+    const MAX_TAGS: usize = 8;
+    const TIMES: [u32; 3] = [15, 30, 60];
+    const MAX_FIELD_VALUES: usize = 4;
 
-    #[test]
-    fn test_error_correct_query() {
-        assert_eq!(error_correct_query("a b c"), "a b c");
-        assert_eq!(error_correct_query("a | c"), "a | c");
-        assert_eq!(error_correct_query("(b c"), "(b c)");
-        assert_eq!(error_correct_query("(a b)"), "(a b)");
-        assert_eq!(error_correct_query("a | (b | c)"), "a | (b | c)");
-        assert_eq!(error_correct_query("b) c"), "(b) c");
-    }
-}
+    let total = facets.len();
+    let terms = srch.terms();
+    let mut filters = Vec::new();
 
-fn parse_disjunct_chunks(query: &str) -> Vec<&str> {
-    let mut depth = 0;
-    let mut from = 0;
-    let mut output = Vec::new();
-    for (to, ch) in query.char_indices() {
-        if ch == '(' {
-            depth += 1;
-        } else if ch == ')' {
-            depth -= 1;
-        } else if depth == 0 && ch == '|' {
-            if from != to {
-                output.push(query[from..to].trim())
+    if !terms.iter().any(|t| matches!(t, Searcher::Time(_))) {
+        for max in TIMES {
+            let count = facets
+                .iter()
+                .filter(|f| f.time.is_some_and(|t| t <= max))
+                .count();
+            if count > 0 && count < total {
+                let filter = Searcher::Time(max);
+                filters.push(context! {
+                    kind => "time",
+                    value => filter.to_query().trim_start_matches("time:"),
+                    query => srch.and(filter).to_query(),
+                    count,
+                });
             }
-            from = to + 1;
         }
     }
-    if from < query.len() {
-        output.push(query[from..query.len()].trim());
-    }
-    output
-}
 
-fn parse_conjunct_chunks(query: &str) -> Vec<&str> {
-    let mut depth = 0;
-    let mut from = 0;
-    let mut output = Vec::new();
-    for (to, ch) in query.char_indices() {
-        if ch == '(' {
-            depth += 1;
-        } else if ch == ')' {
-            depth -= 1;
-        } else if depth == 0 && ch.is_whitespace() {
-            if from != to {
-                output.push(query[from..to].trim())
-            }
-            from = to + 1;
+    // parents of hierarchical tags count every recipe under them
+    let mut tags = HashMap::<&str, usize>::new();
+    for f in &facets {
+        let mut levels = f
+            .tags
+            .iter()
+            .flat_map(|t| tag_levels(t))
+            .collect::<Vec<_>>();
+        levels.sort_unstable();
+        levels.dedup();
+        for level in levels {
+            *tags.entry(level).or_default() += 1;
         }
     }
-    if from < query.len() {
-        output.push(query[from..query.len()].trim());
+    let mut tags = tags
+        .into_iter()
+        .filter(|&(tag, count)| count < total && !terms.contains(&Searcher::Tag(tag.to_string())))
+        .collect::<Vec<_>>();
+    tags.sort_unstable_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(b.0)));
+    for (tag, count) in tags.into_iter().take(MAX_TAGS) {
+        let filter = Searcher::Tag(tag.to_string());
+        filters.push(context! {
+            kind => "tag",
+            value => tag,
+            query => srch.and(filter).to_query(),
+            count,
+        });
     }
-    output
-}
 
-impl From<SearchQuery> for Searcher {
-    fn from(value: SearchQuery) -> Self {
-        if value.q.is_none() {
-            return Searcher::All(Vec::new());
+    for field in MetaField::ALL {
+        if terms
+            .iter()
+            .any(|t| matches!(t, Searcher::Field(f, _) if *f == field))
+        {
+            continue;
         }
-        let q = error_correct_query(
-            value
-                .q
-                .unwrap()
-                // We can bring back the necessary parenthesis via error correction.
-                .trim_matches(|m: char| m.is_whitespace() || m == ')' || m == '('),
-        );
-
-        let mut parts = parse_disjunct_chunks(&q);
-        let mut output = if parts.len() == 1 {
-            parts = parse_conjunct_chunks(&q);
-            Searcher::All(Vec::new())
-        } else {
-            Searcher::Any(Vec::new())
-        };
-        for part in parts {
-            let mut negated = false;
-            let part = match part.strip_prefix('!') {
-                None => part,
-                Some(new_part) => {
-                    negated = true;
-                    new_part
-                }
-            };
-            if let Some(mut next) = if part.contains(['|', ' ', '(', ')']) {
-                Some(Searcher::from(SearchQuery {
-                    q: Some(part.to_owned()),
-                }))
-            } else {
-                let part = part.replace('+', " ");
-                if let Some(tag) = part.strip_prefix("tag:") {
-                    if is_valid_tag(tag) {
-                        Some(Searcher::Tag(tag.to_owned()))
-                    } else {
-                        None
-                    }
-                } else if let Some(ingredient) = part.strip_prefix("ingredient:") {
-                    Some(Searcher::Ingredient(ingredient.to_owned()))
-                } else if let Some(cookware) = part.strip_prefix("cookware:") {
-                    Some(Searcher::Cookware(cookware.to_owned()))
-                } else {
-                    Some(Searcher::NamePart(part.to_owned()))
-                }
-            } {
-                if negated {
-                    next = Searcher::Not(Box::new(next));
-                }
-                match &mut output {
-                    Searcher::All(v) => v.push(next),
-                    Searcher::Any(v) => v.push(next),
-                    _ => unreachable!(),
-                }
-            }
+        let mut values = HashMap::<&str, usize>::new();
+        for (_, value) in facets
+            .iter()
+            .flat_map(|f| &f.fields)
+            .filter(|(f, _)| *f == field)
+        {
+            *values.entry(value.as_str()).or_default() += 1;
         }
-        match &mut output {
-            Searcher::All(v) => {
-                if v.len() == 1 {
-                    v.pop().unwrap()
-                } else {
-                    output
-                }
-            }
-            Searcher::Any(v) => {
-                if v.len() == 1 {
-                    v.pop().unwrap()
-                } else {
-                    output
-                }
-            }
-            _ => unreachable!(),
+        let mut values = values
+            .into_iter()
+            .filter(|&(_, count)| count < total)
+            .collect::<Vec<_>>();
+        values.sort_unstable_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(b.0)));
+        for (value, count) in values.into_iter().take(MAX_FIELD_VALUES) {
+            let filter = Searcher::Field(field, value.to_string());
+            filters.push(context! {
+                kind => field.key(),
+                value,
+                query => srch.and(filter).to_query(),
+                count,
+            });
         }
     }
-}
 
-impl Searcher {
-    fn to_query(&self) -> String {
-        match self {
-            Searcher::All(v) => v
-                .iter()
-                .map(|s| match s {
-                    Searcher::Any(_) => format!("({})", s.to_query()),
-                    _ => s.to_query(),
-                })
-                .collect::<Vec<_>>()
-                .join(" "),
-            Searcher::Any(v) => v
-                .iter()
-                .map(|s| s.to_query())
-                .collect::<Vec<_>>()
-                .join(" | "),
-            Searcher::Not(s) => {
-                let str = s.to_query();
-                match s.borrow() {
-                    Searcher::Any(_) => format!("!({str})"),
-                    Searcher::All(_) => format!("!({str})"),
-                    _ => format!("!{str}"),
-                }
-            }
-            Searcher::NamePart(name) => name.replace(' ', "+"),
-            Searcher::Tag(tag) => format!("tag:{tag}").replace(' ', "+"),
-            Searcher::Ingredient(ingredient) => {
-                format!("ingredient:{ingredient}").replace(' ', "+")
-            }
-            Searcher::Cookware(cookware) => format!("cookware:{cookware}").replace(' ', "+"),
-        }
-    }
+    Value::from(filters)
 }
@@ -0,0 +1,225 @@
+use axum::{
+    extract::{Path, State},
+    http::{header, HeaderMap, StatusCode},
+    response::{AppendHeaders, Html, IntoResponse, Redirect, Response},
+    Form,
+};
+use cooklang::{ingredient_list::IngredientList, quantity::GroupedQuantity};
+use minijinja::{context, Value};
+use serde::Deserialize;
+use tokio::task::block_in_place;
+
+use crate::{
+    cmd::serve::{
+        client_ip::ClientIp, locale::UserLocale, shopping_list::ListEntry, users::ClientId,
+        AppState, S,
+    },
+    util::{map_recipe, meta_name},
+};
+
+use super::{check_path, mj_ok, ok_status, recipe_url, users::new_client_id};
+
+#[derive(Deserialize)]
+pub struct ToggleForm {
+    servings: Option<u32>,
+}
+
+/// Recipes in the list and their ingredients added up
+pub async fn shopping_list(
+    UserLocale(t): UserLocale,
+    ClientId(id): ClientId,
+    State(state): State<S>,
+) -> Response {
+    let entries = state.shopping_lists.get(id.as_deref());
+    let mut list = IngredientList::new();
+    let mut recipes = Vec::with_capacity(entries.len());
+    for entry in &entries {
+        let (name, href) = add_recipe(&state, entry, &mut list).await.unzip();
+        recipes.push(context! {
+            path => entry.recipe,
+            servings => entry.servings,
+            name,
+            href,
+        });
+    }
+    let categories = block_in_place(|| categories(&state, list));
+
+    let tmpl = mj_ok!(state.templates.get_template("shopping_list.html"));
+    let res = tmpl.render(context! {
+        t,
+        recipes,
+        categories,
+    });
+    let content = mj_ok!(res);
+    Html(content).into_response()
+}
+
+/// Adds or removes a recipe from the list
+///
+/// Renders the new button of the recipe page, or goes back to the list when
+/// it's not an htmx request from the button.
+pub async fn toggle_shopping_list(
+    UserLocale(t): UserLocale,
+    ClientId(id): ClientId,
+    ClientIp(who): ClientIp,
+    headers: HeaderMap,
+    State(state): State<S>,
+    Path(path): Path<String>,
+    Form(form): Form<ToggleForm>,
+) -> Response {
+    if let Err(e) = check_path(&path) {
+        return e.into_response();
+    }
+    // recipes removed from the collection can still be removed from the list
+    let listed = || {
+        state
+            .shopping_lists
+            .get(id.as_deref())
+            .iter()
+            .any(|e| e.recipe == path)
+    };
+    if state.recipe_index.get(&path).await.is_err() && !listed() {
+        return StatusCode::NOT_FOUND.into_response();
+    }
+
+    let (id, cookie) = match id {
+        Some(id) => (id, None),
+        None => match new_client_id(&state) {
+            Ok((id, cookie)) => (id, Some(cookie)),
+            Err(res) => return res,
+        },
+    };
+    let in_list = ok_status!(state.shopping_lists.toggle(&id, &path, form.servings));
+    let action = if in_list {
+        "shopping_list_add"
+    } else {
+        "shopping_list_remove"
+    };
+    state.audit.record(who, action, &path);
+    let cookie = AppendHeaders(cookie.map(|cookie| (header::SET_COOKIE, cookie)));
+
+    if !headers.contains_key("hx-request") || headers.contains_key("hx-boosted") {
+        let to = format!("{}/shopping-list", state.base_url);
+        return (cookie, Redirect::to(&to)).into_response();
+    }
+    let tmpl = mj_ok!(state
+        .templates
+        .get_template("components/shopping_list_button.html"));
+    let res = tmpl.render(context! {
+        t,
+        path,
+        in_list,
+        servings => form.servings,
+    });
+    let content = mj_ok!(res);
+    (cookie, Html(content)).into_response()
+}
+
+/// Removes every recipe from the list
+pub async fn clear_shopping_list(
+    ClientId(id): ClientId,
+    ClientIp(who): ClientIp,
+    State(state): State<S>,
+) -> Response {
+    if let Some(id) = id {
+        ok_status!(state.shopping_lists.clear(&id));
+        state.audit.record(who, "shopping_list_clear", "");
+    }
+    Redirect::to(&format!("{}/shopping-list", state.base_url)).into_response()
+}
+
+/// The list as a meal plan file for `chef shopping-list --plan`
+pub async fn export_shopping_list(ClientId(id): ClientId, State(state): State<S>) -> Response {
+    let mut plan = String::new();
+    for entry in state.shopping_lists.get(id.as_deref()) {
+        plan.push_str(&entry.plan_line());
+        plan.push('\n');
+    }
+    (
+        [
+            (header::CONTENT_TYPE, "text/plain; charset=utf-8"),
+            (
+                header::CONTENT_DISPOSITION,
+                "attachment; filename=\"shopping-list.txt\"",
+            ),
+        ],
+        plan,
+    )
+        .into_response()
+}
+
+/// Adds the ingredients of a recipe in the list and returns its name and url
+///
+/// `None` if the recipe is not in the collection anymore or has errors.
+async fn add_recipe(
+    state: &AppState,
+    entry: &ListEntry,
+    list: &mut IngredientList,
+) -> Option<(String, String)> {
+    let recipe_entry = state.recipe_index.get(&entry.recipe).await.ok()?;
+    let bytes = tokio::fs::read(recipe_entry.path()).await.ok()?;
+    let (content, _) = cooklang_fs::decode(bytes);
+    block_in_place(|| {
+        let res = state
+            .parser
+            .parse_with_options(&content, state.parse_options(Some(recipe_entry.path())))
+            .map(|r| map_recipe(r, &state.config))
+            .into_result();
+        let (recipe, _) = match res {
+            Ok(recipe) => recipe,
+            Err(_) => {
+                tracing::warn!("Shopping list recipe '{}' has errors", entry.recipe);
+                return None;
+            }
+        };
+        let converter = state.parser.converter();
+        let recipe = match entry.servings {
+            Some(servings) => recipe.scale(servings, converter),
+            None => recipe.default_scale(),
+        };
+        list.add_recipe(&recipe, converter);
+        let name = meta_name(&recipe.metadata)
+            .unwrap_or(recipe_entry.name())
+            .to_string();
+        Some((name, recipe_url(recipe_entry.path(), state)))
+    })
+}
+
+/// Ingredients grouped by the categories of the aisle configuration, or a
+/// single group without name if there is none
+fn categories(state: &AppState, list: IngredientList) -> Value {
+    fn item((name, quantity): (String, GroupedQuantity)) -> Value {
+        context! {
+            name,
+            quantities => quantity.iter().map(|q| context! {
+                value => q.value(),
+                unit => q.unit(),
+            }).collect::<Value>(),
+        }
+    }
+
+    let aisle_content = state
+        .config
+        .aisle(&state.base_path)
+        .and_then(|path| std::fs::read_to_string(path).ok());
+    let aisle = aisle_content.as_deref().and_then(|content| {
+        cooklang::aisle::parse(content)
+            .inspect_err(|_| tracing::warn!("Invalid aisle file, the shopping list is not grouped"))
+            .ok()
+    });
+    match aisle {
+        Some(aisle) if !aisle.categories.is_empty() => list
+            .categorize(&aisle)
+            .into_iter()
+            .map(|(name, items)| {
+                context! {
+                    name,
+                    items => items.into_iter().map(item).collect::<Value>(),
+                }
+            })
+            .collect(),
+        _ => Value::from_iter([context! {
+            items => list.into_iter().map(item).collect::<Value>(),
+        }]),
+    }
+}
@@ -30,6 +30,13 @@ pub async fn sse_updates(
                     "to": p(&to)
                 }))
                 .unwrap(),
+            Update::Image { path, recipe } => e
+                .event("image")
+                .json_data(serde_json::json!({
+                    "path": p(&path),
+                    "recipe": p(&recipe)
+                }))
+                .unwrap(),
         }
     });
 
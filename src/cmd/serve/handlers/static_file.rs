@@ -1,18 +1,26 @@
 use axum::{
     body::Body,
+    extract::State,
     http::{HeaderMap, StatusCode, Uri},
     response::{IntoResponse, Redirect, Response},
 };
 
-use crate::cmd::serve::Assets;
+use crate::cmd::serve::{
+    cache::{etag_matches, make_etag},
+    Assets, S,
+};
 
-pub async fn static_file(uri: Uri, headers: HeaderMap) -> Result<Response, StatusCode> {
+pub async fn static_file(
+    State(state): State<S>,
+    uri: Uri,
+    headers: HeaderMap,
+) -> Result<Response, StatusCode> {
     const INDEX_HTML: &str = "index.html";
 
     let path = uri.path().trim_start_matches('/');
 
     if path.is_empty() || path == INDEX_HTML {
-        return Ok(Redirect::permanent("/").into_response());
+        return Ok(Redirect::permanent(&format!("{}/", state.base_url)).into_response());
     }
 
     match Assets::get(path) {
@@ -20,25 +28,10 @@ pub async fn static_file(uri: Uri, headers: HeaderMap) -> Result<Response, Statu
             use axum::http::header;
             let body = Body::from(content.data);
             let mime = mime_guess::from_path(path).first_or_octet_stream();
-            let etag = content
-                .metadata
-                .last_modified()
-                .unwrap_or_else(|| {
-                    tracing::warn!("can't cache '{path}'");
-                    std::time::SystemTime::now()
-                        .duration_since(std::time::UNIX_EPOCH)
-                        .expect("system time before unix epoch")
-                        .as_secs()
-                })
-                .to_string();
+            let etag = make_etag(content.metadata.sha256_hash());
 
-            if let Some(client_etag) = headers
-                .get(header::IF_NONE_MATCH)
-                .and_then(|v| v.to_str().ok())
-            {
-                if client_etag == etag {
-                    return Ok(StatusCode::NOT_MODIFIED.into_response());
-                }
+            if etag_matches(&headers, &etag) {
+                return Ok(StatusCode::NOT_MODIFIED.into_response());
             }
 
             let cache_control = {
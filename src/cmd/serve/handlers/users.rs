@@ -0,0 +1,143 @@
+use axum::{
+    extract::State,
+    http::{header, HeaderMap, StatusCode},
+    response::{Html, IntoResponse, Redirect, Response},
+    Form,
+};
+use minijinja::context;
+use serde::Deserialize;
+
+use crate::cmd::serve::{
+    client_ip::{client_scheme, ClientIp},
+    locale::UserLocale,
+    users::{
+        random_token, session_token, CurrentUser, LoginError, CLIENT_COOKIE, COOKIE,
+        SESSION_MAX_IDLE,
+    },
+    AppState, S,
+};
+
+use super::mj_ok;
+
+#[derive(Deserialize)]
+pub struct LoginForm {
+    name: String,
+    #[serde(default)]
+    password: String,
+}
+
+/// Login form, or who is logged in and a button to log out
+pub async fn login_page(
+    UserLocale(t): UserLocale,
+    CurrentUser(user): CurrentUser,
+    State(state): State<S>,
+) -> Response {
+    if !state.users.enabled() {
+        return StatusCode::NOT_FOUND.into_response();
+    }
+    render_login(&state, t, user, None)
+}
+
+pub async fn login(
+    UserLocale(t): UserLocale,
+    ClientIp(who): ClientIp,
+    headers: HeaderMap,
+    State(state): State<S>,
+    Form(form): Form<LoginForm>,
+) -> Response {
+    if !state.users.enabled() {
+        return StatusCode::NOT_FOUND.into_response();
+    }
+    let res = tokio::task::block_in_place(|| state.users.login(&form.name, &form.password, who));
+    let token = match res {
+        Ok(token) => token,
+        Err(LoginError::Wrong) => {
+            let page = render_login(&state, t, None, Some("users.wrong"));
+            return (StatusCode::UNAUTHORIZED, page).into_response();
+        }
+        Err(LoginError::Throttled) => {
+            tracing::warn!("Too many failed logins from '{who}'");
+            let page = render_login(&state, t, None, Some("users.throttled"));
+            return (StatusCode::TOO_MANY_REQUESTS, page).into_response();
+        }
+        Err(LoginError::Internal) => return StatusCode::INTERNAL_SERVER_ERROR.into_response(),
+    };
+    state.audit.record(who, "login", &form.name);
+
+    // as long as the session lasts without using it
+    let cookie = format!(
+        "{COOKIE}={token}; Path={}; Max-Age={SESSION_MAX_IDLE}; SameSite=Lax; HttpOnly{}",
+        cookie_path(&state),
+        secure(&state, &headers)
+    );
+    (
+        [(header::SET_COOKIE, cookie)],
+        Redirect::to(&format!("{}/", state.base_url)),
+    )
+        .into_response()
+}
+
+pub async fn logout(headers: HeaderMap, State(state): State<S>) -> Response {
+    if let Some(token) = session_token(&headers) {
+        state.users.logout(token);
+    }
+    let cookie = format!(
+        "{COOKIE}=; Path={}; Max-Age=0{}",
+        cookie_path(&state),
+        secure(&state, &headers)
+    );
+    (
+        [(header::SET_COOKIE, cookie)],
+        Redirect::to(&format!("{}/login", state.base_url)),
+    )
+        .into_response()
+}
+
+fn render_login(
+    state: &AppState,
+    t: minijinja::Value,
+    user: Option<String>,
+    error: Option<&str>,
+) -> Response {
+    let tmpl = mj_ok!(state.templates.get_template("login.html"));
+    let res = tmpl.render(context! {
+        t,
+        user,
+        error,
+    });
+    let content = mj_ok!(res);
+    Html(content).into_response()
+}
+
+/// Id for a browser without one, and the cookie to keep it
+///
+/// The cookie lasts a year and is only set with the first change of the
+/// browser, so it's not renewed.
+pub(super) fn new_client_id(state: &AppState) -> Result<(String, String), Response> {
+    let id = random_token().map_err(|e| {
+        tracing::error!("Could not create a client id: {e}");
+        StatusCode::INTERNAL_SERVER_ERROR.into_response()
+    })?;
+    let cookie = format!(
+        "{CLIENT_COOKIE}={id}; Path={}; Max-Age=31536000; SameSite=Lax; HttpOnly",
+        cookie_path(state)
+    );
+    Ok((id, cookie))
+}
+
+/// Only send the cookie over HTTPS if the client uses it
+fn secure(state: &AppState, headers: &HeaderMap) -> &'static str {
+    if client_scheme(headers, state.tls) == "https" {
+        "; Secure"
+    } else {
+        ""
+    }
+}
+
+fn cookie_path(state: &AppState) -> &str {
+    if state.base_url.is_empty() {
+        "/"
+    } else {
+        &state.base_url
+    }
+}
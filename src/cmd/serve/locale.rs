@@ -5,6 +5,7 @@ use axum::{
     http::{request::Parts, HeaderMap},
 };
 use camino::Utf8Path;
+use cooklang::convert::System;
 use minijinja::{value::Object, Value};
 
 use super::{get_cookie, Locales};
@@ -62,6 +63,26 @@ impl Locale {
         let value = current.as_str()?;
         Some(value)
     }
+
+    /// Decimal separator of numbers, `.` if the locale does not set one
+    pub fn decimal_separator(&self) -> &str {
+        self.get("_decimalSeparator")
+            .filter(|s| !s.is_empty())
+            .unwrap_or(".")
+    }
+
+    /// Unit system quantities are converted to if nothing else is chosen
+    pub fn preferred_system(&self) -> Option<System> {
+        self.get("_units")?.parse().ok()
+    }
+}
+
+/// Formats a number with up to 3 decimals
+pub fn format_number(n: f64, decimal_separator: &str) -> String {
+    let s = format!("{n:.3}");
+    let s = s.trim_end_matches('0').trim_end_matches('.');
+    let s = if s == "-0" { "0" } else { s };
+    s.replacen('.', decimal_separator, 1)
 }
 
 impl Object for Locale {
@@ -160,3 +181,17 @@ impl FromRequestParts<super::S> for UserLocale {
         )))
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn numbers() {
+        assert_eq!(format_number(2.0, ","), "2");
+        assert_eq!(format_number(1.5, ","), "1,5");
+        assert_eq!(format_number(0.33333, "."), "0.333");
+        assert_eq!(format_number(1000.25, ","), "1000,25");
+        assert_eq!(format_number(-0.0001, "."), "0");
+    }
+}
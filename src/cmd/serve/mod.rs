@@ -1,15 +1,31 @@
+mod access_log;
 mod async_index;
+mod audit;
+mod cache;
+mod client_ip;
+mod cook_log;
+mod db;
+pub mod export_site;
+mod favorites;
+mod fragment;
 mod handlers;
 mod locale;
+mod query;
+mod remote_images;
+mod shopping_list;
+mod slugs;
+mod tls;
+mod users;
 
 use self::{
-    async_index::{AsyncFsIndex, Update},
+    access_log::AccessLog,
+    async_index::{AsyncFsIndex, Loader, Update},
     locale::{make_locale_store, LocaleStore},
 };
-use crate::Context;
+use crate::{config::NavLink, Context, COOK_DIR};
 use anyhow::{bail, Context as _, Result};
 use axum::{
-    extract::Request,
+    extract::{Request, State},
     http::{header::CONTENT_TYPE, HeaderMap, HeaderValue, StatusCode},
     middleware::{self, Next},
     response::Response,
@@ -43,16 +59,56 @@ pub struct ServeArgs {
     /// Open browser on start
     #[arg(long, conflicts_with = "host", default_value_t = false)]
     open: bool,
+
+    /// Serve the web UI under a path, like `/recipes`
+    ///
+    /// Useful behind a reverse proxy that forwards a subpath. Overrides the
+    /// `ui.base_url` config.
+    #[arg(long, value_name = "PATH")]
+    base_url: Option<String>,
+
+    /// Serve with HTTPS using this PEM certificate chain
+    #[arg(long, value_name = "FILE", requires = "tls_key")]
+    tls_cert: Option<Utf8PathBuf>,
+
+    /// PEM private key of the certificate
+    #[arg(long, value_name = "FILE", requires = "tls_cert")]
+    tls_key: Option<Utf8PathBuf>,
+
+    /// Redirect plain HTTP requests on this port to HTTPS
+    #[arg(long, value_name = "PORT", requires = "tls_cert")]
+    http_redirect_port: Option<u16>,
+
+    /// Write a JSON line for every request to this file, or `-` for stdout
+    #[arg(long, value_name = "FILE")]
+    access_log: Option<Utf8PathBuf>,
+
+    /// Ask for a password and print its hash for `ui.users`, without serving
+    #[arg(long, exclusive = true)]
+    hash_password: bool,
 }
 
 #[tokio::main]
 pub async fn run(ctx: Context, args: ServeArgs) -> Result<()> {
+    if args.hash_password {
+        let password = inquire::Password::new("Password:").prompt()?;
+        println!("{}", users::hash_password(&password)?);
+        return Ok(());
+    }
     if !ctx.is_collection {
         bail!("`serve` needs to run inside a collection");
     }
 
-    let state = build_state(ctx).context("failed to build web server")?;
-    let app = make_router(state);
+    let base_url = normalize_base_url(
+        args.base_url
+            .as_deref()
+            .or(ctx.config.ui.base_url.as_deref())
+            .unwrap_or_default(),
+    );
+    let access_log = AccessLog::new(args.access_log.as_deref())?;
+    let state = build_state(ctx, base_url.clone(), false, args.tls_cert.is_some())
+        .context("failed to build web server")?;
+    let app = make_router(state, access_log);
 
     let addr = if args.host {
         SocketAddr::from(([0, 0, 0, 0], args.port))
@@ -60,10 +116,15 @@ pub async fn run(ctx: Context, args: ServeArgs) -> Result<()> {
         SocketAddr::from(([127, 0, 0, 1], args.port))
     };
 
-    info!("Listening on {addr}");
+    let scheme = if args.tls_cert.is_some() {
+        "https"
+    } else {
+        "http"
+    };
+    info!("Listening on {scheme}://{addr}");
 
     if args.open {
-        let url = format!("http://{}:{}", addr.ip(), addr.port());
+        let url = format!("{scheme}://{}:{}{}/", addr.ip(), addr.port(), base_url);
         info!("Serving web UI on {url}");
         tokio::task::spawn(async move {
             tokio::time::sleep(tokio::time::Duration::from_millis(500)).await;
@@ -73,39 +134,145 @@ pub async fn run(ctx: Context, args: ServeArgs) -> Result<()> {
         });
     }
 
-    let listener = tokio::net::TcpListener::bind(&addr).await.unwrap();
-    let app_service = app.into_make_service_with_connect_info::<SocketAddr>();
-    axum::serve(listener, app_service).await.unwrap();
+    if let (Some(cert), Some(key)) = (&args.tls_cert, &args.tls_key) {
+        tls::serve(app, addr, cert, key, args.http_redirect_port).await?;
+    } else {
+        let listener = tokio::net::TcpListener::bind(&addr).await.unwrap();
+        let app_service = app.into_make_service_with_connect_info::<SocketAddr>();
+        axum::serve(listener, app_service).await.unwrap();
+    }
 
     info!("Server stopped");
 
     Ok(())
 }
 
+/// Makes sure the base url starts with `/` and does not end with it
+///
+/// An empty string means no base url.
+fn normalize_base_url(url: &str) -> String {
+    let url = url.trim().trim_matches('/');
+    if url.is_empty() {
+        String::new()
+    } else {
+        format!("/{url}")
+    }
+}
+
 #[tracing::instrument(level = "debug", skip_all)]
-fn make_router(state: Arc<AppState>) -> Router {
-    Router::new()
+fn make_router(state: Arc<AppState>, access_log: AccessLog) -> Router {
+    let base_url = state.base_url.clone();
+    let trusted_proxies = Arc::<[_]>::from(state.config.ui.trusted_proxies.as_slice());
+    // pages that can also be requested as htmx fragments
+    let pages = Router::new()
         .route("/", get(handlers::index))
         .route("/d/{*path}", get(handlers::index))
         .route("/search", get(handlers::search))
+        .route("/r/{*path}", get(handlers::recipe));
+    let fragments = pages.clone().layer(middleware::from_fn_with_state(
+        Arc::<str>::from(base_url.as_str()),
+        fragment::fragment_layer,
+    ));
+    let router = Router::new()
+        .merge(pages)
+        .nest("/fragments", fragments)
         .route("/about", get(handlers::about))
-        .route("/r/{*path}", get(handlers::recipe))
+        .route("/diagnostics", get(handlers::diagnostics))
+        .route("/pages/{name}", get(handlers::page))
+        .route("/favorites", get(handlers::favorites))
+        .route("/favorites.txt", get(handlers::export_favorites))
+        .route("/favorites/{*path}", post(handlers::toggle_favorite))
+        .route(
+            "/shopping-list",
+            get(handlers::shopping_list).post(handlers::clear_shopping_list),
+        )
+        .route("/shopping-list.txt", get(handlers::export_shopping_list))
+        .route(
+            "/shopping-list/{*path}",
+            post(handlers::toggle_shopping_list),
+        )
+        .route("/login", get(handlers::login_page).post(handlers::login))
+        .route("/logout", post(handlers::logout))
+        .route(
+            "/log/{*path}",
+            get(handlers::cook_log).post(handlers::add_cook_log),
+        )
         .route("/updates", get(handlers::sse_updates))
         .route("/open_editor/{*path}", get(handlers::open_editor))
+        .route("/reveal/{*path}", get(handlers::reveal))
+        .route("/admin/log", get(handlers::audit_log))
         .route("/convert_modal", post(handlers::convert_popover))
+        .route("/api/openapi.json", get(handlers::openapi))
+        .route("/sitemap.xml", get(handlers::sitemap))
+        .route("/feed.xml", get(handlers::feed))
+        .route("/sw.js", get(handlers::service_worker))
+        .route("/manifest.webmanifest", get(handlers::manifest))
+        .route("/remote_image/{key}", get(handlers::remote_image))
         .nest_service(
             "/src",
             ServiceBuilder::new()
                 .layer(middleware::from_fn(filter_files))
+                .layer(middleware::from_fn(cache::src_etag))
                 .layer(middleware::from_fn(cook_mime_type))
                 .service(tower_http::services::ServeDir::new(&state.base_path)),
         )
         .fallback(handlers::static_file)
-        .with_state(state)
+        .with_state(state);
+
+    let app = if base_url.is_empty() {
+        router
+    } else {
+        // the whole app is a fallback so the middleware runs before the
+        // routing of the inner router
+        Router::new()
+            .fallback_service(router)
+            .layer(middleware::from_fn_with_state(
+                Arc::<str>::from(base_url),
+                strip_base_url,
+            ))
+    };
+    // the client ip is needed in the access log, so it runs first
+    app.layer(middleware::from_fn_with_state(
+        Arc::new(access_log),
+        access_log::access_log_layer,
+    ))
+    .layer(middleware::from_fn_with_state(
+        trusted_proxies,
+        client_ip::client_ip_layer,
+    ))
+}
+
+/// Removes the base url from the request so the app can route it
+async fn strip_base_url(
+    State(base_url): State<Arc<str>>,
+    mut req: Request,
+    next: Next,
+) -> Response {
+    use axum::http::{uri::PathAndQuery, Uri};
+    use axum::response::{IntoResponse, Redirect};
+
+    let rest = match req.uri().path().strip_prefix(&*base_url) {
+        Some("") => return Redirect::permanent(&format!("{base_url}/")).into_response(),
+        Some(rest) if rest.starts_with('/') => rest,
+        _ => return StatusCode::NOT_FOUND.into_response(),
+    };
+    let path_and_query = match req.uri().query() {
+        Some(q) => format!("{rest}?{q}"),
+        None => rest.to_string(),
+    };
+    let mut parts = req.uri().clone().into_parts();
+    parts.path_and_query = path_and_query.parse::<PathAndQuery>().ok();
+    match Uri::from_parts(parts) {
+        Ok(uri) => *req.uri_mut() = uri,
+        Err(_) => return StatusCode::BAD_REQUEST.into_response(),
+    }
+    next.run(req).await
 }
 
 pub struct AppState {
     templates: Environment<'static>,
+    /// Prefix of every url, empty or starting with `/`
+    base_url: String,
     locales: LocaleStore,
     parser: Arc<CooklangParser>,
     base_path: Utf8PathBuf,
@@ -114,12 +281,25 @@ pub struct AppState {
     config: crate::config::Config,
     editor_command: Option<Vec<String>>,
     editor_count: AtomicI32,
+    file_manager_command: Option<Vec<String>>,
+    favorites: favorites::FavoritesStore,
+    cook_log: cook_log::CookLogStore,
+    shopping_lists: shopping_list::ShoppingListStore,
+    audit: audit::AuditLog,
+    remote_images: remote_images::RemoteImages,
+    users: users::Users,
+    /// Rendering pages for a static copy of the site, see [`export_site`]
+    static_site: bool,
+    /// Served with `--tls-cert`
+    tls: bool,
+    /// Files or dirs that could not be indexed when starting
+    index_errors: Vec<String>,
 }
 
 type S = Arc<AppState>;
 
 #[tracing::instrument(level = "debug", skip_all)]
-fn build_state(ctx: Context) -> Result<S> {
+fn build_state(ctx: Context, base_url: String, static_site: bool, tls: bool) -> Result<S> {
     ctx.parser()?;
     let Context {
         parser,
@@ -127,31 +307,108 @@ fn build_state(ctx: Context) -> Result<S> {
         base_path,
         config,
         chef_config,
+        fs_write,
+        global_args,
         ..
     } = ctx;
     let parser = Arc::new(parser.into_inner().unwrap());
-    let complete_index = recipe_index
-        .index_all()
-        .context("failed to index the recipes")?;
-    let (recipe_index, updates) = AsyncFsIndex::new(complete_index, Arc::clone(&parser));
+    // a few bad files should not take down the whole collection
+    let complete_index = recipe_index.index_all();
+    let index_errors = complete_index
+        .issues()
+        .iter()
+        .map(ToString::to_string)
+        .collect();
+    let loader = Loader {
+        parser: Arc::clone(&parser),
+        tag_aliases: config.tag_aliases.clone(),
+        include_drafts: global_args.include_drafts,
+        slugs: config.ui.slugs,
+    };
+    let (recipe_index, updates) = AsyncFsIndex::new(complete_index, loader);
 
     let locales = make_locale_store();
-    let templates = make_template_env(&locales);
+    let mut templates = make_template_env(&locales, &base_url, static_site);
+    templates.add_global("nav_links", nav_links(&config.ui.nav, &base_url));
+    templates.add_global("users_enabled", !config.ui.users.is_empty() && !static_site);
+    // localized in the browser, this is for no js and exported sites
+    let duration = config.duration.clone();
+    templates.add_filter("format_minutes", move |minutes: u32| {
+        duration.format(minutes)
+    });
+    if !fs_write && !static_site {
+        tracing::info!("Writing to the collection is disabled, changes are kept in memory");
+    }
+    // a static site does not change anything
+    let db = db::Db::open(
+        &base_path.join(COOK_DIR).join(DB_FILE),
+        fs_write && !static_site,
+    )?;
+    let db = Arc::new(db);
+    let favorites = favorites::FavoritesStore::new(
+        Arc::clone(&db),
+        &base_path.join(COOK_DIR).join(LEGACY_FAVORITES_FILE),
+    );
+    let cook_log = cook_log::CookLogStore::new(
+        Arc::clone(&db),
+        &base_path.join(COOK_DIR).join(LEGACY_COOK_LOG_FILE),
+    );
+    let shopping_lists = shopping_list::ShoppingListStore::new(db);
+    let audit = audit::AuditLog::new(base_path.join(COOK_DIR).join(AUDIT_LOG_FILE), fs_write);
+    let users = users::Users::load(
+        base_path.join(COOK_DIR).join(SESSIONS_FILE),
+        config.ui.users.clone(),
+        fs_write,
+    );
+    let remote_images = remote_images::RemoteImages::new(
+        base_path.clone(),
+        config.ui.proxy_images,
+        fs_write,
+        static_site,
+    );
 
     Ok(Arc::new(AppState {
         templates,
+        base_url,
         locales,
         parser,
         base_path,
         recipe_index,
         updates_stream: updates,
         config,
-        editor_command: chef_config.editor().ok(),
+        // editing is writing
+        editor_command: chef_config.editor().ok().filter(|_| fs_write),
         editor_count: 0.into(),
+        file_manager_command: chef_config.file_manager().ok(),
+        favorites,
+        cook_log,
+        shopping_lists,
+        audit,
+        remote_images,
+        users,
+        static_site,
+        tls,
+        index_errors,
+    }))
+}
+
+/// Links of `ui.nav`, with the base url added to the ones inside the UI
+fn nav_links(nav: &[NavLink], base_url: &str) -> Value {
+    Value::from_iter(nav.iter().map(|link| {
+        let href = if link.url.starts_with('/') {
+            format!("{base_url}{}", link.url)
+        } else {
+            link.url.clone()
+        };
+        context! { name => link.name, href }
     }))
 }
 
-fn make_template_env(locales: &LocaleStore) -> Environment<'static> {
+fn make_template_env(
+    locales: &LocaleStore,
+    base_url: &str,
+    static_site: bool,
+) -> Environment<'static> {
     let mut env = Environment::new();
 
     env.set_loader(|name| match Templates::get(name) {
@@ -162,6 +419,9 @@ fn make_template_env(locales: &LocaleStore) -> Environment<'static> {
         None => Ok(None),
     });
 
+    env.add_global("base_url", base_url);
+    env.add_global("static_site", static_site);
+
     env.add_global(
         "all_locales",
         Value::from_iter(locales.locales.iter().map(|l| {
@@ -218,6 +478,10 @@ fn make_template_env(locales: &LocaleStore) -> Environment<'static> {
         },
     );
 
+    env.add_filter("markdown", |v: &str| {
+        Value::from_safe_string(crate::util::markdown_to_html(v))
+    });
+
     env.add_filter("unicode_fraction", |v: &str| {
         Value::from(match v {
             "1/2" => "½",
@@ -260,12 +524,13 @@ fn make_template_env(locales: &LocaleStore) -> Environment<'static> {
         },
     );
 
-    env.add_filter("zeroless_float", |v: f64| {
-        if v.fract() == 0.0 {
-            Value::from(v as i128)
-        } else {
-            Value::from(v)
-        }
+    env.add_filter("format_number", |state: &minijinja::State, v: f64| {
+        let t = state.lookup("t");
+        let separator = t
+            .as_ref()
+            .and_then(|t| t.downcast_object_ref::<locale::Locale>())
+            .map_or(".", |l| l.decimal_separator());
+        locale::format_number(v, separator)
     });
 
     env
@@ -275,7 +540,10 @@ fn make_template_env(locales: &LocaleStore) -> Environment<'static> {
 async fn filter_files(req: Request, next: Next) -> impl axum::response::IntoResponse {
     let path = req.uri().path();
     let (_, ext) = path.rsplit_once('.').ok_or(StatusCode::NOT_FOUND)?;
-    if ext == "cook" || cooklang_fs::IMAGE_EXTENSIONS.contains(&ext) {
+    if ext == "cook"
+        || cooklang_fs::IMAGE_EXTENSIONS.contains(&ext)
+        || cooklang_fs::VIDEO_EXTENSIONS.contains(&ext)
+    {
         Ok(next.run(req).await)
     } else {
         Err(StatusCode::NOT_FOUND)
@@ -305,6 +573,15 @@ fn get_cookie<'a>(headers: &'a HeaderMap, cookie: &str) -> Option<&'a str> {
     Some(value)
 }
 
+/// Favorites, cook logs and shopping lists of the web UI, in the collection
+/// `.cooklang` dir
+const DB_FILE: &str = "chef.redb";
+/// Imported to the database the first time
+const LEGACY_FAVORITES_FILE: &str = "favorites.json";
+const LEGACY_COOK_LOG_FILE: &str = "cook-log.json";
+const AUDIT_LOG_FILE: &str = "logs/audit.log";
+const SESSIONS_FILE: &str = "sessions.json";
+
 #[derive(RustEmbed)]
 #[folder = "ui/templates/"]
 struct Templates;
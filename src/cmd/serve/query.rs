@@ -0,0 +1,431 @@
+//! Search query language
+//!
+//! A query is a list of terms that all have to match, separated by spaces.
+//! Terms separated by `|` are alternatives, `!` negates a term and
+//! parenthesis group them. A term can be:
+//!
+//! - `tag:<tag>`
+//! - `ingredient:<name>`
+//! - `cookware:<name>`
+//! - `time:<max>` with a total time of at most `max`, like `30`, `45m` or
+//!   `1h30m`. Plain numbers are minutes.
+//! - `difficulty:<value>`, `cuisine:<value>` or `course:<value>`, compared
+//!   normalized, see [`MetaField`]
+//! - `missing:<key>` when the recipe does not have a metadata key. `tag` and
+//!   `time` check the parsed values.
+//! - anything else, part of the recipe name
+//!
+//! Spaces inside a term are written as `+`.
+
+use std::{borrow::Borrow, collections::HashMap};
+
+use cooklang::{metadata::Metadata, Converter};
+
+use super::async_index::RecipeData;
+use crate::util::{is_valid_tag, normalize_tag, MetaField};
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Searcher {
+    All(Vec<Self>),
+    Any(Vec<Self>),
+    Not(Box<Self>),
+    NamePart(String),
+    Tag(String),
+    Ingredient(String),
+    Cookware(String),
+    /// Max total time in minutes
+    Time(u32),
+    /// Normalized value of a metadata field
+    Field(MetaField, String),
+    Missing(String),
+}
+
+impl Searcher {
+    pub fn parse(query: &str) -> Self {
+        let q = error_correct_query(
+            // We can bring back the necessary parenthesis via error correction.
+            query.trim_matches(|m: char| m.is_whitespace() || m == ')' || m == '('),
+        );
+
+        let mut parts = parse_disjunct_chunks(&q);
+        let mut output = if parts.len() <= 1 {
+            parts = parse_conjunct_chunks(&q);
+            Searcher::All(Vec::new())
+        } else {
+            Searcher::Any(Vec::new())
+        };
+        for part in parts {
+            let mut negated = false;
+            let part = match part.strip_prefix('!') {
+                None => part,
+                Some(new_part) => {
+                    negated = true;
+                    new_part
+                }
+            };
+            let next = if part.contains(['|', ' ', '(', ')']) {
+                Some(Searcher::parse(part))
+            } else {
+                parse_term(&part.replace('+', " "))
+            };
+            if let Some(mut next) = next {
+                if negated {
+                    next = Searcher::Not(Box::new(next));
+                }
+                match &mut output {
+                    Searcher::All(v) => v.push(next),
+                    Searcher::Any(v) => v.push(next),
+                    _ => unreachable!(),
+                }
+            }
+        }
+        match &mut output {
+            Searcher::All(v) | Searcher::Any(v) if v.len() == 1 => v.pop().unwrap(),
+            _ => output,
+        }
+    }
+
+    /// Replaces the tags with their normalized form, so an alias finds the
+    /// same recipes as its tag
+    pub fn normalize_tags(&mut self, aliases: &HashMap<String, String>) {
+        match self {
+            Self::All(v) | Self::Any(v) => v.iter_mut().for_each(|s| s.normalize_tags(aliases)),
+            Self::Not(s) => s.normalize_tags(aliases),
+            Self::Tag(tag) => *tag = normalize_tag(tag, aliases),
+            _ => {}
+        }
+    }
+
+    pub fn matches_recipe(&self, name: &str, tokens: &RecipeData, converter: &Converter) -> bool {
+        match self {
+            Self::All(v) => {
+                v.is_empty() | v.iter().all(|s| s.matches_recipe(name, tokens, converter))
+            }
+            Self::Any(v) => {
+                v.is_empty() | v.iter().any(|s| s.matches_recipe(name, tokens, converter))
+            }
+            Self::Not(searcher) => !searcher.matches_recipe(name, tokens, converter),
+            Self::NamePart(part) => name.to_lowercase().contains(part),
+            Self::Tag(tag) => match tokens.metadata.as_ref() {
+                Some(meta) => meta
+                    .tags()
+                    .unwrap_or(vec![])
+                    .iter()
+                    .any(|t| t.contains(tag)),
+                None => false,
+            },
+            Self::Ingredient(ingredient) => tokens
+                .ingredients
+                .iter()
+                .any(|str| str.to_lowercase().contains(ingredient)),
+            Self::Cookware(cookware) => tokens
+                .cookware
+                .iter()
+                .any(|str| str.to_lowercase().contains(cookware)),
+            Self::Time(max) => tokens
+                .metadata
+                .as_ref()
+                .and_then(|meta| total_time(meta, converter))
+                .is_some_and(|time| time <= *max),
+            Self::Field(field, value) => tokens
+                .metadata
+                .as_ref()
+                .and_then(|meta| field.get(meta))
+                .is_some_and(|v| v == *value),
+            Self::Missing(key) => match tokens.metadata.as_ref() {
+                Some(meta) => match key.as_str() {
+                    "tag" | "tags" => meta.tags().map_or(true, |tags| tags.is_empty()),
+                    "time" => total_time(meta, converter).is_none(),
+                    key => meta.get(key).is_none(),
+                },
+                None => true,
+            },
+        }
+    }
+
+    pub fn to_query(&self) -> String {
+        match self {
+            Searcher::All(v) => v
+                .iter()
+                .map(|s| match s {
+                    Searcher::Any(_) => format!("({})", s.to_query()),
+                    _ => s.to_query(),
+                })
+                .collect::<Vec<_>>()
+                .join(" "),
+            Searcher::Any(v) => v
+                .iter()
+                .map(|s| s.to_query())
+                .collect::<Vec<_>>()
+                .join(" | "),
+            Searcher::Not(s) => {
+                let str = s.to_query();
+                match s.borrow() {
+                    Searcher::Any(_) => format!("!({str})"),
+                    Searcher::All(_) => format!("!({str})"),
+                    _ => format!("!{str}"),
+                }
+            }
+            Searcher::NamePart(name) => name.replace(' ', "+"),
+            Searcher::Tag(tag) => format!("tag:{tag}").replace(' ', "+"),
+            Searcher::Ingredient(ingredient) => {
+                format!("ingredient:{ingredient}").replace(' ', "+")
+            }
+            Searcher::Cookware(cookware) => format!("cookware:{cookware}").replace(' ', "+"),
+            Searcher::Time(max) => format!("time:{}", format_minutes(*max)),
+            Searcher::Field(field, value) => format!("{}:{value}", field.key()),
+            Searcher::Missing(key) => format!("missing:{key}").replace(' ', "+"),
+        }
+    }
+
+    /// Terms that must all match
+    pub fn terms(&self) -> &[Self] {
+        match self {
+            Searcher::All(v) => v,
+            _ => std::slice::from_ref(self),
+        }
+    }
+
+    /// Query without the term at `index` of [`Self::terms`]
+    pub fn without_term(&self, index: usize) -> Self {
+        let mut terms = self.terms().to_vec();
+        terms.remove(index);
+        Searcher::All(terms)
+    }
+
+    /// Query with an extra term that must also match
+    pub fn and(&self, term: Self) -> Self {
+        let mut terms = self.terms().to_vec();
+        terms.push(term);
+        Searcher::All(terms)
+    }
+}
+
+fn parse_term(part: &str) -> Option<Searcher> {
+    let searcher = if let Some(tag) = part.strip_prefix("tag:") {
+        if !is_valid_tag(tag) {
+            return None;
+        }
+        Searcher::Tag(tag.to_owned())
+    } else if let Some(ingredient) = part.strip_prefix("ingredient:") {
+        Searcher::Ingredient(ingredient.to_owned())
+    } else if let Some(cookware) = part.strip_prefix("cookware:") {
+        Searcher::Cookware(cookware.to_owned())
+    } else if let Some(time) = part.strip_prefix("time:") {
+        Searcher::Time(parse_minutes(time)?)
+    } else if let Some((field, value)) = MetaField::ALL.into_iter().find_map(|f| {
+        part.strip_prefix(f.key())
+            .and_then(|rest| rest.strip_prefix(':'))
+            .map(|value| (f, value))
+    }) {
+        let value = field.normalize(value);
+        if value.is_empty() {
+            return None;
+        }
+        Searcher::Field(field, value)
+    } else if let Some(key) = part.strip_prefix("missing:") {
+        if key.is_empty() {
+            return None;
+        }
+        Searcher::Missing(key.to_owned())
+    } else {
+        Searcher::NamePart(part.to_owned())
+    };
+    Some(searcher)
+}
+
+fn total_time(meta: &Metadata, converter: &Converter) -> Option<u32> {
+    meta.time(converter).map(|t| t.total())
+}
+
+/// Parses `90`, `90m`, `1h30m`, `2 hours`... into minutes
+fn parse_minutes(s: &str) -> Option<u32> {
+    let mut total = 0;
+    let mut rest = s.trim();
+    if rest.is_empty() {
+        return None;
+    }
+    while !rest.is_empty() {
+        let digits = rest
+            .find(|c: char| !c.is_ascii_digit())
+            .unwrap_or(rest.len());
+        if digits == 0 {
+            return None;
+        }
+        let n: u32 = rest[..digits].parse().ok()?;
+        rest = rest[digits..].trim_start();
+        let unit = rest
+            .find(|c: char| !c.is_alphabetic())
+            .unwrap_or(rest.len());
+        let factor = match &rest[..unit] {
+            "" | "m" | "min" | "mins" | "minute" | "minutes" => 1,
+            "h" | "hr" | "hrs" | "hour" | "hours" => 60,
+            _ => return None,
+        };
+        total += n.checked_mul(factor)?;
+        rest = rest[unit..].trim_start();
+    }
+    Some(total)
+}
+
+fn format_minutes(minutes: u32) -> String {
+    match (minutes / 60, minutes % 60) {
+        (0, m) => format!("{m}m"),
+        (h, 0) => format!("{h}h"),
+        (h, m) => format!("{h}h{m}m"),
+    }
+}
+
+/// Balances parenthesis in the query.
+fn error_correct_query(query: &str) -> String {
+    let mut depth = 0;
+    let mut pad_left = 0;
+    for ch in query.chars() {
+        if ch == '(' {
+            depth += 1;
+        }
+        if ch == ')' {
+            if depth == 0 {
+                pad_left += 1;
+            } else {
+                depth -= 1;
+            }
+        }
+    }
+    let mut working_string = "(".repeat(pad_left);
+    working_string.push_str(query);
+    working_string.push_str(&")".repeat(depth));
+    working_string
+}
+
+fn parse_disjunct_chunks(query: &str) -> Vec<&str> {
+    let mut depth = 0;
+    let mut from = 0;
+    let mut output = Vec::new();
+    for (to, ch) in query.char_indices() {
+        if ch == '(' {
+            depth += 1;
+        } else if ch == ')' {
+            depth -= 1;
+        } else if depth == 0 && ch == '|' {
+            if from != to {
+                output.push(query[from..to].trim())
+            }
+            from = to + 1;
+        }
+    }
+    if from < query.len() {
+        output.push(query[from..query.len()].trim());
+    }
+    output
+}
+
+fn parse_conjunct_chunks(query: &str) -> Vec<&str> {
+    let mut depth = 0;
+    let mut from = 0;
+    let mut output = Vec::new();
+    for (to, ch) in query.char_indices() {
+        if ch == '(' {
+            depth += 1;
+        } else if ch == ')' {
+            depth -= 1;
+        } else if depth == 0 && ch.is_whitespace() {
+            if from != to {
+                output.push(query[from..to].trim())
+            }
+            from = to + 1;
+        }
+    }
+    if from < query.len() {
+        output.push(query[from..query.len()].trim());
+    }
+    output
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_error_correct_query() {
+        assert_eq!(error_correct_query("a b c"), "a b c");
+        assert_eq!(error_correct_query("a | c"), "a | c");
+        assert_eq!(error_correct_query("(b c"), "(b c)");
+        assert_eq!(error_correct_query("(a b)"), "(a b)");
+        assert_eq!(error_correct_query("a | (b | c)"), "a | (b | c)");
+        assert_eq!(error_correct_query("b) c"), "(b) c");
+    }
+
+    #[test]
+    fn parse_terms() {
+        use Searcher::*;
+        assert_eq!(Searcher::parse("pasta"), NamePart("pasta".into()));
+        assert_eq!(
+            Searcher::parse("tag:quick time:1h30m missing:servings"),
+            All(vec![
+                Tag("quick".into()),
+                Time(90),
+                Missing("servings".into())
+            ])
+        );
+        assert_eq!(
+            Searcher::parse("!ingredient:egg | cookware:wok+pan"),
+            Any(vec![
+                Not(Box::new(Ingredient("egg".into()))),
+                Cookware("wok pan".into())
+            ])
+        );
+        // invalid terms are ignored
+        assert_eq!(Searcher::parse("time:soon pasta"), NamePart("pasta".into()));
+        assert_eq!(Searcher::parse("missing: pasta"), NamePart("pasta".into()));
+        assert_eq!(
+            Searcher::parse("course:main+course cuisine:Peruvian"),
+            All(vec![
+                Field(MetaField::Course, "main".into()),
+                Field(MetaField::Cuisine, "peruvian".into())
+            ])
+        );
+    }
+
+    #[test]
+    fn round_trip() {
+        for q in [
+            "pasta tag:quick",
+            "a | b",
+            "(a | b) !c",
+            "time:45m missing:tag",
+            "course:main !difficulty:hard",
+            "time:2h | !(tag:slow missing:time)",
+        ] {
+            assert_eq!(Searcher::parse(q).to_query(), q);
+        }
+    }
+
+    #[test]
+    fn minutes() {
+        assert_eq!(parse_minutes("30"), Some(30));
+        assert_eq!(parse_minutes("30min"), Some(30));
+        assert_eq!(parse_minutes("1h"), Some(60));
+        assert_eq!(parse_minutes("1h30m"), Some(90));
+        assert_eq!(parse_minutes("2 hours"), Some(120));
+        assert_eq!(parse_minutes(""), None);
+        assert_eq!(parse_minutes("h"), None);
+        assert_eq!(parse_minutes("3 days"), None);
+        assert_eq!(format_minutes(90), "1h30m");
+        assert_eq!(format_minutes(45), "45m");
+        assert_eq!(format_minutes(120), "2h");
+    }
+
+    #[test]
+    fn edit_terms() {
+        let q = Searcher::parse("pasta tag:quick");
+        assert_eq!(q.without_term(0).to_query(), "tag:quick");
+        assert_eq!(
+            q.and(Searcher::Time(30)).to_query(),
+            "pasta tag:quick time:30m"
+        );
+        let q = Searcher::parse("a | b");
+        assert_eq!(q.terms().len(), 1);
+        assert_eq!(q.and(Searcher::Time(30)).to_query(), "(a | b) time:30m");
+    }
+}
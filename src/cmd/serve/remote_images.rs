@@ -0,0 +1,80 @@
+//! Images of the `image` metadata that are in other sites
+//!
+//! Images downloaded with `chef recipe image fetch` are served from the
+//! collection. The rest are loaded by the browser from the other site, unless
+//! `ui.proxy_images` is set. Then the server downloads them, keeping a copy if
+//! it can write to the collection, so the other site never sees the clients.
+
+use std::{collections::HashMap, sync::RwLock};
+
+use anyhow::{Context as _, Result};
+use camino::Utf8PathBuf;
+
+use crate::remote_image;
+
+pub struct RemoteImages {
+    base_path: Utf8PathBuf,
+    proxy: bool,
+    writable: bool,
+    /// Exported sites link to the other sites
+    hotlink_only: bool,
+    /// Urls by key, only the ones in recipes can be proxied
+    urls: RwLock<HashMap<String, String>>,
+}
+
+impl RemoteImages {
+    pub fn new(base_path: Utf8PathBuf, proxy: bool, writable: bool, hotlink_only: bool) -> Self {
+        Self {
+            base_path,
+            proxy,
+            writable,
+            hotlink_only,
+            urls: Default::default(),
+        }
+    }
+
+    /// Url to show an image of the metadata
+    ///
+    /// Paths and other urls are not changed.
+    pub fn url(&self, image: &str, base_url: &str) -> String {
+        if self.hotlink_only || !remote_image::is_remote(image) {
+            return image.to_string();
+        }
+        let key = remote_image::cache_key(image);
+        if !self.proxy && remote_image::cached(&self.base_path, &key).is_none() {
+            return image.to_string();
+        }
+        let url = format!("{base_url}/remote_image/{key}");
+        self.urls.write().unwrap().insert(key, image.to_string());
+        url
+    }
+
+    /// Bytes and extension of an image, downloading it if it's not saved
+    ///
+    /// This blocks. `None` if the key is not of a known image.
+    pub fn load(&self, key: &str) -> Option<Result<(Vec<u8>, String)>> {
+        if !is_valid_key(key) {
+            return None;
+        }
+        if let Some(path) = remote_image::cached(&self.base_path, key) {
+            let ext = path.extension().unwrap_or_default().to_string();
+            let bytes = std::fs::read(&path).with_context(|| format!("Failed to read '{path}'"));
+            return Some(bytes.map(|b| (b, ext)));
+        }
+        let url = self.urls.read().unwrap().get(key).cloned()?;
+        let fetched = remote_image::fetch(&url).map(|(bytes, ext)| {
+            if self.writable {
+                if let Err(e) = remote_image::save(&self.base_path, key, ext, &bytes) {
+                    tracing::warn!("Could not save the image of {url}: {e:#}");
+                }
+            }
+            (bytes, ext.to_string())
+        });
+        Some(fetched)
+    }
+}
+
+/// Keys are made by [`remote_image::cache_key`], anything else could be a path
+fn is_valid_key(key: &str) -> bool {
+    key.len() == 16 && key.bytes().all(|b| b.is_ascii_hexdigit())
+}
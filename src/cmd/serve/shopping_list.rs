@@ -0,0 +1,111 @@
+//! Shopping list of every client
+//!
+//! A list of recipes with the servings to cook, stored in the database by
+//! [`ClientId`](super::users::ClientId) like the favorites. The ingredients are
+//! added up when the list is shown, so it follows the changes to the recipes.
+
+use std::sync::Arc;
+
+use anyhow::Result;
+use redb::TableDefinition;
+use serde::{Deserialize, Serialize};
+
+use super::db::{Db, Table};
+
+const TABLE: Table = TableDefinition::new("shopping_lists");
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ListEntry {
+    /// Path relative to the collection without extension
+    pub recipe: String,
+    /// The default of the recipe if `None`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub servings: Option<u32>,
+}
+
+impl ListEntry {
+    /// Line of a `chef shopping-list --plan` file
+    pub fn plan_line(&self) -> String {
+        match self.servings {
+            Some(servings) => format!("{}*{servings}", self.recipe),
+            None => self.recipe.clone(),
+        }
+    }
+}
+
+pub struct ShoppingListStore {
+    db: Arc<Db>,
+}
+
+impl ShoppingListStore {
+    pub fn new(db: Arc<Db>) -> Self {
+        Self { db }
+    }
+
+    /// Recipes in the list of a client, in the order they were added
+    pub fn get(&self, id: Option<&str>) -> Vec<ListEntry> {
+        let Some(id) = id else {
+            return Vec::new();
+        };
+        self.db
+            .get(TABLE, id)
+            .unwrap_or_else(|e| {
+                tracing::error!("Could not read the shopping list: {e:#}");
+                None
+            })
+            .unwrap_or_default()
+    }
+
+    /// Adds a recipe or removes it if it's in the list, and returns if it's
+    /// now in the list
+    pub fn toggle(&self, id: &str, recipe: &str, servings: Option<u32>) -> Result<bool> {
+        self.db
+            .update(TABLE, id, |list: &mut Option<Vec<ListEntry>>| {
+                let entries = list.get_or_insert_default();
+                let len = entries.len();
+                entries.retain(|e| e.recipe != recipe);
+                let added = entries.len() == len;
+                if added {
+                    entries.push(ListEntry {
+                        recipe: recipe.to_string(),
+                        servings,
+                    });
+                }
+                if entries.is_empty() {
+                    *list = None;
+                }
+                added
+            })
+    }
+
+    pub fn clear(&self, id: &str) -> Result<()> {
+        self.db
+            .update(TABLE, id, |list: &mut Option<Vec<ListEntry>>| *list = None)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn add_and_remove() {
+        let store = ShoppingListStore::new(Arc::new(Db::in_memory()));
+        assert!(store.toggle("abc", "Pancakes", Some(4)).unwrap());
+        assert!(store.toggle("abc", "Soups/Ramen", None).unwrap());
+        assert!(store.toggle("user:Ana", "Pancakes", None).unwrap());
+        let lines = store
+            .get(Some("abc"))
+            .iter()
+            .map(ListEntry::plan_line)
+            .collect::<Vec<_>>();
+        assert_eq!(lines, ["Pancakes*4", "Soups/Ramen"]);
+
+        assert!(!store.toggle("abc", "Pancakes", None).unwrap());
+        assert_eq!(store.get(Some("abc")).len(), 1);
+        store.clear("abc").unwrap();
+        assert!(store.get(Some("abc")).is_empty());
+        assert_eq!(store.get(Some("user:Ana")).len(), 1);
+        assert!(store.get(None).is_empty());
+    }
+}
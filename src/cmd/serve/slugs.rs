@@ -0,0 +1,176 @@
+//! Short and stable urls for recipes
+//!
+//! With `ui.slugs`, recipe pages are at `/r/<slug>`, made from the file name,
+//! like `creme-brulee` for `Desserts/Crème Brûlée.cook`. The dir is not part
+//! of the slug, so moving a recipe keeps its url. The slug of a renamed
+//! recipe redirects to the new one while the server runs.
+
+use std::collections::HashMap;
+
+use camino::{Utf8Path, Utf8PathBuf};
+
+/// Used when nothing of the name can be in a slug
+const FALLBACK_SLUG: &str = "recipe";
+
+#[derive(Debug, PartialEq)]
+pub enum SlugLookup {
+    /// Path of the recipe with the slug
+    Found(Utf8PathBuf),
+    /// The recipe was renamed and has this slug now
+    Moved(String),
+}
+
+#[derive(Debug, Default)]
+pub struct Slugs {
+    by_slug: HashMap<String, Utf8PathBuf>,
+    by_path: HashMap<Utf8PathBuf, String>,
+    /// Slugs of renamed recipes to their current path
+    moved: HashMap<String, Utf8PathBuf>,
+}
+
+impl Slugs {
+    /// Gives a slug to every recipe
+    ///
+    /// When names collide, the recipe found first by name, the outermost and
+    /// then alphabetically first, gets the slug without a number.
+    pub fn new(paths: impl IntoIterator<Item = Utf8PathBuf>) -> Self {
+        let mut paths = paths.into_iter().collect::<Vec<_>>();
+        paths.sort_unstable_by(|a, b| {
+            let depth = |p: &Utf8Path| p.components().count();
+            depth(a).cmp(&depth(b)).then_with(|| a.cmp(b))
+        });
+        let mut slugs = Self::default();
+        for path in &paths {
+            slugs.insert(path);
+        }
+        slugs
+    }
+
+    pub fn slug(&self, path: &Utf8Path) -> Option<&str> {
+        self.by_path.get(path).map(String::as_str)
+    }
+
+    pub fn lookup(&self, slug: &str) -> Option<SlugLookup> {
+        if let Some(path) = self.by_slug.get(slug) {
+            return Some(SlugLookup::Found(path.clone()));
+        }
+        let path = self.moved.get(slug)?;
+        self.slug(path).map(|s| SlugLookup::Moved(s.to_string()))
+    }
+
+    /// Adds a recipe, existing ones keep their slug
+    pub fn insert(&mut self, path: &Utf8Path) {
+        if self.by_path.contains_key(path) {
+            return;
+        }
+        let slug = self.free_slug(slugify(path.file_stem().unwrap_or_default()));
+        self.moved.remove(&slug);
+        self.by_slug.insert(slug.clone(), path.to_owned());
+        self.by_path.insert(path.to_owned(), slug);
+    }
+
+    pub fn remove(&mut self, path: &Utf8Path) -> Option<String> {
+        let slug = self.by_path.remove(path)?;
+        self.by_slug.remove(&slug);
+        self.moved.retain(|_, p| p != path);
+        Some(slug)
+    }
+
+    /// Moves a recipe, keeping the slug if the name is the same and
+    /// redirecting the old one if not
+    pub fn rename(&mut self, from: &Utf8Path, to: &Utf8Path) {
+        let Some(old) = self.by_path.remove(from) else {
+            self.insert(to);
+            return;
+        };
+        self.by_slug.remove(&old);
+        for path in self.moved.values_mut() {
+            if path == from {
+                *path = to.to_owned();
+            }
+        }
+        let same_name = slugify(from.file_stem().unwrap_or_default())
+            == slugify(to.file_stem().unwrap_or_default());
+        if same_name && !self.by_slug.contains_key(&old) {
+            self.by_slug.insert(old.clone(), to.to_owned());
+            self.by_path.insert(to.to_owned(), old);
+            return;
+        }
+        self.insert(to);
+        self.moved.insert(old, to.to_owned());
+    }
+
+    fn free_slug(&self, slug: String) -> String {
+        if !self.by_slug.contains_key(&slug) {
+            return slug;
+        }
+        (2..)
+            .map(|n| format!("{slug}-{n}"))
+            .find(|s| !self.by_slug.contains_key(s))
+            .unwrap()
+    }
+}
+
+/// Lowercase ASCII letters and numbers separated by `-`
+pub fn slugify(name: &str) -> String {
+    let mut slug = String::with_capacity(name.len());
+    for c in cooklang_fs::fold_name(name).chars() {
+        if c.is_ascii_alphanumeric() {
+            slug.push(c);
+        } else if !slug.is_empty() && !slug.ends_with('-') {
+            slug.push('-');
+        }
+    }
+    let slug = slug.trim_end_matches('-');
+    if slug.is_empty() {
+        FALLBACK_SLUG.to_string()
+    } else {
+        slug.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn p(s: &str) -> &Utf8Path {
+        Utf8Path::new(s)
+    }
+
+    #[test]
+    fn slug() {
+        assert_eq!(slugify("Crème Brûlée"), "creme-brulee");
+        assert_eq!(slugify("  Mac & Cheese (v2) "), "mac-cheese-v2");
+        assert_eq!(slugify("餃子"), "recipe");
+    }
+
+    #[test]
+    fn collisions_and_moves() {
+        let mut slugs = Slugs::new(["b/Pasta.cook".into(), "Pasta.cook".into()]);
+        assert_eq!(slugs.slug(p("Pasta.cook")), Some("pasta"));
+        assert_eq!(slugs.slug(p("b/Pasta.cook")), Some("pasta-2"));
+
+        // moving keeps the slug
+        slugs.rename(p("b/Pasta.cook"), p("c/Pasta.cook"));
+        assert_eq!(slugs.slug(p("c/Pasta.cook")), Some("pasta-2"));
+
+        // renaming redirects the old one
+        slugs.rename(p("Pasta.cook"), p("Pizza.cook"));
+        assert_eq!(
+            slugs.lookup("pasta"),
+            Some(SlugLookup::Moved("pizza".into()))
+        );
+        slugs.rename(p("Pizza.cook"), p("Calzone.cook"));
+        assert_eq!(
+            slugs.lookup("pasta"),
+            Some(SlugLookup::Moved("calzone".into()))
+        );
+
+        // a new recipe takes the free slug
+        slugs.insert(p("Pasta.cook"));
+        assert_eq!(
+            slugs.lookup("pasta"),
+            Some(SlugLookup::Found("Pasta.cook".into()))
+        );
+    }
+}
@@ -0,0 +1,99 @@
+//! HTTPS with rustls
+//!
+//! With a certificate the UI can be used on a LAN without a reverse proxy in
+//! front. Plain HTTP can be redirected to HTTPS listening on another port.
+
+use std::net::SocketAddr;
+
+use anyhow::{Context as _, Result};
+use axum::{
+    extract::State,
+    http::{header::HOST, HeaderMap, StatusCode, Uri},
+    response::{IntoResponse, Redirect, Response},
+    Router,
+};
+use axum_server::tls_rustls::RustlsConfig;
+use camino::Utf8Path;
+use tracing::info;
+
+/// Serves the app with HTTPS and, with a `redirect_port`, redirects HTTP
+/// requests on that port to it
+pub async fn serve(
+    app: Router,
+    addr: SocketAddr,
+    cert: &Utf8Path,
+    key: &Utf8Path,
+    redirect_port: Option<u16>,
+) -> Result<()> {
+    let config = RustlsConfig::from_pem_file(cert, key)
+        .await
+        .with_context(|| format!("Failed to load the certificate '{cert}' and key '{key}'"))?;
+
+    if let Some(port) = redirect_port {
+        let redirect_addr = SocketAddr::new(addr.ip(), port);
+        let listener = tokio::net::TcpListener::bind(&redirect_addr)
+            .await
+            .with_context(|| format!("Failed to listen on {redirect_addr}"))?;
+        let redirect = Router::new()
+            .fallback(redirect_to_https)
+            .with_state(addr.port());
+        info!("Redirecting HTTP on {redirect_addr} to HTTPS");
+        tokio::task::spawn(async move {
+            if let Err(e) = axum::serve(listener, redirect).await {
+                tracing::error!("HTTP redirect server stopped: {e}");
+            }
+        });
+    }
+
+    axum_server::bind_rustls(addr, config)
+        .serve(app.into_make_service_with_connect_info::<SocketAddr>())
+        .await
+        .context("HTTPS server error")
+}
+
+async fn redirect_to_https(State(port): State<u16>, headers: HeaderMap, uri: Uri) -> Response {
+    let Some(host) = headers.get(HOST).and_then(|h| h.to_str().ok()) else {
+        return StatusCode::BAD_REQUEST.into_response();
+    };
+    let path = uri.path_and_query().map(|p| p.as_str()).unwrap_or("/");
+    Redirect::permanent(&https_url(host, port, path)).into_response()
+}
+
+/// Same host and path with `https` and the HTTPS port
+fn https_url(host: &str, port: u16, path: &str) -> String {
+    let host = match host.rsplit_once(':') {
+        // IPv6 addresses are in brackets, their colons are not a port
+        Some((name, p))
+            if !p.is_empty()
+                && p.chars().all(|c| c.is_ascii_digit())
+                && (!name.contains(':') || name.ends_with(']')) =>
+        {
+            name
+        }
+        _ => host,
+    };
+    if port == 443 {
+        format!("https://{host}{path}")
+    } else {
+        format!("https://{host}:{port}{path}")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn redirect_url() {
+        assert_eq!(
+            https_url("example.com", 443, "/r/Pasta?scale=2"),
+            "https://example.com/r/Pasta?scale=2"
+        );
+        assert_eq!(
+            https_url("192.168.1.10:8081", 8443, "/"),
+            "https://192.168.1.10:8443/"
+        );
+        assert_eq!(https_url("[::1]:80", 8443, "/"), "https://[::1]:8443/");
+        assert_eq!(https_url("[::1]", 443, "/"), "https://[::1]/");
+    }
+}
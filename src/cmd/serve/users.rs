@@ -0,0 +1,500 @@
+//! Named users of the web UI
+//!
+//! With `ui.users`, people can log in with their name, and a password if it
+//! has one, so their favorites and shopping list follow them to any browser
+//! and the cook log says who cooked. Without logging in, they are kept by a
+//! random id in a cookie of the browser. Sessions are stored in a file in the
+//! collection, so restarting the server does not log anyone out.
+//!
+//! Passwords in the config are argon2 hashes, made with
+//! `chef serve --hash-password`. After [`MAX_FAILED_LOGINS`] wrong ones, an
+//! address has to wait [`LOGIN_LOCKOUT`] to try again.
+//!
+//! Sessions expire after [`SESSION_MAX_IDLE`] without being used, and each user
+//! keeps the [`MAX_SESSIONS`] last used ones.
+
+use std::{collections::HashMap, net::IpAddr, sync::Mutex};
+
+use argon2::{
+    password_hash::{rand_core::OsRng, PasswordHash, PasswordHasher, PasswordVerifier, SaltString},
+    Argon2,
+};
+use axum::{
+    extract::FromRequestParts,
+    http::{request::Parts, HeaderMap},
+};
+use camino::{Utf8Path, Utf8PathBuf};
+use serde::{Deserialize, Serialize};
+
+use crate::config::UserConfig;
+
+use super::{get_cookie, S};
+
+pub const COOKIE: &str = "chef_session";
+/// Random id of a browser, for clients that are not logged in
+pub const CLIENT_COOKIE: &str = "chef_id";
+
+/// Seconds a session lasts without being used
+pub const SESSION_MAX_IDLE: u64 = 30 * 24 * 60 * 60;
+/// Sessions of a user, the least recently used are removed on login
+const MAX_SESSIONS: usize = 10;
+/// The last use of a session is only saved to the file with this precision
+const LAST_SEEN_PRECISION: u64 = 60 * 60;
+/// Random bytes of the tokens of sessions and clients
+const TOKEN_BYTES: usize = 16;
+/// Wrong logins from an address before it has to wait
+pub const MAX_FAILED_LOGINS: u32 = 5;
+/// Seconds an address has to wait after too many wrong logins
+pub const LOGIN_LOCKOUT: u64 = 15 * 60;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+struct Session {
+    user: String,
+    /// Seconds since the epoch
+    created: u64,
+    /// Seconds since the epoch
+    last_seen: u64,
+}
+
+impl Session {
+    fn is_expired(&self, now: u64) -> bool {
+        now.saturating_sub(self.last_seen) > SESSION_MAX_IDLE
+    }
+}
+
+/// Wrong logins from an address
+struct FailedLogins {
+    count: u32,
+    /// Seconds since the epoch of the last one
+    last: u64,
+}
+
+#[derive(Debug)]
+pub enum LoginError {
+    /// Unknown name or wrong password
+    Wrong,
+    /// Too many wrong logins from the address
+    Throttled,
+    /// The session could not be created
+    Internal,
+}
+
+pub struct Users {
+    users: Vec<UserConfig>,
+    path: Utf8PathBuf,
+    /// Changes are only kept in memory when `false`
+    writable: bool,
+    /// By session token
+    sessions: Mutex<HashMap<String, Session>>,
+    failed: Mutex<HashMap<IpAddr, FailedLogins>>,
+}
+
+impl Users {
+    /// Loads the sessions file, if it exists, without the expired sessions
+    pub fn load(path: Utf8PathBuf, users: Vec<UserConfig>, writable: bool) -> Self {
+        let mut sessions: HashMap<String, Session> = match std::fs::read_to_string(&path) {
+            Ok(content) => serde_json::from_str(&content).unwrap_or_else(|e| {
+                tracing::warn!("Ignoring invalid sessions file '{path}': {e}");
+                HashMap::new()
+            }),
+            Err(_) => HashMap::new(),
+        };
+        let now = now_secs();
+        sessions.retain(|_, s| !s.is_expired(now));
+        for user in &users {
+            if user
+                .password
+                .as_deref()
+                .is_some_and(|p| PasswordHash::new(p).is_err())
+            {
+                tracing::warn!(
+                    "The password of '{}' is not a hash from `chef serve --hash-password`, \
+                     the user can't log in",
+                    user.name
+                );
+            }
+        }
+        Self {
+            users,
+            path,
+            writable,
+            sessions: Mutex::new(sessions),
+            failed: Mutex::new(HashMap::new()),
+        }
+    }
+
+    pub fn enabled(&self) -> bool {
+        !self.users.is_empty()
+    }
+
+    /// Starts a session and returns its token, if the name and password are
+    /// right
+    ///
+    /// Checking the password is slow on purpose, so this blocks.
+    pub fn login(&self, name: &str, password: &str, who: IpAddr) -> Result<String, LoginError> {
+        self.login_at(name, password, who, now_secs())
+    }
+
+    fn login_at(
+        &self,
+        name: &str,
+        password: &str,
+        who: IpAddr,
+        now: u64,
+    ) -> Result<String, LoginError> {
+        {
+            let mut failed = self.failed.lock().unwrap();
+            failed.retain(|_, f| now.saturating_sub(f.last) <= LOGIN_LOCKOUT);
+            if failed
+                .get(&who)
+                .is_some_and(|f| f.count >= MAX_FAILED_LOGINS)
+            {
+                return Err(LoginError::Throttled);
+            }
+        }
+        let user = self
+            .users
+            .iter()
+            .find(|u| u.name == name)
+            .filter(|u| check_password(u, password));
+        let Some(user) = user else {
+            let mut failed = self.failed.lock().unwrap();
+            let f = failed.entry(who).or_insert(FailedLogins {
+                count: 0,
+                last: now,
+            });
+            f.count += 1;
+            f.last = now;
+            return Err(LoginError::Wrong);
+        };
+        self.failed.lock().unwrap().remove(&who);
+
+        let token = random_token().map_err(|e| {
+            tracing::error!("Could not create a session token: {e}");
+            LoginError::Internal
+        })?;
+        let mut sessions = self.sessions.lock().unwrap();
+        sessions.retain(|_, s| !s.is_expired(now));
+        let mut own = sessions
+            .iter()
+            .filter(|(_, s)| s.user == user.name)
+            .map(|(token, s)| (s.last_seen, token.clone()))
+            .collect::<Vec<_>>();
+        if own.len() >= MAX_SESSIONS {
+            own.sort_unstable();
+            for (_, old) in &own[..=own.len() - MAX_SESSIONS] {
+                sessions.remove(old);
+            }
+        }
+        sessions.insert(
+            token.clone(),
+            Session {
+                user: user.name.clone(),
+                created: now,
+                last_seen: now,
+            },
+        );
+        self.save(&sessions);
+        Ok(token)
+    }
+
+    pub fn logout(&self, token: &str) {
+        let mut sessions = self.sessions.lock().unwrap();
+        if sessions.remove(token).is_some() {
+            self.save(&sessions);
+        }
+    }
+
+    /// User of a session, if it has not expired and the user is still in the
+    /// config
+    pub fn user(&self, token: &str) -> Option<String> {
+        self.user_at(token, now_secs())
+    }
+
+    fn user_at(&self, token: &str, now: u64) -> Option<String> {
+        let mut sessions = self.sessions.lock().unwrap();
+        let session = sessions.get_mut(token)?;
+        if session.is_expired(now) {
+            sessions.remove(token);
+            self.save(&sessions);
+            return None;
+        }
+        let name = session.user.clone();
+        if now.saturating_sub(session.last_seen) > LAST_SEEN_PRECISION {
+            session.last_seen = now;
+            self.save(&sessions);
+        }
+        drop(sessions);
+        self.users.iter().any(|u| u.name == name).then_some(name)
+    }
+
+    fn save(&self, sessions: &HashMap<String, Session>) {
+        if !self.writable {
+            return;
+        }
+        if let Err(e) = save(&self.path, sessions) {
+            tracing::error!("Could not save the sessions: {e}");
+        }
+    }
+}
+
+fn save(path: &Utf8Path, sessions: &HashMap<String, Session>) -> std::io::Result<()> {
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    let content = serde_json::to_string_pretty(sessions)?;
+    std::fs::write(path, content)
+}
+
+/// If the password is right, users without one need none
+///
+/// The comparison takes the same time for any wrong password.
+fn check_password(user: &UserConfig, password: &str) -> bool {
+    let Some(hash) = &user.password else {
+        return true;
+    };
+    let Ok(hash) = PasswordHash::new(hash) else {
+        return false;
+    };
+    Argon2::default()
+        .verify_password(password.as_bytes(), &hash)
+        .is_ok()
+}
+
+/// Argon2 hash of a password, for `ui.users`
+pub fn hash_password(password: &str) -> anyhow::Result<String> {
+    let salt = SaltString::generate(&mut OsRng);
+    let hash = Argon2::default()
+        .hash_password(password.as_bytes(), &salt)
+        .map_err(|e| anyhow::anyhow!("Could not hash the password: {e}"))?;
+    Ok(hash.to_string())
+}
+
+/// Random token from the OS, hex encoded, for sessions and the favorites of a
+/// browser
+pub fn random_token() -> Result<String, getrandom::Error> {
+    let mut bytes = [0; TOKEN_BYTES];
+    getrandom::getrandom(&mut bytes)?;
+    Ok(bytes.iter().map(|b| format!("{b:02x}")).collect())
+}
+
+fn now_secs() -> u64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs()
+}
+
+/// Key of the favorites and shopping list of a user, browsers ids are only
+/// hex digits so they are never the same
+pub fn client_key(name: &str) -> String {
+    format!("user:{name}")
+}
+
+/// Session token from the cookie, if any
+pub fn session_token(headers: &HeaderMap) -> Option<&str> {
+    get_cookie(headers, COOKIE)
+        .filter(|token| !token.is_empty() && token.chars().all(|c| c.is_ascii_hexdigit()))
+}
+
+/// Logged in user, if any
+pub struct CurrentUser(pub Option<String>);
+
+impl FromRequestParts<S> for CurrentUser {
+    type Rejection = std::convert::Infallible;
+
+    async fn from_request_parts(parts: &mut Parts, state: &S) -> Result<Self, Self::Rejection> {
+        let user = session_token(&parts.headers).and_then(|token| state.users.user(token));
+        Ok(Self(user))
+    }
+}
+
+/// Key of the favorites and shopping list of the client, of the logged in
+/// user or from the cookie, if any
+pub struct ClientId(pub Option<String>);
+
+impl FromRequestParts<S> for ClientId {
+    type Rejection = std::convert::Infallible;
+
+    async fn from_request_parts(parts: &mut Parts, state: &S) -> Result<Self, Self::Rejection> {
+        let user = session_token(&parts.headers).and_then(|token| state.users.user(token));
+        if let Some(user) = user {
+            return Ok(Self(Some(client_key(&user))));
+        }
+        let id = get_cookie(&parts.headers, CLIENT_COOKIE)
+            .filter(|id| !id.is_empty() && id.chars().all(|c| c.is_ascii_hexdigit()))
+            .map(String::from);
+        Ok(Self(id))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const IP: IpAddr = IpAddr::V4(std::net::Ipv4Addr::LOCALHOST);
+    /// Of `1234`
+    const HASH: &str =
+        "$argon2id$v=19$m=19456,t=2,p=1$jcJdt7P2LutfGYiAgaQP8A$oU3P50GkRBYjkoQVrZy4siUFsEedME6ryJvlagMrL3E";
+
+    fn users(dir: &tempfile::TempDir) -> Users {
+        let path = Utf8Path::from_path(dir.path())
+            .unwrap()
+            .join("sessions.json");
+        let config = vec![
+            UserConfig {
+                name: "Ana".into(),
+                password: None,
+            },
+            UserConfig {
+                name: "Ben".into(),
+                password: Some(HASH.into()),
+            },
+        ];
+        Users::load(path, config, true)
+    }
+
+    #[test]
+    fn login() {
+        let dir = tempfile::tempdir().unwrap();
+        let users = users(&dir);
+        assert!(users.enabled());
+        assert!(users.login("Ben", "wrong", IP).is_err());
+        assert!(users.login("Carla", "", IP).is_err());
+        let token = users.login("Ben", "1234", IP).unwrap();
+        assert_eq!(token.len(), TOKEN_BYTES * 2);
+        assert!(token.chars().all(|c| c.is_ascii_hexdigit()));
+        let other = users.login("Ana", "anything", IP).unwrap();
+        assert_ne!(token, other);
+
+        assert_eq!(users.user(&token).as_deref(), Some("Ben"));
+        assert_eq!(users.user(&other).as_deref(), Some("Ana"));
+        assert_eq!(users.user("0123abcd"), None);
+    }
+
+    #[test]
+    fn logout() {
+        let dir = tempfile::tempdir().unwrap();
+        let users = users(&dir);
+        let token = users.login("Ana", "", IP).unwrap();
+        let other = users.login("Ana", "", IP).unwrap();
+        users.logout(&token);
+        assert_eq!(users.user(&token), None);
+        assert_eq!(users.user(&other).as_deref(), Some("Ana"));
+        users.logout("0123abcd");
+    }
+
+    #[test]
+    fn persistence() {
+        let dir = tempfile::tempdir().unwrap();
+        let token = users(&dir).login("Ana", "", IP).unwrap();
+        let users = users(&dir);
+        assert_eq!(users.user(&token).as_deref(), Some("Ana"));
+
+        // removed from the config
+        let users = Users::load(users.path.clone(), vec![], true);
+        assert!(!users.enabled());
+        assert_eq!(users.user(&token), None);
+    }
+
+    #[test]
+    fn expiry() {
+        let dir = tempfile::tempdir().unwrap();
+        let users = users(&dir);
+        let now = now_secs();
+        let token = users.login_at("Ana", "", IP, now).unwrap();
+        let used = now + SESSION_MAX_IDLE - 10;
+        assert_eq!(users.user_at(&token, used).as_deref(), Some("Ana"));
+        // the use renewed it
+        let later = used + SESSION_MAX_IDLE - 10;
+        assert_eq!(users.user_at(&token, later).as_deref(), Some("Ana"));
+        assert_eq!(users.user_at(&token, later + SESSION_MAX_IDLE + 1), None);
+        assert!(users.sessions.lock().unwrap().is_empty());
+
+        // dropped on load
+        let session = |last_seen| Session {
+            user: "Ana".into(),
+            created: last_seen,
+            last_seen,
+        };
+        let sessions = HashMap::from([
+            ("aa".to_string(), session(now - SESSION_MAX_IDLE - 1)),
+            ("bb".to_string(), session(now - 10)),
+        ]);
+        save(&users.path, &sessions).unwrap();
+        let users = Users::load(users.path.clone(), users.users.clone(), true);
+        assert_eq!(users.sessions.lock().unwrap().len(), 1);
+        assert_eq!(users.user("aa"), None);
+        assert_eq!(users.user("bb").as_deref(), Some("Ana"));
+    }
+
+    #[test]
+    fn session_cap() {
+        let dir = tempfile::tempdir().unwrap();
+        let users = users(&dir);
+        let now = now_secs();
+        let tokens = (0..MAX_SESSIONS + 2)
+            .map(|i| users.login_at("Ana", "", IP, now + i as u64).unwrap())
+            .collect::<Vec<_>>();
+        let ben = users.login_at("Ben", "1234", IP, now).unwrap();
+        assert_eq!(users.sessions.lock().unwrap().len(), MAX_SESSIONS + 1);
+        assert_eq!(users.user(&tokens[0]), None);
+        assert_eq!(users.user(&tokens[1]), None);
+        assert!(tokens[2..].iter().all(|t| users.user(t).is_some()));
+        assert_eq!(users.user(&ben).as_deref(), Some("Ben"));
+    }
+
+    #[test]
+    fn invalid_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let users = users(&dir);
+        std::fs::write(&users.path, "{ not json").unwrap();
+        let users = Users::load(users.path.clone(), users.users.clone(), true);
+        assert!(users.sessions.lock().unwrap().is_empty());
+        let token = users.login("Ana", "", IP).unwrap();
+        assert_eq!(users.user(&token).as_deref(), Some("Ana"));
+        let content = std::fs::read_to_string(&users.path).unwrap();
+        assert!(serde_json::from_str::<HashMap<String, Session>>(&content).is_ok());
+    }
+
+    #[test]
+    fn passwords() {
+        let hash = hash_password("secret").unwrap();
+        assert!(hash.starts_with("$argon2id$"));
+        let user = |password: Option<&str>| UserConfig {
+            name: "Ana".into(),
+            password: password.map(Into::into),
+        };
+        assert!(check_password(&user(Some(&hash)), "secret"));
+        assert!(!check_password(&user(Some(&hash)), "secreto"));
+        assert!(check_password(&user(None), "anything"));
+        // plain text passwords are not hashes
+        assert!(!check_password(&user(Some("secret")), "secret"));
+    }
+
+    #[test]
+    fn throttle() {
+        let dir = tempfile::tempdir().unwrap();
+        let users = users(&dir);
+        let other = IpAddr::V4(std::net::Ipv4Addr::new(10, 0, 0, 2));
+        let now = now_secs();
+        for _ in 0..MAX_FAILED_LOGINS {
+            assert!(matches!(
+                users.login_at("Carla", "", IP, now),
+                Err(LoginError::Wrong)
+            ));
+        }
+        // even with the right password
+        assert!(matches!(
+            users.login_at("Ben", "1234", IP, now),
+            Err(LoginError::Throttled)
+        ));
+        assert!(users.login_at("Ben", "1234", other, now).is_ok());
+        let later = now + LOGIN_LOCKOUT + 1;
+        assert!(users.login_at("Ben", "1234", IP, later).is_ok());
+        // a right one resets the count
+        users.login_at("Carla", "", IP, later).unwrap_err();
+        users.login_at("Ana", "", IP, later).unwrap();
+        assert!(users.failed.lock().unwrap().is_empty());
+    }
+}
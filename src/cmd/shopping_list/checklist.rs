@@ -0,0 +1,205 @@
+//! Interactive checklist of a shopping list
+//!
+//! Items are ticked off as they are bought. What is left can be moved to
+//! other categories and saved to a file at any point.
+
+use std::io::IsTerminal;
+
+use anstream::println;
+use anyhow::{bail, Result};
+use camino::Utf8Path;
+use cooklang::{aisle::AisleConf, ingredient_list::IngredientList, quantity::GroupedQuantity};
+use inquire::{MultiSelect, Select, Text};
+
+use crate::{exit_code::ExitError, util::write_to_output};
+
+struct Item {
+    name: String,
+    quantity: String,
+    category: String,
+    done: bool,
+}
+
+impl std::fmt::Display for Item {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.name)?;
+        if !self.quantity.is_empty() {
+            write!(f, ": {}", self.quantity)?;
+        }
+        if !self.category.is_empty() {
+            write!(f, " [{}]", self.category)?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+enum Action {
+    Tick,
+    Move,
+    Save,
+    Quit,
+}
+
+impl std::fmt::Display for Action {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Action::Tick => "Tick off items",
+            Action::Move => "Move an item to another category",
+            Action::Save => "Save the remaining items",
+            Action::Quit => "Quit",
+        })
+    }
+}
+
+pub fn run(
+    list: IngredientList,
+    aisle: &AisleConf,
+    plain: bool,
+    output: Option<&Utf8Path>,
+) -> Result<()> {
+    if !std::io::stdin().is_terminal() {
+        bail!(ExitError::usage("`--interactive` needs a terminal"));
+    }
+
+    let mut items = if plain {
+        list.into_iter()
+            .map(|(name, qty)| item(name, &qty, String::new()))
+            .collect::<Vec<_>>()
+    } else {
+        list.categorize(aisle)
+            .into_iter()
+            .flat_map(|(category, items)| {
+                items
+                    .into_iter()
+                    .map(move |(name, qty)| item(name, &qty, category.clone()))
+            })
+            .collect()
+    };
+    if items.is_empty() {
+        bail!("The shopping list is empty");
+    }
+
+    loop {
+        let remaining = items.iter().filter(|i| !i.done).count();
+        let message = format!("{remaining} of {} items left", items.len());
+        let actions = vec![Action::Tick, Action::Move, Action::Save, Action::Quit];
+        match Select::new(&message, actions).prompt()? {
+            Action::Tick => tick(&mut items)?,
+            Action::Move => move_item(&mut items)?,
+            Action::Save => {
+                let path = match output {
+                    Some(path) => path.to_owned(),
+                    None => Text::new("Save to")
+                        .with_default("shopping-list.txt")
+                        .prompt()?
+                        .into(),
+                };
+                save(&items, Some(&path))?;
+                println!("Saved {remaining} items to '{path}'");
+            }
+            Action::Quit => break,
+        }
+    }
+
+    if items.iter().any(|i| !i.done) {
+        println!("\nRemaining items:");
+        save(&items, None)?;
+    }
+    Ok(())
+}
+
+fn item(name: String, qty: &GroupedQuantity, category: String) -> Item {
+    let quantity = qty
+        .iter()
+        .map(|q| q.to_string())
+        .collect::<Vec<_>>()
+        .join(", ");
+    Item {
+        name,
+        quantity,
+        category,
+        done: false,
+    }
+}
+
+fn tick(items: &mut [Item]) -> Result<()> {
+    let done = items
+        .iter()
+        .enumerate()
+        .filter_map(|(index, i)| i.done.then_some(index))
+        .collect::<Vec<_>>();
+    let selected = MultiSelect::new("Bought", items.iter().collect())
+        .with_default(&done)
+        .with_page_size(15)
+        .raw_prompt()?;
+    for item in items.iter_mut() {
+        item.done = false;
+    }
+    for option in selected {
+        items[option.index].done = true;
+    }
+    Ok(())
+}
+
+fn move_item(items: &mut [Item]) -> Result<()> {
+    let remaining = items
+        .iter()
+        .enumerate()
+        .filter(|(_, i)| !i.done)
+        .map(|(index, _)| index)
+        .collect::<Vec<_>>();
+    if remaining.is_empty() {
+        println!("Every item is ticked off");
+        return Ok(());
+    }
+    let options = remaining.iter().map(|&i| &items[i]).collect();
+    let index = remaining[Select::new("Item", options).raw_prompt()?.index];
+
+    const NEW: &str = "New category...";
+    let mut categories = items
+        .iter()
+        .map(|i| i.category.as_str())
+        .filter(|c| !c.is_empty())
+        .collect::<Vec<_>>();
+    categories.sort_unstable();
+    categories.dedup();
+    categories.push(NEW);
+    let category = match Select::new("Move to", categories).prompt()? {
+        NEW => Text::new("Category").prompt()?.trim().to_string(),
+        category => category.to_string(),
+    };
+    items[index].category = category;
+    Ok(())
+}
+
+/// Writes the items not ticked off, grouped by category
+fn save(items: &[Item], output: Option<&Utf8Path>) -> Result<()> {
+    use yansi::Paint;
+
+    let mut categories = Vec::<(&str, Vec<&Item>)>::new();
+    for item in items.iter().filter(|i| !i.done) {
+        match categories.iter_mut().find(|(c, _)| *c == item.category) {
+            Some((_, items)) => items.push(item),
+            None => categories.push((item.category.as_str(), vec![item])),
+        }
+    }
+
+    let mut table = tabular::Table::new("{:<} {:<}");
+    for (category, items) in categories {
+        if !category.is_empty() {
+            table.add_heading(format!("[{}]", category.green()));
+        }
+        for item in items {
+            table.add_row(
+                tabular::Row::new()
+                    .with_cell(&item.name)
+                    .with_cell(&item.quantity),
+            );
+        }
+    }
+    write_to_output(output, |mut w| {
+        write!(w, "{table}")?;
+        Ok(())
+    })
+}
@@ -0,0 +1,102 @@
+//! Compact text lists, to paste in messaging apps
+//!
+//! Every category is a paragraph with its items separated by commas and the
+//! quantities rounded to what is useful when shopping. There are no colors or
+//! tables, so it looks the same anywhere.
+
+use cooklang::{
+    aisle::AisleConf,
+    ingredient_list::IngredientList,
+    quantity::{GroupedQuantity, Quantity, Value},
+};
+
+/// Ingredients under an optional heading
+pub struct Group {
+    pub heading: Option<String>,
+    pub items: Vec<(String, GroupedQuantity)>,
+}
+
+/// Groups the list by aisle category, or in a single group if `plain`
+pub fn groups(list: IngredientList, aisle: &AisleConf, plain: bool) -> Vec<Group> {
+    if plain {
+        vec![Group {
+            heading: None,
+            items: list.into_iter().collect(),
+        }]
+    } else {
+        list.categorize(aisle)
+            .into_iter()
+            .map(|(category, items)| Group {
+                heading: Some(category),
+                items: items.into_iter().collect(),
+            })
+            .collect()
+    }
+}
+
+/// One paragraph for every group
+pub fn render(groups: &[Group]) -> String {
+    groups
+        .iter()
+        .filter(|g| !g.items.is_empty())
+        .map(|g| {
+            let items = g
+                .items
+                .iter()
+                .map(|(name, qty)| item(name, qty))
+                .collect::<Vec<_>>()
+                .join(", ");
+            match &g.heading {
+                Some(heading) => format!("{heading}\n{items}\n"),
+                None => format!("{items}\n"),
+            }
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+fn item(name: &str, qty: &GroupedQuantity) -> String {
+    let qty = qty.iter().map(quantity).collect::<Vec<_>>();
+    if qty.is_empty() {
+        name.to_string()
+    } else {
+        format!("{name} {}", qty.join(" + "))
+    }
+}
+
+fn quantity(qty: &Quantity) -> String {
+    let value = match qty.value() {
+        Value::Number(n) => number(n.value()),
+        Value::Range { start, end } => format!("{}-{}", number(start.value()), number(end.value())),
+        Value::Text(t) => t.clone(),
+    };
+    match qty.unit() {
+        Some(unit) => format!("{value} {unit}"),
+        None => value,
+    }
+}
+
+/// Whole numbers from 10, less decimals for bigger numbers below that
+fn number(n: f64) -> String {
+    let decimals = if n >= 10.0 {
+        1.0
+    } else if n >= 1.0 {
+        10.0
+    } else {
+        100.0
+    };
+    format!("{}", (n * decimals).round() / decimals)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rounding() {
+        assert_eq!(number(2.0), "2");
+        assert_eq!(number(12.75), "13");
+        assert_eq!(number(1.333), "1.3");
+        assert_eq!(number(0.333), "0.33");
+    }
+}
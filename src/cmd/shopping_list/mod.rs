@@ -0,0 +1,584 @@
+use anyhow::{bail, Context as _, Result};
+use camino::Utf8PathBuf;
+use clap::{Args, CommandFactory, ValueEnum};
+use cooklang::{
+    aisle::AisleConf,
+    ingredient_list::IngredientList,
+    quantity::{GroupedQuantity, Quantity, Value},
+    ScaledQuantity,
+};
+use serde::Serialize;
+
+use crate::{
+    exit_code::ExitError,
+    util::{meta_name, write_to_output, Input},
+    Context,
+};
+
+mod checklist;
+mod compact;
+mod plan;
+pub mod prices;
+
+#[derive(Debug, Args)]
+pub struct ShoppingListArgs {
+    /// Recipes to add with optional servings number and day
+    ///
+    /// The day is a `YYYY-MM-DD` date or a weekday, like `Pasta*4@monday`.
+    #[arg(value_name = "RECIPE[*<SERVINGS>][@<DAY>]")]
+    recipes: Vec<String>,
+
+    /// Add the recipes of a meal plan file
+    ///
+    /// One recipe per line, with the same format as the arguments. Lines
+    /// starting with `#` are ignored.
+    #[arg(long, value_name = "FILE")]
+    plan: Option<Utf8PathBuf>,
+
+    /// Split the list in days or weeks to go shopping
+    ///
+    /// Defaults to "day" if the recipes have a day. Ingredients needed more
+    /// than once are bought all the first time.
+    #[arg(long, value_enum)]
+    split: Option<plan::Split>,
+
+    /// Output file, none for stdout.
+    #[arg(short, long)]
+    output: Option<Utf8PathBuf>,
+
+    /// Do not display categories
+    ///
+    /// Same as `--group-by none`.
+    #[arg(short, long, conflicts_with = "group_by")]
+    plain: bool,
+
+    /// How to group the ingredients
+    ///
+    /// Defaults to "aisle" if there is an aisle configuration, "none" if not.
+    /// "recipe" lists the ingredients needed for every recipe.
+    #[arg(short, long, value_enum)]
+    group_by: Option<GroupBy>,
+
+    /// Output format
+    ///
+    /// Tries to infer it from output file extension. Defaults to "human".
+    #[arg(short, long, value_enum)]
+    format: Option<OutputFormat>,
+
+    /// Pretty output format, if available
+    #[arg(long)]
+    pretty: bool,
+
+    /// Short text without colors, to paste in messaging apps
+    ///
+    /// Every category is a paragraph with the items separated by commas and
+    /// rounded quantities.
+    #[arg(long, conflicts_with = "pretty")]
+    compact: bool,
+
+    /// Load aisle conf file
+    #[arg(short, long)]
+    aisle: Option<Utf8PathBuf>,
+
+    /// Estimate the cost of the list
+    ///
+    /// Uses the prices file from the config, or the given one with
+    /// `--prices=<FILE>`.
+    #[arg(
+        long,
+        value_name = "FILE",
+        num_args = 0..=1,
+        require_equals = true,
+        default_missing_value = ""
+    )]
+    prices: Option<Utf8PathBuf>,
+
+    /// Open the list as a checklist in the terminal
+    ///
+    /// Items can be ticked off and moved to other categories, and the
+    /// remaining ones saved to the output file.
+    #[arg(short, long, conflicts_with_all = ["format", "pretty", "prices", "compact"])]
+    interactive: bool,
+}
+
+#[derive(Debug, Clone, Copy, ValueEnum, PartialEq)]
+enum GroupBy {
+    Aisle,
+    Recipe,
+    None,
+}
+
+#[derive(Debug, Clone, Copy, ValueEnum)]
+enum OutputFormat {
+    Human,
+    Json,
+}
+
+pub fn run(ctx: &Context, args: ShoppingListArgs) -> Result<()> {
+    let aisle_conf = args
+        .aisle
+        .or_else(|| ctx.config.aisle(&ctx.base_path))
+        .map(|path| -> Result<_> {
+            let content = std::fs::read_to_string(&path).context("Failed to read aisle file")?;
+            Ok((content, path))
+        })
+        .transpose()?;
+
+    let aisle = aisle_conf
+        .as_ref()
+        .map(|(content, path)| {
+            let res = cooklang::aisle::parse(content);
+            if let Err(e) = res {
+                cooklang::error::write_rich_error(
+                    &e,
+                    path.as_str(),
+                    content,
+                    true,
+                    anstream::stderr().lock(),
+                )?;
+                bail!("Error parsing aisle file")
+            }
+            Ok(res.unwrap()) // anyhow
+        })
+        .transpose()?;
+    let aisle = aisle.unwrap_or_default();
+    let group_by = match args.group_by {
+        _ if args.plain => GroupBy::None,
+        Some(GroupBy::Aisle) if aisle.categories.is_empty() => {
+            tracing::warn!("No aisle configuration found, ingredients will not be grouped");
+            GroupBy::None
+        }
+        Some(group_by) => group_by,
+        None if aisle.categories.is_empty() => GroupBy::None,
+        None => GroupBy::Aisle,
+    };
+
+    let format = args.format.unwrap_or_else(|| match &args.output {
+        Some(p) => match p.extension() {
+            Some("json") => OutputFormat::Json,
+            _ => OutputFormat::Human,
+        },
+        None => OutputFormat::Human,
+    });
+    if args.compact && !matches!(format, OutputFormat::Human) {
+        bail!(ExitError::usage(
+            "`--compact` is only available for the human format"
+        ));
+    }
+
+    // retrieve, scale and merge ingredients
+    let mut list = IngredientList::new();
+    let mut recipe_lists = Vec::new();
+    let mut entries = args.recipes;
+    if let Some(path) = &args.plan {
+        entries.extend(plan::read_plan(path)?);
+    }
+    for entry in &entries {
+        let recipe_list = extract_ingredients(entry, &mut list, ctx)?;
+        recipe_lists.push(recipe_list);
+    }
+
+    let split = args.split.or_else(|| {
+        recipe_lists
+            .iter()
+            .any(|r| r.day.is_some())
+            .then_some(plan::Split::Day)
+    });
+    let pickups = match split {
+        Some(split) => {
+            if args.interactive || group_by == GroupBy::Recipe {
+                bail!(ExitError::usage(
+                    "A list split in days can't be interactive or grouped by recipe"
+                ));
+            }
+            Some(plan::split_list(
+                &recipe_lists,
+                split,
+                ctx.parser()?.converter(),
+            )?)
+        }
+        None => None,
+    };
+
+    if args.interactive {
+        if group_by == GroupBy::Recipe {
+            bail!(ExitError::usage(
+                "The interactive list can't be grouped by recipe"
+            ));
+        }
+        return checklist::run(
+            list,
+            &aisle,
+            group_by == GroupBy::None,
+            args.output.as_deref(),
+        );
+    }
+
+    let estimate = match &args.prices {
+        Some(path) => {
+            let path = if path.as_str().is_empty() {
+                ctx.config.prices(&ctx.base_path).ok_or_else(|| {
+                    anyhow::anyhow!("No prices file found. Add one or use `--prices=<FILE>`")
+                })?
+            } else {
+                path.clone()
+            };
+            let conf = prices::PriceConf::load(&path)?;
+            let estimate = prices::estimate(&conf, list.iter(), ctx.parser()?.converter());
+            Some((conf, estimate))
+        }
+        None => None,
+    };
+
+    write_to_output(args.output.as_deref(), |mut w| {
+        match format {
+            OutputFormat::Human if args.compact => {
+                let plain = group_by == GroupBy::None;
+                if let Some(pickups) = pickups {
+                    for (i, pickup) in pickups.into_iter().enumerate() {
+                        if i > 0 {
+                            writeln!(w)?;
+                        }
+                        writeln!(w, "{}\n", pickup.label)?;
+                        let groups = compact::groups(pickup.list, &aisle, plain);
+                        write!(w, "{}", compact::render(&groups))?;
+                    }
+                } else if group_by == GroupBy::Recipe {
+                    let groups = recipe_lists
+                        .into_iter()
+                        .map(|r| compact::Group {
+                            heading: Some(recipe_heading(&r)),
+                            items: r.list.into_iter().collect(),
+                        })
+                        .collect::<Vec<_>>();
+                    write!(w, "{}", compact::render(&groups))?;
+                } else {
+                    let groups = compact::groups(list, &aisle, plain);
+                    write!(w, "{}", compact::render(&groups))?;
+                }
+                if let Some((conf, estimate)) = &estimate {
+                    writeln!(w, "\nEstimated cost: {}", conf.format(estimate.total))?;
+                }
+            }
+            OutputFormat::Human => {
+                if let Some(pickups) = pickups {
+                    use yansi::Paint;
+                    for (i, pickup) in pickups.into_iter().enumerate() {
+                        if i > 0 {
+                            writeln!(w)?;
+                        }
+                        writeln!(w, "{}", pickup.label.bold())?;
+                        let table =
+                            build_human_table(pickup.list, &aisle, group_by == GroupBy::None);
+                        write!(w, "{table}")?;
+                    }
+                } else if group_by == GroupBy::Recipe {
+                    let table = build_human_recipes_table(recipe_lists);
+                    write!(w, "{table}")?;
+                } else {
+                    let table = build_human_table(list, &aisle, group_by == GroupBy::None);
+                    write!(w, "{table}")?;
+                }
+                if let Some((conf, estimate)) = &estimate {
+                    writeln!(w)?;
+                    let table = build_human_cost_table(conf, estimate);
+                    write!(w, "{table}")?;
+                }
+            }
+            OutputFormat::Json => {
+                let mut value = if let Some(pickups) = pickups {
+                    build_json_pickups_value(pickups, &aisle, group_by == GroupBy::None)
+                } else if group_by == GroupBy::Recipe {
+                    build_json_recipes_value(recipe_lists)
+                } else {
+                    build_json_value(list, &aisle, group_by == GroupBy::None)
+                };
+                if let Some((_, estimate)) = &estimate {
+                    value = serde_json::json!({
+                        "items": value,
+                        "cost": estimate,
+                    });
+                }
+                if args.pretty {
+                    serde_json::to_writer_pretty(w, &value)?;
+                } else {
+                    serde_json::to_writer(w, &value)?;
+                }
+            }
+        }
+        Ok(())
+    })
+}
+
+/// Ingredients of a single recipe in the list
+struct RecipeList {
+    name: String,
+    servings: Option<u32>,
+    day: Option<plan::Day>,
+    list: IngredientList,
+}
+
+/// Adds the ingredients of a recipe to the list and returns them separately
+fn extract_ingredients(
+    entry: &str,
+    list: &mut IngredientList,
+    ctx: &Context,
+) -> Result<RecipeList> {
+    let converter = ctx.parser()?.converter();
+
+    let (entry, day) = plan::split_day(entry)?;
+    let (name, servings) = split_servings(entry);
+
+    // Resolve and parse the recipe
+    let input = {
+        let entry = ctx.recipe_index.resolve(name, None)?;
+        Input::File {
+            entry,
+            override_name: None,
+        }
+    };
+    let recipe = input.parse(ctx)?;
+    let recipe_name = match meta_name(&recipe.metadata) {
+        Some(name) => name.to_string(),
+        None => input.name()?.to_string(),
+    };
+
+    // Scale
+    let recipe = tracing::debug_span!("scaling").in_scope(|| match servings {
+        Some(servings) => recipe.scale(servings, converter),
+        None => recipe.default_scale(),
+    });
+
+    // Add ingredients to the list
+    list.add_recipe(&recipe, converter);
+    let mut recipe_list = IngredientList::new();
+    recipe_list.add_recipe(&recipe, converter);
+
+    Ok(RecipeList {
+        name: recipe_name,
+        servings,
+        day,
+        list: recipe_list,
+    })
+}
+
+/// Splits a `RECIPE[*<SERVINGS>]` argument into name and servings
+///
+/// Exits with a usage error if the servings are not a number.
+pub fn split_servings(entry: &str) -> (&str, Option<u32>) {
+    entry
+        .trim()
+        .rsplit_once('*')
+        .map(|(name, servings)| {
+            let target = servings.parse::<u32>().unwrap_or_else(|err| {
+                let mut cmd = crate::CliArgs::command();
+                cmd.error(
+                    clap::error::ErrorKind::InvalidValue,
+                    format!("Invalid scaling target for '{name}': {err}"),
+                )
+                .exit()
+            });
+            (name, Some(target))
+        })
+        .unwrap_or((entry, None))
+}
+
+fn grouped_qty_fmt(qty: &GroupedQuantity, row: &mut tabular::Row) {
+    let content = qty
+        .iter()
+        .map(quantity_fmt)
+        .reduce(|s, q| format!("{s}, {q}"))
+        .unwrap_or_default();
+    row.add_ansi_cell(content);
+}
+
+fn quantity_fmt(qty: &Quantity) -> String {
+    use yansi::Paint;
+
+    if let Some(unit) = qty.unit() {
+        format!("{} {}", qty.value(), unit.italic())
+    } else {
+        format!("{}", qty.value())
+    }
+}
+
+fn build_human_table(list: IngredientList, aisle: &AisleConf, plain: bool) -> tabular::Table {
+    use yansi::Paint;
+
+    let mut table = tabular::Table::new("{:<} {:<}");
+    if plain {
+        for (igr, q) in list {
+            let mut row = tabular::Row::new().with_cell(igr);
+            grouped_qty_fmt(&q, &mut row);
+            table.add_row(row);
+        }
+    } else {
+        let categories = list.categorize(aisle);
+        for (cat, items) in categories {
+            table.add_heading(format!("[{}]", cat.green()));
+            for (igr, q) in items {
+                let mut row = tabular::Row::new().with_cell(igr);
+                grouped_qty_fmt(&q, &mut row);
+                table.add_row(row);
+            }
+        }
+    }
+    table
+}
+
+fn build_human_cost_table(
+    conf: &prices::PriceConf,
+    estimate: &prices::CostEstimate,
+) -> tabular::Table {
+    use yansi::Paint;
+
+    let mut table = tabular::Table::new("{:<} {:>}");
+    table.add_heading(format!("[{}]", "Estimated cost".green()));
+    for item in &estimate.items {
+        table.add_row(
+            tabular::Row::new()
+                .with_cell(&item.name)
+                .with_cell(conf.format(item.cost)),
+        );
+    }
+    table.add_row(
+        tabular::Row::new()
+            .with_ansi_cell("Total".bold())
+            .with_ansi_cell(conf.format(estimate.total).bold()),
+    );
+    if !estimate.unknown.is_empty() {
+        table.add_heading(format!(
+            "{} {}",
+            "Without price:".yellow(),
+            estimate.unknown.join(", ")
+        ));
+    }
+    table
+}
+
+fn recipe_heading(recipe: &RecipeList) -> String {
+    match recipe.servings {
+        Some(servings) => format!("{} \u{00d7}{servings}", recipe.name),
+        None => recipe.name.clone(),
+    }
+}
+
+fn build_human_recipes_table(recipes: Vec<RecipeList>) -> tabular::Table {
+    use yansi::Paint;
+
+    let mut table = tabular::Table::new("{:<} {:<}");
+    for recipe in recipes {
+        let heading = recipe_heading(&recipe);
+        let items = recipe.list.into_iter().collect::<Vec<_>>();
+        let count = items.len();
+        table.add_heading(format!(
+            "[{}] {}",
+            heading.green(),
+            format!("{count} ingredient{}", if count == 1 { "" } else { "s" }).dim()
+        ));
+        for (igr, q) in items {
+            let mut row = tabular::Row::new().with_cell(igr);
+            grouped_qty_fmt(&q, &mut row);
+            table.add_row(row);
+        }
+    }
+    table
+}
+
+#[derive(Serialize)]
+struct JsonIngredient {
+    name: String,
+    quantity: Vec<ScaledQuantity>,
+}
+
+impl From<(String, GroupedQuantity)> for JsonIngredient {
+    fn from((name, qty): (String, GroupedQuantity)) -> Self {
+        JsonIngredient {
+            name,
+            quantity: qty.into_vec(),
+        }
+    }
+}
+
+fn build_json_recipes_value(recipes: Vec<RecipeList>) -> serde_json::Value {
+    #[derive(Serialize)]
+    struct Recipe {
+        recipe: String,
+        servings: Option<u32>,
+        items: Vec<JsonIngredient>,
+    }
+
+    serde_json::to_value(
+        recipes
+            .into_iter()
+            .map(|r| Recipe {
+                recipe: r.name,
+                servings: r.servings,
+                items: r.list.into_iter().map(JsonIngredient::from).collect(),
+            })
+            .collect::<Vec<_>>(),
+    )
+    .unwrap()
+}
+
+fn build_json_pickups_value(
+    pickups: Vec<plan::Pickup>,
+    aisle: &AisleConf,
+    plain: bool,
+) -> serde_json::Value {
+    serde_json::Value::Array(
+        pickups
+            .into_iter()
+            .map(|p| {
+                serde_json::json!({
+                    "pickup": p.label,
+                    "items": build_json_value(p.list, aisle, plain),
+                })
+            })
+            .collect(),
+    )
+}
+
+fn build_json_value<'a>(
+    list: IngredientList,
+    aisle: &'a AisleConf<'a>,
+    plain: bool,
+) -> serde_json::Value {
+    #[derive(Serialize)]
+    struct Quantity {
+        value: Value,
+        unit: Option<String>,
+    }
+    impl From<cooklang::quantity::Quantity> for Quantity {
+        fn from(qty: cooklang::quantity::Quantity) -> Self {
+            let unit = qty.unit().map(|s| s.to_owned());
+            let value = qty.value().clone();
+            Self { value, unit }
+        }
+    }
+    #[derive(Serialize)]
+    struct Category {
+        category: String,
+        items: Vec<JsonIngredient>,
+    }
+
+    if plain {
+        serde_json::to_value(
+            list.into_iter()
+                .map(JsonIngredient::from)
+                .collect::<Vec<_>>(),
+        )
+        .unwrap()
+    } else {
+        serde_json::to_value(
+            list.categorize(aisle)
+                .into_iter()
+                .map(|(category, items)| Category {
+                    category,
+                    items: items.into_iter().map(JsonIngredient::from).collect(),
+                })
+                .collect::<Vec<_>>(),
+        )
+        .unwrap()
+    }
+}
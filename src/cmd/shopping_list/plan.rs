@@ -0,0 +1,197 @@
+//! Shopping lists split by the days of a meal plan
+//!
+//! Recipes can have a day, like `Pasta*4@monday` or `Pasta@2024-05-06`, and
+//! the list is split in pickups, one for every day or week. An ingredient
+//! needed in more than one pickup is bought all in the earliest one.
+
+use std::collections::{BTreeMap, HashMap};
+
+use anyhow::{bail, Context as _, Result};
+use camino::Utf8Path;
+use clap::ValueEnum;
+use cooklang::{ingredient_list::IngredientList, Converter};
+
+use crate::util::{civil_from_days, days_from_civil};
+
+use super::RecipeList;
+
+#[derive(Debug, Clone, Copy, ValueEnum, PartialEq)]
+pub enum Split {
+    Day,
+    Week,
+}
+
+const WEEKDAYS: [&str; 7] = [
+    "monday",
+    "tuesday",
+    "wednesday",
+    "thursday",
+    "friday",
+    "saturday",
+    "sunday",
+];
+
+/// Day of a recipe in the plan
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Day {
+    /// Days since 1970-01-01
+    Date(i64),
+    /// From 0 for monday
+    Weekday(usize),
+}
+
+impl Day {
+    /// Parses a `YYYY-MM-DD` date or a weekday name, which can be shortened
+    /// to 3 letters
+    pub fn parse(s: &str) -> Option<Self> {
+        let s = s.trim().to_lowercase();
+        if let Some(i) = WEEKDAYS
+            .iter()
+            .position(|w| *w == s || (s.len() == 3 && w.starts_with(&s)))
+        {
+            return Some(Self::Weekday(i));
+        }
+        let mut parts = s.splitn(3, '-');
+        let (year, month, day) = (parts.next()?, parts.next()?, parts.next()?);
+        let digits = |s: &str, len: usize| s.len() == len && s.bytes().all(|b| b.is_ascii_digit());
+        if !digits(year, 4) || !digits(month, 2) || !digits(day, 2) {
+            return None;
+        }
+        let (year, month, day) = (year.parse().ok()?, month.parse().ok()?, day.parse().ok()?);
+        let days = days_from_civil(year, month, day);
+        // invalid dates like 02-30 don't round trip
+        (civil_from_days(days) == (year, month, day)).then_some(Self::Date(days))
+    }
+
+    /// First day of the pickup this day is in
+    fn pickup(self, split: Split) -> Result<Self> {
+        match (split, self) {
+            (Split::Day, _) => Ok(self),
+            // 1970-01-01 was a thursday
+            (Split::Week, Self::Date(d)) => Ok(Self::Date(d - (d + 3).rem_euclid(7))),
+            (Split::Week, Self::Weekday(_)) => bail!("Splitting by week needs dates, not weekdays"),
+        }
+    }
+
+    fn label(self, split: Split) -> String {
+        match self {
+            Self::Date(d) => {
+                let (year, month, day) = civil_from_days(d);
+                let date = format!("{year:04}-{month:02}-{day:02}");
+                match split {
+                    Split::Day => date,
+                    Split::Week => format!("Week of {date}"),
+                }
+            }
+            Self::Weekday(i) => {
+                let name = WEEKDAYS[i];
+                name[..1].to_uppercase() + &name[1..]
+            }
+        }
+    }
+}
+
+/// Splits a `RECIPE[*<SERVINGS>][@<DAY>]` argument into the recipe and day
+pub fn split_day(entry: &str) -> Result<(&str, Option<Day>)> {
+    match entry.trim().rsplit_once('@') {
+        Some((recipe, day)) => {
+            let day = Day::parse(day).with_context(|| {
+                format!("Invalid day '{day}' for '{recipe}', use a date or a weekday")
+            })?;
+            Ok((recipe, Some(day)))
+        }
+        None => Ok((entry, None)),
+    }
+}
+
+/// Recipes of a plan file, one per line like in the arguments
+///
+/// Empty lines and lines starting with `#` are skipped.
+pub fn read_plan(path: &Utf8Path) -> Result<Vec<String>> {
+    let content = std::fs::read_to_string(path).context("Failed to read plan file")?;
+    Ok(content
+        .lines()
+        .map(str::trim)
+        .filter(|l| !l.is_empty() && !l.starts_with('#'))
+        .map(str::to_string)
+        .collect())
+}
+
+/// Part of the list bought together
+pub struct Pickup {
+    pub label: String,
+    pub list: IngredientList,
+}
+
+/// Splits the ingredients of the recipes in pickups, earliest first
+pub fn split_list(
+    recipes: &[RecipeList],
+    split: Split,
+    converter: &Converter,
+) -> Result<Vec<Pickup>> {
+    let mut days = Vec::with_capacity(recipes.len());
+    for recipe in recipes {
+        let Some(day) = recipe.day else {
+            bail!(
+                "'{}' has no day. Every recipe needs one to split the list",
+                recipe.name
+            );
+        };
+        days.push(day.pickup(split)?);
+    }
+    if days.iter().any(|d| matches!(d, Day::Date(_)))
+        && days.iter().any(|d| matches!(d, Day::Weekday(_)))
+    {
+        bail!("Use only dates or only weekdays in a plan");
+    }
+
+    let mut first = HashMap::<&str, Day>::new();
+    for (recipe, &day) in recipes.iter().zip(&days) {
+        for (name, _) in recipe.list.iter() {
+            first
+                .entry(name.as_str())
+                .and_modify(|d| *d = (*d).min(day))
+                .or_insert(day);
+        }
+    }
+
+    let mut lists = BTreeMap::<Day, IngredientList>::new();
+    for recipe in recipes {
+        for (name, qty) in recipe.list.iter() {
+            lists
+                .entry(first[name.as_str()])
+                .or_insert_with(IngredientList::new)
+                .add_ingredient(name.clone(), qty, converter);
+        }
+    }
+    Ok(lists
+        .into_iter()
+        .map(|(day, list)| Pickup {
+            label: day.label(split),
+            list,
+        })
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn days() {
+        assert_eq!(Day::parse("Monday"), Some(Day::Weekday(0)));
+        assert_eq!(Day::parse("sun"), Some(Day::Weekday(6)));
+        assert_eq!(Day::parse("su"), None);
+        assert_eq!(Day::parse("1970-01-02"), Some(Day::Date(1)));
+        assert_eq!(Day::parse("2024-02-30"), None);
+        assert_eq!(Day::parse("2024-5-06"), None);
+
+        // thursday to the monday before
+        let day = Day::parse("2024-05-09").unwrap();
+        let week = day.pickup(Split::Week).unwrap();
+        assert_eq!(week.label(Split::Week), "Week of 2024-05-06");
+        assert_eq!(day.label(Split::Day), "2024-05-09");
+        assert_eq!(Day::Weekday(2).label(Split::Day), "Wednesday");
+        assert!(Day::Weekday(2).pickup(Split::Week).is_err());
+    }
+}
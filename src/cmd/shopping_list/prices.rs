@@ -0,0 +1,193 @@
+//! Cost estimation of a shopping list
+//!
+//! The prices file is a TOML file like:
+//!
+//! ```toml
+//! currency = "€"
+//!
+//! [items]
+//! flour = { price = 1.20, unit = "kg" }
+//! eggs = { price = 0.25 } # per item
+//! ```
+
+use std::collections::HashMap;
+
+use anyhow::{Context as _, Result};
+use camino::Utf8Path;
+use cooklang::{
+    quantity::{GroupedQuantity, Value},
+    Converter,
+};
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Deserialize)]
+pub struct PriceConf {
+    #[serde(default)]
+    pub currency: Option<String>,
+    /// By lowercase ingredient name
+    #[serde(deserialize_with = "lowercase_keys")]
+    items: HashMap<String, Price>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Price {
+    /// Price of one `unit`
+    price: f64,
+    /// None for items without unit, like `@eggs{3}`
+    unit: Option<String>,
+}
+
+fn lowercase_keys<'de, D>(d: D) -> Result<HashMap<String, Price>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    let items = HashMap::<String, Price>::deserialize(d)?;
+    Ok(items
+        .into_iter()
+        .map(|(k, v)| (k.to_lowercase(), v))
+        .collect())
+}
+
+impl PriceConf {
+    pub fn load(path: &Utf8Path) -> Result<Self> {
+        let content = std::fs::read_to_string(path).context("Failed to read prices file")?;
+        toml::from_str(&content).with_context(|| format!("Invalid prices file: {path}"))
+    }
+
+    /// Estimated cost of an ingredient, None if it has no price or the
+    /// quantity can't be converted to the unit of the price
+    pub fn cost(&self, name: &str, qty: &GroupedQuantity, converter: &Converter) -> Option<f64> {
+        let price = self.items.get(&name.to_lowercase())?;
+        let mut total = 0.0;
+        for q in qty.iter() {
+            let mut q = q.clone();
+            match (&price.unit, q.unit()) {
+                (Some(unit), Some(_)) => q.convert(unit.as_str(), converter).ok()?,
+                (None, None) => {}
+                _ => return None,
+            }
+            // ranges are estimated with the largest amount
+            let amount = match q.value() {
+                Value::Number(n) => n.value(),
+                Value::Range { end, .. } => end.value(),
+                Value::Text(_) => return None,
+            };
+            total += amount * price.price;
+        }
+        Some(total)
+    }
+
+    pub fn format(&self, cost: f64) -> String {
+        match &self.currency {
+            Some(c) => format!("{cost:.2} {c}"),
+            None => format!("{cost:.2}"),
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct CostEstimate {
+    pub items: Vec<ItemCost>,
+    pub total: f64,
+    pub currency: Option<String>,
+    /// Ingredients without a price or with quantities that can't be priced
+    pub unknown: Vec<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ItemCost {
+    pub name: String,
+    pub cost: f64,
+}
+
+pub fn estimate<'a>(
+    prices: &PriceConf,
+    list: impl IntoIterator<Item = (&'a String, &'a GroupedQuantity)>,
+    converter: &Converter,
+) -> CostEstimate {
+    let mut items = Vec::new();
+    let mut unknown = Vec::new();
+    for (name, qty) in list {
+        match prices.cost(name, qty, converter) {
+            Some(cost) => items.push(ItemCost {
+                name: name.clone(),
+                cost,
+            }),
+            None => unknown.push(name.clone()),
+        }
+    }
+    CostEstimate {
+        total: items.iter().map(|i| i.cost).sum(),
+        items,
+        currency: prices.currency.clone(),
+        unknown,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_conf() {
+        let conf: PriceConf = toml::from_str(
+            r#"
+            currency = "€"
+            [items]
+            Flour = { price = 1.2, unit = "kg" }
+            eggs = { price = 0.25 }
+            "#,
+        )
+        .unwrap();
+        assert_eq!(conf.currency.as_deref(), Some("€"));
+        assert!(conf.items.contains_key("flour"));
+        assert_eq!(conf.items["eggs"].unit, None);
+        assert_eq!(conf.format(2.5), "2.50 €");
+    }
+
+    /// Cost of the only ingredient in the shopping list of `recipe`
+    fn recipe_cost(conf: &PriceConf, recipe: &str) -> Option<f64> {
+        let parser = cooklang::CooklangParser::extended();
+        let recipe = parser.parse(recipe).into_output().unwrap();
+        let mut list = cooklang::ingredient_list::IngredientList::new();
+        list.add_recipe(&recipe.default_scale(), parser.converter());
+        let (name, qty) = list.iter().next().unwrap();
+        conf.cost(name, qty, parser.converter())
+    }
+
+    #[test]
+    fn cost() {
+        let conf: PriceConf = toml::from_str(
+            r#"
+            [items]
+            flour = { price = 1.2, unit = "kg" }
+            milk = { price = 1.0, unit = "l" }
+            eggs = { price = 0.25 }
+            "#,
+        )
+        .unwrap();
+        let cost = |recipe: &str| recipe_cost(&conf, recipe);
+        let approx =
+            |cost: Option<f64>, expected: f64| cost.is_some_and(|c| (c - expected).abs() < 1e-9);
+
+        assert!(approx(cost("@flour{1%kg}"), 1.2));
+        assert!(approx(cost("@Flour{1%kg}"), 1.2));
+        assert!(approx(cost("@eggs{3}"), 0.75));
+        // converted to the unit of the price
+        assert!(approx(cost("@flour{500%g}"), 0.6));
+        assert!(approx(cost("@milk{250%ml}"), 0.25));
+        assert!(approx(cost("@flour{500%g} and @flour{1%kg}"), 1.8));
+        // ranges use the largest amount
+        assert!(approx(cost("@flour{1-2%kg}"), 2.4));
+
+        // units that can't be converted to the unit of the price
+        assert_eq!(cost("@flour{2%cups}"), None);
+        assert_eq!(cost("@milk{200%g}"), None);
+        assert_eq!(cost("@flour{3%handfuls}"), None);
+        // only one of them has a unit
+        assert_eq!(cost("@flour{3}"), None);
+        assert_eq!(cost("@eggs{2%kg}"), None);
+        assert_eq!(cost("@flour{a bit}"), None);
+        assert_eq!(cost("@cheese{100%g}"), None);
+    }
+}
@@ -32,11 +32,27 @@ pub struct UnitsArgs {
     #[arg(short, long, value_enum)]
     sort: Vec<Sort>,
 
+    /// Show every spelling the parser accepts for each unit
+    ///
+    /// These are the names, symbols and aliases.
+    #[arg(long)]
+    aliases: bool,
+
+    /// Output format
+    #[arg(long, value_enum, default_value_t = Format::Table, conflicts_with = "count")]
+    format: Format,
+
     /// Writes all units in json format, one per line along with conversion data
     #[arg(long, exclusive = true)]
     dump: bool,
 }
 
+#[derive(Debug, ValueEnum, Clone, Copy, PartialEq)]
+pub enum Format {
+    Table,
+    Json,
+}
+
 #[derive(Debug, ValueEnum, Clone, Copy)]
 pub enum System {
     Metric,
@@ -118,7 +134,24 @@ pub fn run(converter: &Converter, args: UnitsArgs) -> Result<()> {
             });
         }
 
-        if args.long {
+        if args.format == Format::Json {
+            #[derive(serde::Serialize)]
+            struct JsonUnit<'a> {
+                #[serde(flatten)]
+                unit: &'a Unit,
+                #[serde(skip_serializing_if = "Option::is_none")]
+                spellings: Option<Vec<&'a str>>,
+            }
+            let units = all_units
+                .into_iter()
+                .map(|unit| JsonUnit {
+                    unit,
+                    spellings: args.aliases.then(|| spellings(unit)),
+                })
+                .collect::<Vec<_>>();
+            serde_json::to_writer_pretty(std::io::stdout(), &units)?;
+            println!();
+        } else if args.long {
             let mut table = tabular::Table::new("{:<} {:<} {:<} {:<} {:<} {:<} {:<} {:<}");
             let mut total = 0;
 
@@ -141,8 +174,16 @@ pub fn run(converter: &Converter, args: UnitsArgs) -> Result<()> {
                 );
             }
             println!("total {total}\n{table}");
+        } else if args.aliases {
+            for unit in all_units {
+                println!(
+                    "{}: {}",
+                    unit.names.first().unwrap().bold(),
+                    spellings(unit).join(", ")
+                );
+            }
         } else {
-            for unit in converter.all_units().filter(filter_units(&args)) {
+            for unit in all_units {
                 println!("{}", unit.names.first().unwrap());
             }
         }
@@ -169,6 +210,17 @@ fn list(l: &[std::sync::Arc<str>], all: bool) -> String {
     }
 }
 
+/// All the ways to write a unit, without duplicates
+fn spellings(unit: &Unit) -> Vec<&str> {
+    let mut all = Vec::new();
+    for s in unit.names.iter().chain(&unit.symbols).chain(&unit.aliases) {
+        if !all.contains(&s.as_ref()) {
+            all.push(s.as_ref());
+        }
+    }
+    all
+}
+
 fn style_quantity(q: cooklang::convert::PhysicalQuantity) -> String {
     let color = match q {
         cooklang::convert::PhysicalQuantity::Volume => yansi::Color::Green,
@@ -1,8 +1,9 @@
 use std::{
-    collections::HashMap,
+    collections::{BTreeMap, HashMap},
     env,
     fs::{self, File},
     io::{self, Read},
+    net::IpAddr,
     path::{Path, PathBuf},
 };
 
@@ -16,13 +17,22 @@ use crate::{APP_NAME, COOK_DIR, UTF8_PATH_PANIC};
 pub const CONFIG_FILE: &str = "config.toml";
 pub const AUTO_AISLE: &str = "aisle.conf";
 pub const AUTO_UNITS: &str = "units.toml";
+pub const AUTO_PRICES: &str = "prices.toml";
 pub const DEFAULT_CONFIG_FILE: &str = "default-config.toml";
 pub const CHEF_CONFIG_FILE: &str = "chef-config.toml";
+pub const METADATA_CACHE_FILE: &str = "metadata-cache.json";
+/// Prefix of the environment variables that override config keys
+pub const ENV_PREFIX: &str = "CHEF_";
 
 #[derive(Serialize, Deserialize, Clone)]
 pub struct ChefConfig {
     pub default_collection: Option<Utf8PathBuf>,
     pub editor_command: Option<Vec<String>>,
+    /// Command to open a directory, the OS file manager if none
+    pub file_manager_command: Option<Vec<String>>,
+    /// Named collections, for `--collection` and `collection switch`
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub collections: BTreeMap<String, Utf8PathBuf>,
 }
 
 impl ChefConfig {
@@ -51,6 +61,44 @@ impl ChefConfig {
         };
         Ok(cmd)
     }
+
+    pub fn file_manager(&self) -> Result<Vec<String>> {
+        if let Some(custom) = &self.file_manager_command {
+            if custom.is_empty() {
+                bail!("Invalid custom file manager command in global config. Fix it please.");
+            }
+            return Ok(custom.clone());
+        }
+        let cmd = if cfg!(windows) {
+            "explorer"
+        } else if cfg!(target_os = "macos") {
+            "open"
+        } else {
+            "xdg-open"
+        };
+        Ok(vec![cmd.to_string()])
+    }
+
+    /// Path of a collection in the registry
+    pub fn collection(&self, name: &str) -> Result<&Utf8Path> {
+        match self.collections.get(name) {
+            Some(path) => Ok(path),
+            None if self.collections.is_empty() => {
+                bail!(
+                    "Unknown collection '{name}'. Add it with `chef collection add {name} <PATH>`"
+                )
+            }
+            None => {
+                let names = self
+                    .collections
+                    .keys()
+                    .map(String::as_str)
+                    .collect::<Vec<_>>()
+                    .join(", ");
+                bail!("Unknown collection '{name}'. The known collections are: {names}")
+            }
+        }
+    }
 }
 
 #[allow(clippy::derivable_impls)] // I like to see the exact defaults of the config
@@ -59,6 +107,8 @@ impl Default for ChefConfig {
         Self {
             default_collection: None,
             editor_command: None,
+            file_manager_command: None,
+            collections: BTreeMap::new(),
         }
     }
 }
@@ -74,6 +124,26 @@ pub struct Config {
     pub warnings_as_errors: bool,
     pub recipe_ref_check: bool,
     pub max_depth: usize,
+    pub cache_metadata: bool,
+    /// Find recipes by name ignoring accents, like `creme brulee`
+    pub ignore_diacritics: bool,
+    /// Write recipe references as terminal hyperlinks, to the file with
+    /// `"file"` or to the web UI with its URL
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub hyperlinks: Option<String>,
+    /// Spelling of units in the outputs, like `tbsp` for every way to write
+    /// tablespoons
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub display_units: Vec<String>,
+    /// Smallest value to use a unit for when converting to a system, like
+    /// `kg = 1.5` for `1200 g` instead of `1.2 kg`
+    #[serde(skip_serializing_if = "HashMap::is_empty")]
+    pub unit_thresholds: HashMap<String, f64>,
+    /// Other names of tags, like `cakes = "dessert/cake"`
+    #[serde(skip_serializing_if = "HashMap::is_empty")]
+    pub tag_aliases: HashMap<String, String>,
+    #[serde(skip_serializing_if = "is_default")]
+    pub duration: DurationFormat,
     #[serde(with = "extensions_serde")]
     pub extensions: Extensions,
     #[serde(skip_serializing_if = "Load::is_empty")]
@@ -92,6 +162,13 @@ impl Default for Config {
             warnings_as_errors: false,
             recipe_ref_check: true,
             max_depth: 10,
+            cache_metadata: false,
+            ignore_diacritics: false,
+            hyperlinks: None,
+            display_units: Vec::new(),
+            unit_thresholds: HashMap::new(),
+            tag_aliases: HashMap::new(),
+            duration: Default::default(),
             load: Default::default(),
             ui: Default::default(),
             export: Default::default(),
@@ -106,31 +183,119 @@ pub struct Load {
     pub units: Vec<PathBuf>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub aisle: Option<PathBuf>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub prices: Option<PathBuf>,
 }
 
 impl Load {
     fn is_empty(&self) -> bool {
-        self.units.is_empty() && self.aisle.is_none()
+        self.units.is_empty() && self.aisle.is_none() && self.prices.is_none()
     }
 }
 
 #[derive(Serialize, Deserialize, Default, Clone)]
+#[serde(default)]
 pub struct UiConfig {
+    #[serde(skip_serializing_if = "HashMap::is_empty")]
     pub tags: HashMap<String, TagProps>,
+    /// Public URL where the web UI is reachable, used for share links
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub public_url: Option<String>,
+    /// Path prefix of the web UI, like `/recipes`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub base_url: Option<String>,
+    /// Reverse proxies whose `X-Forwarded-For` header has the client address
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub trusted_proxies: Vec<IpAddr>,
+    /// Extra links in the navigation bar
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub nav: Vec<NavLink>,
+    /// Recipe urls by a slug of the name instead of the path
+    #[serde(skip_serializing_if = "is_default")]
+    pub slugs: bool,
+    /// Load the images of other sites through the server, so browsers do not
+    /// connect to them
+    #[serde(skip_serializing_if = "is_default")]
+    pub proxy_images: bool,
+    /// People that can log in to have their own favorites and shopping list
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub users: Vec<UserConfig>,
 }
 
 impl UiConfig {
     fn is_empty(&self) -> bool {
         self.tags.is_empty()
+            && self.public_url.is_none()
+            && self.base_url.is_none()
+            && self.trusted_proxies.is_empty()
+            && self.nav.is_empty()
+            && !self.slugs
+            && !self.proxy_images
+            && self.users.is_empty()
     }
 }
 
+#[derive(Serialize, Deserialize, Clone)]
+pub struct UserConfig {
+    pub name: String,
+    /// Argon2 hash from `chef serve --hash-password`, anyone can log in as
+    /// the user without one
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub password: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct NavLink {
+    pub name: String,
+    /// Paths starting with `/` are inside the web UI, like `/pages/about`
+    pub url: String,
+}
+
 #[derive(Serialize, Deserialize, Default, Clone)]
 #[serde(default)]
 pub struct TagProps {
     pub emoji: Option<String>,
 }
 
+/// How to write durations, like the time of a recipe
+///
+/// `{}` in each part is replaced by the number.
+#[derive(Serialize, Deserialize, Clone, PartialEq)]
+#[serde(default)]
+pub struct DurationFormat {
+    pub day: String,
+    pub hour: String,
+    pub minute: String,
+    pub separator: String,
+}
+
+impl Default for DurationFormat {
+    fn default() -> Self {
+        Self {
+            day: "{}d".into(),
+            hour: "{}h".into(),
+            minute: "{}m".into(),
+            separator: " ".into(),
+        }
+    }
+}
+
+impl DurationFormat {
+    pub fn format(&self, minutes: u32) -> String {
+        let (d, h, m) = (minutes / 1440, minutes / 60 % 24, minutes % 60);
+        let mut parts = Vec::new();
+        for (n, part) in [(d, &self.day), (h, &self.hour), (m, &self.minute)] {
+            if n > 0 {
+                parts.push(part.replace("{}", &n.to_string()));
+            }
+        }
+        if parts.is_empty() {
+            return self.minute.replace("{}", "0");
+        }
+        parts.join(&self.separator)
+    }
+}
+
 #[derive(Serialize, Deserialize, Default, Clone, PartialEq)]
 #[serde(default)]
 pub struct ExportConfig {
@@ -154,15 +319,45 @@ impl Config {
         }
         tracing::debug!("Loading local config from {local}");
         let content = std::fs::read_to_string(local)?;
-        let config = toml::from_str(&content)?;
+        let config = from_toml(&content)?;
         Ok(config)
     }
 
+    /// Overrides config keys with `CHEF_*` environment variables
+    ///
+    /// Nested keys are separated by a double underscore, so `ui.public_url`
+    /// is `CHEF_UI__PUBLIC_URL`. Values are read as TOML values, or as a
+    /// string if they are not valid TOML. Variables that are not a config key
+    /// are ignored with a warning, other programs may use `CHEF_*` too.
+    pub fn override_with_env(&mut self) -> Result<()> {
+        self.override_with_vars(env::vars())
+    }
+
+    fn override_with_vars(&mut self, vars: impl Iterator<Item = (String, String)>) -> Result<()> {
+        let mut config = toml::Value::try_from(&*self)?;
+        let mut changed = false;
+        for (var, value) in vars {
+            match apply_env_var(&mut config, &var, &value)? {
+                EnvVar::Applied => changed = true,
+                EnvVar::UnknownKey(key) => {
+                    tracing::warn!("Ignoring '{var}', '{key}' is not a config key")
+                }
+                EnvVar::Other => {}
+            }
+        }
+        if changed {
+            *self = config
+                .try_into()
+                .context("Invalid config value in environment variables")?;
+        }
+        Ok(())
+    }
+
     pub fn override_with_args(&mut self, args: &crate::GlobalArgs) {
         if args.no_default_units {
             self.default_units = false;
         }
-        if args.no_extensions {
+        if args.no_extensions || args.strict_canonical {
             self.extensions = Extensions::empty();
         } else if args.all_extensions {
             self.extensions = Extensions::all();
@@ -183,7 +378,9 @@ impl Config {
         if args.warnings_as_errors {
             self.warnings_as_errors = true;
         }
-        self.max_depth = args.max_depth;
+        if let Some(max_depth) = args.max_depth {
+            self.max_depth = max_depth;
+        }
         if !args.units.is_empty() {
             let new_units = args.units.iter().flat_map(|p| p.canonicalize().ok());
             if args.override_units {
@@ -211,8 +408,25 @@ impl Config {
             })
     }
 
+    pub fn prices(&self, base_path: &Utf8Path) -> Option<Utf8PathBuf> {
+        self.load
+            .prices
+            .as_ref()
+            .map(|p| resolve_path(base_path, p))
+            .or_else(|| {
+                let auto = base_path.join(COOK_DIR).join(AUTO_PRICES);
+                tracing::trace!("checking auto prices file: {auto}");
+                auto.is_file().then_some(auto)
+            })
+            .or_else(|| {
+                let global = global_file_path(AUTO_PRICES).ok()?;
+                tracing::trace!("checking global auto prices file: {global}");
+                global.is_file().then_some(global)
+            })
+    }
+
     pub fn units(&self, base_path: &Utf8Path) -> Vec<Utf8PathBuf> {
-        (!self.load.is_empty())
+        (!self.load.units.is_empty())
             .then(|| {
                 self.load
                     .units
@@ -234,6 +448,109 @@ impl Config {
     }
 }
 
+/// Environment variables that override config keys
+pub fn env_overrides() -> Vec<String> {
+    let Ok(mut config) = toml::Value::try_from(Config::default()) else {
+        return vec![];
+    };
+    env::vars()
+        .filter(|(var, value)| {
+            matches!(
+                apply_env_var(&mut config, var, value),
+                Ok(EnvVar::Applied) | Err(_)
+            )
+        })
+        .map(|(var, _)| var)
+        .collect()
+}
+
+enum EnvVar {
+    Applied,
+    /// A `CHEF_*` variable with a key that is not in the config
+    UnknownKey(String),
+    /// Not a `CHEF_*` variable
+    Other,
+}
+
+/// Sets the config key of a `CHEF_*` variable in the TOML of a config
+///
+/// The TOML is left as it was if the key is not in the config. Values that
+/// are not valid for the key are an error.
+fn apply_env_var(config: &mut toml::Value, var: &str, value: &str) -> Result<EnvVar> {
+    let Some(key) = var.strip_prefix(ENV_PREFIX) else {
+        return Ok(EnvVar::Other);
+    };
+    let key = key.to_lowercase();
+    let path = key.split("__").collect::<Vec<_>>();
+    let mut new = config.clone();
+    if !set_toml_value(&mut new, &path, parse_toml_value(value)) {
+        bail!("Invalid config key in environment variable '{var}'");
+    }
+    let (_, unknown) = from_toml_unknown::<Config, _>(new.clone())
+        .with_context(|| format!("Invalid config value in environment variable '{var}'"))?;
+    if !unknown.is_empty() {
+        return Ok(EnvVar::UnknownKey(path.join(".")));
+    }
+    tracing::debug!("Config '{}' overridden by '{var}'", path.join("."));
+    *config = new;
+    Ok(EnvVar::Applied)
+}
+
+/// Reads a config from TOML, warning about keys that do not exist
+pub fn from_toml<T: DeserializeOwned>(content: &str) -> Result<T, toml::de::Error> {
+    let (config, unknown) = from_toml_unknown(toml::Deserializer::new(content))?;
+    for key in unknown {
+        tracing::warn!("Ignoring unknown config key '{key}'");
+    }
+    Ok(config)
+}
+
+/// Reads a config and the keys in it that do not exist
+pub fn from_toml_unknown<'de, T, D>(de: D) -> Result<(T, Vec<String>), D::Error>
+where
+    T: DeserializeOwned,
+    D: serde::Deserializer<'de>,
+{
+    let mut unknown = Vec::new();
+    let config = serde_ignored::deserialize(de, |path| unknown.push(path.to_string()))?;
+    Ok((config, unknown))
+}
+
+/// Parses a TOML value, or takes it as a string if it's not valid TOML
+pub fn parse_toml_value(s: &str) -> toml::Value {
+    format!("v = {s}")
+        .parse::<toml::Table>()
+        .ok()
+        .and_then(|mut t| t.remove("v"))
+        .unwrap_or_else(|| toml::Value::String(s.to_string()))
+}
+
+/// Sets a nested value, creating the tables in the path
+///
+/// Returns false if the path is empty or goes through a value that is not a
+/// table.
+fn set_toml_value(root: &mut toml::Value, path: &[&str], value: toml::Value) -> bool {
+    let Some((last, tables)) = path.split_last() else {
+        return false;
+    };
+    let mut current = root;
+    for key in tables {
+        let Some(table) = current.as_table_mut() else {
+            return false;
+        };
+        current = table
+            .entry(*key)
+            .or_insert_with(|| toml::Value::Table(Default::default()));
+    }
+    match current.as_table_mut() {
+        Some(table) if !last.is_empty() => {
+            table.insert(last.to_string(), value);
+            true
+        }
+        _ => false,
+    }
+}
+
 pub fn resolve_path(base_path: &Utf8Path, path: &Path) -> Utf8PathBuf {
     let path = Utf8Path::from_path(path).expect(UTF8_PATH_PANIC);
     if path.is_absolute() {
@@ -261,7 +578,7 @@ pub fn global_load<T: DeserializeOwned + Serialize + Default>(name: &str) -> Res
         Ok(mut f) => {
             let mut content = String::new();
             f.read_to_string(&mut content)?;
-            toml::from_str(&content).context("Bad TOML data")
+            from_toml(&content).context("Bad TOML data")
         }
         Err(e) if e.kind() == io::ErrorKind::NotFound => {
             let val = T::default();
@@ -363,3 +680,86 @@ mod extensions_serde {
         de.deserialize_str(ExtensionsVisitor)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn env_override() {
+        let mut config = Config::default();
+        let vars = [
+            ("CHEF_MAX_DEPTH", "3"),
+            ("CHEF_UI__PUBLIC_URL", "https://recipes.example.com"),
+            ("CHEF_EXTENSIONS", "none"),
+            ("OTHER_VAR", "1"),
+        ];
+        config
+            .override_with_vars(vars.iter().map(|(k, v)| (k.to_string(), v.to_string())))
+            .unwrap();
+        assert_eq!(config.max_depth, 3);
+        assert_eq!(
+            config.ui.public_url.as_deref(),
+            Some("https://recipes.example.com")
+        );
+        assert!(config.extensions.is_empty());
+
+        let bad = [("CHEF_MAX_DEPTH__X".to_string(), "1".to_string())];
+        assert!(config.override_with_vars(bad.into_iter()).is_err());
+        let bad = [("CHEF_MAX_DEPTH".to_string(), "deep".to_string())];
+        assert!(config.override_with_vars(bad.into_iter()).is_err());
+    }
+
+    #[test]
+    fn env_override_unknown_key() {
+        let mut value = toml::Value::try_from(Config::default()).unwrap();
+        let before = value.clone();
+        assert!(matches!(
+            apply_env_var(&mut value, "CHEF_MAX_DEPHT", "3"),
+            Ok(EnvVar::UnknownKey(key)) if key == "max_depht"
+        ));
+        assert!(matches!(
+            apply_env_var(&mut value, "CHEF_UI__PUBLIK_URL", "x"),
+            Ok(EnvVar::UnknownKey(key)) if key == "ui.publik_url"
+        ));
+        assert!(matches!(
+            apply_env_var(&mut value, "HOME", "/home/me"),
+            Ok(EnvVar::Other)
+        ));
+        assert_eq!(value, before);
+
+        let mut config = Config::default();
+        let vars = [("CHEF_MAX_DEPHT".to_string(), "3".to_string())];
+        config.override_with_vars(vars.into_iter()).unwrap();
+        assert_eq!(config.max_depth, Config::default().max_depth);
+    }
+
+    #[test]
+    fn unknown_keys() {
+        let unknown = |s| {
+            from_toml_unknown::<Config, _>(toml::Deserializer::new(s))
+                .unwrap()
+                .1
+        };
+        assert!(unknown("max_depth = 3").is_empty());
+        assert_eq!(unknown("max_depht = 3"), ["max_depht"]);
+        assert_eq!(unknown("[ui]\npublik_url = 'x'"), ["ui.publik_url"]);
+        assert!(from_toml::<Config>("max_depth = 'deep'").is_err());
+        let config = from_toml::<Config>("max_depht = 3").unwrap();
+        assert_eq!(config.max_depth, Config::default().max_depth);
+    }
+
+    #[test]
+    fn duration_format() {
+        let default = DurationFormat::default();
+        assert_eq!(default.format(90), "1h 30m");
+        assert_eq!(default.format(1500), "1d 1h");
+        assert_eq!(default.format(0), "0m");
+        let spaced = DurationFormat {
+            hour: "{} h".into(),
+            minute: "{} min".into(),
+            ..Default::default()
+        };
+        assert_eq!(spaced.format(90), "1 h 30 min");
+    }
+}
@@ -0,0 +1,71 @@
+//! Exit codes, so scripts can tell why a command failed
+//!
+//! - `0`: everything went well.
+//! - `1`: a recipe has errors, or warnings with `--warnings-as-errors`.
+//! - `2`: wrong usage, like unknown or conflicting arguments.
+//! - `3`: anything else, like a file that can't be read or a bad config.
+//!
+//! Errors are `3` unless they have an [`ExitError`] in their chain. Clap
+//! already exits with `2` when the arguments can't be parsed.
+
+use std::fmt;
+
+pub const RECIPE_ERRORS: u8 = 1;
+pub const USAGE: u8 = 2;
+pub const FAILURE: u8 = 3;
+
+/// Error with a specific exit code
+#[derive(Debug)]
+pub struct ExitError {
+    code: u8,
+    message: String,
+}
+
+impl ExitError {
+    /// A recipe is not valid
+    pub fn recipe(message: impl Into<String>) -> Self {
+        Self {
+            code: RECIPE_ERRORS,
+            message: message.into(),
+        }
+    }
+
+    /// The arguments can't be used like that
+    pub fn usage(message: impl Into<String>) -> Self {
+        Self {
+            code: USAGE,
+            message: message.into(),
+        }
+    }
+}
+
+impl fmt::Display for ExitError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.message)
+    }
+}
+
+impl std::error::Error for ExitError {}
+
+/// Exit code of an error
+pub fn code(err: &anyhow::Error) -> u8 {
+    err.chain()
+        .find_map(|e| e.downcast_ref::<ExitError>())
+        .map_or(FAILURE, |e| e.code)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anyhow::{anyhow, Context as _};
+
+    #[test]
+    fn codes() {
+        assert_eq!(code(&anyhow!("no such file")), FAILURE);
+        let err = Err::<(), _>(ExitError::recipe("Error parsing recipe"))
+            .context("Pancakes.cook")
+            .unwrap_err();
+        assert_eq!(code(&err), RECIPE_ERRORS);
+        assert_eq!(code(&ExitError::usage("bad").into()), USAGE);
+    }
+}
@@ -1,12 +1,16 @@
+// the OpenAPI document of the web UI is a single `json!`
+#![recursion_limit = "256"]
+
 use anstream::ColorChoice;
 use anyhow::{bail, Context as _, Result};
 use args::{CliArgs, Command, GlobalArgs};
 use camino::{Utf8Path, Utf8PathBuf};
 use clap::Parser;
-use config::{global_load, ChefConfig, Config, CHEF_CONFIG_FILE};
+use config::{global_load, ChefConfig, Config, CHEF_CONFIG_FILE, METADATA_CACHE_FILE};
 use cooklang::{convert::ConverterBuilder, Converter, CooklangParser, ParseOptions};
-use cooklang_fs::LazyFsIndex;
+use cooklang_fs::{LazyFsIndex, MetadataCache};
 use once_cell::sync::OnceCell;
+use std::process::ExitCode;
 use util::metadata_validator;
 
 // commands
@@ -15,49 +19,96 @@ mod cmd;
 // other modules
 mod args;
 mod config;
+mod exit_code;
+#[cfg(any(feature = "import", feature = "serve"))]
+mod remote_image;
+mod timings;
 mod util;
 
 const COOK_DIR: &str = ".cooklang";
 const APP_NAME: &str = "cooklang-chef";
 const UTF8_PATH_PANIC: &str = "chef only supports UTF-8 paths. If this is problem for you, file an issue in the cooklang-chef github repository";
 
-pub fn main() -> Result<()> {
-    let args = CliArgs::parse();
+pub fn main() -> ExitCode {
+    let mut args = CliArgs::parse();
+    if args.global_args.strict_canonical {
+        args.global_args.warnings_as_errors = true;
+    }
 
     let color_ctx = init_color(args.global_args.color);
-    if args.global_args.debug_trace {
-        tracing_subscriber::FmtSubscriber::builder()
-            .compact()
-            .with_max_level(tracing::Level::TRACE)
-            .with_span_events(
-                tracing_subscriber::fmt::format::FmtSpan::CLOSE
-                    | tracing_subscriber::fmt::format::FmtSpan::NEW,
-            )
-            .with_ansi(color_ctx.color_stderr)
-            .init();
-    } else {
-        tracing_subscriber::FmtSubscriber::builder()
-            .compact()
-            .with_target(false)
-            .with_ansi(color_ctx.color_stderr)
-            .init();
-    }
+    let timings = args.global_args.timings.then(timings::Timings::default);
+    init_tracing(
+        args.global_args.debug_trace,
+        args.global_args.quiet,
+        color_ctx.color_stderr,
+        timings.clone(),
+    );
 
-    let ctx = configure_context(args.global_args, color_ctx)?;
+    let res = configure_context(args.global_args, color_ctx).and_then(|ctx| run(ctx, args.command));
+    if let Some(timings) = timings {
+        timings.print();
+    }
+    match res {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            anstream::eprintln!("Error: {e:?}");
+            ExitCode::from(exit_code::code(&e))
+        }
+    }
+}
 
-    match args.command {
+fn run(ctx: Context, command: Command) -> Result<()> {
+    match command {
         Command::Recipe(args) => cmd::recipe::run(&ctx, args),
         Command::List(args) => cmd::list::run(&ctx, args),
+        Command::Grep(args) => cmd::grep::run(&ctx, args),
         #[cfg(feature = "serve")]
         Command::Serve(args) => cmd::serve::run(ctx, args),
+        #[cfg(feature = "serve")]
+        Command::ExportSite(args) => cmd::serve::export_site::run(ctx, args),
         Command::ShoppingList(args) => cmd::shopping_list::run(&ctx, args),
-        Command::Units(args) => cmd::units::run(ctx.parser()?.converter(), args),
-        Command::Convert(args) => cmd::convert::run(ctx.parser()?.converter(), args),
+        Command::Book(args) => cmd::book::run(&ctx, args),
+        Command::Units(args) => ctx
+            .parser()
+            .and_then(|p| cmd::units::run(p.converter(), args)),
+        Command::Convert(args) => ctx
+            .parser()
+            .and_then(|p| cmd::convert::run(p.converter(), args)),
         Command::Config(args) => cmd::config::run(&ctx, args),
         Command::Collection(args) => cmd::collection::run(&ctx, args),
+        Command::Check(args) => cmd::check::run(&ctx, args),
+        Command::Doctor(args) => cmd::doctor::run(&ctx, args),
         Command::GenerateCompletions(args) => cmd::generate_completions::run(args),
         Command::New(args) => cmd::new::run(args, &ctx),
         Command::Edit(args) => cmd::edit::run(args, &ctx),
+        Command::Reveal(args) => cmd::reveal::run(args, &ctx),
+    }
+}
+
+fn init_tracing(debug_trace: bool, quiet: bool, color: bool, timings: Option<timings::Timings>) {
+    use tracing_subscriber::{filter::LevelFilter, layer::SubscriberExt, util::SubscriberInitExt};
+    use tracing_subscriber::{fmt::format::FmtSpan, Layer};
+
+    // timings need the debug spans even if they are not printed
+    let registry =
+        tracing_subscriber::registry().with(timings.map(|t| t.with_filter(LevelFilter::DEBUG)));
+    let fmt = tracing_subscriber::fmt::layer().compact().with_ansi(color);
+    if debug_trace {
+        registry
+            .with(
+                fmt.with_span_events(FmtSpan::CLOSE | FmtSpan::NEW)
+                    .with_filter(LevelFilter::TRACE),
+            )
+            .init();
+    } else {
+        let level = if quiet {
+            LevelFilter::WARN
+        } else {
+            LevelFilter::INFO
+        };
+        registry
+            .with(fmt.with_target(false).with_filter(level))
+            .init();
     }
 }
 
@@ -74,13 +125,18 @@ fn init_color(color: colorchoice_clap::Color) -> ColorContext {
 
 pub struct Context {
     parser: OnceCell<CooklangParser>,
+    /// Parser with every extension, for `--strict-canonical`
+    extended_parser: OnceCell<CooklangParser>,
     recipe_index: LazyFsIndex,
+    metadata_cache: MetadataCache,
     global_args: GlobalArgs,
     base_path: Utf8PathBuf,
     config: config::Config,
     chef_config: config::ChefConfig,
     color: ColorContext,
     is_collection: bool,
+    /// Commands can write to the collection, `false` with `--no-fs-write`
+    fs_write: bool,
 }
 
 #[tracing::instrument(level = "debug", skip_all)]
@@ -88,9 +144,15 @@ fn configure_context(args: GlobalArgs, color_ctx: ColorContext) -> Result<Contex
     let chef_config: ChefConfig =
         global_load(CHEF_CONFIG_FILE).context("Error loading global config file")?;
 
+    let named = args
+        .collection
+        .as_deref()
+        .map(|name| chef_config.collection(name))
+        .transpose()?;
     let base_path = args
         .path
         .as_deref()
+        .or(named)
         .or_else(|| {
             Utf8Path::new(COOK_DIR)
                 .is_dir()
@@ -107,17 +169,31 @@ fn configure_context(args: GlobalArgs, color_ctx: ColorContext) -> Result<Contex
     } else {
         Config::read(&config::config_file_path(base_path))?
     };
+    config.override_with_env()?;
     config.override_with_args(&args);
 
     let recipe_index = cooklang_fs::new_index(base_path, config.max_depth)?
         .config_dir(COOK_DIR.to_string())
+        .ignore_diacritics(config.ignore_diacritics)
         .lazy();
 
+    let is_collection = base_path.join(COOK_DIR).is_dir();
+    let metadata_cache = MetadataCache::new();
+    if config.cache_metadata && is_collection {
+        let path = base_path.join(COOK_DIR).join(METADATA_CACHE_FILE);
+        if let Err(e) = metadata_cache.load_file(&path) {
+            tracing::warn!("Could not load metadata cache: {e}");
+        }
+    }
+
     Ok(Context {
-        is_collection: base_path.join(COOK_DIR).is_dir(),
+        is_collection,
+        fs_write: !args.no_fs_write,
         base_path: base_path.to_owned(),
         parser: OnceCell::new(),
+        extended_parser: OnceCell::new(),
         recipe_index,
+        metadata_cache,
         config,
         chef_config,
         global_args: args,
@@ -133,6 +209,13 @@ impl Context {
             .get_or_try_init(|| configure_parser(&self.config, &self.base_path))
     }
 
+    fn extended_parser(&self) -> Result<&CooklangParser> {
+        self.extended_parser.get_or_try_init(|| {
+            let converter = self.parser()?.converter().clone();
+            Ok(CooklangParser::new(cooklang::Extensions::all(), converter))
+        })
+    }
+
     fn checker(
         &self,
         relative_to: Option<&Utf8Path>,
@@ -160,6 +243,32 @@ impl Context {
         }
     }
 
+    /// Fails if writing to the collection is disabled
+    fn check_fs_write(&self) -> Result<()> {
+        if !self.fs_write {
+            bail!("Writing to the collection is disabled with `--no-fs-write`");
+        }
+        Ok(())
+    }
+
+    /// Saves the metadata cache in the collection, if enabled
+    fn save_metadata_cache(&self) {
+        if !(self.config.cache_metadata && self.is_collection && self.fs_write) {
+            return;
+        }
+        let path = self.base_path.join(COOK_DIR).join(METADATA_CACHE_FILE);
+        if let Err(e) = self.metadata_cache.save_file(&path) {
+            tracing::warn!("Could not save metadata cache: {e}");
+        }
+    }
+
+    /// Prints a message about what was done, unless `--quiet`
+    fn status(&self, msg: impl std::fmt::Display) {
+        if !self.global_args.quiet {
+            anstream::eprintln!("{msg}");
+        }
+    }
+
     fn parse_options(&self, relative_to: Option<&Utf8Path>) -> ParseOptions {
         ParseOptions {
             recipe_ref_check: self.checker(relative_to),
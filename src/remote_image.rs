@@ -0,0 +1,146 @@
+//! Images of the `image` metadata that are in other sites
+//!
+//! They can be downloaded to `.cooklang/images`, named by a hash of the url,
+//! so the recipe still has an image if the site goes down and the web UI does
+//! not need to load it from the other site.
+
+use std::{io::Read, time::Duration};
+
+use anyhow::{bail, Context as _, Result};
+use camino::{Utf8Path, Utf8PathBuf};
+
+use crate::{util::url_scheme, COOK_DIR};
+
+/// Dir inside [`COOK_DIR`] with the downloaded images
+pub const CACHE_DIR: &str = "images";
+
+/// Images bigger than this are not downloaded
+const MAX_SIZE: u64 = 20 * 1024 * 1024;
+/// Time to connect to the other site
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(10);
+/// Time to wait for each read of the response
+const READ_TIMEOUT: Duration = Duration::from_secs(30);
+/// Time for the whole download, so slow sites can't hold a request forever
+const TIMEOUT: Duration = Duration::from_secs(120);
+
+/// If the image is a http or https url
+pub fn is_remote(image: &str) -> bool {
+    url_scheme(image).is_some_and(|s| matches!(s.to_ascii_lowercase().as_str(), "http" | "https"))
+}
+
+/// Name of the downloaded image without the extension
+///
+/// It's a FNV-1a hash of the url, so it does not change between versions.
+pub fn cache_key(url: &str) -> String {
+    let mut hash: u64 = 0xcbf29ce484222325;
+    for b in url.bytes() {
+        hash ^= u64::from(b);
+        hash = hash.wrapping_mul(0x100000001b3);
+    }
+    format!("{hash:016x}")
+}
+
+pub fn cache_dir(base_path: &Utf8Path) -> Utf8PathBuf {
+    base_path.join(COOK_DIR).join(CACHE_DIR)
+}
+
+/// Downloaded image with a key, if any
+pub fn cached(base_path: &Utf8Path, key: &str) -> Option<Utf8PathBuf> {
+    let dir = cache_dir(base_path);
+    cooklang_fs::IMAGE_EXTENSIONS
+        .iter()
+        .map(|ext| dir.join(format!("{key}.{ext}")))
+        .find(|path| path.is_file())
+}
+
+/// Downloads an image, returning its bytes and the extension
+pub fn fetch(url: &str) -> Result<(Vec<u8>, &'static str)> {
+    if !is_remote(url) {
+        bail!("Only http and https images can be downloaded: {url}");
+    }
+    let agent = ureq::AgentBuilder::new()
+        .timeout_connect(CONNECT_TIMEOUT)
+        .timeout_read(READ_TIMEOUT)
+        .timeout(TIMEOUT)
+        .user_agent(concat!("chef/", env!("CARGO_PKG_VERSION")))
+        .build();
+    let res = agent
+        .get(url)
+        .call()
+        .with_context(|| format!("Failed to fetch {url}"))?;
+    let Some(ext) = extension(res.content_type(), url) else {
+        bail!("Not an image: {url}");
+    };
+    let mut bytes = Vec::new();
+    res.into_reader()
+        .take(MAX_SIZE + 1)
+        .read_to_end(&mut bytes)
+        .context("Failed to read the image")?;
+    if bytes.len() as u64 > MAX_SIZE {
+        bail!("The image is too big: {url}");
+    }
+    Ok((bytes, ext))
+}
+
+/// Downloads an image to the cache of the collection
+pub fn download(base_path: &Utf8Path, url: &str) -> Result<Utf8PathBuf> {
+    let (bytes, ext) = fetch(url)?;
+    save(base_path, &cache_key(url), ext, &bytes)
+}
+
+/// Writes an image to the cache, replacing one with other extension
+pub fn save(base_path: &Utf8Path, key: &str, ext: &str, bytes: &[u8]) -> Result<Utf8PathBuf> {
+    if let Some(old) = cached(base_path, key) {
+        std::fs::remove_file(&old).with_context(|| format!("Failed to remove '{old}'"))?;
+    }
+    let dir = cache_dir(base_path);
+    std::fs::create_dir_all(&dir).with_context(|| format!("Failed to create '{dir}'"))?;
+    let path = dir.join(format!("{key}.{ext}"));
+    std::fs::write(&path, bytes).with_context(|| format!("Failed to write '{path}'"))?;
+    Ok(path)
+}
+
+/// Extension from the content type, or the url if it's not specific
+fn extension(content_type: &str, url: &str) -> Option<&'static str> {
+    let from_type = match content_type.trim().to_ascii_lowercase().as_str() {
+        "image/jpeg" | "image/jpg" => Some("jpg"),
+        "image/png" => Some("png"),
+        "image/gif" => Some("gif"),
+        "image/webp" => Some("webp"),
+        "image/heic" => Some("heic"),
+        "application/octet-stream" | "binary/octet-stream" | "" => None,
+        _ => return None,
+    };
+    from_type.or_else(|| {
+        let path = url.split(['?', '#']).next().unwrap_or(url);
+        let (_, ext) = path.rsplit_once('.')?;
+        let ext = ext.to_ascii_lowercase();
+        cooklang_fs::IMAGE_EXTENSIONS
+            .iter()
+            .find(|e| **e == ext)
+            .copied()
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn remote_urls() {
+        assert!(is_remote("https://example.com/a.jpg"));
+        assert!(is_remote("HTTP://example.com/a.jpg"));
+        assert!(!is_remote("Pancakes.jpg"));
+        assert_eq!(cache_key("https://example.com/a.jpg").len(), 16);
+    }
+
+    #[test]
+    fn image_extension() {
+        assert_eq!(extension("image/jpeg", "https://a.com/x"), Some("jpg"));
+        assert_eq!(
+            extension("application/octet-stream", "https://a.com/x.PNG?w=200"),
+            Some("png")
+        );
+        assert_eq!(extension("text/html", "https://a.com/x.png"), None);
+    }
+}
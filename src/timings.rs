@@ -0,0 +1,131 @@
+//! Time spent in each part of a command, for `--timings`
+//!
+//! A tracing layer adds up the time spans are entered, grouped by name. Spans
+//! can be nested, so the time of a part can also be in the one containing it,
+//! like looking up referenced recipes while parsing.
+
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+use tracing::{span, Subscriber};
+use tracing_subscriber::{layer::Context, registry::LookupSpan, Layer};
+
+#[derive(Default, Clone)]
+pub struct Timings {
+    totals: Arc<Mutex<HashMap<&'static str, Total>>>,
+}
+
+#[derive(Default, Clone, Copy)]
+struct Total {
+    count: u32,
+    busy: Duration,
+}
+
+/// Stored in every span while it's open
+#[derive(Default)]
+struct Busy {
+    entered: Option<Instant>,
+    busy: Duration,
+}
+
+/// Name a span is reported as
+fn part(span_name: &'static str) -> &'static str {
+    match span_name {
+        "index_all" | "lazy_index_get" => "indexing",
+        "configure_parser" => "loading units",
+        name => name,
+    }
+}
+
+impl Timings {
+    /// Parts with their number of spans and total time, slowest first
+    fn summary(&self) -> Vec<(&'static str, Total)> {
+        let totals = self.totals.lock().unwrap();
+        let mut summary = totals.iter().map(|(k, v)| (*k, *v)).collect::<Vec<_>>();
+        summary.sort_by(|a, b| b.1.busy.cmp(&a.1.busy).then(a.0.cmp(b.0)));
+        summary
+    }
+
+    pub fn print(&self) {
+        use yansi::Paint;
+
+        let mut table = tabular::Table::new("  {:<}  {:>} {:>}");
+        for (name, total) in self.summary() {
+            table.add_row(
+                tabular::Row::new()
+                    .with_ansi_cell(name.green())
+                    .with_cell(format!("{:.1?}", total.busy))
+                    .with_cell(format!("({}x)", total.count)),
+            );
+        }
+        anstream::eprintln!("{}\n{table}", "Timings:".bold());
+    }
+}
+
+impl<S> Layer<S> for Timings
+where
+    S: Subscriber + for<'a> LookupSpan<'a>,
+{
+    fn on_new_span(&self, _attrs: &span::Attributes<'_>, id: &span::Id, ctx: Context<'_, S>) {
+        if let Some(span) = ctx.span(id) {
+            span.extensions_mut().insert(Busy::default());
+        }
+    }
+
+    fn on_enter(&self, id: &span::Id, ctx: Context<'_, S>) {
+        if let Some(span) = ctx.span(id) {
+            if let Some(b) = span.extensions_mut().get_mut::<Busy>() {
+                b.entered = Some(Instant::now());
+            }
+        }
+    }
+
+    fn on_exit(&self, id: &span::Id, ctx: Context<'_, S>) {
+        if let Some(span) = ctx.span(id) {
+            if let Some(b) = span.extensions_mut().get_mut::<Busy>() {
+                if let Some(entered) = b.entered.take() {
+                    b.busy += entered.elapsed();
+                }
+            }
+        }
+    }
+
+    fn on_close(&self, id: span::Id, ctx: Context<'_, S>) {
+        let Some(span) = ctx.span(&id) else { return };
+        let Some(busy) = span.extensions().get::<Busy>().map(|b| b.busy) else {
+            return;
+        };
+        let mut totals = self.totals.lock().unwrap();
+        let total = totals.entry(part(span.name())).or_default();
+        total.count += 1;
+        total.busy += busy;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tracing_subscriber::layer::SubscriberExt;
+
+    #[test]
+    fn adds_up_spans() {
+        let timings = Timings::default();
+        let subscriber = tracing_subscriber::registry().with(timings.clone());
+        tracing::subscriber::with_default(subscriber, || {
+            for _ in 0..3 {
+                let _s = tracing::debug_span!("parsing").entered();
+                std::thread::sleep(Duration::from_millis(2));
+            }
+            let _s = tracing::debug_span!("lazy_index_get").entered();
+        });
+        let summary = timings.summary();
+        assert_eq!(summary[0].0, "parsing");
+        assert_eq!(summary[0].1.count, 3);
+        assert!(summary[0].1.busy >= Duration::from_millis(6));
+        assert_eq!(summary[1].0, "indexing");
+        assert_eq!(summary[1].1.count, 1);
+    }
+}
@@ -1,16 +1,24 @@
-use std::borrow::Cow;
+use std::{
+    borrow::Cow,
+    collections::{BTreeMap, HashMap},
+    io::Write as _,
+    sync::Arc,
+};
 
 use anyhow::{bail, Context as _, Result};
 
 use camino::Utf8Path;
 use cooklang::{
     analysis::{CheckOptions, CheckResult},
-    metadata::CooklangValueExt,
-    Metadata, ScalableRecipe,
+    convert::{System, Unit},
+    error::{Severity, SourceDiag, SourceReport, Stage},
+    metadata::{CooklangValueExt, NameAndUrl},
+    scale::ScaleOutcome,
+    Converter, Metadata, Quantity, ScalableRecipe, ScaledRecipe,
 };
 use cooklang_fs::{RecipeContent, RecipeEntry};
 
-use crate::Context;
+use crate::{args::MessageFormat, config::Config, exit_code::ExitError, Context};
 
 /// Utility to create lazy regex
 /// from <https://docs.rs/once_cell/latest/once_cell/#lazily-compiled-regex>
@@ -25,6 +33,7 @@ macro_rules! regex {
 }
 pub(crate) use regex;
 
+#[tracing::instrument(level = "debug", name = "rendering", skip_all)]
 pub fn write_to_output<F>(output: Option<&Utf8Path>, f: F) -> Result<()>
 where
     F: FnOnce(Box<dyn std::io::Write>) -> Result<()>,
@@ -40,7 +49,294 @@ where
     Ok(())
 }
 
-pub fn map_recipe(mut r: ScalableRecipe) -> ScalableRecipe {
+/// Percent encodes a path for a URL, keeping the `/`
+pub fn url_encode_path(path: &str) -> String {
+    let mut encoded = String::with_capacity(path.len());
+    for b in path.bytes() {
+        match b {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' | b'/' => {
+                encoded.push(b as char)
+            }
+            _ => encoded.push_str(&format!("%{b:02X}")),
+        }
+    }
+    encoded
+}
+
+/// Scheme of a url, like `https`, none for paths
+pub fn url_scheme(url: &str) -> Option<&str> {
+    let (scheme, _) = url.split_once(':')?;
+    let mut chars = scheme.chars();
+    let valid = chars.next().is_some_and(|c| c.is_ascii_alphabetic())
+        && chars.all(|c| c.is_ascii_alphanumeric() || matches!(c, '+' | '-' | '.'));
+    // not windows paths like `C:\image.jpg`
+    (valid && scheme.len() > 1).then_some(scheme)
+}
+
+/// Renders markdown to HTML, for the pages of the web UI and the books
+///
+/// Raw HTML is written as text and links to other schemes than `http`,
+/// `https` and `mailto` are removed, so the text can't run scripts.
+pub fn markdown_to_html(text: &str) -> String {
+    use pulldown_cmark::{html, CowStr, Event, Options, Parser, Tag};
+
+    let parser = Parser::new_ext(
+        text,
+        Options::ENABLE_TABLES | Options::ENABLE_STRIKETHROUGH | Options::ENABLE_TASKLISTS,
+    )
+    .map(|event| match event {
+        Event::Html(html) | Event::InlineHtml(html) => Event::Text(html),
+        Event::Start(Tag::Link {
+            link_type,
+            dest_url,
+            title,
+            id,
+        }) if !is_safe_url(&dest_url) => Event::Start(Tag::Link {
+            link_type,
+            dest_url: CowStr::Borrowed(""),
+            title,
+            id,
+        }),
+        Event::Start(Tag::Image {
+            link_type,
+            dest_url,
+            title,
+            id,
+        }) if !is_safe_url(&dest_url) => Event::Start(Tag::Image {
+            link_type,
+            dest_url: CowStr::Borrowed(""),
+            title,
+            id,
+        }),
+        event => event,
+    });
+    let mut out = String::with_capacity(text.len() * 3 / 2);
+    html::push_html(&mut out, parser);
+    out
+}
+
+/// Relative urls and `http`, `https` and `mailto` ones
+fn is_safe_url(url: &str) -> bool {
+    url_scheme(url)
+        .is_none_or(|s| matches!(s.to_ascii_lowercase().as_str(), "http" | "https" | "mailto"))
+}
+
+/// Escapes text to put it in HTML or XML, also inside attributes
+pub fn escape_markup(s: &str) -> String {
+    let mut out = String::with_capacity(s.len());
+    for c in s.chars() {
+        match c {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '"' => out.push_str("&quot;"),
+            c => out.push(c),
+        }
+    }
+    out
+}
+
+/// Opens a file with the editor and waits for it to close
+pub fn open_editor(ctx: &Context, path: &Utf8Path) -> Result<()> {
+    let editor = ctx
+        .chef_config
+        .editor()
+        .context("Could not determine editor")?;
+    run_with_path(&editor, path, "editor")
+}
+
+/// Runs a command of the config, like the editor, with a path as the last
+/// argument and waits for it
+///
+/// `what` names the command in the messages.
+pub fn run_with_path(command: &[String], path: &Utf8Path, what: &str) -> Result<()> {
+    let Some((cmd, args)) = command.split_first() else {
+        bail!("The {what} command is empty");
+    };
+    let ok = std::process::Command::new(cmd)
+        .args(args)
+        .arg(path)
+        .status()
+        .with_context(|| format!("Could not run '{cmd}'"))?
+        .success();
+    if !ok {
+        tracing::warn!("The {what} didn't exit successfully")
+    }
+    Ok(())
+}
+
+/// Current UTC date as `YYYY-MM-DD`
+pub fn today() -> String {
+    let secs = std::time::SystemTime::now()
+        .duration_since(std::time::SystemTime::UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs();
+    let (year, month, day) = civil_from_days((secs / 86400) as i64);
+    format!("{year:04}-{month:02}-{day:02}")
+}
+
+/// Current UTC date and time, like `2025-01-14T18:30:00Z`
+pub fn now_rfc3339() -> String {
+    let secs = std::time::SystemTime::now()
+        .duration_since(std::time::SystemTime::UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs();
+    rfc3339(secs)
+}
+
+/// UTC date and time of seconds since the epoch, like `2024-05-01T10:20:30Z`
+pub fn rfc3339(secs: u64) -> String {
+    let (year, month, day) = civil_from_days((secs / 86400) as i64);
+    let t = secs % 86400;
+    format!(
+        "{year:04}-{month:02}-{day:02}T{:02}:{:02}:{:02}Z",
+        t / 3600,
+        t % 3600 / 60,
+        t % 60
+    )
+}
+
+/// Year, month and day of a number of days since 1970-01-01
+// http://howardhinnant.github.io/date_algorithms.html#civil_from_days
+pub fn civil_from_days(days: i64) -> (i64, u32, u32) {
+    let z = days + 719468;
+    let era = z.div_euclid(146097);
+    let doe = z.rem_euclid(146097);
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = (doy - (153 * mp + 2) / 5 + 1) as u32;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 } as u32;
+    let year = yoe + era * 400 + if month <= 2 { 1 } else { 0 };
+    (year, month, day)
+}
+
+/// Days since 1970-01-01 of a date, the inverse of [`civil_from_days`]
+// http://howardhinnant.github.io/date_algorithms.html#days_from_civil
+pub fn days_from_civil(year: i64, month: u32, day: u32) -> i64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let yoe = year.rem_euclid(400);
+    let mp = if month > 2 { month - 3 } else { month + 9 } as i64;
+    let doy = (153 * mp + 2) / 5 + day as i64 - 1;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+    era * 146097 + doe - 719468
+}
+
+/// Writes the units with the spellings in `display_units`, so `tablespoon`,
+/// `tbs` and `tbsp` are all written as `tbsp`
+///
+/// Spellings that are not a known unit are ignored with a warning.
+pub fn respell_units(recipe: &mut ScaledRecipe, spellings: &[String], converter: &Converter) {
+    if spellings.is_empty() {
+        return;
+    }
+    let mut table = Vec::with_capacity(spellings.len());
+    for spelling in spellings {
+        match converter.find_unit(spelling) {
+            Some(unit) => table.push((unit, spelling.as_str())),
+            None => tracing::warn!("Unknown unit '{spelling}' in display_units"),
+        }
+    }
+    let respell = |q: &mut Quantity<cooklang::Value>| {
+        let Some(unit) = q.unit_info(converter) else {
+            return;
+        };
+        if let Some((_, spelling)) = table.iter().find(|(u, _)| Arc::ptr_eq(u, &unit)) {
+            *q = Quantity::new(q.value().clone(), Some(spelling.to_string()));
+        }
+    };
+    recipe
+        .ingredients
+        .iter_mut()
+        .filter_map(|i| i.quantity.as_mut())
+        .chain(recipe.timers.iter_mut().filter_map(|t| t.quantity.as_mut()))
+        .chain(recipe.inline_quantities.iter_mut())
+        .for_each(respell);
+}
+
+/// Converts a recipe to a unit system, following the `unit_thresholds`
+///
+/// The converter picks the best unit for each quantity, then quantities below
+/// the threshold of their unit go to the next smaller best unit of the same
+/// system. Thresholds of unknown units are ignored with a warning.
+pub fn convert_recipe(
+    recipe: &mut ScaledRecipe,
+    to: System,
+    thresholds: &HashMap<String, f64>,
+    converter: &Converter,
+) {
+    let _ = recipe.convert(to, converter);
+    if thresholds.is_empty() {
+        return;
+    }
+    let mut table = Vec::with_capacity(thresholds.len());
+    for (unit, min) in thresholds {
+        match converter.find_unit(unit) {
+            Some(unit) => table.push((unit, *min)),
+            None => tracing::warn!("Unknown unit '{unit}' in unit_thresholds"),
+        }
+    }
+    let apply = |q: &mut Quantity<cooklang::Value>| {
+        let Some(unit) = q.unit_info(converter) else {
+            return;
+        };
+        let Some(&(_, min)) = table.iter().find(|(u, _)| Arc::ptr_eq(u, &unit)) else {
+            return;
+        };
+        let value = match q.value() {
+            cooklang::Value::Number(n) => n.value(),
+            cooklang::Value::Range { start, .. } => start.value(),
+            cooklang::Value::Text(_) => return,
+        };
+        if value >= min {
+            return;
+        }
+        let smaller = converter
+            .all_units()
+            .filter(|u| {
+                u.physical_quantity == unit.physical_quantity
+                    && u.system == unit.system
+                    && u.ratio < unit.ratio
+                    && converter.is_best_unit(u)
+            })
+            .max_by(|a, b| a.ratio.total_cmp(&b.ratio));
+        if let Some(target) = smaller.and_then(|u| u.symbols.first().or(u.names.first())) {
+            let _ = q.convert(target.as_ref(), converter);
+        }
+    };
+    recipe
+        .ingredients
+        .iter_mut()
+        .filter_map(|i| i.quantity.as_mut())
+        .chain(recipe.timers.iter_mut().filter_map(|t| t.quantity.as_mut()))
+        .chain(recipe.inline_quantities.iter_mut())
+        .for_each(apply);
+}
+
+/// Units a unit can be converted to, the ones of the same physical quantity,
+/// grouped by system and from small to big
+///
+/// The unit itself is left out. None if the unit is not known.
+pub fn compatible_units<'c>(
+    converter: &'c Converter,
+    unit: &str,
+) -> Option<BTreeMap<Option<System>, Vec<&'c Unit>>> {
+    let unit = converter.find_unit(unit)?;
+    let mut groups: BTreeMap<_, Vec<&Unit>> = BTreeMap::new();
+    for other in converter.all_units() {
+        if other.physical_quantity == unit.physical_quantity && other.names != unit.names {
+            groups.entry(other.system).or_default().push(other);
+        }
+    }
+    for units in groups.values_mut() {
+        units.sort_by(|a, b| a.ratio.total_cmp(&b.ratio));
+    }
+    Some(groups)
+}
+
+pub fn map_recipe(mut r: ScalableRecipe, config: &Config) -> ScalableRecipe {
+    normalize_tags(&mut r.metadata, &config.tag_aliases);
     if let Some(emoji_str) = r
         .metadata
         .get("emoji")
@@ -65,10 +361,16 @@ pub enum Input {
 
 impl Input {
     pub fn parse(&self, ctx: &Context) -> Result<cooklang::ScalableRecipe> {
-        self.parse_result(ctx)
-            .and_then(|r| unwrap_recipe(r, self.file_name(), self.text()?.as_ref(), ctx))
+        let recipe = self
+            .parse_result(ctx)
+            .and_then(|r| unwrap_recipe(r, self, self.text()?.as_ref(), ctx))?;
+        if ctx.global_args.strict_canonical {
+            check_canonical(&recipe, self, ctx)?;
+        }
+        Ok(recipe)
     }
 
+    #[tracing::instrument(level = "debug", name = "parsing", skip_all)]
     pub fn parse_result(&self, ctx: &Context) -> Result<cooklang::RecipeResult> {
         let parser = ctx.parser()?;
         let options = match self {
@@ -77,7 +379,7 @@ impl Input {
         };
         let r = parser
             .parse_with_options(self.text()?.as_ref(), options)
-            .map(map_recipe);
+            .map(|r| map_recipe(r, &ctx.config));
         Ok(r)
     }
 
@@ -114,11 +416,18 @@ impl Input {
             Input::Stdin { .. } => None,
         }
     }
+
+    /// Path to show in diagnostics, the file name if there is no path
+    pub fn display_path(&self) -> &str {
+        self.path()
+            .map(Utf8Path::as_str)
+            .unwrap_or(self.file_name())
+    }
 }
 
 pub fn unwrap_recipe(
     r: cooklang::RecipeResult,
-    file_name: &str,
+    input: &Input,
     text: &str,
     ctx: &Context,
 ) -> Result<cooklang::ScalableRecipe> {
@@ -127,17 +436,96 @@ pub fn unwrap_recipe(
         if ctx.global_args.ignore_warnings {
             report.remove_warnings();
         }
-        report.eprint(file_name, text, ctx.color.color_stderr)?;
-        bail!("Error parsing recipe");
+        print_report(&report, input, text, ctx)?;
+        bail!(ExitError::recipe("Error parsing recipe"));
     } else {
         let (recipe, warnings) = r.into_result().unwrap();
         if !ctx.global_args.ignore_warnings && !warnings.is_empty() {
-            warnings.eprint(file_name, text, ctx.color.color_stderr)?;
+            print_report(&warnings, input, text, ctx)?;
         }
         Ok(recipe)
     }
 }
 
+/// Fails if the recipe is read differently with every extension enabled
+///
+/// Used by `--strict-canonical`. Extensions reuse the cooklang syntax, so
+/// their recipes can be valid but mean something else to other tools.
+pub fn check_canonical(recipe: &ScalableRecipe, input: &Input, ctx: &Context) -> Result<()> {
+    let extended = ctx
+        .extended_parser()?
+        .parse_with_options(input.text()?.as_ref(), ctx.parse_options(input.path()))
+        .map(|r| map_recipe(r, &ctx.config));
+    let Some(extended) = extended.output() else {
+        return Ok(());
+    };
+    if serde_json::to_value(recipe)? != serde_json::to_value(extended)? {
+        bail!(ExitError::recipe(format!(
+            "'{}' uses syntax from extensions to the cooklang spec, other tools may read it differently",
+            input.display_path()
+        )));
+    }
+    Ok(())
+}
+
+/// Prints a report of a recipe with the format given in the args
+pub fn print_report(report: &SourceReport, input: &Input, text: &str, ctx: &Context) -> Result<()> {
+    match ctx.global_args.message_format {
+        MessageFormat::Human => report.eprint(input.file_name(), text, ctx.color.color_stderr)?,
+        MessageFormat::Json => {
+            let mut stderr = std::io::stderr().lock();
+            for diag in report.iter() {
+                let json = diagnostic_json(diag, input.display_path(), text);
+                serde_json::to_writer(&mut stderr, &json)?;
+                writeln!(stderr)?;
+            }
+        }
+    }
+    Ok(())
+}
+
+fn diagnostic_json(diag: &SourceDiag, path: &str, text: &str) -> serde_json::Value {
+    let severity = match diag.severity {
+        Severity::Error => "error",
+        Severity::Warning => "warning",
+    };
+    let code = match diag.stage {
+        Stage::Parse => "parse",
+        Stage::Analysis => "analysis",
+    };
+    let labels = diag
+        .labels
+        .iter()
+        .map(|(span, message)| {
+            let (line, column) = line_column(text, span.start());
+            serde_json::json!({
+                "start": span.start(),
+                "end": span.end(),
+                "line": line,
+                "column": column,
+                "message": message,
+            })
+        })
+        .collect::<Vec<_>>();
+    serde_json::json!({
+        "path": path,
+        "severity": severity,
+        "code": code,
+        "message": diag.message,
+        "labels": labels,
+        "help": diag.hints,
+    })
+}
+
+/// 1-based line and column (in chars) of a byte offset
+pub(crate) fn line_column(text: &str, offset: usize) -> (usize, usize) {
+    let before = &text[..offset.min(text.len())];
+    let line = before.matches('\n').count() + 1;
+    let line_start = before.rfind('\n').map_or(0, |i| i + 1);
+    let column = before[line_start..].chars().count() + 1;
+    (line, column)
+}
+
 pub fn meta_name(meta: &cooklang::Metadata) -> Option<&str> {
     ["name", "title"]
         .iter()
@@ -145,9 +533,76 @@ pub fn meta_name(meta: &cooklang::Metadata) -> Option<&str> {
         .and_then(|n| n.as_str())
 }
 
+/// Unit system the recipe prefers with the `units` metadata key
+///
+/// Used as the default conversion target when none is given.
+pub fn preferred_system(meta: &Metadata) -> Option<cooklang::convert::System> {
+    meta.get("units")
+        .and_then(|v| v.as_str())
+        .and_then(|s| s.trim().parse().ok())
+}
+
+/// Entries of a name and URL key like `author` or `source`
+///
+/// The value can be a single entry or a list of them.
+pub fn name_and_url_list(meta: &Metadata, key: &str) -> Vec<NameAndUrl> {
+    meta.get(key)
+        .map(|value| match value.as_sequence() {
+            Some(entries) => entries.iter().filter_map(parse_name_and_url).collect(),
+            None => parse_name_and_url(value).into_iter().collect(),
+        })
+        .unwrap_or_default()
+}
+
+fn parse_name_and_url(value: &serde_yaml::Value) -> Option<NameAndUrl> {
+    // reuse the parser handling of a single entry
+    let mut single = Metadata::default();
+    single.map.insert("author".into(), value.clone());
+    single.author()
+}
+
+/// Ingredients of a scaled recipe that did not scale
+#[derive(Debug, Default, serde::Serialize)]
+pub struct ScaleReport<'a> {
+    /// Ingredients with a fixed quantity
+    pub fixed: Vec<&'a str>,
+    /// Ingredients that failed to scale and why
+    pub errors: Vec<(&'a str, String)>,
+}
+
+impl ScaleReport<'_> {
+    pub fn is_empty(&self) -> bool {
+        self.fixed.is_empty() && self.errors.is_empty()
+    }
+}
+
+/// Lists the ingredients that did not scale
+///
+/// Empty if the recipe was not scaled.
+pub fn scale_report(recipe: &ScaledRecipe) -> ScaleReport<'_> {
+    let mut report = ScaleReport::default();
+    let Some(data) = recipe.scaled_data() else {
+        return report;
+    };
+    for (igr, outcome) in recipe.ingredients.iter().zip(&data.ingredients) {
+        if !igr.modifiers().should_be_listed() {
+            continue;
+        }
+        let name = igr.name.as_str();
+        match outcome {
+            ScaleOutcome::Fixed if !report.fixed.contains(&name) => report.fixed.push(name),
+            ScaleOutcome::Error(err) if !report.errors.iter().any(|(n, _)| *n == name) => {
+                report.errors.push((name, err.to_string()))
+            }
+            _ => {}
+        }
+    }
+    report
+}
+
 pub struct CachedRecipeEntry {
     entry: RecipeEntry,
-    metadata: once_cell::unsync::OnceCell<Box<Metadata>>,
+    metadata: once_cell::unsync::OnceCell<Arc<Metadata>>,
     parsed: once_cell::unsync::OnceCell<Box<cooklang::RecipeResult>>,
 }
 
@@ -171,12 +626,20 @@ impl CachedRecipeEntry {
                 let r = self
                     .content()?
                     .parse_with_options(parser, ctx.parse_options(Some(self.entry.path())))
-                    .map(map_recipe);
+                    .map(|r| map_recipe(r, &ctx.config));
                 Ok(Box::new(r))
             })
             .map(|r| r.as_ref())
     }
 
+    /// Drafts are skipped unless `--include-drafts`
+    pub fn is_hidden_draft(&self, ctx: &Context) -> bool {
+        if ctx.global_args.include_drafts {
+            return false;
+        }
+        self.entry.is_draft_name() || self.metadata(ctx, false).is_ok_and(is_draft_metadata)
+    }
+
     pub fn metadata(&self, ctx: &Context, try_full: bool) -> Result<&Metadata> {
         // first try cached full recipe
         if let Some(m) = self
@@ -194,16 +657,14 @@ impl CachedRecipeEntry {
                 if try_full {
                     if let Ok(r) = self.parsed(ctx) {
                         if let Some(m) = r.output().map(|r| &r.metadata) {
-                            return Ok(Box::new(m.clone()));
+                            return Ok(Arc::new(m.clone()));
                         }
                     }
                 }
-                let m = self
-                    .content()?
-                    .metadata_with_options(parser, ctx.parse_options(None))
-                    .into_output()
-                    .ok_or(anyhow::anyhow!("Can't parse metadata"))?;
-                Ok(Box::new(m))
+                let m = ctx.metadata_cache.get_or_parse(&self.entry, |content| {
+                    content.metadata_with_options(parser, ctx.parse_options(None))
+                })?;
+                Ok(m)
             })
             .map(|m| m.as_ref())
     }
@@ -234,7 +695,7 @@ pub fn metadata_validator(
                     let t = t.trim();
                     if t.is_empty() {
                         return CheckResult::Warning(vec!["The tag is empty".into()]);
-                    } else if t.chars().count() > 32 {
+                    } else if t.split('/').any(|l| l.chars().count() > 32) {
                         return CheckResult::Warning(vec![TAG_TOO_LONG_MSG.into()]);
                     } else if !is_valid_tag(t) {
                         return CheckResult::Warning(vec![IS_VALID_TAG_MSG.into()]);
@@ -242,20 +703,167 @@ pub fn metadata_validator(
                 }
             }
         }
+        "author" | "source" => {
+            if let Some(entries) = value.as_sequence() {
+                if entries.iter().any(|e| parse_name_and_url(e).is_none()) {
+                    return CheckResult::Warning(vec![
+                        "Every entry should be a name, a URL or both".into(),
+                    ]);
+                }
+            }
+        }
+        "units" => {
+            if value
+                .as_str()
+                .and_then(|s| s.trim().parse::<cooklang::convert::System>().ok())
+                .is_none()
+            {
+                return CheckResult::Warning(vec!["Value should be metric or imperial".into()]);
+            }
+        }
+        "image" => {
+            let scheme = value.as_str().and_then(url_scheme);
+            let is_web = |s: &str| matches!(s.to_ascii_lowercase().as_str(), "http" | "https");
+            if scheme.is_some_and(|s| !is_web(s)) {
+                opts.include(false);
+                return CheckResult::Warning(vec!["Image URLs should be http or https".into()]);
+            }
+        }
         "emoji" => {
             if value.as_str().and_then(get_emoji).is_none() {
                 opts.include(false);
                 return CheckResult::Warning(vec!["Value is not an emoji".into()]);
             }
         }
-        _ => {}
+        key => {
+            if let Some(field) = MetaField::from_key(key) {
+                let known = value.as_str().and_then(|v| field.known_value(v));
+                if known.is_none() {
+                    return CheckResult::Warning(vec![format!(
+                        "Unknown {key}, expected one of: {}",
+                        field.values().join(", ")
+                    )]);
+                }
+            }
+        }
     }
     CheckResult::Ok
 }
 
+/// Metadata keys with a known set of values that can be used to filter
+/// recipes
+///
+/// Values are compared normalized, so `Main course` and `main` are the same
+/// course. Unknown values are a warning but still work as filters.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MetaField {
+    Difficulty,
+    Cuisine,
+    Course,
+}
+
+impl MetaField {
+    pub const ALL: [Self; 3] = [Self::Difficulty, Self::Cuisine, Self::Course];
+
+    pub fn from_key(key: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|f| f.key() == key)
+    }
+
+    pub fn key(self) -> &'static str {
+        match self {
+            MetaField::Difficulty => "difficulty",
+            MetaField::Cuisine => "cuisine",
+            MetaField::Course => "course",
+        }
+    }
+
+    /// Known values with their aliases
+    fn table(self) -> &'static [(&'static str, &'static [&'static str])] {
+        match self {
+            MetaField::Difficulty => &[
+                ("easy", &["simple", "beginner"]),
+                ("medium", &["intermediate", "moderate"]),
+                ("hard", &["difficult", "advanced"]),
+            ],
+            MetaField::Cuisine => &[
+                ("american", &[]),
+                ("british", &["english"]),
+                ("chinese", &[]),
+                ("french", &[]),
+                ("german", &[]),
+                ("greek", &[]),
+                ("indian", &[]),
+                ("italian", &[]),
+                ("japanese", &[]),
+                ("korean", &[]),
+                ("mediterranean", &[]),
+                ("mexican", &["tex-mex"]),
+                ("middle-eastern", &["middle-east", "levantine"]),
+                ("spanish", &[]),
+                ("thai", &[]),
+                ("vietnamese", &[]),
+            ],
+            MetaField::Course => &[
+                ("breakfast", &["brunch"]),
+                ("appetizer", &["starter"]),
+                ("soup", &[]),
+                ("salad", &[]),
+                ("main", &["main-course", "main-dish", "dinner", "lunch"]),
+                ("side", &["side-dish"]),
+                ("dessert", &["sweet"]),
+                ("snack", &[]),
+                ("drink", &["drinks", "beverage"]),
+                ("sauce", &["dressing"]),
+                ("bread", &["baking"]),
+            ],
+        }
+    }
+
+    /// Known values, without the aliases
+    pub fn values(self) -> Vec<&'static str> {
+        self.table().iter().map(|(v, _)| *v).collect()
+    }
+
+    /// The known value `value` is or is an alias of
+    pub fn known_value(self, value: &str) -> Option<&'static str> {
+        let value = normalize_meta_value(value);
+        self.table()
+            .iter()
+            .find(|(v, aliases)| *v == value || aliases.contains(&value.as_str()))
+            .map(|(v, _)| *v)
+    }
+
+    /// Normalized value to compare, also for unknown values
+    pub fn normalize(self, value: &str) -> String {
+        match self.known_value(value) {
+            Some(v) => v.to_string(),
+            None => normalize_meta_value(value),
+        }
+    }
+
+    /// Normalized value of the field in the recipe metadata
+    pub fn get(self, meta: &Metadata) -> Option<String> {
+        meta.get(self.key())
+            .and_then(|v| v.as_str())
+            .map(|v| self.normalize(v))
+            .filter(|v| !v.is_empty())
+    }
+}
+
+/// Trimmed lowercase with the spaces as `-`
+fn normalize_meta_value(value: &str) -> String {
+    value
+        .split(|c: char| c.is_whitespace() || c == '_' || c == '-')
+        .filter(|s| !s.is_empty())
+        .collect::<Vec<_>>()
+        .join("-")
+        .to_lowercase()
+}
+
 /// Checks that a tag is valid
 ///
-/// A tag is valid when:
+/// Tags can be hierarchical, with levels separated by '/', like
+/// `dessert/cake`. Each level is valid when:
 /// - 32 characters
 /// - lowercase letters and numbers separated by a single '-'
 /// - starts with a letter
@@ -263,11 +871,83 @@ pub fn is_valid_tag(tag: &str) -> bool {
     let tag_len = 1..=32;
     let re = regex!(r"^\p{Ll}[\p{Ll}\d]*(-[\p{Ll}\d]+)*$");
 
-    tag_len.contains(&tag.chars().count()) && re.is_match(tag)
+    tag.split('/')
+        .all(|level| tag_len.contains(&level.chars().count()) && re.is_match(level))
 }
 
 const IS_VALID_TAG_MSG: &str =
-    "The tag should only have lower case letters and numbers separated by a single hyphen ('-')";
+    "The tag should only have lower case letters and numbers separated by a single hyphen ('-'), and levels separated by '/'";
+
+/// Normalized form of a tag
+///
+/// The levels are trimmed and lowercase, without empty ones, and the longest
+/// prefix in `aliases` is replaced, so with `cakes = "dessert/cake"` the tag
+/// `cakes/chocolate` is `dessert/cake/chocolate`.
+pub fn normalize_tag(tag: &str, aliases: &HashMap<String, String>) -> String {
+    let levels = tag
+        .split('/')
+        .map(str::trim)
+        .filter(|l| !l.is_empty())
+        .map(str::to_lowercase)
+        .collect::<Vec<_>>();
+    for end in (1..=levels.len()).rev() {
+        if let Some(target) = aliases.get(&levels[..end].join("/")) {
+            return std::iter::once(target.trim_matches('/'))
+                .chain(levels[end..].iter().map(String::as_str))
+                .collect::<Vec<_>>()
+                .join("/");
+        }
+    }
+    levels.join("/")
+}
+
+/// Normalized tags of a recipe, without duplicates
+pub fn recipe_tags(metadata: &Metadata, aliases: &HashMap<String, String>) -> Vec<String> {
+    let mut tags = Vec::new();
+    for tag in metadata.tags().unwrap_or_default() {
+        let tag = normalize_tag(&tag, aliases);
+        if !tag.is_empty() && !tags.contains(&tag) {
+            tags.push(tag);
+        }
+    }
+    tags
+}
+
+/// Replaces the tags of the metadata with their [normalized](normalize_tag)
+/// form
+pub fn normalize_tags(metadata: &mut Metadata, aliases: &HashMap<String, String>) {
+    if metadata.tags().is_none() {
+        return;
+    }
+    let tags = recipe_tags(metadata, aliases);
+    metadata.map.insert("tags".into(), tags.into());
+}
+
+/// Recipes with `draft: true` in the metadata
+pub fn is_draft_metadata(metadata: &Metadata) -> bool {
+    match metadata.get("draft") {
+        Some(serde_yaml::Value::Bool(draft)) => *draft,
+        Some(serde_yaml::Value::String(s)) => matches!(s.trim(), "true" | "yes"),
+        _ => false,
+    }
+}
+
+/// Checks if a recipe has a tag or a tag under it, so `dessert` is in
+/// `dessert/cake`
+pub fn has_tag(metadata: &Metadata, tag: &str, aliases: &HashMap<String, String>) -> bool {
+    let tag = normalize_tag(tag, aliases);
+    recipe_tags(metadata, aliases)
+        .iter()
+        .any(|t| tag_levels(t).any(|l| l == tag))
+}
+
+/// Each level of a hierarchical tag with its parents, so `dessert/cake` is
+/// `dessert` and `dessert/cake`
+pub fn tag_levels(tag: &str) -> impl Iterator<Item = &str> {
+    tag.match_indices('/')
+        .map(|(i, _)| &tag[..i])
+        .chain(std::iter::once(tag))
+}
 
 const TAG_TOO_LONG_MSG: &str = "The tag is too long";
 
@@ -283,6 +963,13 @@ pub fn get_emoji(s: &str) -> Option<&'static str> {
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_rfc3339() {
+        assert_eq!(rfc3339(0), "1970-01-01T00:00:00Z");
+        assert_eq!(rfc3339(1_714_558_830), "2024-05-01T10:20:30Z");
+        assert_eq!(rfc3339(1_736_879_400), "2025-01-14T18:30:00Z");
+    }
+
     #[test]
     fn test_is_valid_tag() {
         assert!(is_valid_tag("uwu"));
@@ -294,7 +981,119 @@ mod tests {
         assert!(!is_valid_tag("111"));
         assert!(!is_valid_tag("1starts-with-number"));
         assert!(!is_valid_tag("many---hyphens"));
-        assert!(!is_valid_tag("other/characters"));
+        assert!(is_valid_tag("dessert/cake"));
+        assert!(!is_valid_tag("dessert//cake"));
+        assert!(!is_valid_tag("/cake"));
         assert!(!is_valid_tag("other@[]chara€cters"));
     }
+
+    #[test]
+    fn test_normalize_tag() {
+        let aliases = HashMap::from([
+            ("cakes".to_string(), "dessert/cake".to_string()),
+            ("veggie".to_string(), "vegetarian".to_string()),
+        ]);
+        assert_eq!(normalize_tag("quick", &aliases), "quick");
+        assert_eq!(normalize_tag(" Dessert / Cake/", &aliases), "dessert/cake");
+        assert_eq!(normalize_tag("cakes", &aliases), "dessert/cake");
+        assert_eq!(
+            normalize_tag("cakes/chocolate", &aliases),
+            "dessert/cake/chocolate"
+        );
+        assert_eq!(normalize_tag("veggies", &aliases), "veggies");
+        assert_eq!(
+            tag_levels("dessert/cake").collect::<Vec<_>>(),
+            ["dessert", "dessert/cake"]
+        );
+    }
+
+    #[test]
+    fn test_meta_field() {
+        assert_eq!(MetaField::from_key("course"), Some(MetaField::Course));
+        assert_eq!(
+            MetaField::Course.known_value(" Main  Course "),
+            Some("main")
+        );
+        assert_eq!(MetaField::Difficulty.known_value("Beginner"), Some("easy"));
+        assert_eq!(
+            MetaField::Cuisine.known_value("Middle_Eastern"),
+            Some("middle-eastern")
+        );
+        assert_eq!(MetaField::Cuisine.known_value("peruvian"), None);
+        assert_eq!(MetaField::Cuisine.normalize("Peruvian "), "peruvian");
+    }
+
+    #[test]
+    fn test_line_column() {
+        let text = "first\nsé@cond\n";
+        assert_eq!(line_column(text, 0), (1, 1));
+        assert_eq!(line_column(text, 6), (2, 1));
+        assert_eq!(line_column(text, 9), (2, 3));
+        assert_eq!(line_column(text, 100), (3, 1));
+    }
+
+    #[test]
+    fn test_convert_recipe_thresholds() {
+        let parser = cooklang::CooklangParser::extended();
+        let recipe = parser
+            .parse("Mix @flour{1200%g} and @sugar{2500%g}.")
+            .into_output()
+            .unwrap();
+        let mut scaled = recipe.default_scale();
+        let thresholds = HashMap::from([("kg".to_string(), 1.5)]);
+        convert_recipe(&mut scaled, System::Metric, &thresholds, parser.converter());
+        let units = scaled
+            .ingredients
+            .iter()
+            .map(|i| i.quantity.as_ref().and_then(|q| q.unit()))
+            .collect::<Vec<_>>();
+        assert_eq!(units, [Some("g"), Some("kg")]);
+    }
+
+    #[test]
+    fn test_url_encode_path() {
+        assert_eq!(
+            url_encode_path("/home/me/Pasta.cook"),
+            "/home/me/Pasta.cook"
+        );
+        assert_eq!(url_encode_path("a b/Crème?"), "a%20b/Cr%C3%A8me%3F");
+    }
+
+    #[test]
+    fn test_url_scheme() {
+        assert_eq!(url_scheme("https://example.com"), Some("https"));
+        assert_eq!(url_scheme("javascript:alert(1)"), Some("javascript"));
+        assert_eq!(url_scheme("Pancakes.jpg"), None);
+        assert_eq!(url_scheme(r"C:\images\a.jpg"), None);
+    }
+
+    #[test]
+    fn markdown() {
+        assert_eq!(
+            markdown_to_html("# Hi\n\nSome *text* and ~~old~~."),
+            "<h1>Hi</h1>\n<p>Some <em>text</em> and <del>old</del>.</p>\n"
+        );
+        let table = markdown_to_html("| a | b |\n|---|---|\n| 1 | 2 |\n");
+        assert!(table.contains("<td>1</td><td>2</td>"));
+        let links = markdown_to_html("[ok](https://example.com) [rel](/r/Pasta)");
+        assert!(links.contains(r#"<a href="https://example.com">ok</a>"#));
+        assert!(links.contains(r#"<a href="/r/Pasta">rel</a>"#));
+    }
+
+    #[test]
+    fn markdown_without_scripts() {
+        let html = markdown_to_html("<script>alert(1)</script>\n\nafter");
+        assert!(html.starts_with("&lt;script&gt;alert(1)&lt;/script&gt;"));
+        let html = markdown_to_html("a <img src=x onerror=alert(1)> b");
+        assert_eq!(html, "<p>a &lt;img src=x onerror=alert(1)&gt; b</p>\n");
+        let html = markdown_to_html("<div onclick=\"x()\">\nhi\n</div>\n");
+        assert!(!html.contains("<div"));
+
+        let html = markdown_to_html(
+            "[a](javascript:alert(1)) [b](JavaScript:alert(1)) ![c](data:text/html,x) <javascript:alert(1)>",
+        );
+        assert!(!html.to_lowercase().contains("href=\"javascript"));
+        assert!(!html.contains("data:"));
+        assert_eq!(html.matches(r#"href="""#).count(), 3);
+    }
 }
@@ -0,0 +1,88 @@
+//! The parser and the formatters never panic, whatever the input
+//!
+//! Like the fuzz target in `fuzz/`, but with random inputs in every
+//! `cargo test`.
+
+use std::sync::OnceLock;
+
+use cooklang::CooklangParser;
+use proptest::prelude::*;
+
+fn parser() -> &'static CooklangParser {
+    static PARSER: OnceLock<CooklangParser> = OnceLock::new();
+    PARSER.get_or_init(CooklangParser::extended)
+}
+
+/// Parses and, if it's a recipe, renders it with every output format
+fn parse_and_render(input: &str) {
+    let parser = parser();
+    let Some(recipe) = parser.parse(input).into_output() else {
+        return;
+    };
+    let converter = parser.converter();
+
+    let _ = cooklang_to_cooklang::print_cooklang(&recipe, std::io::sink());
+    for recipe in [recipe.clone().default_scale(), recipe.scale(3, converter)] {
+        let _ = cooklang_to_human::print_human(&recipe, "prop", converter, std::io::sink());
+        let _ = cooklang_to_md::print_md(&recipe, "prop", converter, std::io::sink());
+        let _ = cooklang_to_cooklang::print_cooklang_scaled(&recipe, std::io::sink());
+    }
+}
+
+/// Pieces of the cooklang syntax, random text rarely has them
+const PIECES: &[&str] = &[
+    "@",
+    "#",
+    "~",
+    "{",
+    "}",
+    "%",
+    "(",
+    ")",
+    "&",
+    "?",
+    "-",
+    "+",
+    "*",
+    "=",
+    "==",
+    ">",
+    ">>",
+    ":",
+    "|",
+    "[-",
+    "-]",
+    "--",
+    "\\",
+    "\n",
+    "\n\n",
+    " ",
+    "1",
+    "1/2",
+    "2.5",
+    "0",
+    "g",
+    "cup",
+    "egg",
+    "é",
+    "🥄",
+    "---\n",
+    "servings: 2\n",
+];
+
+/// Text made of [`PIECES`]
+fn cooklang_like() -> impl Strategy<Value = String> {
+    prop::collection::vec(prop::sample::select(PIECES), 0..64).prop_map(|pieces| pieces.concat())
+}
+
+proptest! {
+    #[test]
+    fn any_utf8(input in any::<String>()) {
+        parse_and_render(&input);
+    }
+
+    #[test]
+    fn cooklang_syntax(input in cooklang_like()) {
+        parse_and_render(&input);
+    }
+}
@@ -42,35 +42,81 @@ function triggerHotReload() {
   el.dispatchEvent(new Event("hot-reload", { bubbles: true }));
 }
 
+// only updates the card of a recipe, if it's in the page
+function refreshRecipeCard(recipe) {
+  const path = recipe.replace(/\.cook$/, "");
+  const selector = `[data-recipe-card="${CSS.escape(path)}"]`;
+  if (!document.querySelector(selector)) {
+    return;
+  }
+  htmx.ajax("GET", window.location.href, {
+    target: selector,
+    select: selector,
+    swap: "outerHTML",
+  });
+}
+
+/* Offline copies of visited pages */
+if ("serviceWorker" in navigator) {
+  const baseUrl = document.documentElement.dataset.baseUrl || "";
+  navigator.serviceWorker
+    .register(baseUrl + "/sw.js", { scope: baseUrl + "/" })
+    .catch((err) => console.warn("service worker not registered", err));
+}
+
+// so a deleted or old version of a recipe is not shown offline
+function forgetOfflineCopy(recipe) {
+  const sw = navigator.serviceWorker && navigator.serviceWorker.controller;
+  if (sw) sw.postMessage({ type: "changed", recipe });
+}
+
 let hrEventSource = null;
 function hrConnect() {
+  const baseUrl = document.documentElement.dataset.baseUrl || "";
   if (hrEventSource !== null) hrEventSource.close();
-  hrEventSource = new EventSource("/updates");
+  hrEventSource = new EventSource(baseUrl + "/updates");
+
+  function currentPath() {
+    return window.location.pathname.slice(baseUrl.length);
+  }
 
   function isIndex() {
-    const path = window.location.pathname;
+    const path = currentPath();
     return path == "/" || path.startsWith("/d/") || path.startsWith("/search");
   }
 
   function isCurrentRecipe(triggered) {
-    let path = window.location.pathname;
-    if (!path.startsWith("/r/")) {
-      return false;
-    }
-    let currentRecipe = decodeURI(path.slice(3));
-    let triggeredRecipe = triggered.replace(/\.cook$/, "");
-    return currentRecipe === triggeredRecipe;
+    // the url may be a slug, the page has the path
+    const content = document.querySelector("#content[data-recipe-path]");
+    return content !== null && content.dataset.recipePath === triggered;
   }
 
   hrEventSource.addEventListener("open", () => hrSetIndicatorState(true));
   hrEventSource.addEventListener("error", () => hrSetIndicatorState(false));
 
-  hrEventSource.addEventListener("modified", triggerHotReload);
+  hrEventSource.addEventListener("modified", (ev) => {
+    forgetOfflineCopy(ev.data);
+    if (isCurrentRecipe(ev.data)) {
+      triggerHotReload();
+    } else if (isIndex()) {
+      refreshRecipeCard(ev.data);
+    }
+  });
+  hrEventSource.addEventListener("image", (ev) => {
+    const data = JSON.parse(ev.data);
+    forgetOfflineCopy(data.recipe);
+    if (isCurrentRecipe(data.recipe)) {
+      triggerHotReload();
+    } else if (isIndex()) {
+      refreshRecipeCard(data.recipe);
+    }
+  });
   hrEventSource.addEventListener("deleted", (ev) => {
+    forgetOfflineCopy(ev.data);
     if (isIndex()) {
       triggerHotReload();
     } else if (isCurrentRecipe(ev.data)) {
-      window.location = "/?deleted=" + ev.data.replace(/\.cook$/, "");
+      window.location = baseUrl + "/?deleted=" + ev.data.replace(/\.cook$/, "");
     }
   });
   hrEventSource.addEventListener("added", () => {
@@ -79,13 +125,15 @@ function hrConnect() {
     }
   });
   hrEventSource.addEventListener("renamed", (ev) => {
+    let data = JSON.parse(ev.data);
+    forgetOfflineCopy(data.from);
     if (isIndex()) {
       return triggerHotReload();
     }
-    let data = JSON.parse(ev.data);
     if (isCurrentRecipe(data.from)) {
       console.log("renamed current recipe");
-      let url = "/r/" + data.to.replace(/\.cook$/, "") + window.location.search;
+      let url =
+        baseUrl + "/r/" + data.to.replace(/\.cook$/, "") + window.location.search;
       window.location.replace(url);
     }
   });
@@ -298,15 +298,9 @@ function formatTimestamp(secsFromEpoch) {
   }).format(date);
 }
 
-function formatNumber(num) {
-  return new Intl.NumberFormat(currentLocale, {
-    maximumFractionDigits: 3,
-  }).format(num);
-}
-
 function formatAllElements(rootElement) {
   htmx.findAll(rootElement, "[format-minutes]").forEach((el) => {
-    const num = Number(el.textContent);
+    const num = Number(el.getAttribute("format-minutes"));
     if (Number.isFinite(num)) {
       const formatted = formatTime(num);
       el.textContent = formatted;
@@ -320,14 +314,6 @@ function formatAllElements(rootElement) {
       el.textContent = formatted;
     }
   });
-
-  htmx.findAll(rootElement, "[format-number]").forEach((el) => {
-    const num = Number(el.textContent);
-    if (Number.isFinite(num)) {
-      const formatted = formatNumber(num);
-      el.textContent = formatted;
-    }
-  });
 }
 
 // Format on initial page load
@@ -120,3 +120,8 @@ document.body.addEventListener("keyup", (ev) => {
       .forEach((el) => (el.hidden = true));
   }
 });
+
+/* Print, to paper or a PDF */
+document.querySelectorAll("[data-print]").forEach((el) => {
+  el.addEventListener("click", () => window.print());
+});
@@ -0,0 +1,109 @@
+/* Service worker to keep visited pages and their images for offline use */
+
+// BASE_URL and VERSION are added by the server
+
+const CACHE = "chef-" + VERSION;
+// spotty connections are slower than the cache
+const NETWORK_TIMEOUT = 4000;
+
+// assets are embedded in the binary, so they only change with the version
+function isAsset(path) {
+  return (
+    /^\/(js|vendor|fonts)\//.test(path) ||
+    /^\/(styles\.css|favicon\.svg|favicon\.png)$/.test(path)
+  );
+}
+
+// never from the cache
+function isLive(path) {
+  return (
+    path === "/updates" ||
+    path === "/sw.js" ||
+    path.startsWith("/open_editor/") ||
+    path.startsWith("/reveal/") ||
+    path.startsWith("/admin/")
+  );
+}
+
+self.addEventListener("install", () => self.skipWaiting());
+
+self.addEventListener("activate", (ev) => {
+  ev.waitUntil(
+    caches
+      .keys()
+      .then((keys) =>
+        Promise.all(
+          keys
+            .filter((k) => k.startsWith("chef-") && k !== CACHE)
+            .map((k) => caches.delete(k)),
+        ),
+      )
+      .then(() => self.clients.claim()),
+  );
+});
+
+self.addEventListener("fetch", (ev) => {
+  const req = ev.request;
+  const url = new URL(req.url);
+  if (
+    req.method !== "GET" ||
+    url.origin !== self.location.origin ||
+    !url.pathname.startsWith(BASE_URL + "/")
+  ) {
+    return;
+  }
+  const path = url.pathname.slice(BASE_URL.length);
+  if (isLive(path)) {
+    return;
+  }
+  ev.respondWith(isAsset(path) ? cacheFirst(req) : networkFirst(req));
+});
+
+async function fetchAndStore(req) {
+  const res = await fetch(req);
+  if (res.ok) {
+    const cache = await caches.open(CACHE);
+    await cache.put(req, res.clone());
+  }
+  return res;
+}
+
+async function cacheFirst(req) {
+  const cached = await caches.match(req);
+  return cached || fetchAndStore(req);
+}
+
+async function networkFirst(req) {
+  const network = fetchAndStore(req);
+  const timeout = new Promise((resolve) => setTimeout(resolve, NETWORK_TIMEOUT));
+  try {
+    const res = await Promise.race([network, timeout]);
+    if (res) return res;
+  } catch (err) {
+    // offline, try the cache
+  }
+  const cached = await caches.match(req);
+  return cached || network;
+}
+
+/* Forget the pages of recipes that changed, sent by hot-reload.js */
+self.addEventListener("message", (ev) => {
+  if (ev.data && ev.data.type === "changed") {
+    ev.waitUntil(forget(ev.data.recipe));
+  }
+});
+
+async function forget(recipe) {
+  const path = recipe.replace(/\.cook$/, "");
+  const cache = await caches.open(CACHE);
+  for (const req of await cache.keys()) {
+    const p = decodeURI(new URL(req.url).pathname.slice(BASE_URL.length));
+    if (
+      p === "/r/" + path ||
+      p === "/fragments/r/" + path ||
+      p.startsWith("/r/" + path + "/")
+    ) {
+      await cache.delete(req);
+    }
+  }
+}
@@ -55,11 +55,15 @@ html_templates = os.path.join(translations, "..", "templates")
 safelist = set(
     [
         "_lang",
+        "_decimalSeparator",
+        "_units",
         "r.convertSelector.default",
         "r.convertSelector.metric",
         "r.convertSelector.imperial",
         "openInEditor.error",
         "openInEditor.success",
+        "reveal.error",
+        "reveal.success",
     ]
 )
 